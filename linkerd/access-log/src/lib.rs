@@ -0,0 +1,14 @@
+#![deny(warnings, rust_2018_idioms)]
+#![forbid(unsafe_code)]
+
+mod record;
+mod service;
+mod tcp;
+mod writer;
+
+pub use self::{
+    record::{Direction, Format},
+    service::{AccessLogService, NewAccessLog, RequestBody, ResponseBody, ResponseFuture},
+    tcp::{NewTcpAccessLog, TcpAccessLogService, TcpConnection, TcpFuture, TcpIo, TcpSensor},
+    writer::{AccessLog, Config},
+};
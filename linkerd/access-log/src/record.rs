@@ -0,0 +1,510 @@
+use crate::tcp::TcpConnection;
+use linkerd_errno::Errno;
+use linkerd_tls as tls;
+use serde_json::json;
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The format in which access log records are written.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Each record is written as a single-line JSON object.
+    Json,
+
+    /// Each record is written in the Apache Common Log Format, followed by the proxy-specific
+    /// fields that the common format does not describe.
+    Apache,
+}
+
+/// Indicates which of the proxy's servers handled a request.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Inbound,
+    Outbound,
+}
+
+/// A record written to the access log.
+#[derive(Clone, Debug)]
+pub(crate) enum Record {
+    Http(HttpRecord),
+    Tcp(TcpRecord),
+}
+
+/// Describes a single request and its response.
+#[derive(Clone, Debug)]
+pub(crate) struct HttpRecord {
+    pub timestamp: SystemTime,
+    pub direction: Direction,
+    pub client_addr: Option<SocketAddr>,
+    pub client_id: Option<tls::ClientId>,
+    pub authority: Option<String>,
+    pub method: http::Method,
+    pub path: String,
+    pub version: http::Version,
+    pub status: Option<http::StatusCode>,
+    pub proxy_error: Option<String>,
+    pub error: Option<String>,
+    pub route: Option<Arc<BTreeMap<String, String>>>,
+    pub response_latency: Option<Duration>,
+    pub total_duration: Duration,
+    pub request_bytes: u64,
+    pub response_bytes: u64,
+}
+
+/// Describes a single forwarded connection, from when it was accepted until it closed.
+#[derive(Clone, Debug)]
+pub(crate) struct TcpRecord {
+    pub timestamp: SystemTime,
+    pub direction: Direction,
+    pub client_addr: Option<SocketAddr>,
+    pub connection: TcpConnection,
+    pub duration: Duration,
+
+    /// The number of bytes read from the client.
+    pub bytes_in: u64,
+
+    /// The number of bytes written to the client.
+    pub bytes_out: u64,
+
+    /// The system error, if any, that closed the client's connection.
+    pub errno: Option<Errno>,
+
+    /// The error, if any, that caused the proxy to stop forwarding the connection.
+    pub error: Option<String>,
+}
+
+// === impl Direction ===
+
+impl Direction {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Inbound => "inbound",
+            Self::Outbound => "outbound",
+        }
+    }
+}
+
+// === impl Record ===
+
+impl Record {
+    /// Formats the record as a single line, without a trailing newline.
+    pub fn fmt(&self, format: Format) -> String {
+        match (self, format) {
+            (Self::Http(record), Format::Json) => record.json().to_string(),
+            (Self::Http(record), Format::Apache) => record.apache(),
+            (Self::Tcp(record), Format::Json) => record.json().to_string(),
+            (Self::Tcp(record), Format::Apache) => record.apache(),
+        }
+    }
+}
+
+// === impl HttpRecord ===
+
+impl HttpRecord {
+    fn json(&self) -> serde_json::Value {
+        json!({
+            "timestamp": rfc3339(self.timestamp),
+            "direction": self.direction.as_str(),
+            "protocol": "http",
+            "client": {
+                "addr": self.client_addr.map(|a| a.to_string()),
+                "id": self.client_id.as_ref().map(|id| id.to_string()),
+            },
+            "authority": self.authority,
+            "method": self.method.as_str(),
+            "path": self.path,
+            "version": format!("{:?}", self.version),
+            "status": self.status.map(|s| s.as_u16()),
+            "l5d-proxy-error": self.proxy_error,
+            "error": self.error,
+            "route": self.route.as_deref(),
+            "response_latency_us": self.response_latency.map(|d| d.as_micros() as u64),
+            "total_duration_us": self.total_duration.as_micros() as u64,
+            "request_bytes": self.request_bytes,
+            "response_bytes": self.response_bytes,
+        })
+    }
+
+    fn apache(&self) -> String {
+        let route = self.route.as_ref().map(|labels| {
+            labels
+                .iter()
+                .map(|(k, v)| format!("{}={}", k, v))
+                .collect::<Vec<_>>()
+                .join(",")
+        });
+
+        let mut line = String::new();
+        let _ = write!(
+            line,
+            "{} - {} [{}] \"{} {} {:?}\" {} {}",
+            or_dash(self.client_addr.map(|a| a.ip())),
+            or_dash(self.client_id.as_ref()),
+            rfc3339(self.timestamp),
+            self.method,
+            self.path,
+            self.version,
+            or_dash(self.status.map(|s| s.as_u16())),
+            self.response_bytes,
+        );
+        let _ = write!(
+            line,
+            " \"{}\" {} {} {} {} \"{}\" \"{}\"",
+            escape(or_dash(self.authority.as_ref())),
+            self.direction.as_str(),
+            self.request_bytes,
+            or_dash(self.response_latency.map(|d| d.as_micros())),
+            self.total_duration.as_micros(),
+            escape(or_dash(self.proxy_error.as_ref().or(self.error.as_ref()))),
+            escape(or_dash(route)),
+        );
+        line
+    }
+}
+
+// === impl TcpRecord ===
+
+impl TcpRecord {
+    fn json(&self) -> serde_json::Value {
+        let tls = &self.connection.tls;
+        json!({
+            "timestamp": rfc3339(self.timestamp),
+            "direction": self.direction.as_str(),
+            "protocol": "tcp",
+            "client": {
+                "addr": self.client_addr.map(|a| a.to_string()),
+                "id": client_id(tls).map(|id| id.to_string()),
+            },
+            "orig_dst": self.connection.orig_dst.map(|a| a.to_string()),
+            "target": self.connection.target,
+            "tls": tls_status(tls),
+            "sni": match tls {
+                tls::ConditionalServerTls::Some(tls::ServerTls::Passthru { sni }) => {
+                    Some(sni.to_string())
+                }
+                _ => None,
+            },
+            "no_tls_reason": match tls {
+                tls::ConditionalServerTls::None(tls::NoServerTls::Disabled) => None,
+                tls::ConditionalServerTls::None(why) => Some(why.to_string()),
+                tls::ConditionalServerTls::Some(_) => None,
+            },
+            "server": self.connection.server.as_deref(),
+            "authz": self.connection.authz.as_deref(),
+            "duration_us": self.duration.as_micros() as u64,
+            "bytes_in": self.bytes_in,
+            "bytes_out": self.bytes_out,
+            "errno": self.errno.map(|e| e.to_string()),
+            "error": self.error,
+        })
+    }
+
+    /// Formats the record in the shape of the Common Log Format, where the request line names the
+    /// connection's original destination and the response size is the number of bytes written to
+    /// the client.
+    fn apache(&self) -> String {
+        let tls = &self.connection.tls;
+        let close = self
+            .error
+            .clone()
+            .or_else(|| self.errno.map(|e| e.to_string()));
+
+        let mut line = String::new();
+        let _ = write!(
+            line,
+            "{} - {} [{}] \"TCP {}\" - {}",
+            or_dash(self.client_addr.map(|a| a.ip())),
+            or_dash(client_id(tls)),
+            rfc3339(self.timestamp),
+            or_dash(self.connection.orig_dst),
+            self.bytes_out,
+        );
+        let _ = write!(
+            line,
+            " \"{}\" {} {} - {} \"{}\" \"server={},authz={},tls={}\"",
+            escape(or_dash(self.connection.target.as_ref())),
+            self.direction.as_str(),
+            self.bytes_in,
+            self.duration.as_micros(),
+            escape(or_dash(close)),
+            escape(or_dash(self.connection.server.as_ref())),
+            escape(or_dash(self.connection.authz.as_ref())),
+            tls_status(tls),
+        );
+        line
+    }
+}
+
+fn client_id(tls: &tls::ConditionalServerTls) -> Option<&tls::ClientId> {
+    match tls {
+        tls::ConditionalServerTls::Some(tls::ServerTls::Established { client_id, .. }) => {
+            client_id.as_ref()
+        }
+        _ => None,
+    }
+}
+
+/// Describes a connection's TLS status with the values used by the `tls` metrics label.
+fn tls_status(tls: &tls::ConditionalServerTls) -> &'static str {
+    match tls {
+        tls::ConditionalServerTls::None(tls::NoServerTls::Disabled) => "disabled",
+        tls::ConditionalServerTls::None(_) => "no_identity",
+        tls::ConditionalServerTls::Some(tls::ServerTls::Established { .. }) => "true",
+        tls::ConditionalServerTls::Some(tls::ServerTls::Passthru { .. }) => "opaque",
+    }
+}
+
+fn or_dash<T: ToString>(value: Option<T>) -> String {
+    value
+        .map(|v| v.to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn escape(s: String) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Formats a time as an RFC 3339 UTC timestamp with millisecond precision.
+fn rfc3339(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let secs_of_day = secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3_600,
+        (secs_of_day % 3_600) / 60,
+        secs_of_day % 60,
+        since_epoch.subsec_millis(),
+    )
+}
+
+/// Converts a number of days since the UNIX epoch into a (year, month, day) date.
+///
+/// See http://howardhinnant.github.io/date_algorithms.html#civil_from_days.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record() -> HttpRecord {
+        HttpRecord {
+            timestamp: UNIX_EPOCH + Duration::from_millis(1_700_000_000_123),
+            direction: Direction::Inbound,
+            client_addr: Some(([192, 0, 2, 3], 50000).into()),
+            client_id: Some(
+                "foo.ns.serviceaccount.identity.linkerd.cluster.local"
+                    .parse()
+                    .unwrap(),
+            ),
+            authority: Some("bar.ns.svc.cluster.local:8080".to_string()),
+            method: http::Method::GET,
+            path: "/api/v1/things".to_string(),
+            version: http::Version::HTTP_11,
+            status: Some(http::StatusCode::OK),
+            proxy_error: None,
+            error: None,
+            route: Some(Arc::new(
+                Some(("rt_route".to_string(), "things".to_string()))
+                    .into_iter()
+                    .collect(),
+            )),
+            response_latency: Some(Duration::from_micros(1_500)),
+            total_duration: Duration::from_micros(2_000),
+            request_bytes: 0,
+            response_bytes: 1_024,
+        }
+    }
+
+    fn tcp_record() -> TcpRecord {
+        TcpRecord {
+            timestamp: UNIX_EPOCH + Duration::from_millis(1_700_000_000_123),
+            direction: Direction::Inbound,
+            client_addr: Some(([192, 0, 2, 3], 50000).into()),
+            connection: TcpConnection {
+                orig_dst: Some(([192, 0, 2, 10], 5432).into()),
+                tls: tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+                    client_id: Some(
+                        "foo.ns.serviceaccount.identity.linkerd.cluster.local"
+                            .parse()
+                            .unwrap(),
+                    ),
+                    negotiated_protocol: None,
+                }),
+                server: Some("db".into()),
+                authz: Some("db-clients".into()),
+                target: None,
+            },
+            duration: Duration::from_micros(30_000_000),
+            bytes_in: 2_048,
+            bytes_out: 65_536,
+            errno: Some(Errno::from(104)),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn formats_timestamps() {
+        assert_eq!(rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        assert_eq!(
+            rfc3339(UNIX_EPOCH + Duration::from_secs(951_782_400)),
+            "2000-02-29T00:00:00.000Z"
+        );
+        assert_eq!(
+            rfc3339(UNIX_EPOCH + Duration::from_millis(1_700_000_000_123)),
+            "2023-11-14T22:13:20.123Z"
+        );
+    }
+
+    #[test]
+    fn formats_json() {
+        let line = Record::Http(record()).fmt(Format::Json);
+        assert!(!line.contains('\n'));
+        let value = serde_json::from_str::<serde_json::Value>(&line).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "timestamp": "2023-11-14T22:13:20.123Z",
+                "direction": "inbound",
+                "protocol": "http",
+                "client": {
+                    "addr": "192.0.2.3:50000",
+                    "id": "foo.ns.serviceaccount.identity.linkerd.cluster.local",
+                },
+                "authority": "bar.ns.svc.cluster.local:8080",
+                "method": "GET",
+                "path": "/api/v1/things",
+                "version": "HTTP/1.1",
+                "status": 200,
+                "l5d-proxy-error": null,
+                "error": null,
+                "route": { "rt_route": "things" },
+                "response_latency_us": 1_500,
+                "total_duration_us": 2_000,
+                "request_bytes": 0,
+                "response_bytes": 1_024,
+            })
+        );
+    }
+
+    #[test]
+    fn formats_apache() {
+        assert_eq!(
+            Record::Http(record()).fmt(Format::Apache),
+            "192.0.2.3 - foo.ns.serviceaccount.identity.linkerd.cluster.local \
+            [2023-11-14T22:13:20.123Z] \"GET /api/v1/things HTTP/1.1\" 200 1024 \
+            \"bar.ns.svc.cluster.local:8080\" inbound 0 1500 2000 \"-\" \"rt_route=things\""
+        );
+
+        let unanswered = HttpRecord {
+            client_id: None,
+            status: None,
+            response_latency: None,
+            error: Some("connection \"closed\"".to_string()),
+            route: None,
+            ..record()
+        };
+        assert_eq!(
+            Record::Http(unanswered).fmt(Format::Apache),
+            "192.0.2.3 - - [2023-11-14T22:13:20.123Z] \"GET /api/v1/things HTTP/1.1\" - 1024 \
+            \"bar.ns.svc.cluster.local:8080\" inbound 0 - 2000 \"connection \\\"closed\\\"\" \"-\""
+        );
+    }
+
+    #[test]
+    fn formats_tcp_json() {
+        let line = Record::Tcp(tcp_record()).fmt(Format::Json);
+        assert!(!line.contains('\n'));
+        let value = serde_json::from_str::<serde_json::Value>(&line).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "timestamp": "2023-11-14T22:13:20.123Z",
+                "direction": "inbound",
+                "protocol": "tcp",
+                "client": {
+                    "addr": "192.0.2.3:50000",
+                    "id": "foo.ns.serviceaccount.identity.linkerd.cluster.local",
+                },
+                "orig_dst": "192.0.2.10:5432",
+                "target": null,
+                "tls": "true",
+                "sni": null,
+                "no_tls_reason": null,
+                "server": "db",
+                "authz": "db-clients",
+                "duration_us": 30_000_000,
+                "bytes_in": 2_048,
+                "bytes_out": 65_536,
+                "errno": "ECONNRESET",
+                "error": null,
+            })
+        );
+    }
+
+    #[test]
+    fn formats_tcp_apache() {
+        assert_eq!(
+            Record::Tcp(tcp_record()).fmt(Format::Apache),
+            "192.0.2.3 - foo.ns.serviceaccount.identity.linkerd.cluster.local \
+            [2023-11-14T22:13:20.123Z] \"TCP 192.0.2.10:5432\" - 65536 \
+            \"-\" inbound 2048 - 30000000 \"ECONNRESET\" \"server=db,authz=db-clients,tls=true\""
+        );
+
+        let plaintext = TcpRecord {
+            connection: TcpConnection {
+                tls: tls::ConditionalServerTls::None(tls::NoServerTls::NoClientHello),
+                target: Some("db.ns.svc.cluster.local:5432".to_string()),
+                ..tcp_record().connection
+            },
+            errno: None,
+            error: Some("connect timed out".to_string()),
+            ..tcp_record()
+        };
+        assert_eq!(
+            Record::Tcp(plaintext).fmt(Format::Apache),
+            "192.0.2.3 - - [2023-11-14T22:13:20.123Z] \"TCP 192.0.2.10:5432\" - 65536 \
+            \"db.ns.svc.cluster.local:5432\" inbound 2048 - 30000000 \"connect timed out\" \
+            \"server=db,authz=db-clients,tls=no_identity\""
+        );
+
+        let outbound = TcpRecord {
+            direction: Direction::Outbound,
+            connection: TcpConnection {
+                orig_dst: None,
+                tls: tls::ConditionalServerTls::None(tls::NoServerTls::Loopback),
+                server: None,
+                authz: None,
+                target: Some("db.ns.svc.cluster.local:5432".to_string()),
+            },
+            errno: None,
+            ..tcp_record()
+        };
+        assert_eq!(
+            Record::Tcp(outbound).fmt(Format::Apache),
+            "192.0.2.3 - - [2023-11-14T22:13:20.123Z] \"TCP -\" - 65536 \
+            \"db.ns.svc.cluster.local:5432\" outbound 2048 - 30000000 \"-\" \
+            \"server=-,authz=-,tls=no_identity\""
+        );
+    }
+}
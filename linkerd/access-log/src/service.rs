@@ -0,0 +1,528 @@
+use crate::{
+    record::{Direction, HttpRecord, Record},
+    writer::AccessLog,
+};
+use bytes::Buf;
+use futures::{ready, TryFuture};
+use http_body::Body;
+use linkerd_proxy_http::ClientHandle;
+use linkerd_service_profiles as profiles;
+use linkerd_stack::{layer, ExtractParam, NewService, Service};
+use linkerd_tls as tls;
+use pin_project::pin_project;
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant, SystemTime},
+};
+
+const L5D_PROXY_ERROR: &str = "l5d-proxy-error";
+
+/// Writes an access log record for each request handled by the inner service.
+///
+/// A request's record is written once its response body completes (or is dropped), so that it
+/// describes the entire response. When no `AccessLog` is configured, requests pass through
+/// without being recorded.
+#[derive(Clone, Debug)]
+pub struct NewAccessLog<P, N> {
+    direction: Direction,
+    log: Option<AccessLog>,
+    params: P,
+    inner: N,
+}
+
+#[derive(Clone, Debug)]
+pub struct AccessLogService<S> {
+    direction: Direction,
+    log: Option<AccessLog>,
+    client_id: Option<tls::ClientId>,
+    inner: S,
+}
+
+#[pin_project]
+#[derive(Debug)]
+pub struct ResponseFuture<F> {
+    #[pin]
+    inner: F,
+    recorder: Option<Recorder>,
+}
+
+/// Counts the bytes read from a request body.
+#[pin_project]
+#[derive(Debug, Default)]
+pub struct RequestBody<B> {
+    #[pin]
+    inner: B,
+    bytes: Option<Arc<AtomicU64>>,
+}
+
+/// Counts the bytes written to a response body, writing the request's record when the body is
+/// dropped.
+#[pin_project]
+#[derive(Debug, Default)]
+pub struct ResponseBody<B> {
+    #[pin]
+    inner: B,
+    recorder: Option<Recorder>,
+}
+
+/// Holds a request's record while its response is in flight, writing it when dropped.
+#[derive(Debug)]
+struct Recorder {
+    log: AccessLog,
+    record: Option<HttpRecord>,
+    start: Instant,
+    request_bytes: Arc<AtomicU64>,
+}
+
+// === impl NewAccessLog ===
+
+impl<P: Clone, N> NewAccessLog<P, N> {
+    pub fn layer(
+        direction: Direction,
+        log: Option<AccessLog>,
+        params: P,
+    ) -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(move |inner| Self {
+            direction,
+            log: log.clone(),
+            params: params.clone(),
+            inner,
+        })
+    }
+}
+
+impl<T, P, N> NewService<T> for NewAccessLog<P, N>
+where
+    P: ExtractParam<tls::ConditionalServerTls, T>,
+    N: NewService<T>,
+{
+    type Service = AccessLogService<N::Service>;
+
+    fn new_service(&self, target: T) -> Self::Service {
+        let client_id = match self.params.extract_param(&target) {
+            tls::ConditionalServerTls::Some(tls::ServerTls::Established { client_id, .. }) => {
+                client_id
+            }
+            _ => None,
+        };
+        AccessLogService {
+            direction: self.direction,
+            log: self.log.clone(),
+            client_id,
+            inner: self.inner.new_service(target),
+        }
+    }
+}
+
+// === impl AccessLogService ===
+
+impl<S, A, B> Service<http::Request<A>> for AccessLogService<S>
+where
+    S: Service<http::Request<RequestBody<A>>, Response = http::Response<B>>,
+    S::Error: fmt::Display,
+{
+    type Response = http::Response<ResponseBody<B>>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<A>) -> Self::Future {
+        let log = match self.log.as_ref() {
+            Some(log) => log,
+            None => {
+                return ResponseFuture {
+                    inner: self
+                        .inner
+                        .call(req.map(|inner| RequestBody { inner, bytes: None })),
+                    recorder: None,
+                }
+            }
+        };
+
+        // Clients may send requests in origin form, in which case the authority is only known
+        // from the `host` header.
+        let authority = req.uri().authority().map(|a| a.to_string()).or_else(|| {
+            req.headers()
+                .get(http::header::HOST)
+                .and_then(|h| h.to_str().ok())
+                .map(String::from)
+        });
+        let record = HttpRecord {
+            timestamp: SystemTime::now(),
+            direction: self.direction,
+            client_addr: req.extensions().get::<ClientHandle>().map(|c| c.addr),
+            client_id: self.client_id.clone(),
+            authority,
+            method: req.method().clone(),
+            path: req.uri().path().to_string(),
+            version: req.version(),
+            status: None,
+            proxy_error: None,
+            error: None,
+            route: None,
+            response_latency: None,
+            total_duration: Duration::ZERO,
+            request_bytes: 0,
+            response_bytes: 0,
+        };
+
+        let request_bytes = Arc::new(AtomicU64::new(0));
+        let req = req.map(|inner| RequestBody {
+            inner,
+            bytes: Some(request_bytes.clone()),
+        });
+        ResponseFuture {
+            inner: self.inner.call(req),
+            recorder: Some(Recorder {
+                log: log.clone(),
+                record: Some(record),
+                start: Instant::now(),
+                request_bytes,
+            }),
+        }
+    }
+}
+
+// === impl ResponseFuture ===
+
+impl<F, B> Future for ResponseFuture<F>
+where
+    F: TryFuture<Ok = http::Response<B>>,
+    F::Error: fmt::Display,
+{
+    type Output = Result<http::Response<ResponseBody<B>>, F::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = ready!(this.inner.try_poll(cx));
+        let mut recorder = this.recorder.take();
+        match res {
+            Ok(rsp) => {
+                if let Some(recorder) = recorder.as_mut() {
+                    recorder.response(&rsp);
+                }
+                Poll::Ready(Ok(rsp.map(|inner| ResponseBody { inner, recorder })))
+            }
+            Err(error) => {
+                // The record is written as the recorder is dropped.
+                if let Some(recorder) = recorder.as_mut() {
+                    recorder.error(&error);
+                }
+                Poll::Ready(Err(error))
+            }
+        }
+    }
+}
+
+// === impl Recorder ===
+
+impl Recorder {
+    fn response<B>(&mut self, rsp: &http::Response<B>) {
+        if let Some(record) = self.record.as_mut() {
+            record.status = Some(rsp.status());
+            record.response_latency = Some(self.start.elapsed());
+            record.proxy_error = rsp
+                .headers()
+                .get(L5D_PROXY_ERROR)
+                .and_then(|v| v.to_str().ok())
+                .map(String::from);
+            record.route = rsp
+                .extensions()
+                .get::<profiles::http::Route>()
+                .map(|route| route.labels().clone());
+        }
+    }
+
+    fn error(&mut self, error: &impl fmt::Display) {
+        if let Some(record) = self.record.as_mut() {
+            record.error = Some(error.to_string());
+        }
+    }
+
+    fn response_bytes(&mut self, bytes: usize) {
+        if let Some(record) = self.record.as_mut() {
+            record.response_bytes += bytes as u64;
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if let Some(mut record) = self.record.take() {
+            record.total_duration = self.start.elapsed();
+            record.request_bytes = self.request_bytes.load(Ordering::Acquire);
+            self.log.send(Record::Http(record));
+        }
+    }
+}
+
+// === impl RequestBody ===
+
+impl<B: Body> Body for RequestBody<B> {
+    type Data = B::Data;
+    type Error = B::Error;
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let this = self.project();
+        let data = ready!(this.inner.poll_data(cx));
+        if let (Some(Ok(data)), Some(bytes)) = (data.as_ref(), this.bytes.as_ref()) {
+            bytes.fetch_add(data.remaining() as u64, Ordering::Release);
+        }
+        Poll::Ready(data)
+    }
+
+    #[inline]
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        self.project().inner.poll_trailers(cx)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+// === impl ResponseBody ===
+
+impl<B> Body for ResponseBody<B>
+where
+    B: Body,
+    B::Error: fmt::Display,
+{
+    type Data = B::Data;
+    type Error = B::Error;
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let this = self.project();
+        let data = ready!(this.inner.poll_data(cx));
+        if let Some(recorder) = this.recorder.as_mut() {
+            match data.as_ref() {
+                Some(Ok(data)) => recorder.response_bytes(data.remaining()),
+                Some(Err(error)) => recorder.error(error),
+                None => {}
+            }
+        }
+        Poll::Ready(data)
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        let this = self.project();
+        let trailers = ready!(this.inner.poll_trailers(cx));
+        if let (Err(error), Some(recorder)) = (trailers.as_ref(), this.recorder.as_mut()) {
+            recorder.error(error);
+        }
+        Poll::Ready(trailers)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use http_body::Full;
+    use linkerd_stack::service_fn;
+    use std::{collections::VecDeque, sync::mpsc};
+
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    type Request = http::Request<RequestBody<Full<Bytes>>>;
+
+    /// A response body that yields its chunks and then never ends.
+    #[derive(Debug, Default)]
+    struct Stalled(VecDeque<Bytes>);
+
+    impl Body for Stalled {
+        type Data = Bytes;
+        type Error = Error;
+
+        fn poll_data(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<Option<Result<Bytes, Error>>> {
+            match self.0.pop_front() {
+                Some(chunk) => Poll::Ready(Some(Ok(chunk))),
+                None => Poll::Pending,
+            }
+        }
+
+        fn poll_trailers(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<Result<Option<http::HeaderMap>, Error>> {
+            Poll::Pending
+        }
+    }
+
+    fn access_log<S>(inner: S) -> (AccessLogService<S>, mpsc::Receiver<Record>) {
+        let (log, rx) = AccessLog::for_test();
+        let svc = AccessLogService {
+            direction: Direction::Outbound,
+            log: Some(log),
+            client_id: None,
+            inner,
+        };
+        (svc, rx)
+    }
+
+    fn request() -> http::Request<Full<Bytes>> {
+        http::Request::builder()
+            .method(http::Method::POST)
+            .uri("/things")
+            .header(http::header::HOST, "web.ns.svc.cluster.local:8080")
+            .body(Full::new(Bytes::from_static(b"ping")))
+            .unwrap()
+    }
+
+    fn http_record(rx: &mpsc::Receiver<Record>) -> HttpRecord {
+        match rx.try_recv().expect("a record must be written") {
+            Record::Http(record) => record,
+            Record::Tcp(record) => panic!("unexpected TCP record: {:?}", record),
+        }
+    }
+
+    #[tokio::test]
+    async fn records_responses() {
+        let route = profiles::http::Route::new(
+            Some(("rt_route".to_string(), "things".to_string())).into_iter(),
+            Vec::new(),
+        );
+        let (mut svc, rx) = access_log(service_fn(move |req: Request| {
+            let route = route.clone();
+            async move {
+                // Read the request body so that its bytes are counted.
+                let mut body = req.into_body();
+                while let Some(chunk) = body.data().await {
+                    chunk?;
+                }
+
+                let mut rsp = http::Response::builder()
+                    .status(http::StatusCode::SERVICE_UNAVAILABLE)
+                    .header(L5D_PROXY_ERROR, "no endpoints")
+                    .body(Full::new(Bytes::from_static(b"unavailable")))
+                    .unwrap();
+                rsp.extensions_mut().insert(route);
+                Ok::<_, Error>(rsp)
+            }
+        }));
+
+        let rsp = svc.call(request()).await.expect("request must succeed");
+        assert!(
+            rx.try_recv().is_err(),
+            "the record must not be written until the response body completes"
+        );
+
+        let mut body = rsp.into_body();
+        while let Some(chunk) = body.data().await {
+            chunk.expect("body must not fail");
+        }
+        drop(body);
+
+        let record = http_record(&rx);
+        assert_eq!(record.direction, Direction::Outbound);
+        assert_eq!(record.method, http::Method::POST);
+        assert_eq!(record.path, "/things");
+        assert_eq!(
+            record.authority.as_deref(),
+            Some("web.ns.svc.cluster.local:8080")
+        );
+        assert_eq!(record.status, Some(http::StatusCode::SERVICE_UNAVAILABLE));
+        assert_eq!(record.proxy_error.as_deref(), Some("no endpoints"));
+        assert_eq!(record.error, None);
+        assert_eq!(
+            record
+                .route
+                .as_ref()
+                .and_then(|labels| labels.get("rt_route"))
+                .map(String::as_str),
+            Some("things")
+        );
+        assert!(record.response_latency.is_some());
+        assert_eq!(record.request_bytes, 4);
+        assert_eq!(record.response_bytes, 11);
+        assert!(rx.try_recv().is_err(), "only one record must be written");
+    }
+
+    #[tokio::test]
+    async fn records_errors() {
+        let (mut svc, rx) = access_log(service_fn(|_: Request| async {
+            Err::<http::Response<Full<Bytes>>, Error>("connection refused".into())
+        }));
+
+        let error = svc.call(request()).await.expect_err("request must fail");
+        assert_eq!(error.to_string(), "connection refused");
+
+        let record = http_record(&rx);
+        assert_eq!(record.status, None);
+        assert_eq!(record.response_latency, None);
+        assert_eq!(record.proxy_error, None);
+        assert_eq!(record.error.as_deref(), Some("connection refused"));
+        assert_eq!(record.request_bytes, 0);
+        assert_eq!(record.response_bytes, 0);
+        assert!(rx.try_recv().is_err(), "only one record must be written");
+    }
+
+    #[tokio::test]
+    async fn records_bodies_dropped_midstream() {
+        let (mut svc, rx) = access_log(service_fn(|_: Request| async {
+            let chunks = vec![Bytes::from_static(b"abc"), Bytes::from_static(b"de")];
+            Ok::<_, Error>(http::Response::new(Stalled(chunks.into())))
+        }));
+
+        let rsp = svc.call(request()).await.expect("request must succeed");
+        let mut body = rsp.into_body();
+        body.data().await.unwrap().expect("body must not fail");
+        body.data().await.unwrap().expect("body must not fail");
+        assert!(
+            rx.try_recv().is_err(),
+            "the record must not be written while the response body is in flight"
+        );
+
+        // The client goes away before the body completes.
+        drop(body);
+
+        let record = http_record(&rx);
+        assert_eq!(record.status, Some(http::StatusCode::OK));
+        assert_eq!(record.error, None);
+        assert_eq!(record.request_bytes, 0);
+        assert_eq!(record.response_bytes, 5);
+        assert!(rx.try_recv().is_err(), "only one record must be written");
+    }
+}
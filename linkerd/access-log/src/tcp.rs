@@ -0,0 +1,415 @@
+use crate::{
+    record::{Direction, Record, TcpRecord},
+    writer::AccessLog,
+};
+use futures::{ready, TryFuture};
+use linkerd_errno::Errno;
+use linkerd_io as io;
+use linkerd_stack::{layer, ExtractParam, NewService, Service};
+use linkerd_tls as tls;
+use parking_lot::Mutex;
+use pin_project::pin_project;
+use std::{
+    fmt,
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// Describes a forwarded connection. The client's address is read from the connection itself.
+#[derive(Clone, Debug)]
+pub struct TcpConnection {
+    /// The connection's original destination, if it is known when the connection is forwarded.
+    pub orig_dst: Option<SocketAddr>,
+
+    pub tls: tls::ConditionalServerTls,
+
+    /// The name of the inbound policy server that the connection targets.
+    pub server: Option<Arc<str>>,
+
+    /// The name of the inbound authorization that permitted the connection.
+    pub authz: Option<Arc<str>>,
+
+    /// The name to which the connection is forwarded, e.g. by a gateway or an outbound load
+    /// balancer.
+    pub target: Option<String>,
+}
+
+/// Writes an access log record for each connection handled by the inner service.
+///
+/// A connection's record is written once the inner service completes (or is dropped), so that it
+/// describes how the connection was closed. When no `AccessLog` is configured, connections pass
+/// through without being recorded.
+#[derive(Clone, Debug)]
+pub struct NewTcpAccessLog<P, N> {
+    direction: Direction,
+    log: Option<AccessLog>,
+    params: P,
+    inner: N,
+}
+
+#[derive(Clone, Debug)]
+pub struct TcpAccessLogService<S> {
+    direction: Direction,
+    log: Option<(AccessLog, TcpConnection)>,
+    inner: S,
+}
+
+/// Counts the bytes read from and written to a client connection.
+#[derive(Debug)]
+pub struct TcpSensor(Option<Arc<Counts>>);
+
+pub type TcpIo<I> = io::SensorIo<I, TcpSensor>;
+
+#[pin_project]
+#[derive(Debug)]
+pub struct TcpFuture<F> {
+    #[pin]
+    inner: F,
+    recorder: Option<Recorder>,
+}
+
+#[derive(Debug, Default)]
+struct Counts {
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+    errno: Mutex<Option<Errno>>,
+}
+
+/// Holds a connection's record while it is being forwarded, writing it when dropped.
+#[derive(Debug)]
+struct Recorder {
+    log: AccessLog,
+    record: Option<TcpRecord>,
+    start: Instant,
+    counts: Arc<Counts>,
+}
+
+// === impl NewTcpAccessLog ===
+
+impl<P: Clone, N> NewTcpAccessLog<P, N> {
+    pub fn layer(
+        direction: Direction,
+        log: Option<AccessLog>,
+        params: P,
+    ) -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(move |inner| Self {
+            direction,
+            log: log.clone(),
+            params: params.clone(),
+            inner,
+        })
+    }
+}
+
+impl<T, P, N> NewService<T> for NewTcpAccessLog<P, N>
+where
+    P: ExtractParam<TcpConnection, T>,
+    N: NewService<T>,
+{
+    type Service = TcpAccessLogService<N::Service>;
+
+    fn new_service(&self, target: T) -> Self::Service {
+        let log = self
+            .log
+            .clone()
+            .map(|log| (log, self.params.extract_param(&target)));
+        TcpAccessLogService {
+            direction: self.direction,
+            log,
+            inner: self.inner.new_service(target),
+        }
+    }
+}
+
+// === impl TcpAccessLogService ===
+
+impl<S, I> Service<I> for TcpAccessLogService<S>
+where
+    I: io::PeerAddr,
+    S: Service<TcpIo<I>, Response = ()>,
+    S::Error: fmt::Display,
+{
+    type Response = ();
+    type Error = S::Error;
+    type Future = TcpFuture<S::Future>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, io: I) -> Self::Future {
+        let (log, connection) = match self.log.as_ref() {
+            Some(log) => log,
+            None => {
+                return TcpFuture {
+                    inner: self.inner.call(io::SensorIo::new(io, TcpSensor(None))),
+                    recorder: None,
+                }
+            }
+        };
+
+        let record = TcpRecord {
+            timestamp: SystemTime::now(),
+            direction: self.direction,
+            client_addr: io.peer_addr().ok(),
+            connection: connection.clone(),
+            duration: Duration::ZERO,
+            bytes_in: 0,
+            bytes_out: 0,
+            errno: None,
+            error: None,
+        };
+
+        let counts = Arc::new(Counts::default());
+        let io = io::SensorIo::new(io, TcpSensor(Some(counts.clone())));
+        TcpFuture {
+            inner: self.inner.call(io),
+            recorder: Some(Recorder {
+                log: log.clone(),
+                record: Some(record),
+                start: Instant::now(),
+                counts,
+            }),
+        }
+    }
+}
+
+// === impl TcpFuture ===
+
+impl<F> Future for TcpFuture<F>
+where
+    F: TryFuture<Ok = ()>,
+    F::Error: fmt::Display,
+{
+    type Output = Result<(), F::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = ready!(this.inner.try_poll(cx));
+        if let (Err(error), Some(recorder)) = (res.as_ref(), this.recorder.as_mut()) {
+            recorder.error(error);
+        }
+        // The record is written as the recorder is dropped.
+        drop(this.recorder.take());
+        Poll::Ready(res)
+    }
+}
+
+// === impl Recorder ===
+
+impl Recorder {
+    fn error(&mut self, error: &impl fmt::Display) {
+        if let Some(record) = self.record.as_mut() {
+            record.error = Some(error.to_string());
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if let Some(mut record) = self.record.take() {
+            record.duration = self.start.elapsed();
+            record.bytes_in = self.counts.bytes_in.load(Ordering::Acquire);
+            record.bytes_out = self.counts.bytes_out.load(Ordering::Acquire);
+            record.errno = *self.counts.errno.lock();
+            self.log.send(Record::Tcp(record));
+        }
+    }
+}
+
+// === impl TcpSensor ===
+
+impl io::Sensor for TcpSensor {
+    fn record_read(&mut self, sz: usize) {
+        if let Some(counts) = self.0.as_ref() {
+            counts.bytes_in.fetch_add(sz as u64, Ordering::Release);
+        }
+    }
+
+    fn record_write(&mut self, sz: usize) {
+        if let Some(counts) = self.0.as_ref() {
+            counts.bytes_out.fetch_add(sz as u64, Ordering::Release);
+        }
+    }
+
+    fn record_close(&mut self, eos: Option<Errno>) {
+        if let (Some(counts), Some(errno)) = (self.0.as_ref(), eos) {
+            // Only the first error describes why the connection closed.
+            counts.errno.lock().get_or_insert(errno);
+        }
+    }
+
+    fn record_error<T>(&mut self, op: io::Poll<T>) -> io::Poll<T> {
+        if let Poll::Ready(Err(e)) = &op {
+            if e.kind() != io::ErrorKind::WouldBlock {
+                self.record_close(e.raw_os_error().map(Errno::from));
+            }
+        }
+        op
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use io::{AsyncReadExt, AsyncWriteExt};
+    use linkerd_stack::service_fn;
+    use std::sync::mpsc;
+
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    /// A connection that fails with `ECONNRESET` when it is read.
+    #[derive(Debug)]
+    struct Reset;
+
+    impl io::AsyncRead for Reset {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            _: &mut io::ReadBuf<'_>,
+        ) -> io::Poll<()> {
+            Poll::Ready(Err(io::Error::from_raw_os_error(104)))
+        }
+    }
+
+    impl io::AsyncWrite for Reset {
+        fn poll_write(self: Pin<&mut Self>, _: &mut Context<'_>, buf: &[u8]) -> io::Poll<usize> {
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> io::Poll<()> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> io::Poll<()> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    impl io::PeerAddr for Reset {
+        fn peer_addr(&self) -> io::Result<SocketAddr> {
+            Ok(([192, 0, 2, 3], 50000).into())
+        }
+    }
+
+    fn access_log<S>(inner: S) -> (TcpAccessLogService<S>, mpsc::Receiver<Record>) {
+        let (log, rx) = AccessLog::for_test();
+        let connection = TcpConnection {
+            orig_dst: Some(([192, 0, 2, 10], 5432).into()),
+            tls: tls::ConditionalServerTls::None(tls::NoServerTls::NoClientHello),
+            server: Some("db".into()),
+            authz: Some("all-unauthenticated".into()),
+            target: None,
+        };
+        let svc = TcpAccessLogService {
+            direction: Direction::Inbound,
+            log: Some((log, connection)),
+            inner,
+        };
+        (svc, rx)
+    }
+
+    fn tcp_record(rx: &mpsc::Receiver<Record>) -> TcpRecord {
+        match rx.try_recv().expect("a record must be written") {
+            Record::Tcp(record) => record,
+            Record::Http(record) => panic!("unexpected HTTP record: {:?}", record),
+        }
+    }
+
+    #[tokio::test]
+    async fn records_byte_counts() {
+        let (mut svc, rx) = access_log(service_fn(|mut io: TcpIo<io::DuplexStream>| async move {
+            let mut buf = [0u8; 5];
+            io.read_exact(&mut buf).await?;
+            io.write_all(b"pong").await?;
+            Ok::<_, Error>(())
+        }));
+
+        let (mut client, server) = io::duplex(64);
+        client.write_all(b"hello").await.unwrap();
+        svc.call(server).await.expect("connection must succeed");
+
+        let mut buf = [0u8; 4];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"pong");
+
+        let record = tcp_record(&rx);
+        assert_eq!(record.direction, Direction::Inbound);
+        assert_eq!(record.client_addr, Some(([0, 0, 0, 0], 0).into()));
+        assert_eq!(record.connection.server.as_deref(), Some("db"));
+        assert_eq!(record.bytes_in, 5);
+        assert_eq!(record.bytes_out, 4);
+        assert_eq!(record.errno, None);
+        assert_eq!(record.error, None);
+        assert!(rx.try_recv().is_err(), "only one record must be written");
+    }
+
+    #[tokio::test]
+    async fn records_close_reasons() {
+        let (mut svc, rx) = access_log(service_fn(|mut io: TcpIo<Reset>| async move {
+            let mut buf = [0u8; 1];
+            io.read(&mut buf).await?;
+            Ok::<_, Error>(())
+        }));
+
+        let error = svc.call(Reset).await.expect_err("connection must fail");
+
+        let record = tcp_record(&rx);
+        assert_eq!(record.client_addr, Some(([192, 0, 2, 3], 50000).into()));
+        assert_eq!(record.bytes_in, 0);
+        assert_eq!(record.errno, Some(Errno::from(104)));
+        assert_eq!(record.error, Some(error.to_string()));
+        assert!(rx.try_recv().is_err(), "only one record must be written");
+    }
+
+    #[tokio::test]
+    async fn records_dropped_connections() {
+        let (mut svc, rx) = access_log(service_fn(|_: TcpIo<io::DuplexStream>| {
+            futures::future::pending::<Result<(), Error>>()
+        }));
+
+        let (_client, server) = io::duplex(64);
+        let conn = svc.call(server);
+        assert!(
+            rx.try_recv().is_err(),
+            "the record must not be written while the connection is forwarded"
+        );
+
+        // The proxy stops forwarding the connection, e.g. as it shuts down.
+        drop(conn);
+
+        let record = tcp_record(&rx);
+        assert_eq!(record.errno, None);
+        assert_eq!(record.error, None);
+        assert!(rx.try_recv().is_err(), "only one record must be written");
+    }
+
+    #[test]
+    fn sensor_records_first_close_reason() {
+        use io::Sensor;
+
+        let counts = Arc::new(Counts::default());
+        let mut sensor = TcpSensor(Some(counts.clone()));
+        sensor.record_read(3);
+        sensor.record_write(4);
+
+        // Would-block errors do not close the connection.
+        let _ = sensor.record_error::<()>(Poll::Ready(Err(io::ErrorKind::WouldBlock.into())));
+        assert_eq!(*counts.errno.lock(), None);
+
+        let _ = sensor.record_error::<()>(Poll::Ready(Err(io::Error::from_raw_os_error(104))));
+        sensor.record_close(Some(Errno::from(32)));
+
+        assert_eq!(counts.bytes_in.load(Ordering::Acquire), 3);
+        assert_eq!(counts.bytes_out.load(Ordering::Acquire), 4);
+        assert_eq!(*counts.errno.lock(), Some(Errno::from(104)));
+    }
+}
@@ -0,0 +1,107 @@
+use crate::record::{Format, Record};
+use linkerd_metrics::{metrics, Counter, FmtMetrics};
+use std::{
+    fmt,
+    fs::OpenOptions,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    sync::{mpsc, Arc},
+    thread,
+};
+use tracing::warn;
+
+metrics! {
+    access_log_dropped_records_total: Counter {
+        "Total count of access log records dropped because the writer could not keep up"
+    }
+}
+
+/// The number of records that may be buffered before new records are dropped.
+const CAPACITY: usize = 10_000;
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub format: Format,
+
+    /// The file to which records are appended. When unset, records are written to stderr.
+    pub path: Option<PathBuf>,
+}
+
+/// Writes access log records.
+///
+/// Records are formatted and written on a dedicated thread so that serving requests never blocks
+/// on the log's output. If the writer falls behind, records are dropped rather than buffered
+/// without bound.
+#[derive(Clone, Debug)]
+pub struct AccessLog {
+    tx: mpsc::SyncSender<Record>,
+    dropped: Arc<Counter>,
+}
+
+// === impl Config ===
+
+impl Config {
+    /// Opens the log's output and spawns a thread that writes records to it.
+    pub fn build(self) -> io::Result<AccessLog> {
+        let out: Box<dyn Write + Send> = match self.path {
+            Some(path) => Box::new(OpenOptions::new().create(true).append(true).open(path)?),
+            None => Box::new(io::stderr()),
+        };
+
+        let (tx, rx) = mpsc::sync_channel(CAPACITY);
+        let format = self.format;
+        thread::Builder::new()
+            .name("access-log".into())
+            .spawn(move || write_records(rx, format, BufWriter::new(out)))?;
+
+        Ok(AccessLog {
+            tx,
+            dropped: Default::default(),
+        })
+    }
+}
+
+fn write_records(rx: mpsc::Receiver<Record>, format: Format, mut out: impl Write) {
+    while let Ok(record) = rx.recv() {
+        // Write all of the records that are ready before flushing the output.
+        let mut next = Some(record);
+        while let Some(record) = next {
+            if let Err(error) = writeln!(out, "{}", record.fmt(format)) {
+                warn!(%error, "Failed to write access log record");
+            }
+            next = rx.try_recv().ok();
+        }
+        if let Err(error) = out.flush() {
+            warn!(%error, "Failed to flush access log");
+        }
+    }
+}
+
+// === impl AccessLog ===
+
+impl AccessLog {
+    /// Returns a log whose records are sent to the returned receiver rather than written.
+    #[cfg(test)]
+    pub(crate) fn for_test() -> (Self, mpsc::Receiver<Record>) {
+        let (tx, rx) = mpsc::sync_channel(CAPACITY);
+        let log = Self {
+            tx,
+            dropped: Default::default(),
+        };
+        (log, rx)
+    }
+
+    pub(crate) fn send(&self, record: Record) {
+        if self.tx.try_send(record).is_err() {
+            self.dropped.incr();
+        }
+    }
+}
+
+impl FmtMetrics for AccessLog {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        access_log_dropped_records_total.fmt_help(f)?;
+        access_log_dropped_records_total.fmt_metric(f, &*self.dropped)?;
+        Ok(())
+    }
+}
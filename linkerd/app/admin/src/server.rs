@@ -2,12 +2,18 @@
 //!
 //! * `GET /metrics` -- reports prometheus-formatted metrics.
 //! * `GET /ready` -- returns 200 when the proxy is ready to participate in meshed
-//!   traffic.
+//!   traffic and is not draining.
 //! * `GET /live` -- returns 200 when the proxy is live.
 //! * `GET /proxy-log-level` -- returns the current proxy tracing filter.
 //! * `PUT /proxy-log-level` -- sets a new tracing filter.
 //! * `GET /tasks` -- returns a dump of spawned Tokio tasks (when enabled by the
 //!   tracing configuration).
+//! * `GET /identity` -- returns a JSON description of the proxy's identity,
+//!   certificate, and trust anchors.
+//! * `GET /config_dump` -- returns a JSON description of the proxy's configuration
+//!   and the policies, profiles, and endpoints it has discovered.
+//! * `POST /drain` -- stops accepting connections and gracefully closes existing
+//!   connections, without shutting down the proxy.
 //! * `POST /shutdown` -- shuts down the proxy.
 
 use futures::future;
@@ -17,6 +23,7 @@ use hyper::{
     Request, Response,
 };
 use linkerd_app_core::{
+    identity::{client::Status as IdentityStatus, LocalId},
     metrics::{self as metrics, FmtMetrics},
     proxy::http::ClientHandle,
     trace, Error,
@@ -24,43 +31,75 @@ use linkerd_app_core::{
 use std::{
     future::Future,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
 };
 use tokio::sync::mpsc;
 
+mod config_dump;
+mod drain;
+mod identity;
 mod level;
 mod readiness;
 
-pub use self::readiness::{Latch, Readiness};
+pub use self::{
+    config_dump::DumpConfig,
+    drain::{Drain, DrainState},
+    readiness::{Latch, Readiness},
+};
 
 #[derive(Clone)]
 pub struct Admin<M> {
     metrics: metrics::Serve<M>,
     tracing: trace::Handle,
     ready: Readiness,
+    drain: Drain,
     shutdown_tx: mpsc::UnboundedSender<()>,
+    local_id: LocalId,
+    identity: IdentityStatus,
+    config: Arc<dyn DumpConfig + Send + Sync>,
 }
 
 pub type ResponseFuture =
     Pin<Box<dyn Future<Output = Result<Response<Body>, Error>> + Send + 'static>>;
 
 impl<M> Admin<M> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         metrics: M,
         ready: Readiness,
+        drain: Drain,
         shutdown_tx: mpsc::UnboundedSender<()>,
         tracing: trace::Handle,
+        local_id: LocalId,
+        identity: IdentityStatus,
+        config: Arc<dyn DumpConfig + Send + Sync>,
     ) -> Self {
         Self {
             metrics: metrics::Serve::new(metrics),
             ready,
+            drain,
             shutdown_tx,
             tracing,
+            local_id,
+            identity,
+            config,
         }
     }
 
     fn ready_rsp(&self) -> Response<Body> {
-        if self.ready.is_ready() {
+        let draining = match self.drain.state() {
+            DrainState::Serving => None,
+            DrainState::Draining => Some("draining\n"),
+            DrainState::Drained => Some("drained\n"),
+        };
+        if let Some(draining) = draining {
+            Response::builder()
+                .status(StatusCode::SERVICE_UNAVAILABLE)
+                .header(http::header::CONTENT_TYPE, "text/plain")
+                .body(draining.into())
+                .expect("builder with known status code must not fail")
+        } else if self.ready.is_ready() {
             Response::builder()
                 .status(StatusCode::OK)
                 .header(http::header::CONTENT_TYPE, "text/plain")
@@ -82,6 +121,15 @@ impl<M> Admin<M> {
             .expect("builder with known status code must not fail")
     }
 
+    fn drain(&self) -> Response<Body> {
+        self.drain.start();
+        Response::builder()
+            .status(StatusCode::OK)
+            .header(http::header::CONTENT_TYPE, "text/plain")
+            .body("draining\n".into())
+            .expect("builder with known status code must not fail")
+    }
+
     fn shutdown(&self) -> Response<Body> {
         if self.shutdown_tx.send(()).is_ok() {
             Response::builder()
@@ -181,6 +229,41 @@ where
                     Box::pin(future::ok(Self::forbidden_not_localhost()))
                 }
             }
+            "/identity" => {
+                if Self::client_is_localhost(&req) {
+                    let rsp = identity::serve(&self.local_id, &self.identity, req).unwrap_or_else(
+                        |error| {
+                            tracing::error!(%error, "Failed to describe identity");
+                            Self::internal_error_rsp(error)
+                        },
+                    );
+                    Box::pin(future::ok(rsp))
+                } else {
+                    Box::pin(future::ok(Self::forbidden_not_localhost()))
+                }
+            }
+            "/config_dump" => {
+                if Self::client_is_localhost(&req) {
+                    let rsp = config_dump::serve(&*self.config, req).unwrap_or_else(|error| {
+                        tracing::error!(%error, "Failed to dump config");
+                        Self::internal_error_rsp(error)
+                    });
+                    Box::pin(future::ok(rsp))
+                } else {
+                    Box::pin(future::ok(Self::forbidden_not_localhost()))
+                }
+            }
+            "/drain" => {
+                if req.method() == http::Method::POST {
+                    if Self::client_is_localhost(&req) {
+                        Box::pin(future::ok(self.drain()))
+                    } else {
+                        Box::pin(future::ok(Self::forbidden_not_localhost()))
+                    }
+                } else {
+                    Box::pin(future::ok(Self::method_not_allowed()))
+                }
+            }
             "/shutdown" => {
                 if req.method() == http::Method::POST {
                     if Self::client_is_localhost(&req) {
@@ -212,9 +295,8 @@ mod tests {
         let (r, l0) = Readiness::new();
         let l1 = l0.clone();
 
-        let (_, t) = trace::Settings::default().build();
-        let (s, _) = mpsc::unbounded_channel();
-        let admin = Admin::new((), r, s, t);
+        let (drain, _drain_rx) = mk_drain();
+        let admin = mk_admin(r, drain);
         macro_rules! call {
             () => {{
                 let r = Request::builder()
@@ -235,4 +317,82 @@ mod tests {
         drop(l1);
         assert_eq!(call!().status(), StatusCode::OK);
     }
+
+    #[tokio::test]
+    async fn identity_requires_localhost() {
+        let (drain, _drain_rx) = mk_drain();
+        let admin = mk_admin(Readiness::default(), drain);
+
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri("http://0.0.0.0/identity")
+            .body(Body::empty())
+            .unwrap();
+        let rsp = timeout(TIMEOUT, admin.oneshot(req))
+            .await
+            .expect("timeout")
+            .expect("call");
+        assert_eq!(rsp.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn not_ready_while_draining() {
+        let (drain, drain_rx) = mk_drain();
+        let admin = mk_admin(Readiness::default(), drain.clone());
+        macro_rules! call {
+            () => {{
+                let r = Request::builder()
+                    .method(Method::GET)
+                    .uri("http://0.0.0.0/ready")
+                    .body(Body::empty())
+                    .unwrap();
+                let f = admin.clone().oneshot(r);
+                timeout(TIMEOUT, f).await.expect("timeout").expect("call")
+            }};
+        }
+
+        assert_eq!(call!().status(), StatusCode::OK);
+
+        // Hold a connection open so that draining can't complete.
+        let conn = drain_rx.signaled();
+        drain.start();
+        assert_eq!(drain.state(), DrainState::Draining);
+        assert_eq!(call!().status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let released = conn.await;
+        drop(released);
+        timeout(TIMEOUT, drain.clone().drain())
+            .await
+            .expect("drain must complete once connections are released");
+        assert_eq!(drain.state(), DrainState::Drained);
+        assert_eq!(call!().status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    fn mk_admin(ready: Readiness, drain: Drain) -> Admin<()> {
+        let (_, t) = trace::Settings::default().build();
+        let (s, _) = mpsc::unbounded_channel();
+        Admin::new(
+            (),
+            ready,
+            drain,
+            s,
+            t,
+            local_id(),
+            IdentityStatus::default(),
+            Arc::new(()),
+        )
+    }
+
+    fn mk_drain() -> (Drain, linkerd_app_core::drain::Watch) {
+        let (signal, watch) = linkerd_app_core::drain::channel();
+        (Drain::new(signal, Duration::from_secs(10)), watch)
+    }
+
+    fn local_id() -> LocalId {
+        LocalId(
+            "foo.ns1.serviceaccount.identity.linkerd.cluster.local"
+                .parse()
+                .unwrap(),
+        )
+    }
 }
@@ -0,0 +1,33 @@
+use hyper::Body;
+use linkerd_app_core::Result;
+
+/// Describes the proxy's effective configuration and the state it has discovered at runtime.
+pub trait DumpConfig {
+    fn dump_config(&self) -> serde_json::Value;
+}
+
+/// Serves the proxy's configuration dump as JSON.
+pub(super) fn serve<B>(
+    dump: &(dyn DumpConfig + Send + Sync),
+    req: http::Request<B>,
+) -> Result<http::Response<Body>> {
+    if req.method() != http::Method::GET {
+        return Ok(http::Response::builder()
+            .status(http::StatusCode::METHOD_NOT_ALLOWED)
+            .body(Body::empty())
+            .expect("builder with known status code must not fail"));
+    }
+
+    let body = serde_json::to_vec_pretty(&dump.dump_config())?;
+    Ok(http::Response::builder()
+        .status(http::StatusCode::OK)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(body.into())
+        .expect("builder with known status code must not fail"))
+}
+
+impl DumpConfig for () {
+    fn dump_config(&self) -> serde_json::Value {
+        serde_json::Value::Null
+    }
+}
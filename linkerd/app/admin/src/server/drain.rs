@@ -0,0 +1,121 @@
+use linkerd_app_core::{
+    drain,
+    metrics::{metrics, FmtMetrics, Gauge},
+};
+use parking_lot::Mutex;
+use std::{
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{sync::watch, time};
+use tracing::{info, warn};
+
+metrics! {
+    drain_state: Gauge {
+        "Whether the proxy is serving (0), draining its connections (1), or has finished draining (2)."
+    },
+
+    drain_elapsed_seconds: Gauge {
+        "The time since the proxy started to drain its connections (in seconds)."
+    }
+}
+
+/// Drains the proxy's connections, either when requested via the admin server or when the process
+/// is shutting down.
+///
+/// Once draining starts, the proxy stops accepting new connections and closes existing connections
+/// gracefully: HTTP/2 connections are sent a GOAWAY and HTTP/1 connections are closed once their
+/// in-flight responses complete. Draining completes when all connections are closed or when the
+/// grace period elapses, whichever is first.
+#[derive(Clone)]
+pub struct Drain(Arc<Inner>);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DrainState {
+    Serving,
+    Draining,
+    Drained,
+}
+
+struct Inner {
+    signal: Mutex<Option<drain::Signal>>,
+    grace_period: Duration,
+    started: Mutex<Option<Instant>>,
+    tx: watch::Sender<DrainState>,
+    rx: watch::Receiver<DrainState>,
+}
+
+// === impl Drain ===
+
+impl Drain {
+    pub fn new(signal: drain::Signal, grace_period: Duration) -> Self {
+        let (tx, rx) = watch::channel(DrainState::Serving);
+        Self(Arc::new(Inner {
+            signal: Mutex::new(Some(signal)),
+            grace_period,
+            started: Mutex::new(None),
+            tx,
+            rx,
+        }))
+    }
+
+    pub fn state(&self) -> DrainState {
+        *self.0.rx.borrow()
+    }
+
+    /// Starts draining connections in the background, if the proxy is not already draining.
+    pub fn start(&self) {
+        let signal = match self.0.signal.lock().take() {
+            Some(signal) => signal,
+            None => return,
+        };
+        *self.0.started.lock() = Some(Instant::now());
+        let _ = self.0.tx.send(DrainState::Draining);
+
+        let grace_period = self.0.grace_period;
+        info!(?grace_period, "Draining connections");
+        let inner = self.0.clone();
+        tokio::spawn(async move {
+            if time::timeout(grace_period, signal.drain()).await.is_err() {
+                warn!(
+                    ?grace_period,
+                    "Connections did not drain within the grace period"
+                );
+            } else {
+                info!("Connections drained");
+            }
+            let _ = inner.tx.send(DrainState::Drained);
+        });
+    }
+
+    /// Drains connections, completing once the proxy has drained.
+    pub async fn drain(self) {
+        self.start();
+        let mut rx = self.0.rx.clone();
+        while *rx.borrow() != DrainState::Drained {
+            if rx.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+impl FmtMetrics for Drain {
+    fn fmt_metrics(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = match self.state() {
+            DrainState::Serving => 0,
+            DrainState::Draining => 1,
+            DrainState::Drained => 2,
+        };
+        drain_state.fmt_help(f)?;
+        drain_state.fmt_metric(f, &Gauge::from(state))?;
+
+        if let Some(started) = *self.0.started.lock() {
+            drain_elapsed_seconds.fmt_help(f)?;
+            drain_elapsed_seconds.fmt_metric(f, &Gauge::from(started.elapsed().as_secs()))?;
+        }
+
+        Ok(())
+    }
+}
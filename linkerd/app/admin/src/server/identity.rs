@@ -0,0 +1,97 @@
+use hyper::Body;
+use linkerd_app_core::{
+    identity::{client::Status, LocalId},
+    Result,
+};
+use serde_json::{json, Value};
+use std::time::{SystemTime, UNIX_EPOCH};
+use x509_parser::{certificate::X509Certificate, extensions::GeneralName};
+
+/// Serves a JSON description of the proxy's identity, its current certificate, and its trust
+/// anchors.
+pub(super) fn serve<B>(
+    local_id: &LocalId,
+    status: &Status,
+    req: http::Request<B>,
+) -> Result<http::Response<Body>> {
+    if req.method() != http::Method::GET {
+        return Ok(http::Response::builder()
+            .status(http::StatusCode::METHOD_NOT_ALLOWED)
+            .body(Body::empty())
+            .expect("builder with known status code must not fail"));
+    }
+
+    let certificates = status.certificates();
+    let certificate = match certificates.first() {
+        Some(leaf) => certificate(leaf)?,
+        None => Value::Null,
+    };
+
+    let trust_anchors = rustls_pemfile::certs(&mut status.trust_anchors_pem().as_bytes())?
+        .iter()
+        .map(Vec::as_slice)
+        .map(trust_anchor)
+        .collect::<Result<Vec<_>>>()?;
+
+    let body = json!({
+        "name": local_id.to_string(),
+        "certificate": certificate,
+        "intermediates": certificates.len().saturating_sub(1),
+        "next_refresh": status.next_refresh().and_then(unix_secs),
+        "trust_anchors": trust_anchors,
+    });
+
+    Ok(http::Response::builder()
+        .status(http::StatusCode::OK)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(serde_json::to_vec_pretty(&body)?.into())
+        .expect("builder with known status code must not fail"))
+}
+
+/// Describes a DER-encoded leaf certificate. Times are in seconds since the UNIX epoch.
+fn certificate(der: &[u8]) -> Result<Value> {
+    let (_, cert) = x509_parser::parse_x509_certificate(der)?;
+    Ok(json!({
+        "serial": cert.tbs_certificate.raw_serial_as_string(),
+        "subject": cert.subject().to_string(),
+        "issuer": cert.issuer().to_string(),
+        "subject_alt_names": subject_alt_names(&cert),
+        "not_before": cert.validity().not_before.timestamp(),
+        "not_after": cert.validity().not_after.timestamp(),
+    }))
+}
+
+/// Describes a DER-encoded trust anchor by its subject and SHA-256 fingerprint.
+fn trust_anchor(der: &[u8]) -> Result<Value> {
+    let (_, cert) = x509_parser::parse_x509_certificate(der)?;
+    let digest = ring::digest::digest(&ring::digest::SHA256, der);
+    let sha256 = digest
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(":");
+    Ok(json!({
+        "subject": cert.subject().to_string(),
+        "sha256": sha256,
+    }))
+}
+
+fn subject_alt_names(cert: &X509Certificate<'_>) -> Vec<String> {
+    let sans = match cert.tbs_certificate.subject_alternative_name() {
+        Some((_critical, sans)) => sans,
+        None => return vec![],
+    };
+    sans.general_names
+        .iter()
+        .filter_map(|san| match san {
+            GeneralName::DNSName(name) => Some(format!("DNS:{}", name)),
+            GeneralName::URI(uri) => Some(format!("URI:{}", uri)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn unix_secs(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs())
+}
@@ -11,7 +11,7 @@ use linkerd_app_core::{
     Error, Result,
 };
 use linkerd_app_inbound as inbound;
-use std::{pin::Pin, time::Duration};
+use std::{pin::Pin, sync::Arc, time::Duration};
 use thiserror::Error;
 use tokio::sync::mpsc;
 use tracing::debug;
@@ -20,6 +20,8 @@ use tracing::debug;
 pub struct Config {
     pub server: ServerConfig,
     pub metrics_retain_idle: Duration,
+    /// The maximum time to wait for connections to close when the proxy drains.
+    pub shutdown_grace_period: Duration,
 }
 
 pub struct Task {
@@ -34,7 +36,7 @@ struct NonHttpClient(Remote<ClientAddr>);
 
 #[derive(Debug, Error)]
 #[error("Unexpected TLS connection to {} from {}", self.0, self.1)]
-struct UnexpectedSni(tls::ServerId, Remote<ClientAddr>);
+struct UnexpectedSni(tls::ServerName, Remote<ClientAddr>);
 
 #[derive(Clone, Debug)]
 struct Tcp {
@@ -75,10 +77,13 @@ impl Config {
         bind: B,
         policy: impl inbound::policy::CheckPolicy,
         identity: identity::Server,
+        identity_status: identity::client::Status,
+        config: Arc<dyn crate::DumpConfig + Send + Sync>,
         report: R,
         metrics: inbound::Metrics,
         trace: trace::Handle,
         drain: drain::Watch,
+        proxy_drain: crate::Drain,
         shutdown: mpsc::UnboundedSender<()>,
     ) -> Result<Task>
     where
@@ -92,7 +97,16 @@ impl Config {
         let policy = policy.check_policy(OrigDstAddr(listen_addr.into()))?;
 
         let (ready, latch) = crate::server::Readiness::new();
-        let admin = crate::server::Admin::new(report, ready, shutdown, trace);
+        let admin = crate::server::Admin::new(
+            report,
+            ready,
+            proxy_drain,
+            shutdown,
+            trace,
+            identity.param(),
+            identity_status,
+            config,
+        );
         let admin = svc::stack(move |_| admin.clone())
             .push(metrics.proxy.http_endpoint.to_layer::<classify::Response, _, Permitted>())
             .push_map_target(|(permit, http)| Permitted { permit, http })
@@ -295,6 +309,9 @@ impl errors::HttpRescue<Error> for Rescue {
         if cause.is::<inbound::policy::DeniedUnauthorized>() {
             return Ok(errors::SyntheticHttpResponse::permission_denied(error));
         }
+        if cause.is::<inbound::policy::RateLimited>() {
+            return Ok(errors::SyntheticHttpResponse::too_many_requests(error));
+        }
 
         tracing::warn!(%error, "Unexpected error");
         Ok(errors::SyntheticHttpResponse::unexpected_error())
@@ -25,9 +25,19 @@ pub enum Eos {
     Default(http::StatusCode),
     Grpc(GrpcEos),
     Profile(Class),
+    ProfileTrailers(ProfileEos),
     Error(&'static str),
 }
 
+/// Defers profile classification until the end of the stream, for response classes that match on
+/// trailers.
+#[derive(Clone, Debug)]
+pub struct ProfileEos {
+    classes: profiles::http::ResponseClasses,
+    status: http::StatusCode,
+    headers: http::HeaderMap,
+}
+
 #[derive(Clone, Debug)]
 pub enum GrpcEos {
     NoBody(Class),
@@ -102,12 +112,14 @@ impl Default for Response {
 }
 
 impl Response {
-    fn match_class<B>(
-        rsp: &http::Response<B>,
+    fn match_class(
+        status: http::StatusCode,
+        headers: &http::HeaderMap,
+        trailers: Option<&http::HeaderMap>,
         classes: &[profiles::http::ResponseClass],
     ) -> Option<Class> {
         for class in classes {
-            if class.is_match(rsp) {
+            if class.is_match(status, headers, trailers) {
                 let result = if class.is_failure() {
                     SuccessOrFailure::Failure
                 } else {
@@ -133,13 +145,22 @@ impl classify::ClassifyResponse for Response {
             Response::Grpc => grpc_class(rsp.headers())
                 .map(|c| Eos::Grpc(GrpcEos::NoBody(c)))
                 .unwrap_or(Eos::Grpc(GrpcEos::Open)),
-            Response::Profile(ref classes) => Self::match_class(rsp, classes.as_ref())
-                .map(Eos::Profile)
-                .unwrap_or_else(|| {
-                    grpc_class(rsp.headers())
-                        .map(|c| Eos::Grpc(GrpcEos::NoBody(c)))
-                        .unwrap_or_else(|| Eos::Default(rsp.status()))
-                }),
+            Response::Profile(classes) if classes.requires_trailers() => {
+                Eos::ProfileTrailers(ProfileEos {
+                    classes,
+                    status: rsp.status(),
+                    headers: rsp.headers().clone(),
+                })
+            }
+            Response::Profile(ref classes) => {
+                Self::match_class(rsp.status(), rsp.headers(), None, classes.as_ref())
+                    .map(Eos::Profile)
+                    .unwrap_or_else(|| {
+                        grpc_class(rsp.headers())
+                            .map(|c| Eos::Grpc(GrpcEos::NoBody(c)))
+                            .unwrap_or_else(|| Eos::Default(rsp.status()))
+                    })
+            }
         }
     }
 
@@ -172,6 +193,13 @@ impl classify::ClassifyEos for Eos {
                 .and_then(grpc_class)
                 .unwrap_or(Class::Grpc(SuccessOrFailure::Success, 0)),
             Eos::Profile(class) => class,
+            Eos::ProfileTrailers(ProfileEos {
+                classes,
+                status,
+                headers,
+            }) => Response::match_class(status, &headers, trailers, classes.as_ref())
+                .or_else(|| grpc_class(&headers))
+                .unwrap_or_else(|| Eos::Default(status).eos(trailers)),
             Eos::Error(msg) => Class::Stream(SuccessOrFailure::Failure, msg.into()),
         }
     }
@@ -227,9 +255,16 @@ impl Class {
 #[cfg(test)]
 mod tests {
     use super::{Class, SuccessOrFailure};
+    use crate::profiles::http::{ResponseClass, ResponseClasses, ResponseMatch, Route};
     use http::{HeaderMap, Response, StatusCode};
     use linkerd_http_classify::{ClassifyEos, ClassifyResponse};
 
+    fn classes(classes: Vec<ResponseClass>) -> ResponseClasses {
+        Route::new(std::iter::empty(), classes)
+            .response_classes()
+            .clone()
+    }
+
     #[test]
     fn http_response_status_ok() {
         let rsp = Response::builder().status(StatusCode::OK).body(()).unwrap();
@@ -318,4 +353,61 @@ mod tests {
             .eos(Some(&trailers));
         assert_eq!(class, Class::Grpc(SuccessOrFailure::Failure, 4));
     }
+
+    #[test]
+    fn profile_grpc_status_trailer_failure() {
+        let classes = classes(vec![ResponseClass::new(
+            true,
+            ResponseMatch::GrpcStatus(tonic::Code::Unavailable as u32),
+        )]);
+        let rsp = Response::builder().status(StatusCode::OK).body(()).unwrap();
+
+        let mut trailers = HeaderMap::new();
+        trailers.insert("grpc-status", 14.into());
+        let class = super::Response::Profile(classes.clone())
+            .start(&rsp)
+            .eos(Some(&trailers));
+        assert_eq!(class, Class::Default(SuccessOrFailure::Failure));
+
+        // Unmatched responses fall back to the default gRPC classification.
+        let mut trailers = HeaderMap::new();
+        trailers.insert("grpc-status", 0.into());
+        let class = super::Response::Profile(classes)
+            .start(&rsp)
+            .eos(Some(&trailers));
+        assert_eq!(class, Class::Grpc(SuccessOrFailure::Success, 0));
+    }
+
+    #[test]
+    fn profile_grpc_status_trailers_only() {
+        let classes = classes(vec![ResponseClass::new(
+            true,
+            ResponseMatch::GrpcStatus(tonic::Code::Unavailable as u32),
+        )]);
+        let rsp = Response::builder()
+            .header("grpc-status", "14")
+            .status(StatusCode::OK)
+            .body(())
+            .unwrap();
+        let class = super::Response::Profile(classes).start(&rsp).eos(None);
+        assert_eq!(class, Class::Default(SuccessOrFailure::Failure));
+    }
+
+    #[test]
+    fn profile_response_header() {
+        let classes = classes(vec![ResponseClass::new(
+            true,
+            ResponseMatch::Header(
+                http::header::HeaderName::from_static("x-failure"),
+                crate::profiles::http::ValueMatch::Exact("true".into()),
+            ),
+        )]);
+        let rsp = Response::builder()
+            .header("x-failure", "true")
+            .status(StatusCode::OK)
+            .body(())
+            .unwrap();
+        let class = super::Response::Profile(classes).start(&rsp).eos(None);
+        assert_eq!(class, Class::Default(SuccessOrFailure::Failure));
+    }
 }
@@ -1,14 +1,25 @@
+use crate::{metrics::EndpointLabels, tls, Conditional};
 use linkerd_error::Error;
 use linkerd_opencensus::proto::trace::v1 as oc;
-use linkerd_stack::layer;
+use linkerd_stack::{layer, NewService, Param};
 use linkerd_trace_context::{self as trace_context, TraceContext};
+pub use linkerd_trace_context::{ForceSample, Sampler};
 use std::{collections::HashMap, sync::Arc};
 use thiserror::Error;
 use tokio::sync::mpsc;
 
-pub type OpenCensusSink = Option<mpsc::Sender<oc::Span>>;
+pub type OpenCensusSink = Option<SpanSink>;
 pub type Labels = Arc<HashMap<String, String>>;
 
+/// Sends the proxy's spans to the trace collector.
+#[derive(Clone, Debug)]
+pub struct SpanSink {
+    pub spans: mpsc::Sender<oc::Span>,
+
+    /// Decides which unsampled requests the proxy starts traces for.
+    pub sampler: Sampler,
+}
+
 /// SpanConverter converts trace_context::Span objects into OpenCensus agent
 /// protobuf span objects. SpanConverter receives trace_context::Span objects by
 /// implmenting the SpanSink trait. For each span that it receives, it converts
@@ -20,6 +31,14 @@ pub struct SpanConverter {
     labels: Labels,
 }
 
+/// Builds client spans that also describe the endpoint to which requests are sent.
+#[derive(Clone, Debug)]
+pub struct NewClientTracing<N> {
+    sink: OpenCensusSink,
+    labels: Labels,
+    inner: N,
+}
+
 #[derive(Debug, Error)]
 #[error("ID '{:?} should have {} bytes, but it has {}", self.id, self.expected_size, self.actual_size)]
 pub struct IdLengthError {
@@ -35,11 +54,16 @@ pub fn server<S>(
     SpanConverter::layer(Kind::Server, sink, labels)
 }
 
-pub fn client<S>(
+pub fn client<N>(
     sink: OpenCensusSink,
     labels: impl Into<Labels>,
-) -> impl layer::Layer<S, Service = TraceContext<Option<SpanConverter>, S>> + Clone {
-    SpanConverter::layer(Kind::Client, sink, labels)
+) -> impl layer::Layer<N, Service = NewClientTracing<N>> + Clone {
+    let labels = labels.into();
+    layer::mk(move |inner| NewClientTracing {
+        sink: sink.clone(),
+        labels: labels.clone(),
+        inner,
+    })
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -54,38 +78,50 @@ impl SpanConverter {
         sink: OpenCensusSink,
         labels: impl Into<Labels>,
     ) -> impl layer::Layer<S, Service = TraceContext<Option<Self>, S>> + Clone {
-        TraceContext::layer(sink.map(move |sink| Self {
+        // Traces are only started as requests are received, so that a request that isn't sampled
+        // by the server isn't reconsidered as it is sent to an endpoint.
+        let sampler = match (kind, sink.as_ref()) {
+            (Kind::Server, Some(sink)) => sink.sampler.clone(),
+            _ => Sampler::default(),
+        };
+        let converter = sink.map(move |sink| Self {
             kind,
-            sink,
+            sink: sink.spans,
             labels: labels.into(),
-        }))
+        });
+        TraceContext::layer(converter, sampler)
     }
 
     fn mk_span(&self, mut span: trace_context::Span) -> Result<oc::Span, IdLengthError> {
         let mut attributes = HashMap::<String, oc::AttributeValue>::new();
         for (k, v) in self.labels.iter() {
-            attributes.insert(
-                k.clone(),
-                oc::AttributeValue {
-                    value: Some(oc::attribute_value::Value::StringValue(truncatable(
-                        v.clone(),
-                    ))),
-                },
-            );
+            attributes.insert(k.clone(), string_attribute(v.clone()));
         }
+        let status = span
+            .labels
+            .get("grpc.status_code")
+            .and_then(|code| code.parse().ok())
+            .map(|code| oc::Status {
+                code,
+                message: String::new(),
+            });
         for (k, v) in span.labels.drain() {
-            attributes.insert(
-                k.to_string(),
-                oc::AttributeValue {
-                    value: Some(oc::attribute_value::Value::StringValue(truncatable(v))),
-                },
-            );
+            attributes.insert(k.to_string(), string_attribute(v));
         }
+        let time_events = if span.events.is_empty() {
+            None
+        } else {
+            Some(oc::span::TimeEvents {
+                time_event: span.events.drain(..).map(time_event).collect(),
+                dropped_annotations_count: 0,
+                dropped_message_events_count: 0,
+            })
+        };
         Ok(oc::Span {
             trace_id: into_bytes(span.trace_id, 16)?,
             span_id: into_bytes(span.span_id, 8)?,
-            tracestate: None,
-            parent_span_id: into_bytes(span.parent_id, 8)?,
+            tracestate: span.trace_state.map(tracestate),
+            parent_span_id: parent_bytes(span.parent_id)?,
             name: Some(truncatable(span.span_name)),
             kind: self.kind as i32,
             start_time: Some(span.start.into()),
@@ -95,9 +131,9 @@ impl SpanConverter {
                 dropped_attributes_count: 0,
             }),
             stack_trace: None,
-            time_events: None,
+            time_events,
             links: None,
-            status: None, // TODO: this is gRPC status; we must read response trailers to populate this
+            status,
             resource: None,
             same_process_as_parent_span: Some(self.kind == Kind::Client),
             child_span_count: None,
@@ -105,6 +141,28 @@ impl SpanConverter {
     }
 }
 
+// === impl NewClientTracing ===
+
+impl<T, N> NewService<T> for NewClientTracing<N>
+where
+    T: Param<EndpointLabels>,
+    N: NewService<T>,
+{
+    type Service = TraceContext<Option<SpanConverter>, N::Service>;
+
+    fn new_service(&self, target: T) -> Self::Service {
+        let mut labels = (*self.labels).clone();
+        if self.sink.is_some() {
+            endpoint_labels(&mut labels, target.param());
+        }
+        let inner = self.inner.new_service(target);
+        layer::Layer::layer(
+            &SpanConverter::layer(Kind::Client, self.sink.clone(), labels),
+            inner,
+        )
+    }
+}
+
 impl trace_context::SpanSink for SpanConverter {
     #[inline]
     fn is_enabled(&self) -> bool {
@@ -131,6 +189,83 @@ fn into_bytes(id: trace_context::Id, size: usize) -> Result<Vec<u8>, IdLengthErr
     }
 }
 
+/// Describes the endpoint to which a client sends requests, and the TLS identity of its peer.
+fn endpoint_labels(labels: &mut HashMap<String, String>, endpoint: EndpointLabels) {
+    let (addr, tls, identity) = match endpoint {
+        EndpointLabels::Outbound(ep) => {
+            let (tls, id) = match ep.server_id {
+                Conditional::Some(tls) => ("true".to_string(), Some(tls.server_id.to_string())),
+                Conditional::None(reason) => (reason.to_string(), None),
+            };
+            (ep.target_addr, tls, id.map(|id| ("endpoint.identity", id)))
+        }
+        EndpointLabels::Inbound(ep) => {
+            let (tls, id) = match ep.tls {
+                Conditional::Some(tls::ServerTls::Established { client_id, .. }) => {
+                    ("true".to_string(), client_id.map(|id| id.to_string()))
+                }
+                Conditional::Some(tls::ServerTls::Passthru { .. }) => ("opaque".to_string(), None),
+                Conditional::None(reason) => (reason.to_string(), None),
+            };
+            (ep.target_addr, tls, id.map(|id| ("client.identity", id)))
+        }
+    };
+    labels.insert("endpoint.addr".to_string(), addr.to_string());
+    labels.insert("tls".to_string(), tls);
+    if let Some((key, id)) = identity {
+        labels.insert(key.to_string(), id);
+    }
+}
+
+fn time_event(event: trace_context::Event) -> oc::span::TimeEvent {
+    let attribute_map = event
+        .labels
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), string_attribute(v)))
+        .collect();
+    oc::span::TimeEvent {
+        time: Some(event.time.into()),
+        value: Some(oc::span::time_event::Value::Annotation(
+            oc::span::time_event::Annotation {
+                description: Some(truncatable(event.name.to_string())),
+                attributes: Some(oc::span::Attributes {
+                    attribute_map,
+                    dropped_attributes_count: 0,
+                }),
+            },
+        )),
+    }
+}
+
+fn string_attribute(value: String) -> oc::AttributeValue {
+    oc::AttributeValue {
+        value: Some(oc::attribute_value::Value::StringValue(truncatable(value))),
+    }
+}
+
+/// Root spans, started by the proxy, have no parent.
+fn parent_bytes(id: trace_context::Id) -> Result<Vec<u8>, IdLengthError> {
+    if id.as_ref().is_empty() {
+        return Ok(Vec::new());
+    }
+    into_bytes(id, 8)
+}
+
+/// Splits a W3C `tracestate` list into its `key=value` entries.
+fn tracestate(trace_state: String) -> oc::span::Tracestate {
+    let entries = trace_state
+        .split(',')
+        .filter_map(|member| {
+            let (key, value) = member.trim().split_once('=')?;
+            Some(oc::span::tracestate::Entry {
+                key: key.to_string(),
+                value: value.to_string(),
+            })
+        })
+        .collect();
+    oc::span::Tracestate { entries }
+}
+
 fn truncatable(value: String) -> oc::TruncatableString {
     oc::TruncatableString {
         value,
@@ -12,6 +12,7 @@
 
 pub use drain;
 pub use ipnet::{IpNet, Ipv4Net, Ipv6Net};
+pub use linkerd_access_log as access_log;
 pub use linkerd_addr::{self as addr, Addr, NameAddr};
 pub use linkerd_cache as cache;
 pub use linkerd_conditional::Conditional;
@@ -22,6 +23,7 @@ pub use linkerd_exp_backoff as exp_backoff;
 pub use linkerd_http_metrics as http_metrics;
 pub use linkerd_io as io;
 pub use linkerd_opencensus as opencensus;
+pub use linkerd_opentelemetry as opentelemetry;
 pub use linkerd_service_profiles as profiles;
 pub use linkerd_stack_metrics as stack_metrics;
 pub use linkerd_stack_tracing as stack_tracing;
@@ -63,6 +65,7 @@ pub struct ProxyRuntime {
     pub metrics: metrics::Proxy,
     pub tap: proxy::tap::Registry,
     pub span_sink: http_tracing::OpenCensusSink,
+    pub access_log: Option<access_log::AccessLog>,
     pub drain: drain::Watch,
 }
 
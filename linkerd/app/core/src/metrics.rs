@@ -1,12 +1,13 @@
 pub use crate::transport::labels::{TargetAddr, TlsAccept};
 use crate::{
     classify::{Class, SuccessOrFailure},
-    control, http_metrics, http_metrics as metrics, opencensus, profiles, stack_metrics,
+    control, http_metrics, http_metrics as metrics, opencensus, opentelemetry, profiles,
+    stack_metrics,
     svc::Param,
     telemetry, tls,
     transport::{self, labels::TlsConnect},
 };
-use linkerd_addr::Addr;
+use linkerd_addr::{Addr, NameAddr};
 pub use linkerd_metrics::*;
 use std::{
     fmt::{self, Write},
@@ -30,6 +31,7 @@ pub struct Metrics {
     pub proxy: Proxy,
     pub control: ControlHttp,
     pub opencensus: opencensus::metrics::Registry,
+    pub opentelemetry: opentelemetry::metrics::Registry,
 }
 
 #[derive(Clone, Debug)]
@@ -92,6 +94,7 @@ pub struct StackLabels {
 pub struct RouteLabels {
     direction: Direction,
     addr: profiles::LogicalAddr,
+    backend: Option<NameAddr>,
     labels: Option<String>,
 }
 
@@ -172,11 +175,13 @@ impl Metrics {
         };
 
         let (opencensus, opencensus_report) = opencensus::metrics::new();
+        let (opentelemetry, opentelemetry_report) = opentelemetry::metrics::new();
 
         let metrics = Metrics {
             proxy,
             control,
             opencensus,
+            opentelemetry,
         };
 
         let report = endpoint_report
@@ -186,6 +191,7 @@ impl Metrics {
             .and_report(control_report)
             .and_report(transport_report)
             .and_report(opencensus_report)
+            .and_report(opentelemetry_report)
             .and_report(stack)
             .and_report(process)
             .and_report(build_info);
@@ -222,6 +228,7 @@ impl RouteLabels {
         Self {
             addr,
             labels,
+            backend: None,
             direction: Direction::In,
         }
     }
@@ -231,9 +238,22 @@ impl RouteLabels {
         Self {
             addr,
             labels,
+            backend: None,
             direction: Direction::Out,
         }
     }
+
+    /// Labels an outbound route's requests with the backend to which they are pinned, if any.
+    pub fn outbound_backend(
+        addr: profiles::LogicalAddr,
+        route: &profiles::http::Route,
+        backend: Option<NameAddr>,
+    ) -> Self {
+        Self {
+            backend,
+            ..Self::outbound(addr, route)
+        }
+    }
 }
 
 impl FmtLabels for RouteLabels {
@@ -241,6 +261,10 @@ impl FmtLabels for RouteLabels {
         self.direction.fmt_labels(f)?;
         write!(f, ",dst=\"{}\"", self.addr)?;
 
+        if let Some(backend) = self.backend.as_ref() {
+            write!(f, ",backend=\"{}\"", backend)?;
+        }
+
         if let Some(labels) = self.labels.as_ref() {
             write!(f, ",{}", labels)?;
         }
@@ -7,6 +7,7 @@ mod tests;
 
 use self::gateway::NewGateway;
 use linkerd_app_core::{
+    access_log,
     config::ProxyConfig,
     identity, io, metrics,
     profiles::{self, DiscoveryRejected},
@@ -163,7 +164,7 @@ where
                 .push_spawn_buffer(buffer_capacity),
         )
         .push_cache(cache_max_idle_age)
-        .check_new_service::<NameAddr, I>();
+        .check_new_service::<NameAddr, access_log::TcpIo<I>>();
 
     // Cache an HTTP gateway service for each destination and HTTP version.
     //
@@ -234,6 +235,17 @@ where
                 None => Ok::<_, Infallible>(svc::Either::B(gth)),
             },
             tcp.push_map_target(|(_permit, gth): (_, GatewayTransportHeader)| gth.target)
+                .push(inbound.access_log_tcp(
+                    |(permit, gth): &(policy::Permit, GatewayTransportHeader)| {
+                        access_log::TcpConnection {
+                            orig_dst: Some(gth.client.local_addr.into()),
+                            tls: gth.param(),
+                            server: Some(permit.labels.server.0.clone()),
+                            authz: Some(permit.labels.authz.clone()),
+                            target: Some(gth.target.to_string()),
+                        }
+                    },
+                ))
                 .push(inbound.authorize_tcp())
                 .check_new_service::<GatewayTransportHeader, I>()
                 .push_on_service(svc::BoxService::layer())
@@ -253,8 +265,8 @@ impl Param<http::normalize_uri::DefaultAuthority> for HttpTransportHeader {
     }
 }
 
-impl Param<Option<identity::Name>> for HttpTransportHeader {
-    fn param(&self) -> Option<identity::Name> {
+impl Param<Option<identity::Id>> for HttpTransportHeader {
+    fn param(&self) -> Option<identity::Id> {
         Some(self.client.client_id.clone().0)
     }
 }
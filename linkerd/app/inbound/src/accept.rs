@@ -131,6 +131,8 @@ mod tests {
                     networks: vec![Default::default()],
                     name: "testsaz".into(),
                 }],
+                http_routes: vec![],
+                rate_limit: None,
                 name: "testsrv".into(),
             },
             None,
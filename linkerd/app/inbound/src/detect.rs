@@ -3,7 +3,7 @@ use crate::{
     Inbound,
 };
 use linkerd_app_core::{
-    detect, identity, io,
+    access_log, detect, identity, io,
     proxy::http,
     svc, tls,
     transport::{
@@ -308,6 +308,18 @@ impl svc::Param<transport::labels::Key> for Forward {
     }
 }
 
+impl svc::Param<access_log::TcpConnection> for Forward {
+    fn param(&self) -> access_log::TcpConnection {
+        access_log::TcpConnection {
+            orig_dst: Some(self.orig_dst_addr.into()),
+            tls: self.tls.clone(),
+            server: Some(self.permit.labels.server.0.clone()),
+            authz: Some(self.permit.labels.authz.clone()),
+            target: None,
+        }
+    }
+}
+
 // === impl Tls ===
 
 impl svc::Param<AllowPolicy> for Tls {
@@ -383,8 +395,8 @@ impl svc::Param<http::normalize_uri::DefaultAuthority> for Http {
     }
 }
 
-impl svc::Param<Option<identity::Name>> for Http {
-    fn param(&self) -> Option<identity::Name> {
+impl svc::Param<Option<identity::Id>> for Http {
+    fn param(&self) -> Option<identity::Id> {
         self.tls
             .status
             .value()
@@ -471,6 +483,8 @@ mod tests {
                     networks: vec![client_addr().ip().into()],
                     name: "testsaz".into(),
                 }],
+                http_routes: vec![],
+                rate_limit: None,
                 name: "testsrv".into(),
             },
         );
@@ -1,6 +1,6 @@
 use crate::{policy, Inbound};
 use linkerd_app_core::{
-    identity, io,
+    access_log, identity, io,
     proxy::http,
     svc::{self, ExtractParam, InsertParam, Param},
     tls,
@@ -287,6 +287,21 @@ impl Param<transport::labels::Key> for LocalTcp {
     }
 }
 
+impl Param<access_log::TcpConnection> for LocalTcp {
+    fn param(&self) -> access_log::TcpConnection {
+        access_log::TcpConnection {
+            orig_dst: Some(self.addr.into()),
+            tls: tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+                client_id: Some(self.client_id.clone()),
+                negotiated_protocol: None,
+            }),
+            server: Some(self.permit.labels.server.0.clone()),
+            authz: Some(self.permit.labels.authz.clone()),
+            target: None,
+        }
+    }
+}
+
 // === impl LocalHttp ===
 
 impl Param<Remote<ServerAddr>> for LocalHttp {
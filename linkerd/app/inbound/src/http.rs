@@ -1,9 +1,12 @@
+mod client_limit;
 mod router;
 mod server;
 mod set_identity_header;
 #[cfg(test)]
 mod tests;
 
+pub(crate) use self::client_limit::ClientConcurrencyLimited;
+
 fn trace_labels() -> std::collections::HashMap<String, String> {
     let mut l = std::collections::HashMap::new();
     l.insert("direction".to_string(), "inbound".to_string());
@@ -218,6 +221,8 @@ pub mod fuzz {
                         networks: vec![std::net::IpAddr::from([192, 0, 2, 3]).into()],
                         name: "testsaz".into(),
                     }],
+                    http_routes: vec![],
+                    rate_limit: None,
                     name: "testsrv".into(),
                 },
             );
@@ -237,8 +242,8 @@ pub mod fuzz {
         }
     }
 
-    impl svc::Param<Option<identity::Name>> for Target {
-        fn param(&self) -> Option<identity::Name> {
+    impl svc::Param<Option<identity::Id>> for Target {
+        fn param(&self) -> Option<identity::Id> {
             None
         }
     }
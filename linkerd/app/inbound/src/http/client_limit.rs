@@ -0,0 +1,307 @@
+use futures::{ready, TryFuture};
+use linkerd_app_core::{
+    metrics::ServerLabel,
+    svc::{self, Param},
+    tls,
+    transport::{ClientAddr, Remote},
+    Error,
+};
+use parking_lot::Mutex;
+use pin_project::pin_project;
+use std::{
+    collections::HashMap,
+    future::Future,
+    net::IpAddr,
+    pin::Pin,
+    sync::{Arc, Weak},
+    task::{Context, Poll},
+};
+use thiserror::Error;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+#[derive(Debug, Error)]
+#[error("too many concurrent requests from client")]
+pub struct ClientConcurrencyLimited(());
+
+/// Limits the number of in-flight requests that each client identity may have on each server.
+///
+/// Unlike the proxy's global concurrency limit, requests that exceed a client's limit fail
+/// immediately, so that one client cannot exhaust the in-flight budget shared with other clients.
+/// Clients without an identity are limited by their IP address.
+#[derive(Clone, Debug)]
+pub struct NewClientLimit<N> {
+    inner: N,
+    limits: Option<Limits>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ClientLimit<S> {
+    inner: S,
+    semaphore: Option<Arc<Semaphore>>,
+}
+
+/// Holds the client's permit until the response is received.
+#[pin_project]
+#[derive(Debug)]
+pub struct ResponseFuture<F> {
+    #[pin]
+    inner: Option<F>,
+    permit: Option<OwnedSemaphorePermit>,
+}
+
+type Key = (ServerLabel, Client);
+
+/// Identifies a client by its TLS identity or, when it has none, by its IP address, so that
+/// unauthenticated clients cannot exhaust each other's in-flight budget.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Client {
+    Identity(tls::server::ClientId),
+    Addr(IpAddr),
+}
+
+/// Tracks a semaphore for each client of each server. Semaphores are shared by all of a client's
+/// connections to a server and are dropped once the client has no open connections.
+#[derive(Clone, Debug)]
+struct Limits {
+    max_in_flight_requests: usize,
+    semaphores: Arc<Mutex<HashMap<Key, Weak<Semaphore>>>>,
+}
+
+// === impl NewClientLimit ===
+
+impl<N> NewClientLimit<N> {
+    pub fn layer(
+        max_in_flight_requests: Option<usize>,
+    ) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        let limits = max_in_flight_requests.map(|max_in_flight_requests| Limits {
+            max_in_flight_requests,
+            semaphores: Default::default(),
+        });
+        svc::layer::mk(move |inner| Self {
+            inner,
+            limits: limits.clone(),
+        })
+    }
+}
+
+impl<T, N> svc::NewService<T> for NewClientLimit<N>
+where
+    T: Param<tls::ConditionalServerTls> + Param<ServerLabel> + Param<Remote<ClientAddr>>,
+    N: svc::NewService<T>,
+{
+    type Service = ClientLimit<N::Service>;
+
+    fn new_service(&self, target: T) -> Self::Service {
+        let semaphore = self.limits.as_ref().map(|limits| {
+            let client = match target.param() {
+                tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+                    client_id: Some(id),
+                    ..
+                }) => Client::Identity(id),
+                _ => {
+                    let Remote(ClientAddr(addr)) = target.param();
+                    Client::Addr(addr.ip())
+                }
+            };
+            limits.semaphore((target.param(), client))
+        });
+        ClientLimit {
+            semaphore,
+            inner: self.inner.new_service(target),
+        }
+    }
+}
+
+// === impl Limits ===
+
+impl Limits {
+    fn semaphore(&self, key: Key) -> Arc<Semaphore> {
+        let mut semaphores = self.semaphores.lock();
+        if let Some(semaphore) = semaphores.get(&key).and_then(Weak::upgrade) {
+            return semaphore;
+        }
+
+        // Drop the semaphores of clients that no longer have any connections.
+        semaphores.retain(|_, s| s.strong_count() > 0);
+
+        let semaphore = Arc::new(Semaphore::new(self.max_in_flight_requests));
+        semaphores.insert(key, Arc::downgrade(&semaphore));
+        semaphore
+    }
+}
+
+// === impl ClientLimit ===
+
+impl<Req, S> svc::Service<Req> for ClientLimit<S>
+where
+    S: svc::Service<Req>,
+    S::Error: Into<Error>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = ResponseFuture<S::Future>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        let permit = match self.semaphore.as_ref() {
+            None => None,
+            Some(semaphore) => match semaphore.clone().try_acquire_owned() {
+                Ok(permit) => Some(permit),
+                Err(_) => {
+                    tracing::debug!("Client concurrency limit exceeded");
+                    return ResponseFuture {
+                        inner: None,
+                        permit: None,
+                    };
+                }
+            },
+        };
+
+        ResponseFuture {
+            inner: Some(self.inner.call(req)),
+            permit,
+        }
+    }
+}
+
+// === impl ResponseFuture ===
+
+impl<F> Future for ResponseFuture<F>
+where
+    F: TryFuture,
+    F::Error: Into<Error>,
+{
+    type Output = Result<F::Ok, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let inner = match this.inner.as_pin_mut() {
+            Some(inner) => inner,
+            None => return Poll::Ready(Err(ClientConcurrencyLimited(()).into())),
+        };
+        let rsp = ready!(inner.try_poll(cx)).map_err(Into::into);
+        drop(this.permit.take());
+        Poll::Ready(rsp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::svc::{Layer, NewService, Service, ServiceExt};
+    use std::net::SocketAddr;
+
+    #[derive(Clone, Debug)]
+    struct Target {
+        client_id: Option<&'static str>,
+        client_addr: SocketAddr,
+    }
+
+    impl Target {
+        fn identified(client_id: &'static str) -> Self {
+            Self {
+                client_id: Some(client_id),
+                client_addr: ([192, 0, 2, 1], 40000).into(),
+            }
+        }
+
+        fn unidentified(client_addr: SocketAddr) -> Self {
+            Self {
+                client_id: None,
+                client_addr,
+            }
+        }
+    }
+
+    impl Param<tls::ConditionalServerTls> for Target {
+        fn param(&self) -> tls::ConditionalServerTls {
+            tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+                client_id: self.client_id.map(|id| id.parse().unwrap()),
+                negotiated_protocol: None,
+            })
+        }
+    }
+
+    impl Param<ServerLabel> for Target {
+        fn param(&self) -> ServerLabel {
+            ServerLabel("test".into())
+        }
+    }
+
+    impl Param<Remote<ClientAddr>> for Target {
+        fn param(&self) -> Remote<ClientAddr> {
+            Remote(ClientAddr(self.client_addr))
+        }
+    }
+
+    #[tokio::test]
+    async fn limits_each_client() {
+        const A: &str = "a.ns.serviceaccount.identity.linkerd.cluster.local";
+        const B: &str = "b.ns.serviceaccount.identity.linkerd.cluster.local";
+
+        let new_limit = NewClientLimit::layer(Some(1))
+            .layer(|_: Target| svc::mk(|()| futures::future::ok::<(), Error>(())));
+        let mut a0 = new_limit.new_service(Target::identified(A));
+        let mut a1 = new_limit.new_service(Target::identified(A));
+        let mut b = new_limit.new_service(Target::identified(B));
+
+        // Holds the client's only permit until the response future completes.
+        let held = a0.ready().await.unwrap().call(());
+
+        let error = a1
+            .ready()
+            .await
+            .unwrap()
+            .call(())
+            .await
+            .expect_err("client must be limited");
+        assert!(error.is::<ClientConcurrencyLimited>());
+        b.ready()
+            .await
+            .unwrap()
+            .call(())
+            .await
+            .expect("other clients must not be limited");
+
+        held.await.expect("request must succeed");
+        a1.ready()
+            .await
+            .unwrap()
+            .call(())
+            .await
+            .expect("permit must be released");
+    }
+
+    #[tokio::test]
+    async fn limits_unidentified_clients_by_ip() {
+        let new_limit = NewClientLimit::layer(Some(1))
+            .layer(|_: Target| svc::mk(|()| futures::future::ok::<(), Error>(())));
+        let mut a0 = new_limit.new_service(Target::unidentified(([192, 0, 2, 1], 40000).into()));
+        // Another connection from the same host shares the host's limit.
+        let mut a1 = new_limit.new_service(Target::unidentified(([192, 0, 2, 1], 40001).into()));
+        let mut b = new_limit.new_service(Target::unidentified(([192, 0, 2, 2], 40000).into()));
+
+        let held = a0.ready().await.unwrap().call(());
+
+        let error = a1
+            .ready()
+            .await
+            .unwrap()
+            .call(())
+            .await
+            .expect_err("client must be limited");
+        assert!(error.is::<ClientConcurrencyLimited>());
+        b.ready()
+            .await
+            .unwrap()
+            .call(())
+            .await
+            .expect("other unidentified clients must not be limited");
+
+        held.await.expect("request must succeed");
+    }
+}
@@ -123,12 +123,12 @@ impl<C> Inbound<C> {
                         .http_endpoint
                         .to_layer::<classify::Response, _, _>(),
                 )
+                .push(http_tracing::client(
+                    rt.span_sink.clone(),
+                    super::trace_labels(),
+                ))
                 .push_on_service(
                     svc::layers()
-                        .push(http_tracing::client(
-                            rt.span_sink.clone(),
-                            super::trace_labels(),
-                        ))
                         .push(http::BoxResponse::layer())
                         // This box is needed to reduce compile times on recent
                         // (2021-10-17) nightlies, though this may be fixed by
@@ -158,6 +158,8 @@ impl<C> Inbound<C> {
                         .push_on_service(http::BoxResponse::layer())
                         .push(classify::NewClassify::layer())
                         .push_http_insert_target::<profiles::http::Route>()
+                        // Records the route on each response so that it may be access logged.
+                        .push_http_response_insert_target::<profiles::http::Route>()
                         .push_map_target(|(route, profile)| Route { route, profile })
                         .into_inner(),
                 ))
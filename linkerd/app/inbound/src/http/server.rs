@@ -1,17 +1,18 @@
-use super::set_identity_header::NewSetIdentityHeader;
+use super::{client_limit::NewClientLimit, set_identity_header::NewSetIdentityHeader};
 use crate::Inbound;
 pub use linkerd_app_core::proxy::http::{
     normalize_uri, strip_header, uri, BoxBody, BoxResponse, DetectHttp, Request, Response, Retain,
     Version,
 };
 use linkerd_app_core::{
+    access_log,
     config::{ProxyConfig, ServerConfig},
     errors, http_tracing, io,
     metrics::ServerLabel,
     proxy::http,
     svc::{self, ExtractParam, Param},
     tls,
-    transport::OrigDstAddr,
+    transport::{ClientAddr, OrigDstAddr, Remote},
     Error, Result,
 };
 use tracing::debug_span;
@@ -26,7 +27,8 @@ impl<H> Inbound<H> {
             + Param<http::normalize_uri::DefaultAuthority>
             + Param<tls::ConditionalServerTls>
             + Param<ServerLabel>
-            + Param<OrigDstAddr>,
+            + Param<OrigDstAddr>
+            + Param<Remote<ClientAddr>>,
         T: Clone + Send + Unpin + 'static,
         I: io::AsyncRead + io::AsyncWrite + io::PeerAddr + Send + Unpin + 'static,
         H: svc::NewService<T, Service = HSvc> + Clone + Send + Sync + Unpin + 'static,
@@ -45,6 +47,7 @@ impl<H> Inbound<H> {
                 max_in_flight_requests,
                 ..
             } = config.proxy;
+            let max_in_flight_requests_per_client = config.max_in_flight_requests_per_client;
 
             http.check_new_service::<T, http::Request<_>>()
                 // Convert origin form HTTP/1 URIs to absolute form for Hyper's
@@ -66,6 +69,9 @@ impl<H> Inbound<H> {
                         .push(svc::ConcurrencyLimitLayer::new(max_in_flight_requests))
                         .push(svc::FailFast::layer("HTTP Server", dispatch_timeout)),
                 )
+                // Limit the number of in-flight requests from each client, so that a single client
+                // cannot consume the entire in-flight budget.
+                .push(NewClientLimit::layer(max_in_flight_requests_per_client))
                 .push(rt.metrics.http_errors.to_layer())
                 .push(ServerRescue::layer())
                 .push_on_service(
@@ -78,6 +84,13 @@ impl<H> Inbound<H> {
                         .push(http::normalize_uri::MarkAbsoluteForm::layer())
                         .push(http::BoxResponse::layer()),
                 )
+                // Writes an access log record for each request, if enabled.
+                .push(access_log::NewAccessLog::layer(
+                    access_log::Direction::Inbound,
+                    rt.access_log.clone(),
+                    (),
+                ))
+                .push_on_service(http::BoxResponse::layer())
                 .check_new_service::<T, http::Request<_>>()
                 .instrument(|t: &T| debug_span!("http", v = %Param::<Version>::param(t)))
                 .push(http::NewServeHttp::layer(h2_settings, rt.drain.clone()))
@@ -128,6 +141,12 @@ impl errors::HttpRescue<Error> for ServerRescue {
         if cause.is::<crate::policy::DeniedUnauthorized>() {
             return Ok(errors::SyntheticHttpResponse::permission_denied(cause));
         }
+        if cause.is::<crate::policy::RateLimited>() {
+            return Ok(errors::SyntheticHttpResponse::too_many_requests(cause));
+        }
+        if cause.is::<super::ClientConcurrencyLimited>() {
+            return Ok(errors::SyntheticHttpResponse::too_many_requests(cause));
+        }
         if cause.is::<crate::GatewayDomainInvalid>() {
             return Ok(errors::SyntheticHttpResponse::not_found(cause));
         }
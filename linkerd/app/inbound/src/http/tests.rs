@@ -612,6 +612,8 @@ impl svc::Param<policy::AllowPolicy> for Target {
                     networks: vec![std::net::IpAddr::from([192, 0, 2, 3]).into()],
                     name: "testsaz".into(),
                 }],
+                http_routes: vec![],
+                rate_limit: None,
                 name: "testsrv".into(),
             },
         );
@@ -631,8 +633,8 @@ impl svc::Param<http::normalize_uri::DefaultAuthority> for Target {
     }
 }
 
-impl svc::Param<Option<identity::Name>> for Target {
-    fn param(&self) -> Option<identity::Name> {
+impl svc::Param<Option<identity::Id>> for Target {
+    fn param(&self) -> Option<identity::Id> {
         None
     }
 }
@@ -18,6 +18,7 @@ pub(crate) mod test_util;
 
 pub use self::{metrics::Metrics, policy::DefaultPolicy};
 use linkerd_app_core::{
+    access_log::{self, AccessLog},
     config::{ConnectConfig, ProxyConfig},
     drain,
     http_tracing::OpenCensusSink,
@@ -41,6 +42,9 @@ pub struct Config {
     pub policy: policy::Config,
     pub profile_idle_timeout: Duration,
     pub allowed_ips: transport::AllowIps,
+
+    /// Limits the number of in-flight requests that each client identity may have on each server.
+    pub max_in_flight_requests_per_client: Option<usize>,
 }
 
 #[derive(Clone)]
@@ -56,6 +60,7 @@ struct Runtime {
     identity: identity::creds::Receiver,
     tap: tap::Registry,
     span_sink: OpenCensusSink,
+    access_log: Option<AccessLog>,
     drain: drain::Watch,
 }
 
@@ -103,6 +108,19 @@ impl<S> Inbound<S> {
         policy::NewAuthorizeTcp::layer(self.runtime.metrics.tcp_authz.clone())
     }
 
+    /// Writes an access log record for each connection handled by the inner stack, when access
+    /// logging is enabled.
+    pub fn access_log_tcp<N, P: Clone>(
+        &self,
+        params: P,
+    ) -> impl svc::layer::Layer<N, Service = access_log::NewTcpAccessLog<P, N>> + Clone {
+        access_log::NewTcpAccessLog::layer(
+            access_log::Direction::Inbound,
+            self.runtime.access_log.clone(),
+            params,
+        )
+    }
+
     pub fn into_stack(self) -> svc::Stack<S> {
         self.stack
     }
@@ -132,6 +150,7 @@ impl Inbound<()> {
             identity: runtime.identity,
             tap: runtime.tap,
             span_sink: runtime.span_sink,
+            access_log: runtime.access_log,
             drain: runtime.drain,
         };
         Self {
@@ -13,6 +13,9 @@ metrics! {
     inbound_http_authz_deny_total: Counter {
         "The total number of inbound HTTP requests that could not be processed due to a proxy error."
     },
+    inbound_http_rate_limit_total: Counter {
+        "The total number of inbound HTTP requests that were refused because a server's rate limit was exceeded"
+    },
 
     inbound_tcp_authz_allow_total: Counter {
         "The total number of inbound TCP connections that were authorized"
@@ -35,6 +38,7 @@ pub(crate) struct TcpAuthzMetrics(Arc<TcpInner>);
 struct HttpInner {
     allow: Mutex<HashMap<AuthzKey, Counter>>,
     deny: Mutex<HashMap<SrvKey, Counter>>,
+    rate_limit: Mutex<HashMap<SrvKey, Counter>>,
 }
 
 #[derive(Debug, Default)]
@@ -78,6 +82,15 @@ impl HttpAuthzMetrics {
             .or_default()
             .incr();
     }
+
+    pub fn rate_limit(&self, policy: &AllowPolicy, tls: tls::ConditionalServerTls) {
+        self.0
+            .rate_limit
+            .lock()
+            .entry(SrvKey::new(policy, tls))
+            .or_default()
+            .incr();
+    }
 }
 
 impl FmtMetrics for HttpAuthzMetrics {
@@ -107,6 +120,19 @@ impl FmtMetrics for HttpAuthzMetrics {
         }
         drop(deny);
 
+        let rate_limit = self.0.rate_limit.lock();
+        if !rate_limit.is_empty() {
+            inbound_http_rate_limit_total.fmt_help(f)?;
+            inbound_http_rate_limit_total.fmt_scopes(
+                f,
+                rate_limit
+                    .iter()
+                    .map(|(k, c)| ((k.target, (&k.server, TlsAccept(&k.tls))), c)),
+                |c| c,
+            )?;
+        }
+        drop(rate_limit);
+
         Ok(())
     }
 }
@@ -3,7 +3,8 @@ mod tcp;
 
 pub(crate) use self::{http::HttpErrorMetrics, tcp::TcpErrorMetrics};
 use crate::{
-    policy::{DeniedUnauthorized, DeniedUnknownPort},
+    http::ClientConcurrencyLimited,
+    policy::{DeniedUnauthorized, DeniedUnknownPort, RateLimited},
     GatewayDomainInvalid, GatewayIdentityRequired, GatewayLoop,
 };
 use linkerd_app_core::{errors::FailFastError, metrics::FmtLabels, tls};
@@ -12,6 +13,7 @@ use std::fmt;
 /// Inbound proxy error types.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 enum ErrorKind {
+    ClientConcurrencyLimit,
     DeniedUnknown,
     FailFast,
     GatewayDomainInvalid,
@@ -29,6 +31,11 @@ impl ErrorKind {
         if err.is::<DeniedUnauthorized>() {
             // Unauthorized metrics are tracked separately.and are not considered to be errors.
             None
+        } else if err.is::<RateLimited>() {
+            // Rate limited requests are also tracked separately.
+            None
+        } else if err.is::<ClientConcurrencyLimited>() {
+            Some(ErrorKind::ClientConcurrencyLimit)
         } else if err.is::<DeniedUnknownPort>() {
             Some(ErrorKind::DeniedUnknown)
         } else if err.is::<FailFastError>() {
@@ -57,6 +64,7 @@ impl FmtLabels for ErrorKind {
             f,
             "error=\"{}\"",
             match self {
+                ErrorKind::ClientConcurrencyLimit => "client concurrency limit",
                 ErrorKind::DeniedUnknown => "unknown port denied",
                 ErrorKind::FailFast => "failfast",
                 ErrorKind::TlsDetectTimeout => "tls detection timeout",
@@ -2,13 +2,15 @@ mod authorize;
 mod config;
 pub mod defaults;
 mod discover;
+mod rate_limit;
 mod store;
 #[cfg(test)]
 mod tests;
 
 pub use self::authorize::{NewAuthorizeHttp, NewAuthorizeTcp};
 pub use self::config::Config;
-pub(crate) use self::store::Store;
+pub use self::{rate_limit::RateLimited, store::Store};
+pub(crate) use self::rate_limit::RateLimiter;
 
 pub use linkerd_app_core::metrics::{AuthzLabels, ServerLabel};
 use linkerd_app_core::{
@@ -16,7 +18,9 @@ use linkerd_app_core::{
     transport::{ClientAddr, OrigDstAddr, Remote},
     Result,
 };
-pub use linkerd_server_policy::{Authentication, Authorization, Protocol, ServerPolicy, Suffix};
+pub use linkerd_server_policy::{
+    http, Authentication, Authorization, Protocol, RateLimit, ServerPolicy, Suffix,
+};
 use thiserror::Error;
 use tokio::sync::watch;
 
@@ -45,6 +49,7 @@ pub enum DefaultPolicy {
 pub struct AllowPolicy {
     dst: OrigDstAddr,
     server: watch::Receiver<ServerPolicy>,
+    rate_limiter: RateLimiter,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -70,6 +75,8 @@ impl From<DefaultPolicy> for ServerPolicy {
             DefaultPolicy::Deny => ServerPolicy {
                 protocol: Protocol::Opaque,
                 authorizations: vec![],
+                http_routes: vec![],
+                rate_limit: None,
                 name: "default:deny".into(),
             },
         }
@@ -85,7 +92,11 @@ impl AllowPolicy {
         server: ServerPolicy,
     ) -> (Self, watch::Sender<ServerPolicy>) {
         let (tx, server) = watch::channel(server);
-        let p = Self { dst, server };
+        let p = Self {
+            dst,
+            server,
+            rate_limiter: RateLimiter::default(),
+        };
         (p, tx)
     }
 
@@ -120,38 +131,8 @@ impl AllowPolicy {
     ) -> Result<Permit, DeniedUnauthorized> {
         let server = self.server.borrow();
         for authz in server.authorizations.iter() {
-            if authz.networks.iter().any(|n| n.contains(&client_addr.ip())) {
-                match authz.authentication {
-                    Authentication::Unauthenticated => {
-                        return Ok(Permit::new(self.dst, &*server, authz));
-                    }
-
-                    Authentication::TlsUnauthenticated => {
-                        if let tls::ConditionalServerTls::Some(tls::ServerTls::Established {
-                            ..
-                        }) = tls
-                        {
-                            return Ok(Permit::new(self.dst, &*server, authz));
-                        }
-                    }
-
-                    Authentication::TlsAuthenticated {
-                        ref identities,
-                        ref suffixes,
-                    } => {
-                        if let tls::ConditionalServerTls::Some(tls::ServerTls::Established {
-                            client_id: Some(tls::server::ClientId(ref id)),
-                            ..
-                        }) = tls
-                        {
-                            if identities.contains(id.as_str())
-                                || suffixes.iter().any(|s| s.contains(id.as_str()))
-                            {
-                                return Ok(Permit::new(self.dst, &*server, authz));
-                            }
-                        }
-                    }
-                }
+            if is_authorized(authz, client_addr, tls) {
+                return Ok(Permit::new(self.dst, &*server, authz));
             }
         }
 
@@ -159,6 +140,93 @@ impl AllowPolicy {
             server: server.name.clone(),
         })
     }
+
+    /// Checks whether the destination port's `AllowPolicy` is authorized to accept the given
+    /// request.
+    ///
+    /// If the server has an HTTP route that matches the request, the route's authorizations are
+    /// used. Otherwise, the server's authorizations apply.
+    pub(crate) fn check_http_authorized<B>(
+        &self,
+        client_addr: Remote<ClientAddr>,
+        tls: &tls::ConditionalServerTls,
+        req: &::http::Request<B>,
+    ) -> Result<Permit, DeniedUnauthorized> {
+        let server = self.server.borrow();
+        let authorizations = match http::find(&*server.http_routes, req) {
+            Some(route) => {
+                tracing::trace!(route = %route.name, "Matched HTTP route");
+                &*route.authorizations
+            }
+            None => &*server.authorizations,
+        };
+        for authz in authorizations.iter() {
+            if is_authorized(authz, client_addr, tls) {
+                return Ok(Permit::new(self.dst, &*server, authz));
+            }
+        }
+
+        Err(DeniedUnauthorized {
+            server: server.name.clone(),
+        })
+    }
+
+    /// Checks whether the server's rate limit permits another request from the given client.
+    pub(crate) fn check_rate_limit(
+        &self,
+        tls: &tls::ConditionalServerTls,
+    ) -> Result<(), RateLimited> {
+        let server = self.server.borrow();
+        let limit = match server.rate_limit {
+            Some(limit) => limit,
+            None => return Ok(()),
+        };
+        if self.rate_limiter.acquire(limit, tls) {
+            return Ok(());
+        }
+
+        Err(RateLimited {
+            server: server.name.clone(),
+        })
+    }
+}
+
+fn is_authorized(
+    authz: &Authorization,
+    client_addr: Remote<ClientAddr>,
+    tls: &tls::ConditionalServerTls,
+) -> bool {
+    if !authz.networks.iter().any(|n| n.contains(&client_addr.ip())) {
+        return false;
+    }
+
+    match authz.authentication {
+        Authentication::Unauthenticated => true,
+
+        Authentication::TlsUnauthenticated => {
+            matches!(
+                tls,
+                tls::ConditionalServerTls::Some(tls::ServerTls::Established { .. })
+            )
+        }
+
+        Authentication::TlsAuthenticated {
+            ref identities,
+            ref suffixes,
+        } => match tls {
+            tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+                client_id: Some(tls::server::ClientId(ref id)),
+                ..
+            }) => {
+                identities.contains(id.as_str())
+                    || suffixes.iter().any(|s| match id.dns_name() {
+                        Some(name) => s.contains(name.as_str()),
+                        None => s.is_any(),
+                    })
+            }
+            _ => false,
+        },
+    }
 }
 
 // === impl Permit ===
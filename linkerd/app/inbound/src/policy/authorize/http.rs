@@ -12,6 +12,10 @@ use std::task;
 
 /// A middleware that enforces policy on each HTTP request.
 ///
+/// When the server's policy includes HTTP routes, the request is authorized against the first route
+/// that matches it; otherwise the server's authorizations apply. Authorized requests are then
+/// subject to the server's rate limit, if it has one.
+///
 /// This enforcement is done lazily on each request so that policy updates are honored as the
 /// connection progresses.
 ///
@@ -70,17 +74,17 @@ where
 
 // === impl AuthorizeHttp ===
 
-impl<Req, T, N, S> svc::Service<Req> for AuthorizeHttp<T, N>
+impl<B, T, N, S> svc::Service<http::Request<B>> for AuthorizeHttp<T, N>
 where
     T: Clone,
     N: svc::NewService<(Permit, T), Service = S>,
-    S: svc::Service<Req>,
+    S: svc::Service<http::Request<B>>,
     S::Error: Into<Error>,
 {
     type Response = S::Response;
     type Error = Error;
     type Future = future::Either<
-        future::ErrInto<svc::stack::Oneshot<S, Req>, Error>,
+        future::ErrInto<svc::stack::Oneshot<S, http::Request<B>>, Error>,
         future::Ready<Result<Self::Response, Error>>,
     >;
 
@@ -89,10 +93,24 @@ where
         task::Poll::Ready(Ok(()))
     }
 
-    fn call(&mut self, req: Req) -> Self::Future {
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
         tracing::trace!(policy = ?self.policy, "Authorizing request");
-        match self.policy.check_authorized(self.client_addr, &self.tls) {
+        match self
+            .policy
+            .check_http_authorized(self.client_addr, &self.tls, &req)
+        {
             Ok(permit) => {
+                if let Err(e) = self.policy.check_rate_limit(&self.tls) {
+                    tracing::info!(
+                        server = %self.policy.server_label(),
+                        tls = ?self.tls,
+                        client = %self.client_addr,
+                        "Request rate limited",
+                    );
+                    self.metrics.rate_limit(&self.policy, self.tls.clone());
+                    return future::Either::Right(future::err(e.into()));
+                }
+
                 tracing::debug!(
                     ?permit,
                     tls = ?self.tls,
@@ -1,5 +1,5 @@
 use linkerd_app_core::{IpNet, Ipv4Net, Ipv6Net};
-use linkerd_server_policy::{Authentication, Authorization, Protocol, ServerPolicy, Suffix};
+use linkerd_server_policy::{http, Authentication, Authorization, Protocol, ServerPolicy, Suffix};
 use std::time::Duration;
 
 pub fn all_authenticated(timeout: Duration) -> ServerPolicy {
@@ -53,6 +53,31 @@ pub fn all_mtls_unauthenticated(timeout: Duration) -> ServerPolicy {
     )
 }
 
+/// Returns an HTTP route that only authorizes clients with the given identities to access paths
+/// with the given prefix.
+pub fn identity_route(
+    prefix: impl Into<String>,
+    identities: impl IntoIterator<Item = String>,
+) -> http::Route {
+    let prefix = prefix.into();
+    let name = format!("default:route:{}", prefix);
+    http::Route {
+        matches: vec![http::RequestMatch {
+            path: Some(http::PathMatch::Prefix(prefix)),
+            ..Default::default()
+        }],
+        authorizations: vec![Authorization {
+            networks: all_nets().map(Into::into).collect(),
+            authentication: Authentication::TlsAuthenticated {
+                identities: identities.into_iter().collect(),
+                suffixes: vec![],
+            },
+            name: name.clone().into(),
+        }],
+        name: name.into(),
+    }
+}
+
 fn all_nets() -> impl Iterator<Item = IpNet> {
     vec![Ipv4Net::default().into(), Ipv6Net::default().into()].into_iter()
 }
@@ -77,6 +102,8 @@ fn mk(
             authentication,
             name: name.into(),
         }],
+        http_routes: vec![],
+        rate_limit: None,
         name: name.into(),
     }
 }
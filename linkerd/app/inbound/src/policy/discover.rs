@@ -202,6 +202,11 @@ fn to_policy(proto: api::Server) -> Result<ServerPolicy> {
     Ok(ServerPolicy {
         protocol,
         authorizations,
+        // The inbound API does not yet describe HTTP routes, so all requests are authorized with
+        // the server's authorizations.
+        http_routes: vec![],
+        // Nor does it describe rate limits.
+        rate_limit: None,
         name,
     })
 }
@@ -0,0 +1,162 @@
+use linkerd_app_core::tls;
+use linkerd_server_policy::RateLimit;
+use parking_lot::Mutex;
+use std::{collections::HashMap, num::NonZeroU32, sync::Arc};
+use thiserror::Error;
+use tokio::time::Instant;
+
+/// The number of per-identity buckets that may be tracked. Once this many are tracked, the least
+/// recently used bucket is dropped to track a new identity.
+const MAX_IDENTITY_BUCKETS: usize = 1_000;
+
+#[derive(Clone, Debug, Error)]
+#[error("request rate limit exceeded on server {server}")]
+pub struct RateLimited {
+    pub(super) server: Arc<str>,
+}
+
+/// Tracks the token buckets that limit the rate of requests to a server.
+///
+/// Buckets are shared by all connections to a server and are reset when the server's rate limit
+/// changes.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RateLimiter(Arc<Mutex<State>>);
+
+#[derive(Debug, Default)]
+struct State {
+    limit: Option<RateLimit>,
+    total: Option<Bucket>,
+    identities: HashMap<Option<tls::server::ClientId>, Bucket>,
+}
+
+/// A token bucket that holds up to one second's worth of requests.
+#[derive(Debug)]
+struct Bucket {
+    rate: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+// === impl RateLimiter ===
+
+impl RateLimiter {
+    /// Takes a token for the request from each of the server's buckets, returning false if any of
+    /// the buckets is empty.
+    pub(super) fn acquire(&self, limit: RateLimit, tls: &tls::ConditionalServerTls) -> bool {
+        let now = Instant::now();
+        let mut state = self.0.lock();
+        if state.limit != Some(limit) {
+            *state = State {
+                limit: Some(limit),
+                total: limit.total.map(|rate| Bucket::new(rate, now)),
+                identities: HashMap::default(),
+            };
+        }
+        let State {
+            ref mut total,
+            ref mut identities,
+            ..
+        } = *state;
+
+        let identity = match limit.identity {
+            Some(rate) => {
+                let client_id = match tls {
+                    tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+                        client_id,
+                        ..
+                    }) => client_id.clone(),
+                    _ => None,
+                };
+                if !identities.contains_key(&client_id) && identities.len() >= MAX_IDENTITY_BUCKETS
+                {
+                    let oldest = identities
+                        .iter()
+                        .min_by_key(|(_, bucket)| bucket.updated)
+                        .map(|(id, _)| id.clone());
+                    if let Some(id) = oldest {
+                        identities.remove(&id);
+                    }
+                }
+                Some(
+                    identities
+                        .entry(client_id)
+                        .or_insert_with(|| Bucket::new(rate, now)),
+                )
+            }
+            None => None,
+        };
+
+        // Only take tokens once all buckets are known to have one available.
+        let mut buckets = total.iter_mut().chain(identity).collect::<Vec<_>>();
+        for bucket in buckets.iter_mut() {
+            bucket.refill(now);
+        }
+        if buckets.iter().any(|b| b.tokens < 1.0) {
+            return false;
+        }
+        for bucket in buckets {
+            bucket.tokens -= 1.0;
+        }
+        true
+    }
+}
+
+// === impl Bucket ===
+
+impl Bucket {
+    fn new(rate: NonZeroU32, now: Instant) -> Self {
+        let rate = rate.get() as f64;
+        Self {
+            rate,
+            tokens: rate,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.updated = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time;
+
+    fn client(n: usize) -> tls::ConditionalServerTls {
+        let id = format!("sa{}.ns.serviceaccount.identity.linkerd.cluster.local", n);
+        tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+            client_id: Some(tls::ClientId(id.parse().unwrap())),
+            negotiated_protocol: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn evicts_least_recently_used_identities() {
+        time::pause();
+        let limit = RateLimit {
+            total: None,
+            identity: NonZeroU32::new(1),
+        };
+        let limiter = RateLimiter::default();
+        for n in 0..MAX_IDENTITY_BUCKETS {
+            assert!(limiter.acquire(limit, &client(n)));
+            time::advance(time::Duration::from_micros(100)).await;
+        }
+
+        // None of the buckets has refilled, but the first client's is the least recently used.
+        assert!(limiter.acquire(limit, &client(MAX_IDENTITY_BUCKETS)));
+        assert_eq!(limiter.0.lock().identities.len(), MAX_IDENTITY_BUCKETS);
+        assert!(
+            limiter.acquire(limit, &client(0)),
+            "the evicted client must get a new bucket"
+        );
+        assert!(
+            !limiter.acquire(limit, &client(2)),
+            "other clients must keep their buckets"
+        );
+        assert_eq!(limiter.0.lock().identities.len(), MAX_IDENTITY_BUCKETS);
+    }
+}
@@ -1,6 +1,7 @@
-use super::{discover, AllowPolicy, CheckPolicy, DefaultPolicy, DeniedUnknownPort};
+use super::{discover, AllowPolicy, CheckPolicy, DefaultPolicy, DeniedUnknownPort, RateLimiter};
 use linkerd_app_core::{proxy::http, transport::OrigDstAddr, Error, Result};
 pub use linkerd_server_policy::{Authentication, Authorization, Protocol, ServerPolicy, Suffix};
+use parking_lot::Mutex;
 use std::{
     collections::{HashMap, HashSet},
     hash::{BuildHasherDefault, Hasher},
@@ -12,13 +13,30 @@ use tracing::info_span;
 #[derive(Clone, Debug)]
 pub struct Store {
     // When None, the default policy is 'deny'.
-    default: Option<Rx>,
-    ports: Arc<PortMap<Rx>>,
+    default: Option<DefaultServer>,
+    ports: Arc<PortMap<Server>>,
 }
 
 type Tx = watch::Sender<ServerPolicy>;
 type Rx = watch::Receiver<ServerPolicy>;
 
+/// A server's policy and the rate limits shared by all of its connections.
+#[derive(Clone, Debug)]
+struct Server {
+    rx: Rx,
+    rate_limiter: RateLimiter,
+}
+
+/// The policy for ports that are not explicitly configured.
+///
+/// Though these ports share a policy, each port is a distinct server, so each port's connections
+/// share rate limits that are separate from those of other ports.
+#[derive(Clone, Debug)]
+struct DefaultServer {
+    rx: Rx,
+    rate_limiters: Arc<Mutex<PortMap<RateLimiter>>>,
+}
+
 /// A `HashMap` optimized for lookups by port number.
 type PortMap<T> = HashMap<u16, T, BuildHasherDefault<PortHasher>>;
 
@@ -32,14 +50,17 @@ struct PortHasher(u16);
 // === impl Store ===
 
 impl Store {
-    fn mk_default(default: DefaultPolicy) -> Option<(Tx, Rx)> {
+    fn mk_default(default: DefaultPolicy) -> Option<(Tx, DefaultServer)> {
         match default {
             DefaultPolicy::Deny => None,
-            DefaultPolicy::Allow(sp) => Some(watch::channel(sp)),
+            DefaultPolicy::Allow(sp) => {
+                let (tx, rx) = watch::channel(sp);
+                Some((tx, DefaultServer::new(rx)))
+            }
         }
     }
 
-    pub(crate) fn fixed(
+    pub fn fixed(
         default: impl Into<DefaultPolicy>,
         ports: impl IntoIterator<Item = (u16, ServerPolicy)>,
     ) -> (Self, Option<Tx>) {
@@ -50,12 +71,12 @@ impl Store {
                 // safe to discard the sender, as the receiver will continue to let us
                 // borrow/clone each fixed policy.
                 let (_, rx) = watch::channel(s);
-                (p, rx)
+                (p, Server::new(rx))
             })
             .collect();
 
         let (default_tx, default) = match Self::mk_default(default.into()) {
-            Some((tx, rx)) => (Some(tx), Some(rx)),
+            Some((tx, server)) => (Some(tx), Some(server)),
             None => (None, None),
         };
 
@@ -90,16 +111,16 @@ impl Store {
                 let default = default.clone();
                 let rx = info_span!("watch", %port)
                     .in_scope(|| discover.spawn_with_init(port, default.into()));
-                (port, rx)
+                (port, Server::new(rx))
             })
             .collect::<PortMap<_>>();
 
         let default = match Self::mk_default(default) {
-            Some((tx, rx)) => {
+            Some((tx, server)) => {
                 tokio::spawn(async move {
                     tx.closed().await;
                 });
-                Some(rx)
+                Some(server)
             }
             None => None,
         };
@@ -109,6 +130,25 @@ impl Store {
             ports: Arc::new(rxs),
         }
     }
+
+    /// Returns the current policy of each explicitly configured port, ordered by port.
+    pub fn port_policies(&self) -> Vec<(u16, ServerPolicy)> {
+        let mut policies = self
+            .ports
+            .iter()
+            .map(|(port, server)| (*port, server.rx.borrow().clone()))
+            .collect::<Vec<_>>();
+        policies.sort_by_key(|(port, _)| *port);
+        policies
+    }
+
+    /// Returns the policy used for ports that are not explicitly configured, or `None` if
+    /// connections on these ports are denied.
+    pub fn default_policy(&self) -> Option<ServerPolicy> {
+        self.default
+            .as_ref()
+            .map(|server| server.rx.borrow().clone())
+    }
 }
 
 impl CheckPolicy for Store {
@@ -119,17 +159,52 @@ impl CheckPolicy for Store {
     /// is returned that can be used to check whether the connection is permitted via
     /// [`AllowPolicy::check_authorized`].
     fn check_policy(&self, dst: OrigDstAddr) -> Result<AllowPolicy, DeniedUnknownPort> {
-        let server = self
+        let Server { rx, rate_limiter } = self
             .ports
             .get(&dst.port())
             .cloned()
             .map(Ok)
             .unwrap_or_else(|| match &self.default {
-                Some(rx) => Ok(rx.clone()),
+                Some(default) => Ok(default.server(dst.port())),
                 None => Err(DeniedUnknownPort(dst.port())),
             })?;
 
-        Ok(AllowPolicy { dst, server })
+        Ok(AllowPolicy {
+            dst,
+            server: rx,
+            rate_limiter,
+        })
+    }
+}
+
+// === impl Server ===
+
+impl Server {
+    fn new(rx: Rx) -> Self {
+        Self {
+            rx,
+            rate_limiter: RateLimiter::default(),
+        }
+    }
+}
+
+// === impl DefaultServer ===
+
+impl DefaultServer {
+    fn new(rx: Rx) -> Self {
+        Self {
+            rx,
+            rate_limiters: Default::default(),
+        }
+    }
+
+    /// Returns the default server for the given port.
+    fn server(&self, port: u16) -> Server {
+        let rate_limiter = self.rate_limiters.lock().entry(port).or_default().clone();
+        Server {
+            rx: self.rx.clone(),
+            rate_limiter,
+        }
     }
 }
 
@@ -1,6 +1,8 @@
 use super::*;
-use linkerd_server_policy::{Authentication, Authorization, Protocol, ServerPolicy, Suffix};
-use std::collections::HashSet;
+use linkerd_server_policy::{
+    http, Authentication, Authorization, Protocol, RateLimit, ServerPolicy, Suffix,
+};
+use std::{collections::HashSet, num::NonZeroU32};
 
 #[test]
 fn unauthenticated_allowed() {
@@ -11,6 +13,8 @@ fn unauthenticated_allowed() {
             networks: vec!["192.0.2.0/24".parse().unwrap()],
             name: "unauth".into(),
         }],
+        http_routes: vec![],
+        rate_limit: None,
         name: "test".into(),
     };
 
@@ -49,6 +53,8 @@ fn authenticated_identity() {
             networks: vec!["192.0.2.0/24".parse().unwrap()],
             name: "tls-auth".into(),
         }],
+        http_routes: vec![],
+        rate_limit: None,
         name: "test".into(),
     };
 
@@ -102,6 +108,8 @@ fn authenticated_suffix() {
             networks: vec!["192.0.2.0/24".parse().unwrap()],
             name: "tls-auth".into(),
         }],
+        http_routes: vec![],
+        rate_limit: None,
         name: "test".into(),
     };
 
@@ -142,6 +150,67 @@ fn authenticated_suffix() {
         .expect_err("policy must require a client identity");
 }
 
+#[test]
+fn authenticated_spiffe_id() {
+    const SPIFFE_ID: &str = "spiffe://example.org/ns/testns/sa/testsa";
+    let policy = ServerPolicy {
+        protocol: Protocol::Opaque,
+        authorizations: vec![
+            Authorization {
+                authentication: Authentication::TlsAuthenticated {
+                    identities: vec![SPIFFE_ID.to_string()].into_iter().collect(),
+                    suffixes: vec![],
+                },
+                networks: vec!["192.0.2.0/24".parse().unwrap()],
+                name: "tls-spiffe".into(),
+            },
+            Authorization {
+                authentication: Authentication::TlsAuthenticated {
+                    identities: HashSet::default(),
+                    suffixes: vec![Suffix::from(vec!["local".into()])],
+                },
+                networks: vec!["192.0.2.0/24".parse().unwrap()],
+                name: "tls-suffix".into(),
+            },
+        ],
+        http_routes: vec![],
+        rate_limit: None,
+        name: "test".into(),
+    };
+
+    let (policies, _tx) = Store::fixed(policy.clone(), None);
+    let allowed = policies
+        .check_policy(orig_dst_addr())
+        .expect("port must be known");
+
+    let tls = tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+        client_id: Some(SPIFFE_ID.parse().unwrap()),
+        negotiated_protocol: None,
+    });
+    assert_eq!(
+        allowed
+            .check_authorized(client_addr(), &tls)
+            .expect("SPIFFE identity must be permitted"),
+        Permit {
+            dst: orig_dst_addr(),
+            protocol: policy.protocol,
+            labels: AuthzLabels {
+                server: ServerLabel("test".into()),
+                authz: "tls-spiffe".into(),
+            }
+        }
+    );
+
+    // DNS suffixes must not match SPIFFE IDs, even if the ID's path ends with the suffix.
+    let tls = tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+        client_id: Some("spiffe://example.org/ns/other.local".parse().unwrap()),
+        negotiated_protocol: None,
+    });
+    allowed
+        .check_authorized(client_addr(), &tls)
+        .expect_err("policy must require a permitted SPIFFE identity");
+}
+
 #[test]
 fn tls_unauthenticated() {
     let policy = ServerPolicy {
@@ -151,6 +220,8 @@ fn tls_unauthenticated() {
             networks: vec!["192.0.2.0/24".parse().unwrap()],
             name: "tls-unauth".into(),
         }],
+        http_routes: vec![],
+        rate_limit: None,
         name: "test".into(),
     };
 
@@ -188,6 +259,183 @@ fn tls_unauthenticated() {
         .expect_err("policy must require a TLS termination identity");
 }
 
+#[test]
+fn http_route_authorized() {
+    let metrics_authz = Authorization {
+        authentication: Authentication::TlsAuthenticated {
+            identities: vec![client_id().to_string()].into_iter().collect(),
+            suffixes: vec![],
+        },
+        networks: vec!["192.0.2.0/24".parse().unwrap()],
+        name: "metrics-auth".into(),
+    };
+    let policy = ServerPolicy {
+        protocol: Protocol::Http1,
+        authorizations: vec![Authorization {
+            authentication: Authentication::Unauthenticated,
+            networks: vec!["192.0.2.0/24".parse().unwrap()],
+            name: "unauth".into(),
+        }],
+        http_routes: vec![http::Route {
+            matches: vec![http::RequestMatch {
+                path: Some(http::PathMatch::Prefix("/metrics".into())),
+                ..Default::default()
+            }],
+            authorizations: vec![metrics_authz],
+            name: "metrics".into(),
+        }],
+        name: "test".into(),
+    };
+
+    let (policies, _tx) = Store::fixed(policy.clone(), None);
+    let allowed = policies
+        .check_policy(orig_dst_addr())
+        .expect("port must be known");
+
+    let metrics = ::http::Request::get("/metrics").body(()).unwrap();
+    let other = ::http::Request::get("/api").body(()).unwrap();
+
+    let tls = tls::ConditionalServerTls::None(tls::NoServerTls::NoClientHello);
+    assert_eq!(
+        allowed
+            .check_http_authorized(client_addr(), &tls, &other)
+            .expect("unmatched requests must use the server's authorizations"),
+        Permit {
+            dst: orig_dst_addr(),
+            protocol: policy.protocol,
+            labels: AuthzLabels {
+                server: ServerLabel("test".into()),
+                authz: "unauth".into(),
+            }
+        }
+    );
+    allowed
+        .check_http_authorized(client_addr(), &tls, &metrics)
+        .expect_err("route must require a client identity");
+
+    let tls = tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+        client_id: Some(client_id()),
+        negotiated_protocol: None,
+    });
+    assert_eq!(
+        allowed
+            .check_http_authorized(client_addr(), &tls, &metrics)
+            .expect("authenticated request must be permitted"),
+        Permit {
+            dst: orig_dst_addr(),
+            protocol: policy.protocol,
+            labels: AuthzLabels {
+                server: ServerLabel("test".into()),
+                authz: "metrics-auth".into(),
+            }
+        }
+    );
+}
+
+#[test]
+fn rate_limited() {
+    let policy = ServerPolicy {
+        protocol: Protocol::Http1,
+        authorizations: vec![Authorization {
+            authentication: Authentication::Unauthenticated,
+            networks: vec!["192.0.2.0/24".parse().unwrap()],
+            name: "unauth".into(),
+        }],
+        http_routes: vec![],
+        rate_limit: Some(RateLimit {
+            total: NonZeroU32::new(3),
+            identity: NonZeroU32::new(2),
+        }),
+        name: "test".into(),
+    };
+
+    let (policies, _tx) = Store::fixed(policy, None);
+    let allowed = policies
+        .check_policy(orig_dst_addr())
+        .expect("port must be known");
+
+    let client = tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+        client_id: Some(client_id()),
+        negotiated_protocol: None,
+    });
+    let other = tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+        client_id: Some(tls::ClientId(
+            "othersa.testns.serviceaccount.identity.linkerd.cluster.local"
+                .parse()
+                .unwrap(),
+        )),
+        negotiated_protocol: None,
+    });
+
+    // Each identity is limited separately...
+    for _ in 0..2 {
+        allowed
+            .check_rate_limit(&client)
+            .expect("request must be permitted");
+    }
+    allowed
+        .check_rate_limit(&client)
+        .expect_err("client must be rate limited");
+    allowed
+        .check_rate_limit(&other)
+        .expect("other clients must not be rate limited");
+
+    // ...though all clients share the server's limit. Connections to the same server share limits.
+    let allowed = policies
+        .check_policy(orig_dst_addr())
+        .expect("port must be known");
+    allowed
+        .check_rate_limit(&other)
+        .expect_err("server must be rate limited");
+}
+
+#[test]
+fn default_rate_limited_per_port() {
+    let policy = ServerPolicy {
+        protocol: Protocol::Http1,
+        authorizations: vec![Authorization {
+            authentication: Authentication::Unauthenticated,
+            networks: vec!["192.0.2.0/24".parse().unwrap()],
+            name: "unauth".into(),
+        }],
+        http_routes: vec![],
+        rate_limit: Some(RateLimit {
+            total: NonZeroU32::new(1),
+            identity: None,
+        }),
+        name: "test".into(),
+    };
+
+    // No ports are configured, so all ports use the default policy.
+    let (policies, _tx) = Store::fixed(policy, None);
+    let tls = tls::ConditionalServerTls::None(tls::NoServerTls::NoClientHello);
+
+    let allowed = policies
+        .check_policy(orig_dst_addr())
+        .expect("port must be known");
+    allowed
+        .check_rate_limit(&tls)
+        .expect("request must be permitted");
+    allowed
+        .check_rate_limit(&tls)
+        .expect_err("port must be rate limited");
+
+    // Connections to the same port share its limit...
+    policies
+        .check_policy(orig_dst_addr())
+        .expect("port must be known")
+        .check_rate_limit(&tls)
+        .expect_err("port must be rate limited");
+
+    // ...but other ports are limited separately.
+    let other = OrigDstAddr(([192, 0, 2, 2], 2000).into());
+    policies
+        .check_policy(other)
+        .expect("port must be known")
+        .check_rate_limit(&tls)
+        .expect("other ports must not be rate limited");
+}
+
 fn client_id() -> tls::ClientId {
     "testsa.testns.serviceaccount.identity.linkerd.cluster.local"
         .parse()
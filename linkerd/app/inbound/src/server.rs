@@ -56,6 +56,7 @@ impl Inbound<()> {
             .push_tcp_forward()
             .into_stack()
             .push_map_target(TcpEndpoint::from_param)
+            .push(self.access_log_tcp(()))
             .instrument(|_: &_| debug_span!("tcp"))
             .into_inner();
 
@@ -74,7 +75,10 @@ impl Inbound<()> {
             self.clone()
                 .into_tcp_connect(addr.port())
                 .push_tcp_forward()
-                .map_stack(|_, _, s| s.push_map_target(TcpEndpoint::from_param))
+                .map_stack(|_, _, s| {
+                    s.push_map_target(TcpEndpoint::from_param)
+                        .push(self.access_log_tcp(()))
+                })
                 .push_direct(policies.clone(), gateway, http)
                 .into_stack()
                 .instrument(|_: &_| debug_span!("direct"))
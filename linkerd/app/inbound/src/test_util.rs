@@ -60,6 +60,8 @@ pub fn default_config() -> Config {
                     networks: vec![Default::default()],
                     name: "testsaz".into(),
                 }],
+                http_routes: vec![],
+                rate_limit: None,
                 name: "testsrv".into(),
             }
             .into(),
@@ -67,6 +69,7 @@ pub fn default_config() -> Config {
         },
         profile_idle_timeout: Duration::from_millis(500),
         allowed_ips: Default::default(),
+        max_in_flight_requests_per_client: None,
     }
 }
 
@@ -79,6 +82,7 @@ pub fn runtime() -> (ProxyRuntime, drain::Signal) {
         metrics: metrics.proxy,
         tap,
         span_sink: None,
+        access_log: None,
         drain,
     };
     (runtime, drain_tx)
@@ -1,4 +1,4 @@
-use crate::{tcp, Outbound};
+use crate::{state::NewTrackProfile, tcp, Outbound};
 use linkerd_app_core::{
     io, profiles,
     svc::{self, stack::Param},
@@ -38,6 +38,7 @@ impl<N> Outbound<N> {
         self.map_stack(|config, rt, accept| {
             let allow = config.allow_discovery.clone();
             accept
+                .push(NewTrackProfile::layer(rt.state.clone()))
                 .push(profiles::discover::layer(
                     profiles,
                     move |a: tcp::Accept| {
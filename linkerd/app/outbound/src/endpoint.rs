@@ -157,9 +157,8 @@ impl FromMetadata {
         metadata
             .identity()
             .cloned()
-            .map(move |server_id| {
+            .map(move |client_tls| {
                 Conditional::Some(tls::ClientTls {
-                    server_id,
                     alpn: if use_transport_header {
                         Some(tls::client::AlpnProtocols(vec![
                             transport_header::PROTOCOL.into()
@@ -167,6 +166,7 @@ impl FromMetadata {
                     } else {
                         None
                     },
+                    ..client_tls
                 })
             })
             .unwrap_or(Conditional::None(reason))
@@ -1,12 +1,18 @@
+mod backend;
 pub mod detect;
 mod endpoint;
+mod hash;
+mod hedge;
+mod locality;
 pub mod logical;
+mod outlier;
 mod proxy_connection_close;
 mod require_id_header;
 mod retry;
 mod server;
 mod strip_proxy_error;
 
+pub use self::outlier::OutlierEjection;
 use self::{
     proxy_connection_close::ProxyConnectionClose, require_id_header::NewRequireIdentity,
     strip_proxy_error::NewStripProxyError,
@@ -0,0 +1,326 @@
+use super::{Logical, Route};
+use linkerd_app_core::{
+    metrics,
+    profiles::{self, http::BackendOverride, split::PinnedBackend},
+    proxy::http,
+    svc::{self, layer, stack::Oneshot, NewService, Param, ServiceExt},
+    Error, NameAddr,
+};
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+/// A route's requests that are pinned to the same backend of the logical service's traffic
+/// split, or that are distributed by the split's weights when no backend is set.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(super) struct RouteBackend {
+    route: Route,
+    backend: Option<NameAddr>,
+}
+
+/// Builds services that pin each request to a backend of the logical service's traffic split.
+///
+/// The overrides configured on the proxy apply after those of the request's route, if any.
+/// Discovered routes currently have no overrides of their own, since the destination API cannot
+/// describe them; route-level overrides only take effect once the API supports them.
+#[derive(Clone, Debug)]
+pub(super) struct NewPinBackend<N> {
+    overrides: Arc<[BackendOverride]>,
+    inner: N,
+}
+
+/// Determines the backend to which each request is pinned and records it as a `PinnedBackend`
+/// extension, so that the traffic split dispatches the request to that backend.
+///
+/// Route services are built for each backend when the route is built, so that the actual
+/// metrics of each backend's requests are recorded separately.
+#[derive(Clone, Debug)]
+pub(super) struct PinBackend<S> {
+    overrides: Vec<BackendOverride>,
+    profile: profiles::Receiver,
+    unpinned: S,
+    pinned: HashMap<NameAddr, S>,
+}
+
+// === impl NewPinBackend ===
+
+impl<N> NewPinBackend<N> {
+    pub(super) fn layer(
+        overrides: Vec<BackendOverride>,
+    ) -> impl layer::Layer<N, Service = Self> + Clone {
+        let overrides = Arc::<[BackendOverride]>::from(overrides);
+        layer::mk(move |inner| Self {
+            overrides: overrides.clone(),
+            inner,
+        })
+    }
+}
+
+impl<N> NewService<Logical> for NewPinBackend<N>
+where
+    N: NewService<Logical>,
+{
+    type Service = PinBackend<N::Service>;
+
+    fn new_service(&self, logical: Logical) -> Self::Service {
+        // Requests that match no route share a single service, regardless of their backend.
+        PinBackend {
+            overrides: self.overrides.to_vec(),
+            profile: logical.profile.clone(),
+            unpinned: self.inner.new_service(logical),
+            pinned: HashMap::default(),
+        }
+    }
+}
+
+impl<N> NewService<Route> for NewPinBackend<N>
+where
+    N: NewService<RouteBackend>,
+{
+    type Service = PinBackend<N::Service>;
+
+    fn new_service(&self, route: Route) -> Self::Service {
+        let overrides = route
+            .route
+            .backend_overrides()
+            .iter()
+            .chain(self.overrides.iter())
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let mut pinned = HashMap::with_capacity(overrides.len());
+        for o in overrides.iter() {
+            if !pinned.contains_key(o.backend()) {
+                let svc = self.inner.new_service(RouteBackend {
+                    route: route.clone(),
+                    backend: Some(o.backend().clone()),
+                });
+                pinned.insert(o.backend().clone(), svc);
+            }
+        }
+
+        PinBackend {
+            overrides,
+            profile: route.logical.profile.clone(),
+            pinned,
+            unpinned: self.inner.new_service(RouteBackend {
+                route,
+                backend: None,
+            }),
+        }
+    }
+}
+
+// === impl PinBackend ===
+
+impl<S> PinBackend<S> {
+    fn backend<B>(&self, req: &http::Request<B>) -> Option<&NameAddr> {
+        // Requests are only pinned to backends that are part of the current traffic split.
+        // Otherwise, they are distributed by the split's weights.
+        self.overrides
+            .iter()
+            .find(|o| o.is_match(req))
+            .map(BackendOverride::backend)
+            .filter(|addr| self.profile.has_target(addr))
+    }
+}
+
+impl<B, S> svc::Service<http::Request<B>> for PinBackend<S>
+where
+    S: svc::Service<http::Request<B>> + Clone,
+    S::Error: Into<Error>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = futures::future::ErrInto<Oneshot<S, http::Request<B>>, Error>;
+
+    #[inline]
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, mut req: http::Request<B>) -> Self::Future {
+        use futures::TryFutureExt;
+
+        let backend = self.backend(&req).cloned();
+        let svc = backend
+            .as_ref()
+            .and_then(|addr| self.pinned.get(addr))
+            .unwrap_or(&self.unpinned)
+            .clone();
+        tracing::trace!(?backend, "Pinning request");
+        req.extensions_mut().insert(PinnedBackend(backend));
+        svc.oneshot(req).err_into::<Error>()
+    }
+}
+
+// === impl RouteBackend ===
+
+impl Param<Logical> for RouteBackend {
+    fn param(&self) -> Logical {
+        self.route.logical.clone()
+    }
+}
+
+impl Param<metrics::RouteLabels> for RouteBackend {
+    fn param(&self) -> metrics::RouteLabels {
+        metrics::RouteLabels::outbound_backend(
+            self.route.logical.logical_addr.clone(),
+            &self.route.route,
+            self.backend.clone(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::profiles::{
+        http::{BackendMatch, ValueMatch},
+        LogicalAddr, Profile, Target,
+    };
+    use std::str::FromStr;
+
+    fn canary() -> NameAddr {
+        NameAddr::from_str("web-canary.ns.svc.cluster.local:8080").unwrap()
+    }
+
+    fn stable() -> NameAddr {
+        NameAddr::from_str("web-stable.ns.svc.cluster.local:8080").unwrap()
+    }
+
+    fn canary_override() -> BackendOverride {
+        BackendOverride::new(
+            BackendMatch::Header(
+                http::HeaderName::from_static("x-canary"),
+                ValueMatch::Exact("true".into()),
+            ),
+            canary(),
+        )
+    }
+
+    fn logical(targets: Vec<Target>) -> Logical {
+        let logical_addr =
+            LogicalAddr(NameAddr::from_str("web.ns.svc.cluster.local:8080").unwrap());
+        let (_tx, rx) = tokio::sync::watch::channel(Profile {
+            addr: Some(logical_addr.clone()),
+            targets,
+            ..Profile::default()
+        });
+        Logical {
+            profile: rx.into(),
+            logical_addr,
+            protocol: http::Version::H2,
+        }
+    }
+
+    /// Builds a route service whose inner services respond with the backend of their target and
+    /// of the request's `PinnedBackend` extension.
+    fn route_service(
+        targets: Vec<Target>,
+        proxy_overrides: Vec<BackendOverride>,
+    ) -> impl svc::Service<
+        http::Request<()>,
+        Response = (Option<NameAddr>, Option<NameAddr>),
+        Error = Error,
+    > + Clone {
+        let mut route = profiles::http::Route::new(std::iter::empty(), Vec::new());
+        route.set_backend_overrides(vec![canary_override()]);
+        let route = Route {
+            route,
+            logical: logical(targets),
+        };
+
+        let inner = |RouteBackend { backend, .. }: RouteBackend| {
+            svc::mk(move |req: http::Request<()>| {
+                let PinnedBackend(pinned) =
+                    req.extensions().get::<PinnedBackend>().cloned().unwrap();
+                futures::future::ok::<_, Error>((backend.clone(), pinned))
+            })
+        };
+        svc::Layer::layer(&NewPinBackend::layer(proxy_overrides), inner).new_service(route)
+    }
+
+    fn canary_request() -> http::Request<()> {
+        http::Request::builder()
+            .header("x-canary", "true")
+            .body(())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn pins_matching_requests() {
+        let svc = route_service(
+            vec![
+                Target {
+                    addr: stable(),
+                    weight: 100,
+                },
+                Target {
+                    addr: canary(),
+                    weight: 1,
+                },
+            ],
+            vec![],
+        );
+
+        let rsp = svc.clone().oneshot(canary_request()).await.unwrap();
+        assert_eq!(rsp, (Some(canary()), Some(canary())));
+
+        let rsp = svc.oneshot(http::Request::new(())).await.unwrap();
+        assert_eq!(rsp, (None, None));
+    }
+
+    #[tokio::test]
+    async fn ignores_backends_outside_split() {
+        let svc = route_service(
+            vec![Target {
+                addr: stable(),
+                weight: 100,
+            }],
+            vec![],
+        );
+
+        let rsp = svc.oneshot(canary_request()).await.unwrap();
+        assert_eq!(rsp, (None, None));
+    }
+
+    #[tokio::test]
+    async fn applies_proxy_overrides() {
+        let svc = route_service(
+            vec![
+                Target {
+                    addr: stable(),
+                    weight: 100,
+                },
+                Target {
+                    addr: canary(),
+                    weight: 1,
+                },
+            ],
+            vec![BackendOverride::new(
+                BackendMatch::Cookie("track".into(), ValueMatch::Exact("stable".into())),
+                stable(),
+            )],
+        );
+
+        let req = http::Request::builder()
+            .header("cookie", "track=stable")
+            .body(())
+            .unwrap();
+        let rsp = svc.clone().oneshot(req).await.unwrap();
+        assert_eq!(rsp, (Some(stable()), Some(stable())));
+
+        // The route's overrides take precedence. Discovered routes cannot set overrides until the
+        // destination API describes them, so this route's are set directly.
+        let req = http::Request::builder()
+            .header("x-canary", "true")
+            .header("cookie", "track=stable")
+            .body(())
+            .unwrap();
+        let rsp = svc.oneshot(req).await.unwrap();
+        assert_eq!(rsp, (Some(canary()), Some(canary())));
+    }
+}
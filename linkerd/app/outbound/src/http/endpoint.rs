@@ -70,7 +70,7 @@ impl<C> Outbound<C> {
                         .http_endpoint
                         .to_layer::<classify::Response, _, _>(),
                 )
-                .push_on_service(http_tracing::client(
+                .push(http_tracing::client(
                     rt.span_sink.clone(),
                     crate::trace_labels(),
                 ))
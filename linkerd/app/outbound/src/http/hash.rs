@@ -0,0 +1,122 @@
+use super::Concrete;
+use linkerd_app_core::{
+    profiles::{self, http::HashKey},
+    proxy::http::{
+        self,
+        balance::{HashRequest, StableHasher},
+        ClientHandle,
+    },
+    svc::Param,
+};
+use std::{hash::Hasher, net::IpAddr};
+
+/// Hashes requests by the key configured on a service's profile, so that they may be balanced
+/// over a consistent hash ring.
+#[derive(Clone, Debug)]
+pub struct RequestHash(HashKey);
+
+// === impl RequestHash ===
+
+impl From<HashKey> for RequestHash {
+    fn from(key: HashKey) -> Self {
+        Self(key)
+    }
+}
+
+impl<B> HashRequest<http::Request<B>> for RequestHash {
+    fn hash_request(&self, req: &http::Request<B>) -> Option<u64> {
+        // Raw bytes are hashed with a stable hasher so that every proxy maps a request to the
+        // same point on the ring.
+        let mut hasher = StableHasher::new();
+        match &self.0 {
+            HashKey::Header(name) => hasher.write(req.headers().get(name)?.as_bytes()),
+            HashKey::Cookie(name) => hasher.write(cookie(req.headers(), name)?.as_bytes()),
+            HashKey::ClientAddr => match req.extensions().get::<ClientHandle>()?.addr.ip() {
+                IpAddr::V4(ip) => hasher.write(&ip.octets()),
+                IpAddr::V6(ip) => hasher.write(&ip.octets()),
+            },
+        }
+        Some(hasher.finish())
+    }
+}
+
+fn cookie<'h>(headers: &'h http::header::HeaderMap, name: &str) -> Option<&'h str> {
+    headers
+        .get_all(http::header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .find_map(|pair| {
+            let (k, v) = pair.trim().split_once('=')?;
+            if k == name {
+                Some(v)
+            } else {
+                None
+            }
+        })
+}
+
+// === impl Concrete ===
+
+impl Param<Option<RequestHash>> for Concrete {
+    fn param(&self) -> Option<RequestHash> {
+        // The balancer is chosen when it is built, so later profile updates that change the load
+        // balancer do not apply until the balancer is rebuilt.
+        match self.logical.profile.load_balancer()? {
+            profiles::LoadBalancer::ConsistentHash(key) => Some(RequestHash(key)),
+            profiles::LoadBalancer::PeakEwma | profiles::LoadBalancer::LeastConnections => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(key: HashKey, req: &http::Request<()>) -> Option<u64> {
+        RequestHash(key).hash_request(req)
+    }
+
+    #[test]
+    fn hashes_cookie() {
+        let req = http::Request::builder()
+            .header("cookie", "a=1; session=abc")
+            .body(())
+            .unwrap();
+        let other = http::Request::builder()
+            .header("cookie", "session=abc")
+            .body(())
+            .unwrap();
+        let key = HashKey::Cookie("session".into());
+        assert!(hash(key.clone(), &req).is_some());
+        assert_eq!(hash(key.clone(), &req), hash(key, &other));
+        assert_eq!(hash(HashKey::Cookie("missing".into()), &req), None);
+    }
+
+    #[test]
+    fn hashes_header() {
+        let key = HashKey::Header(http::HeaderName::from_static("x-user"));
+        let a = http::Request::builder()
+            .header("x-user", "a")
+            .body(())
+            .unwrap();
+        let b = http::Request::builder()
+            .header("x-user", "b")
+            .body(())
+            .unwrap();
+        assert_ne!(hash(key.clone(), &a), hash(key.clone(), &b));
+        assert_eq!(hash(key, &http::Request::new(())), None);
+    }
+
+    #[test]
+    fn hashes_are_stable() {
+        // Every proxy must place a request at the same point on the ring, so the hash of a
+        // given value must never change.
+        let key = HashKey::Header(http::HeaderName::from_static("x-user"));
+        let req = http::Request::builder()
+            .header("x-user", "a")
+            .body(())
+            .unwrap();
+        assert_eq!(hash(key, &req), Some(0x8e11_bcca_4562_ca02));
+    }
+}
@@ -0,0 +1,575 @@
+use super::Route;
+use crate::metrics::hedge::{self as metrics, Hedges};
+use bytes::{Buf, Bytes};
+use futures::{
+    future::{self, BoxFuture, Either},
+    ready, FutureExt,
+};
+use linkerd_app_core::{
+    classify,
+    proxy::http::{self, balance::DistinctEndpoints, ClientHandle, HttpBody},
+    svc::{self, layer, Param, ServiceExt},
+    Error,
+};
+use parking_lot::Mutex;
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    sync::{Arc, Weak},
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::time;
+
+pub fn layer<N>(
+    delay: Option<Duration>,
+    max_buffered_bytes: usize,
+    metrics: Hedges,
+) -> impl layer::Layer<N, Service = NewHedge<N>> + Clone {
+    layer::mk(move |inner| NewHedge {
+        inner,
+        delay,
+        max_buffered_bytes,
+        metrics: metrics.clone(),
+    })
+}
+
+/// Builds services that hedge requests on retryable routes when a hedge delay is configured.
+///
+/// Requests are hedged after a fixed delay. Delays based on a latency percentile are out of
+/// scope, since the proxy does not track the latency distribution of each route.
+#[derive(Clone, Debug)]
+pub struct NewHedge<N> {
+    inner: N,
+    delay: Option<Duration>,
+    max_buffered_bytes: usize,
+    metrics: Hedges,
+}
+
+/// Issues a second copy of a request if no response has been received after the hedge delay,
+/// returning whichever response arrives first and dropping (and thereby cancelling) the other.
+///
+/// Both copies of the request are dispatched through the same logical stack. They share
+/// `DistinctEndpoints`, so that the balancer sends the hedged copy to a different endpoint than
+/// the original request.
+///
+/// The original request's body is recorded as it is sent, up to the retry buffer limit, so that
+/// the hedged copy may replay it. (A `ReplayBody` cannot be used, since its clones may not be read
+/// concurrently.) Requests whose bodies are larger than the limit, or that have not been sent
+/// completely when the delay elapses, are not hedged.
+#[derive(Clone, Debug)]
+pub struct Hedge<S> {
+    inner: S,
+    hedge: Option<Params>,
+}
+
+#[derive(Clone, Debug)]
+struct Params {
+    delay: Duration,
+    max_buffered_bytes: usize,
+    metrics: Arc<metrics::Route>,
+}
+
+/// Records a request's body as it is read, so that a copy of the request may be sent with the
+/// same body.
+///
+/// Recording stops once the hedge no longer needs the recording.
+struct RecordBody {
+    inner: http::BoxBody,
+    recording: Weak<Mutex<Recording>>,
+    max_buffered_bytes: usize,
+}
+
+#[derive(Debug, Default)]
+struct Recording {
+    data: Vec<Bytes>,
+    buffered: usize,
+    trailers: Option<http::HeaderMap>,
+    /// Set when the body has been read completely.
+    complete: bool,
+    /// Set when the body exceeded the buffer limit or failed, so that it cannot be replayed.
+    capped: bool,
+}
+
+/// A body replayed from a complete recording.
+struct ReplayedBody {
+    data: VecDeque<Bytes>,
+    trailers: Option<http::HeaderMap>,
+}
+
+// === impl NewHedge ===
+
+impl<N> svc::NewService<Route> for NewHedge<N>
+where
+    N: svc::NewService<Route>,
+{
+    type Service = Hedge<N::Service>;
+
+    fn new_service(&self, route: Route) -> Self::Service {
+        // Only routes that the profile marks as retryable may have their requests sent more than
+        // once.
+        let hedge = match self.delay {
+            Some(delay) if route.route.retries().is_some() => Some(Params {
+                delay,
+                max_buffered_bytes: self.max_buffered_bytes,
+                metrics: self.metrics.route(route.param()),
+            }),
+            _ => None,
+        };
+        Hedge {
+            inner: self.inner.new_service(route),
+            hedge,
+        }
+    }
+}
+
+// === impl Hedge ===
+
+impl<S, B> svc::Service<http::Request<http::BoxBody>> for Hedge<S>
+where
+    S: svc::Service<http::Request<http::BoxBody>, Response = http::Response<B>, Error = Error>,
+    S: Clone + Send + 'static,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = http::Response<B>;
+    type Error = Error;
+    type Future = Either<S::Future, BoxFuture<'static, Result<http::Response<B>, Error>>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<http::BoxBody>) -> Self::Future {
+        let Params {
+            delay,
+            max_buffered_bytes,
+            metrics,
+        } = match self.hedge.as_ref() {
+            Some(params) => params.clone(),
+            None => return Either::Left(self.inner.call(req)),
+        };
+
+        let distinct = DistinctEndpoints::default();
+        let mut hedge = clone_head(&req);
+        hedge.extensions_mut().insert(distinct.clone());
+        let (mut req, recording) = record(req, max_buffered_bytes);
+        req.extensions_mut().insert(distinct);
+
+        let primary = self.inner.call(req);
+        let sleep = Box::pin(time::sleep(delay));
+        let inner = self.inner.clone();
+        Either::Right(
+            async move {
+                let primary = match future::select(primary.boxed(), sleep).await {
+                    Either::Left((rsp, _)) => return rsp,
+                    Either::Right(((), primary)) => primary,
+                };
+
+                let body = recording.lock().replay();
+                let body = match body {
+                    Some(body) => body,
+                    None => {
+                        tracing::debug!("Request body cannot be replayed; not hedging");
+                        return primary.await;
+                    }
+                };
+                // The original request's body is no longer recorded.
+                drop(recording);
+
+                tracing::debug!(?delay, "Hedging request");
+                metrics.hedge();
+                let hedge = inner.oneshot(hedge.map(|()| body)).boxed();
+                match future::select(primary, hedge).await {
+                    Either::Left((Ok(rsp), _)) => Ok(rsp),
+                    Either::Right((Ok(rsp), _)) => {
+                        metrics.win();
+                        Ok(rsp)
+                    }
+                    // If the first request to complete failed, wait for the other.
+                    Either::Left((Err(error), hedge)) => {
+                        tracing::debug!(%error, "Original request failed");
+                        let rsp = hedge.await?;
+                        metrics.win();
+                        Ok(rsp)
+                    }
+                    Either::Right((Err(error), primary)) => {
+                        tracing::debug!(%error, "Hedged request failed");
+                        primary.await
+                    }
+                }
+            }
+            .boxed(),
+        )
+    }
+}
+
+/// Wraps the request's body so that it is recorded as it is read.
+fn record(
+    req: http::Request<http::BoxBody>,
+    max_buffered_bytes: usize,
+) -> (http::Request<http::BoxBody>, Arc<Mutex<Recording>>) {
+    if req.body().is_end_stream() {
+        let recording = Recording {
+            complete: true,
+            ..Recording::default()
+        };
+        return (req, Arc::new(Mutex::new(recording)));
+    }
+
+    let recording = Arc::new(Mutex::new(Recording::default()));
+    let req = req.map(|inner| {
+        http::BoxBody::new(RecordBody {
+            inner,
+            recording: Arc::downgrade(&recording),
+            max_buffered_bytes,
+        })
+    });
+    (req, recording)
+}
+
+/// Copies the request's head, so that the hedged copy may be sent with a replayed body.
+fn clone_head(req: &http::Request<http::BoxBody>) -> http::Request<()> {
+    let mut clone = http::Request::new(());
+    *clone.method_mut() = req.method().clone();
+    *clone.uri_mut() = req.uri().clone();
+    *clone.headers_mut() = req.headers().clone();
+    *clone.version_mut() = req.version();
+
+    // The HTTP server sets a ClientHandle with the client's address and a means to close the
+    // server-side connection.
+    if let Some(client_handle) = req.extensions().get::<ClientHandle>().cloned() {
+        clone.extensions_mut().insert(client_handle);
+    }
+
+    // Ensure that the hedged request's response is classified like the original's.
+    if let Some(classify) = req.extensions().get::<classify::Response>().cloned() {
+        clone.extensions_mut().insert(classify);
+    }
+
+    clone
+}
+
+// === impl Recording ===
+
+impl Recording {
+    fn record(&mut self, data: &Bytes, max_buffered_bytes: usize) {
+        if self.capped {
+            return;
+        }
+        self.buffered += data.len();
+        if self.buffered > max_buffered_bytes {
+            tracing::debug!(
+                buffered = self.buffered,
+                max = max_buffered_bytes,
+                "Request body is too large to replay"
+            );
+            self.capped = true;
+            self.data = Vec::new();
+            return;
+        }
+        self.data.push(data.clone());
+    }
+
+    /// Returns a copy of the recorded body, if it was read completely.
+    fn replay(&self) -> Option<http::BoxBody> {
+        if !self.complete || self.capped {
+            return None;
+        }
+        Some(http::BoxBody::new(ReplayedBody {
+            data: self.data.iter().cloned().collect(),
+            trailers: self.trailers.clone(),
+        }))
+    }
+}
+
+// === impl RecordBody ===
+
+impl RecordBody {
+    fn recording(&self, f: impl FnOnce(&mut Recording)) {
+        if let Some(recording) = self.recording.upgrade() {
+            f(&mut *recording.lock());
+        }
+    }
+}
+
+impl HttpBody for RecordBody {
+    type Data = Bytes;
+    type Error = Error;
+
+    fn poll_data(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Error>>> {
+        let this = self.get_mut();
+        let max_buffered_bytes = this.max_buffered_bytes;
+        match ready!(Pin::new(&mut this.inner).poll_data(cx)) {
+            Some(Ok(mut data)) => {
+                let data = data.copy_to_bytes(data.remaining());
+                this.recording(|r| r.record(&data, max_buffered_bytes));
+                Poll::Ready(Some(Ok(data)))
+            }
+            Some(Err(error)) => {
+                this.recording(|r| r.capped = true);
+                Poll::Ready(Some(Err(error)))
+            }
+            None => {
+                // If trailers follow the data, the body is complete once they are read.
+                if this.inner.is_end_stream() {
+                    this.recording(|r| r.complete = true);
+                }
+                Poll::Ready(None)
+            }
+        }
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::HeaderMap>, Error>> {
+        let this = self.get_mut();
+        match ready!(Pin::new(&mut this.inner).poll_trailers(cx)) {
+            Ok(trailers) => {
+                this.recording(|r| {
+                    r.trailers = trailers.clone();
+                    r.complete = true;
+                });
+                Poll::Ready(Ok(trailers))
+            }
+            Err(error) => {
+                this.recording(|r| r.capped = true);
+                Poll::Ready(Err(error))
+            }
+        }
+    }
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+// === impl ReplayedBody ===
+
+impl HttpBody for ReplayedBody {
+    type Data = Bytes;
+    type Error = Error;
+
+    fn poll_data(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Result<Bytes, Error>>> {
+        Poll::Ready(self.get_mut().data.pop_front().map(Ok))
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::HeaderMap>, Error>> {
+        Poll::Ready(Ok(self.get_mut().trailers.take()))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.data.is_empty() && self.trailers.is_none()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        let len = self.data.iter().map(|d| d.len() as u64).sum();
+        http_body::SizeHint::with_exact(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::{proxy::http::balance::DistinctRequest, svc::Service};
+    use std::{
+        net::SocketAddr,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    /// Builds a service that reads each request's body and then responds after the respective
+    /// delay, identifying the call that produced each response with an `x-call` header and
+    /// echoing the request body.
+    fn delayed(
+        delays: Vec<Duration>,
+    ) -> (
+        impl svc::Service<
+                http::Request<http::BoxBody>,
+                Response = http::Response<Bytes>,
+                Error = Error,
+                Future = impl Send,
+            > + Clone
+            + Send
+            + 'static,
+        Arc<AtomicUsize>,
+    ) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let delays = Arc::new(delays);
+        let svc = svc::mk({
+            let calls = calls.clone();
+            move |req: http::Request<http::BoxBody>| {
+                let call = calls.fetch_add(1, Ordering::SeqCst);
+                let delay = delays[call];
+                async move {
+                    let body = hyper::body::to_bytes(req.into_body()).await?;
+                    time::sleep(delay).await;
+                    Ok::<_, Error>(
+                        http::Response::builder()
+                            .header("x-call", call)
+                            .body(body)
+                            .unwrap(),
+                    )
+                }
+            }
+        });
+        (svc, calls)
+    }
+
+    fn hedge<S>(inner: S, delay: Duration) -> (Hedge<S>, Arc<metrics::Route>) {
+        let metrics = Arc::new(metrics::Route::default());
+        let hedge = Hedge {
+            inner,
+            hedge: Some(Params {
+                delay,
+                max_buffered_bytes: 1024,
+                metrics: metrics.clone(),
+            }),
+        };
+        (hedge, metrics)
+    }
+
+    fn get() -> http::Request<http::BoxBody> {
+        http::Request::get("http://example.com/")
+            .body(http::BoxBody::default())
+            .unwrap()
+    }
+
+    fn post(body: &'static str) -> http::Request<http::BoxBody> {
+        http::Request::post("http://example.com/")
+            .body(http::BoxBody::new(hyper::Body::from(body)))
+            .unwrap()
+    }
+
+    fn call_of<B>(rsp: &http::Response<B>) -> &str {
+        rsp.headers().get("x-call").unwrap().to_str().unwrap()
+    }
+
+    #[tokio::test]
+    async fn hedges_after_delay() {
+        time::pause();
+        let (inner, calls) = delayed(vec![Duration::from_secs(10), Duration::from_secs(1)]);
+        let (mut hedge, _metrics) = hedge(inner, Duration::from_secs(1));
+
+        let start = time::Instant::now();
+        let rsp = hedge.call(get());
+        time::sleep(Duration::from_millis(999)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1, "must wait for the delay");
+
+        let rsp = rsp.await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(call_of(&rsp), "1", "the hedge must respond first");
+        assert_eq!(time::Instant::now() - start, Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn does_not_hedge_fast_responses() {
+        time::pause();
+        let (inner, calls) = delayed(vec![Duration::from_millis(500)]);
+        let (mut hedge, _metrics) = hedge(inner, Duration::from_secs(1));
+
+        let rsp = hedge.call(get()).await.unwrap();
+        assert_eq!(call_of(&rsp), "0");
+        time::sleep(Duration::from_secs(10)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn replays_request_bodies() {
+        time::pause();
+        let (inner, calls) = delayed(vec![Duration::from_secs(10), Duration::from_secs(1)]);
+        let (mut hedge, _metrics) = hedge(inner, Duration::from_secs(1));
+
+        let rsp = hedge.call(post("hello")).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(call_of(&rsp), "1", "the hedge must respond first");
+        assert_eq!(rsp.body(), "hello", "the hedge must replay the body");
+    }
+
+    #[tokio::test]
+    async fn does_not_hedge_bodies_over_limit() {
+        time::pause();
+        let (inner, calls) = delayed(vec![Duration::from_secs(10), Duration::from_secs(1)]);
+        let (mut hedge, _metrics) = hedge(inner, Duration::from_secs(1));
+        if let Some(params) = hedge.hedge.as_mut() {
+            params.max_buffered_bytes = 4;
+        }
+
+        let rsp = hedge.call(post("hello")).await.unwrap();
+        assert_eq!(call_of(&rsp), "0");
+        assert_eq!(rsp.body(), "hello");
+        assert_eq!(
+            calls.load(Ordering::SeqCst),
+            1,
+            "the body must not be replayed"
+        );
+    }
+
+    #[tokio::test]
+    async fn returns_first_response() {
+        time::pause();
+        let (inner, calls) = delayed(vec![Duration::from_secs(3), Duration::from_secs(10)]);
+        let (mut hedge, metrics) = hedge(inner, Duration::from_secs(1));
+
+        let start = time::Instant::now();
+        let rsp = hedge.call(get()).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(
+            call_of(&rsp),
+            "0",
+            "the original request must respond first"
+        );
+        assert_eq!(time::Instant::now() - start, Duration::from_secs(3));
+        assert_eq!(metrics.hedges(), 1.0);
+        assert_eq!(metrics.wins(), 0.0);
+
+        let (inner, _) = delayed(vec![Duration::from_secs(10), Duration::from_secs(1)]);
+        let (mut hedge, metrics) = self::hedge(inner, Duration::from_secs(1));
+        let rsp = hedge.call(get()).await.unwrap();
+        assert_eq!(call_of(&rsp), "1", "the hedge must respond first");
+        assert_eq!(metrics.hedges(), 1.0);
+        assert_eq!(metrics.wins(), 1.0);
+    }
+
+    #[tokio::test]
+    async fn hedges_to_distinct_endpoints() {
+        time::pause();
+        let addr = SocketAddr::from(([10, 0, 0, 1], 8080));
+        let calls = Arc::new(AtomicUsize::new(0));
+        let inner = svc::mk({
+            let calls = calls.clone();
+            move |req: http::Request<http::BoxBody>| {
+                let call = calls.fetch_add(1, Ordering::SeqCst);
+                // Stands in for the balancer, which records the endpoint to which each copy of
+                // the request is dispatched.
+                if call == 0 {
+                    assert!(req.permits(&addr));
+                    req.dispatched(&addr);
+                } else {
+                    assert!(
+                        !req.permits(&addr),
+                        "the hedge must not be sent to the original request's endpoint"
+                    );
+                }
+                async move {
+                    time::sleep(Duration::from_secs(10)).await;
+                    Ok::<_, Error>(http::Response::new(()))
+                }
+            }
+        });
+        let (mut hedge, _metrics) = hedge(inner, Duration::from_secs(1));
+
+        hedge.call(get()).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,82 @@
+use crate::endpoint::Endpoint;
+use linkerd_app_core::{
+    proxy::http::balance::{Locality, Localized},
+    svc::{self, layer},
+};
+use std::sync::Arc;
+
+/// The endpoint label that describes the zone in which an endpoint runs.
+const ZONE_LABEL: &str = "topology.kubernetes.io/zone";
+
+/// Annotates endpoint services with whether the endpoint is in the proxy's zone.
+#[derive(Clone, Debug)]
+pub struct NewLocalized<N> {
+    inner: N,
+    local_zone: Option<Arc<str>>,
+}
+
+// === impl NewLocalized ===
+
+impl<N> NewLocalized<N> {
+    pub fn layer(local_zone: Option<Arc<str>>) -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(move |inner| Self {
+            inner,
+            local_zone: local_zone.clone(),
+        })
+    }
+}
+
+/// Endpoints are built with their balancer's outlier ejections.
+impl<N, E, P> svc::NewService<(E, Endpoint<P>)> for NewLocalized<N>
+where
+    N: svc::NewService<(E, Endpoint<P>)>,
+{
+    type Service = Localized<N::Service>;
+
+    fn new_service(&self, target: (E, Endpoint<P>)) -> Self::Service {
+        let locality = locality(self.local_zone.as_deref(), &target.1);
+        Localized::new(locality, self.inner.new_service(target))
+    }
+}
+
+fn locality<P>(local_zone: Option<&str>, endpoint: &Endpoint<P>) -> Locality {
+    let labels = endpoint.metadata.labels();
+    match (local_zone, labels.get(ZONE_LABEL)) {
+        (Some(local), Some(zone)) if local == zone => Locality::Local,
+        _ => Locality::Remote,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::{
+        proxy::api_resolve::{Metadata, ProtocolHint},
+        tls,
+    };
+    use std::collections::HashSet;
+
+    fn endpoint(zone: Option<&str>) -> Endpoint<()> {
+        let labels = zone.map(|z| (ZONE_LABEL.to_string(), z.to_string()));
+        let metadata = Metadata::new(labels, ProtocolHint::Unknown, None, None, None);
+        Endpoint::from_metadata(
+            ([10, 0, 0, 1], 8080),
+            metadata,
+            tls::NoClientTls::NotProvidedByServiceDiscovery,
+            false,
+            &HashSet::new(),
+        )
+    }
+
+    #[test]
+    fn matches_local_zone() {
+        let zone = Some("us-west-1a");
+        assert_eq!(locality(zone, &endpoint(zone)), Locality::Local);
+        assert_eq!(
+            locality(zone, &endpoint(Some("us-west-1b"))),
+            Locality::Remote
+        );
+        assert_eq!(locality(zone, &endpoint(None)), Locality::Remote);
+        assert_eq!(locality(None, &endpoint(zone)), Locality::Remote);
+    }
+}
@@ -1,4 +1,7 @@
-use super::{retry, CanonicalDstHeader, Concrete, Endpoint, Logical, Route};
+use super::{
+    backend, hash, hedge, locality, outlier, retry, CanonicalDstHeader, Concrete, Endpoint,
+    Logical, Route,
+};
 use crate::{endpoint, resolve, stack_labels, Outbound};
 use linkerd_app_core::{
     classify, config, profiles,
@@ -8,7 +11,8 @@ use linkerd_app_core::{
         http,
         resolve::map_endpoint,
     },
-    svc, Error, Infallible,
+    svc::{self, Param},
+    Error, Infallible,
 };
 use tracing::debug_span;
 
@@ -34,27 +38,46 @@ impl<E> Outbound<E> {
                 ..
             } = config.proxy;
             let watchdog = cache_max_idle_age * 2;
+            let retry_max_buffered_bytes = config.retry_max_buffered_bytes;
+            let retry_backoff = config.retry_backoff;
+            let retry_max_attempts = config.retry_max_attempts;
+            let hedge_delay = config.hedge_delay;
+            let outlier_ejection = config.http_outlier_ejection;
+            let local_zone = config.local_zone.clone();
+            let backend_overrides = config.backend_overrides.clone();
 
             let endpoint =
                 endpoint.instrument(|e: &Endpoint| debug_span!("endpoint", server.addr = %e.addr));
 
             let resolve = svc::stack(resolve.into_service())
                 .check_service::<ConcreteAddr>()
-                .push_request_filter(|c: Concrete| Ok::<_, Infallible>(c.resolve))
+                .push_request_filter(|(_, c): (outlier::Ejections, Concrete)| {
+                    Ok::<_, Infallible>(c.resolve)
+                })
                 .push(svc::layer::mk(move |inner| {
                     map_endpoint::Resolve::new(
-                        endpoint::FromMetadata {
+                        outlier::MapEndpoint(endpoint::FromMetadata {
                             inbound_ips: config.inbound_ips.clone(),
-                        },
+                        }),
                         inner,
                     )
                 }))
+                // Each balancer's endpoints share a limit on how many of them may be ejected.
+                .push_request_filter(|c: Concrete| {
+                    Ok::<_, Infallible>((outlier::Ejections::default(), c))
+                })
                 .check_service::<Concrete>()
                 .into_inner();
 
             let concrete = endpoint
                 .clone()
                 .check_new_service::<Endpoint, http::Request<http::BoxBody>>()
+                // Makes endpoints unavailable to the balancer while they are ejected for
+                // returning failures.
+                .push(outlier::layer(
+                    outlier_ejection,
+                    rt.metrics.http_outliers.clone(),
+                ))
                 .push_on_service(
                     svc::layers().push(http::BoxRequest::layer()).push(
                         rt.metrics
@@ -63,7 +86,10 @@ impl<E> Outbound<E> {
                             .layer(stack_labels("http", "balance.endpoint")),
                     ),
                 )
-                .check_new_service::<Endpoint, http::Request<_>>()
+                // Records whether each endpoint is in the proxy's zone, so that the balancer may
+                // prefer local endpoints.
+                .push(locality::NewLocalized::layer(local_zone))
+                .check_new_service::<(outlier::Ejections, Endpoint), http::Request<_>>()
                 // Resolve the service to its endpoints and balance requests over them.
                 //
                 // If the balancer has been empty/unavailable, eagerly fail requests.
@@ -76,12 +102,17 @@ impl<E> Outbound<E> {
                 // endpoint layer spawns each _connection_ attempt on a background task, but the
                 // decision to attempt the connection must be driven by the balancer.
                 .push(resolve::layer(resolve, watchdog))
+                // Balances requests with p2c, unless the profile or the proxy's configuration
+                // sets a consistent hash key. When the proxy's zone is known, endpoints in the
+                // same zone are preferred. Either way, hedged requests are sent to a different
+                // endpoint than the original request.
+                .push(http::balance::MakeBalance::<_, hash::RequestHash, _, _>::layer(
+                    crate::EWMA_DEFAULT_RTT,
+                    crate::EWMA_DECAY,
+                    config.http_hash_key.clone().map(hash::RequestHash::from),
+                ))
                 .push_on_service(
                     svc::layers()
-                        .push(http::balance::layer(
-                            crate::EWMA_DEFAULT_RTT,
-                            crate::EWMA_DECAY,
-                        ))
                         .push(
                             rt.metrics
                                 .proxy
@@ -111,7 +142,8 @@ impl<E> Outbound<E> {
             // task so it becomes ready without new requests.
             let logical = concrete
                 .check_new_service::<(ConcreteAddr, Logical), _>()
-                .push(profiles::split::layer())
+                // Requests may be pinned to a backend by their route.
+                .push(profiles::split::layer(profiles::split::PinHttp))
                 .push_on_service(
                     svc::layers()
                         .push(svc::layer::mk(svc::SpawnReady::new))
@@ -127,9 +159,11 @@ impl<E> Outbound<E> {
                 .push_cache(cache_max_idle_age);
 
             // If there's no route, use the logical service directly; otherwise
-            // use the per-route stack.
+            // use the per-route stack. Either way, requests may be pinned to a backend of the
+            // traffic split.
             logical
                 .clone()
+                .push(backend::NewPinBackend::layer(backend_overrides.clone()))
                 .push_switch(
                     |(route, logical): (Option<profiles::http::Route>, Logical)| -> Result<_, Infallible> {
                         match route {
@@ -138,21 +172,41 @@ impl<E> Outbound<E> {
                         }
                     },
                     logical
-                        .push_map_target(|r: Route| r.logical)
+                        .push_map_target(|r: backend::RouteBackend| -> Logical { r.param() })
                         .push_on_service(http::BoxRequest::layer())
                         .push(
                             rt.metrics
                                 .proxy
                                 .http_route_actual
-                                .to_layer::<classify::Response, _, Route>(),
+                                .to_layer::<classify::Response, _, backend::RouteBackend>(),
                         )
+                        // Determines whether each request is pinned to a backend of the
+                        // traffic split, so that the actual metrics describe the backend.
+                        .push(backend::NewPinBackend::layer(backend_overrides))
+                        // Sends a second copy of slow requests on retryable routes when
+                        // hedging is enabled.
+                        .push(hedge::layer(
+                            hedge_delay,
+                            retry_max_buffered_bytes,
+                            rt.metrics.http_hedges.clone(),
+                        ))
                         // Depending on whether or not the request can be
                         // retried, it may have one of two `Body` types. This
                         // layer unifies any `Body` type into `BoxBody`.
                         .push_on_service(http::BoxRequest::erased())
                         .push_http_insert_target::<profiles::http::Route>()
+                        // Records the route on each response so that it may be access logged.
+                        .push_http_response_insert_target::<profiles::http::Route>()
+                        // Buffers responses to retryable requests when the route classifies
+                        // responses by their trailers.
+                        .push(retry::NewBufferResponse::layer(retry_max_buffered_bytes))
                         // Sets an optional retry policy.
-                        .push(retry::layer(rt.metrics.proxy.http_route_retry.clone()))
+                        .push(retry::layer(
+                            rt.metrics.proxy.http_route_retry.clone(),
+                            retry_max_buffered_bytes,
+                            retry_backoff,
+                            retry_max_attempts,
+                        ))
                         // Sets an optional request timeout.
                         .push(http::NewTimeout::layer())
                         // Records per-route metrics.
@@ -0,0 +1,601 @@
+use crate::metrics::outlier::{self as metrics, Outliers};
+use futures::{ready, TryFuture};
+use linkerd_app_core::{
+    classify,
+    exp_backoff::ExponentialBackoff,
+    metrics::OutboundEndpointLabels,
+    proxy::{
+        http::{self, HttpBody},
+        resolve::map_endpoint,
+    },
+    svc::{self, layer, Param},
+    Error,
+};
+use linkerd_http_classify::{ClassifyEos, ClassifyResponse};
+use parking_lot::Mutex;
+use pin_project::{pin_project, pinned_drop};
+use std::{
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::time;
+
+/// Configures when endpoints are ejected from an HTTP balancer.
+///
+/// Endpoints are only ejected if at least one of `consecutive_failures` or `failure_rate` is set.
+#[derive(Copy, Clone, Debug)]
+pub struct OutlierEjection {
+    /// Ejects an endpoint after this many consecutive failed responses.
+    pub consecutive_failures: Option<u32>,
+
+    /// Ejects an endpoint when at least this ratio (between 0.0 and 1.0) of its responses fail
+    /// within `window`.
+    pub failure_rate: Option<f64>,
+
+    /// The minimum number of responses that must be observed within `window` before
+    /// `failure_rate` applies.
+    pub failure_rate_min_requests: u32,
+
+    /// The interval over which failure rates are computed.
+    pub window: Duration,
+
+    /// Determines how long an endpoint is ejected. The ejection period grows each time an endpoint
+    /// is ejected again, until it goes a full `window` without being ejected.
+    pub backoff: ExponentialBackoff,
+
+    /// The maximum ratio (between 0.0 and 1.0) of a balancer's endpoints that may be ejected at
+    /// once. Endpoints that would exceed this ratio remain available to the balancer.
+    pub max_ejected_ratio: f64,
+}
+
+/// Tracks how many of a balancer's endpoints are ejected, so that ejections may be limited to a
+/// ratio of the balancer's endpoints.
+///
+/// Each resolution's endpoints share a single `Ejections`.
+#[derive(Clone, Debug, Default)]
+pub struct Ejections(Arc<Mutex<Counts>>);
+
+/// Shares a resolution's `Ejections` with each of its endpoints.
+#[derive(Clone, Debug)]
+pub struct MapEndpoint<M>(pub M);
+
+#[derive(Debug, Default)]
+struct Counts {
+    endpoints: usize,
+    ejected: usize,
+}
+
+pub(crate) fn layer<N>(
+    config: OutlierEjection,
+    metrics: Outliers,
+) -> impl layer::Layer<N, Service = NewOutlier<N>> + Clone {
+    layer::mk(move |inner| NewOutlier {
+        inner,
+        config,
+        metrics: metrics.clone(),
+    })
+}
+
+/// Builds endpoint services that stop advertising readiness while the endpoint is ejected.
+#[derive(Clone, Debug)]
+pub struct NewOutlier<N> {
+    inner: N,
+    config: OutlierEjection,
+    metrics: Outliers,
+}
+
+/// Classifies an endpoint's responses and becomes unready while the endpoint is ejected, so that
+/// the balancer stops dispatching requests to it until its ejection period elapses.
+#[derive(Debug)]
+pub struct Outlier<S> {
+    inner: S,
+    tracker: Option<Arc<Tracker>>,
+    sleep: Option<Pin<Box<time::Sleep>>>,
+}
+
+#[pin_project]
+#[derive(Debug)]
+pub struct ResponseFuture<F> {
+    #[pin]
+    inner: F,
+    classify: Option<(classify::Response, Arc<Tracker>)>,
+}
+
+#[pin_project(PinnedDrop)]
+#[derive(Debug)]
+pub struct ResponseBody<B> {
+    #[pin]
+    inner: B,
+    classify: Option<(classify::Eos, Arc<Tracker>)>,
+}
+
+#[derive(Debug)]
+struct Tracker {
+    config: OutlierEjection,
+    metrics: Arc<metrics::Endpoint>,
+    ejections: Ejections,
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    consecutive_failures: u32,
+    window_start: time::Instant,
+    window_requests: u32,
+    window_failures: u32,
+    /// The number of times the endpoint has been ejected since it last went a full window without
+    /// being ejected.
+    ejections: u32,
+    ejected_until: Option<time::Instant>,
+}
+
+// === impl OutlierEjection ===
+
+impl OutlierEjection {
+    pub fn is_enabled(&self) -> bool {
+        self.consecutive_failures.is_some() || self.failure_rate.is_some()
+    }
+}
+
+// === impl Ejections ===
+
+impl Ejections {
+    fn add_endpoint(&self) {
+        self.0.lock().endpoints += 1;
+    }
+
+    fn remove_endpoint(&self) {
+        let mut counts = self.0.lock();
+        counts.endpoints = counts.endpoints.saturating_sub(1);
+    }
+
+    /// Records an ejection, unless it would eject more than `max_ratio` of the endpoints.
+    fn try_eject(&self, max_ratio: f64) -> bool {
+        let mut counts = self.0.lock();
+        let limit = (max_ratio * counts.endpoints as f64).floor() as usize;
+        if counts.ejected >= limit {
+            return false;
+        }
+        counts.ejected += 1;
+        true
+    }
+
+    fn readmit(&self) {
+        let mut counts = self.0.lock();
+        counts.ejected = counts.ejected.saturating_sub(1);
+    }
+}
+
+// === impl MapEndpoint ===
+
+impl<T, E, M> map_endpoint::MapEndpoint<(Ejections, T), E> for MapEndpoint<M>
+where
+    M: map_endpoint::MapEndpoint<T, E>,
+{
+    type Out = (Ejections, M::Out);
+
+    fn map_endpoint(
+        &self,
+        (ejections, target): &(Ejections, T),
+        addr: SocketAddr,
+        endpoint: E,
+    ) -> Self::Out {
+        (
+            ejections.clone(),
+            self.0.map_endpoint(target, addr, endpoint),
+        )
+    }
+}
+
+// === impl NewOutlier ===
+
+impl<T, N> svc::NewService<(Ejections, T)> for NewOutlier<N>
+where
+    T: Param<OutboundEndpointLabels>,
+    N: svc::NewService<T>,
+{
+    type Service = Outlier<N::Service>;
+
+    fn new_service(&self, (ejections, target): (Ejections, T)) -> Self::Service {
+        let tracker = if self.config.is_enabled() {
+            let metrics = self.metrics.endpoint(target.param());
+            Some(Arc::new(Tracker::new(self.config, metrics, ejections)))
+        } else {
+            None
+        };
+        Outlier {
+            inner: self.inner.new_service(target),
+            tracker,
+            sleep: None,
+        }
+    }
+}
+
+// === impl Outlier ===
+
+impl<S, A, B> svc::Service<http::Request<A>> for Outlier<S>
+where
+    S: svc::Service<http::Request<A>, Response = http::Response<B>>,
+    S::Error: Into<Error>,
+{
+    type Response = http::Response<ResponseBody<B>>;
+    type Error = Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        if let Some(tracker) = self.tracker.as_ref() {
+            loop {
+                if let Some(sleep) = self.sleep.as_mut() {
+                    ready!(sleep.as_mut().poll(cx));
+                    self.sleep = None;
+                }
+                match tracker.ejected_until() {
+                    Some(until) => self.sleep = Some(Box::pin(time::sleep_until(until))),
+                    None => break,
+                }
+            }
+        }
+
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: http::Request<A>) -> Self::Future {
+        let classify = self.tracker.clone().map(|tracker| {
+            let classify = req
+                .extensions()
+                .get::<classify::Response>()
+                .cloned()
+                .unwrap_or_default();
+            (classify, tracker)
+        });
+        ResponseFuture {
+            inner: self.inner.call(req),
+            classify,
+        }
+    }
+}
+
+// === impl ResponseFuture ===
+
+impl<F, B> Future for ResponseFuture<F>
+where
+    F: TryFuture<Ok = http::Response<B>>,
+    F::Error: Into<Error>,
+{
+    type Output = Result<http::Response<ResponseBody<B>>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        match ready!(this.inner.try_poll(cx)) {
+            Ok(rsp) => {
+                let classify = this
+                    .classify
+                    .take()
+                    .map(|(classify, tracker)| (classify.start(&rsp), tracker));
+                Poll::Ready(Ok(rsp.map(|inner| ResponseBody { inner, classify })))
+            }
+            Err(error) => {
+                let error = error.into();
+                if let Some((classify, tracker)) = this.classify.take() {
+                    tracker.record(&classify.error(&error));
+                }
+                Poll::Ready(Err(error))
+            }
+        }
+    }
+}
+
+// === impl ResponseBody ===
+
+impl<B> HttpBody for ResponseBody<B>
+where
+    B: HttpBody,
+    B::Error: Into<Error>,
+{
+    type Data = B::Data;
+    type Error = Error;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Error>>> {
+        let this = self.project();
+        match ready!(this.inner.poll_data(cx)) {
+            Some(Err(error)) => {
+                let error = error.into();
+                if let Some((classify, tracker)) = this.classify.take() {
+                    tracker.record(&classify.error(&error));
+                }
+                Poll::Ready(Some(Err(error)))
+            }
+            data => Poll::Ready(data.map(|d| d.map_err(Into::into))),
+        }
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<::http::HeaderMap>, Error>> {
+        let this = self.project();
+        let res = ready!(this.inner.poll_trailers(cx)).map_err(Into::into);
+        if let Some((classify, tracker)) = this.classify.take() {
+            let class = match res.as_ref() {
+                Ok(trailers) => classify.eos(trailers.as_ref()),
+                Err(error) => classify.error(error),
+            };
+            tracker.record(&class);
+        }
+        Poll::Ready(res)
+    }
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[pinned_drop]
+impl<B> PinnedDrop for ResponseBody<B> {
+    fn drop(self: Pin<&mut Self>) {
+        // Classify the response if the body is dropped before its trailers are read.
+        if let Some((classify, tracker)) = self.project().classify.take() {
+            tracker.record(&classify.eos(None));
+        }
+    }
+}
+
+// === impl Tracker ===
+
+impl Tracker {
+    fn new(config: OutlierEjection, metrics: Arc<metrics::Endpoint>, ejections: Ejections) -> Self {
+        ejections.add_endpoint();
+        Self {
+            config,
+            metrics,
+            ejections,
+            state: Mutex::new(State {
+                consecutive_failures: 0,
+                window_start: time::Instant::now(),
+                window_requests: 0,
+                window_failures: 0,
+                ejections: 0,
+                ejected_until: None,
+            }),
+        }
+    }
+
+    /// Returns the time at which the endpoint will be readmitted, if it is ejected.
+    ///
+    /// Readmits the endpoint if its ejection period has elapsed.
+    fn ejected_until(&self) -> Option<time::Instant> {
+        let mut state = self.state.lock();
+        let until = state.ejected_until?;
+        let now = time::Instant::now();
+        if now < until {
+            return Some(until);
+        }
+
+        tracing::debug!(ejections = state.ejections, "Readmitting endpoint");
+        state.ejected_until = None;
+        state.reset_window(now);
+        self.ejections.readmit();
+        self.metrics.readmit();
+        None
+    }
+
+    fn record(&self, class: &classify::Class) {
+        let now = time::Instant::now();
+        let mut state = self.state.lock();
+        if state.ejected_until.is_some() {
+            // Responses to requests that were dispatched before the endpoint was ejected are
+            // ignored.
+            return;
+        }
+
+        if now.saturating_duration_since(state.window_start) >= self.config.window {
+            // The endpoint went a full window without being ejected.
+            state.ejections = 0;
+            state.reset_window(now);
+        }
+
+        state.window_requests += 1;
+        if class.is_failure() {
+            state.window_failures += 1;
+            state.consecutive_failures += 1;
+        } else {
+            state.consecutive_failures = 0;
+        }
+
+        let consecutive = self
+            .config
+            .consecutive_failures
+            .map(|max| state.consecutive_failures >= max)
+            .unwrap_or(false);
+        let rate = self
+            .config
+            .failure_rate
+            .map(|max| {
+                state.window_requests >= self.config.failure_rate_min_requests
+                    && f64::from(state.window_failures) / f64::from(state.window_requests) >= max
+            })
+            .unwrap_or(false);
+        if !(consecutive || rate) {
+            return;
+        }
+
+        if !self.ejections.try_eject(self.config.max_ejected_ratio) {
+            // Too many of the balancer's endpoints are already ejected. The endpoint may be
+            // ejected once others are readmitted.
+            tracing::debug!(
+                consecutive_failures = state.consecutive_failures,
+                requests = state.window_requests,
+                failures = state.window_failures,
+                "Not ejecting endpoint; too many endpoints are ejected"
+            );
+            return;
+        }
+
+        let delay = self.config.backoff.delay(state.ejections);
+        tracing::info!(
+            consecutive_failures = state.consecutive_failures,
+            requests = state.window_requests,
+            failures = state.window_failures,
+            ?delay,
+            "Ejecting endpoint"
+        );
+        state.ejections = state.ejections.saturating_add(1);
+        state.ejected_until = Some(now + delay);
+        self.metrics.eject();
+    }
+}
+
+impl Drop for Tracker {
+    fn drop(&mut self) {
+        if self.state.get_mut().ejected_until.is_some() {
+            self.ejections.readmit();
+            self.metrics.readmit();
+        }
+        self.ejections.remove_endpoint();
+    }
+}
+
+// === impl State ===
+
+impl State {
+    fn reset_window(&mut self, now: time::Instant) {
+        self.consecutive_failures = 0;
+        self.window_start = now;
+        self.window_requests = 0;
+        self.window_failures = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use classify::{Class, SuccessOrFailure};
+
+    const FAILURE: Class = Class::Default(SuccessOrFailure::Failure);
+    const SUCCESS: Class = Class::Default(SuccessOrFailure::Success);
+
+    fn config(consecutive_failures: Option<u32>, failure_rate: Option<f64>) -> OutlierEjection {
+        OutlierEjection {
+            consecutive_failures,
+            failure_rate,
+            failure_rate_min_requests: 4,
+            window: Duration::from_secs(10),
+            backoff: ExponentialBackoff {
+                min: Duration::from_secs(10),
+                max: Duration::from_secs(60),
+                jitter: 0.0,
+            },
+            max_ejected_ratio: 1.0,
+        }
+    }
+
+    fn tracker(consecutive_failures: Option<u32>, failure_rate: Option<f64>) -> Tracker {
+        let config = config(consecutive_failures, failure_rate);
+        Tracker::new(config, Default::default(), Ejections::default())
+    }
+
+    #[test]
+    fn ejects_after_consecutive_failures() {
+        let tracker = tracker(Some(3), None);
+        tracker.record(&FAILURE);
+        tracker.record(&FAILURE);
+        tracker.record(&SUCCESS);
+        tracker.record(&FAILURE);
+        tracker.record(&FAILURE);
+        assert!(tracker.ejected_until().is_none());
+
+        tracker.record(&FAILURE);
+        assert!(tracker.ejected_until().is_some());
+    }
+
+    #[test]
+    fn ejects_on_failure_rate() {
+        let tracker = tracker(None, Some(0.5));
+        tracker.record(&FAILURE);
+        tracker.record(&FAILURE);
+        tracker.record(&FAILURE);
+        assert!(
+            tracker.ejected_until().is_none(),
+            "too few requests to eject"
+        );
+
+        tracker.record(&SUCCESS);
+        assert!(tracker.ejected_until().is_some());
+    }
+
+    #[test]
+    fn ignores_responses_while_ejected() {
+        let tracker = tracker(Some(1), None);
+        tracker.record(&FAILURE);
+        let until = tracker.ejected_until().expect("endpoint must be ejected");
+
+        tracker.record(&FAILURE);
+        assert_eq!(tracker.ejected_until(), Some(until));
+        assert_eq!(tracker.state.lock().ejections, 1);
+    }
+
+    #[test]
+    fn limits_ejected_endpoints() {
+        let config = OutlierEjection {
+            max_ejected_ratio: 0.5,
+            ..config(Some(1), None)
+        };
+        let ejections = Ejections::default();
+        let trackers = (0..4)
+            .map(|_| Tracker::new(config, Default::default(), ejections.clone()))
+            .collect::<Vec<_>>();
+
+        for tracker in &trackers {
+            tracker.record(&FAILURE);
+        }
+        let ejected = trackers
+            .iter()
+            .filter(|t| t.ejected_until().is_some())
+            .count();
+        assert_eq!(ejected, 2, "at most half of the endpoints may be ejected");
+
+        // Once an endpoint is removed from the balancer, fewer endpoints may be ejected.
+        drop(trackers);
+        let tracker = Tracker::new(config, Default::default(), ejections.clone());
+        tracker.record(&FAILURE);
+        assert!(
+            tracker.ejected_until().is_none(),
+            "a balancer's only endpoint must not be ejected"
+        );
+    }
+
+    #[tokio::test]
+    async fn ejects_after_readmission() {
+        time::pause();
+        let config = OutlierEjection {
+            max_ejected_ratio: 0.5,
+            ..config(Some(1), None)
+        };
+        let ejections = Ejections::default();
+        let first = Tracker::new(config, Default::default(), ejections.clone());
+        let second = Tracker::new(config, Default::default(), ejections.clone());
+
+        first.record(&FAILURE);
+        second.record(&FAILURE);
+        let until = first.ejected_until().expect("endpoint must be ejected");
+        assert!(second.ejected_until().is_none());
+
+        time::sleep_until(until).await;
+        assert!(first.ejected_until().is_none());
+        second.record(&FAILURE);
+        assert!(second.ejected_until().is_some());
+    }
+}
@@ -155,12 +155,14 @@ mod test {
                 http::Response::builder()
                     .status(http::StatusCode::BAD_GATEWAY)
                     .header(L5D_PROXY_CONNECTION, "close")
-                    .extension(tls::ConditionalClientTls::Some(tls::ClientTls {
-                        server_id: "foosa.barns.serviceaccount.identity.linkerd.cluster.local"
+                    .extension(tls::ConditionalClientTls::Some(tls::ClientTls::new(
+                        "foosa.barns.serviceaccount.identity.linkerd.cluster.local"
                             .parse()
                             .unwrap(),
-                        alpn: None,
-                    }))
+                        "foosa.barns.serviceaccount.identity.linkerd.cluster.local"
+                            .parse()
+                            .unwrap(),
+                    )))
                     .body(hyper::Body::default())
                     .unwrap(),
             )
@@ -1,27 +1,96 @@
 use super::Route;
-use futures::future;
+use bytes::{Buf, Bytes};
+use futures::{future, ready, TryFutureExt};
 use linkerd_app_core::{
     classify,
+    exp_backoff::ExponentialBackoff,
     http_metrics::retries::Handle,
     metrics, profiles,
-    proxy::http::{ClientHandle, HttpBody},
-    svc::{layer, Either, Param},
+    proxy::http::{BoxBody, ClientHandle, HttpBody},
+    svc::{self, layer, Either, Param},
     Error,
 };
 use linkerd_http_classify::{Classify, ClassifyEos, ClassifyResponse};
 use linkerd_http_retry::ReplayBody;
 use linkerd_retry as retry;
-use std::sync::Arc;
+use pin_project::pin_project;
+use std::{
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::time;
+
+/// Informs the upstream which attempt of a request is being made. Only set on retries, so the
+/// first value an upstream may observe is `2`.
+pub const L5D_RETRY_ATTEMPT: &str = "l5d-retry-attempt";
 
 pub fn layer<N>(
     metrics: metrics::HttpRouteRetry,
+    max_buffered_bytes: usize,
+    backoff: Option<ExponentialBackoff>,
+    max_attempts: Option<u32>,
 ) -> impl layer::Layer<N, Service = retry::NewRetry<NewRetryPolicy, N>> + Clone {
-    retry::NewRetry::<_, N>::layer(NewRetryPolicy::new(metrics))
+    retry::NewRetry::<_, N>::layer(NewRetryPolicy::new(
+        metrics,
+        max_buffered_bytes,
+        backoff,
+        max_attempts,
+    ))
+}
+
+/// Buffers the responses to retryable requests when a route's response classes match on trailers,
+/// so that the retry policy may classify responses by their trailers (e.g. `grpc-status`).
+///
+/// Responses are buffered up to the retry buffer limit. Larger responses are streamed once
+/// the limit is reached and are classified by their headers alone.
+#[derive(Clone, Debug)]
+pub struct NewBufferResponse<N> {
+    inner: N,
+    max_buffered_bytes: usize,
 }
 
+#[derive(Clone, Debug)]
+pub struct BufferResponse<S> {
+    inner: S,
+    /// Set when the route's responses must be buffered to be classified.
+    max_buffered_bytes: Option<usize>,
+}
+
+/// A response body that replays buffered data and trailers before streaming the remainder of the
+/// original body, if any.
+pub struct BufferedBody {
+    data: VecDeque<Bytes>,
+    trailers: Option<http::HeaderMap>,
+    rest: Option<BoxBody>,
+}
+
+/// Marks a request as retryable, so that its response is buffered if the route classifies
+/// responses by their trailers.
+#[derive(Copy, Clone, Debug)]
+struct Retryable(());
+
+/// The trailers of a buffered response, read by the retry policy to classify the response.
+#[derive(Clone, Debug)]
+struct BufferedTrailers(http::HeaderMap);
+
+type BoxResponseFuture =
+    Pin<Box<dyn Future<Output = Result<http::Response<BoxBody>, Error>> + Send + 'static>>;
+
+/// Builds a retry policy for each route that configures retries.
+///
+/// A route's retry budget and response classes are discovered with its profile. The buffer
+/// limit, backoff and attempt limit are configured on the proxy and apply to every route: the
+/// destination API (as of linkerd2-proxy-api v0.3) does not encode them per route, so per-route
+/// overrides are blocked until the API describes them.
 #[derive(Clone, Debug)]
 pub struct NewRetryPolicy {
     metrics: metrics::HttpRouteRetry,
+    max_buffered_bytes: usize,
+    backoff: Option<ExponentialBackoff>,
+    max_attempts: Option<u32>,
 }
 
 #[derive(Clone, Debug)]
@@ -29,16 +98,38 @@ pub struct RetryPolicy {
     metrics: Handle,
     budget: Arc<retry::Budget>,
     response_classes: profiles::http::ResponseClasses,
+    max_buffered_bytes: usize,
+    backoff: Option<ExponentialBackoff>,
+    max_attempts: Option<u32>,
+    /// The attempt of the request that this policy is handling, starting at 1 for the original
+    /// request.
+    attempt: u32,
 }
 
-/// Allow buffering requests up to 64 kb
-const MAX_BUFFERED_BYTES: usize = 64 * 1024;
+/// Waits for a backoff to elapse before a request is retried.
+#[pin_project]
+#[derive(Debug)]
+pub struct Backoff {
+    #[pin]
+    sleep: time::Sleep,
+    policy: Option<RetryPolicy>,
+}
 
 // === impl NewRetryPolicy ===
 
 impl NewRetryPolicy {
-    pub fn new(metrics: metrics::HttpRouteRetry) -> Self {
-        Self { metrics }
+    pub fn new(
+        metrics: metrics::HttpRouteRetry,
+        max_buffered_bytes: usize,
+        backoff: Option<ExponentialBackoff>,
+        max_attempts: Option<u32>,
+    ) -> Self {
+        Self {
+            metrics,
+            max_buffered_bytes,
+            backoff,
+            max_attempts,
+        }
     }
 }
 
@@ -53,6 +144,10 @@ impl retry::NewPolicy<Route> for NewRetryPolicy {
             metrics,
             budget: retries.budget().clone(),
             response_classes: route.route.response_classes().clone(),
+            max_buffered_bytes: self.max_buffered_bytes,
+            backoff: self.backoff,
+            max_attempts: self.max_attempts,
+            attempt: 1,
         })
     }
 }
@@ -64,7 +159,7 @@ where
     A: HttpBody + Unpin,
     A::Error: Into<Error>,
 {
-    type Future = future::Ready<Self>;
+    type Future = future::Either<future::Ready<Self>, Backoff>;
 
     fn retry(
         &self,
@@ -74,16 +169,21 @@ where
         let retryable = match result {
             Err(_) => false,
             Ok(rsp) => {
-                // is the request a failure?
+                // is the request a failure? If the route's classes match on trailers, the
+                // response has been buffered so that its trailers may be classified.
+                let trailers = rsp.extensions().get::<BufferedTrailers>().map(|t| &t.0);
                 let is_failure = classify::Request::from(self.response_classes.clone())
                     .classify(req)
                     .start(rsp)
-                    .eos(None)
+                    .eos(trailers)
                     .is_failure();
                 // did the body exceed the maximum length limit?
                 let exceeded_max_len = req.body().is_capped();
                 let retryable = is_failure && !exceeded_max_len;
                 tracing::trace!(is_failure, exceeded_max_len, retryable);
+                if is_failure && exceeded_max_len {
+                    self.metrics.incr_body_too_large();
+                }
                 retryable
             }
         };
@@ -93,13 +193,38 @@ where
             return None;
         }
 
+        if let Some(max_attempts) = self.max_attempts {
+            if self.attempt >= max_attempts {
+                tracing::debug!(attempt = self.attempt, "Retry attempts exhausted");
+                // Like any other response that is not retried, the final attempt replenishes the
+                // budget.
+                self.budget.deposit();
+                return None;
+            }
+        }
+
         let withdrew = self.budget.withdraw().is_ok();
         self.metrics.incr_retryable(withdrew);
         if !withdrew {
             return None;
         }
 
-        Some(future::ready(self.clone()))
+        let next = Self {
+            attempt: self.attempt + 1,
+            ..self.clone()
+        };
+        self.metrics.incr_retry(next.attempt);
+        match self.backoff.as_ref() {
+            None => Some(future::Either::Left(future::ready(next))),
+            Some(backoff) => {
+                let delay = backoff.delay(self.attempt - 1);
+                tracing::debug!(attempt = next.attempt, ?delay, "Backing off before retry");
+                Some(future::Either::Right(Backoff {
+                    sleep: time::sleep(delay),
+                    policy: Some(next),
+                }))
+            }
+        }
     }
 
     fn clone_request(
@@ -114,11 +239,17 @@ where
         *clone.headers_mut() = req.headers().clone();
         *clone.version_mut() = req.version();
 
+        // The clone is only sent if the current attempt is retried.
+        clone
+            .headers_mut()
+            .insert(L5D_RETRY_ATTEMPT, http::HeaderValue::from(self.attempt + 1));
+
         // The HTTP server sets a ClientHandle with the client's address and a means to close the
         // server-side connection.
         if let Some(client_handle) = req.extensions().get::<ClientHandle>().cloned() {
             clone.extensions_mut().insert(client_handle);
         }
+        clone.extensions_mut().insert(Retryable(()));
 
         Some(clone)
     }
@@ -136,11 +267,12 @@ where
         req: http::Request<A>,
     ) -> Either<Self::RetryRequest, http::Request<A>> {
         let (head, body) = req.into_parts();
-        let replay_body = match ReplayBody::try_new(body, MAX_BUFFERED_BYTES) {
+        let replay_body = match ReplayBody::try_new(body, self.max_buffered_bytes) {
             Ok(body) => body,
             Err(body) => {
                 tracing::debug!(
                     size = body.size_hint().lower(),
+                    max = self.max_buffered_bytes,
                     "Body is too large to buffer"
                 );
                 return Either::B(http::Request::from_parts(head, body));
@@ -149,6 +281,452 @@ where
 
         // The body may still be too large to be buffered if the body's length was not known.
         // `ReplayBody` handles this gracefully.
-        Either::A(http::Request::from_parts(head, replay_body))
+        let mut req = http::Request::from_parts(head, replay_body);
+        req.extensions_mut().insert(Retryable(()));
+        Either::A(req)
+    }
+
+    fn not_retried(&self, result: Result<&http::Response<B>, &E>) {
+        // The request's body was too large to be buffered. Responses to such requests are not
+        // buffered, so they are classified by their headers alone.
+        if let Ok(rsp) = result {
+            let is_failure = classify::Response::Profile(self.response_classes.clone())
+                .start(rsp)
+                .eos(None)
+                .is_failure();
+            if is_failure {
+                self.metrics.incr_body_too_large();
+            }
+        }
+    }
+}
+
+// === impl Backoff ===
+
+impl Future for Backoff {
+    type Output = RetryPolicy;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        ready!(this.sleep.poll(cx));
+        Poll::Ready(this.policy.take().expect("polled after ready"))
+    }
+}
+
+// === impl NewBufferResponse ===
+
+impl<N> NewBufferResponse<N> {
+    pub fn layer(max_buffered_bytes: usize) -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(move |inner| Self {
+            inner,
+            max_buffered_bytes,
+        })
+    }
+}
+
+impl<N> svc::NewService<Route> for NewBufferResponse<N>
+where
+    N: svc::NewService<Route>,
+{
+    type Service = BufferResponse<N::Service>;
+
+    fn new_service(&self, route: Route) -> Self::Service {
+        let max_buffered_bytes = route
+            .route
+            .retries()
+            .filter(|_| route.route.response_classes().requires_trailers())
+            .map(|_| self.max_buffered_bytes);
+        BufferResponse {
+            inner: self.inner.new_service(route),
+            max_buffered_bytes,
+        }
+    }
+}
+
+// === impl BufferResponse ===
+
+impl<A, B, S> svc::Service<http::Request<A>> for BufferResponse<S>
+where
+    S: svc::Service<http::Request<A>, Response = http::Response<B>, Error = Error>,
+    S::Future: Send + 'static,
+    B: HttpBody + Send + 'static,
+    B::Data: Send + 'static,
+    B::Error: Into<Error>,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Error;
+    type Future = future::Either<
+        future::MapOk<S::Future, fn(http::Response<B>) -> http::Response<BoxBody>>,
+        BoxResponseFuture,
+    >;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<A>) -> Self::Future {
+        let max_buffered_bytes = match self.max_buffered_bytes {
+            Some(max) if req.extensions().get::<Retryable>().is_some() => max,
+            _ => {
+                return future::Either::Left(
+                    self.inner.call(req).map_ok(box_response::<B> as fn(_) -> _),
+                )
+            }
+        };
+
+        let rsp = self.inner.call(req);
+        future::Either::Right(Box::pin(async move {
+            let rsp = rsp.await?;
+            buffer(rsp, max_buffered_bytes).await
+        }))
+    }
+}
+
+fn box_response<B>(rsp: http::Response<B>) -> http::Response<BoxBody>
+where
+    B: HttpBody + Send + 'static,
+    B::Data: Send + 'static,
+    B::Error: Into<Error>,
+{
+    rsp.map(BoxBody::new)
+}
+
+/// Reads a response's body until its trailers are read or more than `max_buffered_bytes` are
+/// buffered.
+///
+/// If the body fails while it is buffered, the response fails.
+async fn buffer<B>(
+    rsp: http::Response<B>,
+    max_buffered_bytes: usize,
+) -> Result<http::Response<BoxBody>, Error>
+where
+    B: HttpBody + Send + 'static,
+    B::Data: Send + 'static,
+    B::Error: Into<Error>,
+{
+    let (mut head, body) = rsp.into_parts();
+    let mut body = BoxBody::new(body);
+    let mut data = VecDeque::new();
+    let mut buffered = 0;
+    while let Some(chunk) = body.data().await {
+        let mut chunk = chunk.map_err(Into::into)?;
+        let bytes = chunk.copy_to_bytes(chunk.remaining());
+        buffered += bytes.len();
+        data.push_back(bytes);
+        if buffered > max_buffered_bytes {
+            tracing::debug!(
+                buffered,
+                max = max_buffered_bytes,
+                "Response is too large to buffer"
+            );
+            let body = BufferedBody {
+                data,
+                trailers: None,
+                rest: Some(body),
+            };
+            return Ok(http::Response::from_parts(head, BoxBody::new(body)));
+        }
+    }
+
+    let trailers = body.trailers().await.map_err(Into::into)?;
+    if let Some(trailers) = trailers.as_ref() {
+        head.extensions.insert(BufferedTrailers(trailers.clone()));
+    }
+    let body = BufferedBody {
+        data,
+        trailers,
+        rest: None,
+    };
+    Ok(http::Response::from_parts(head, BoxBody::new(body)))
+}
+
+// === impl BufferedBody ===
+
+impl HttpBody for BufferedBody {
+    type Data = Bytes;
+    type Error = Error;
+
+    fn poll_data(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Error>>> {
+        let this = self.get_mut();
+        if let Some(data) = this.data.pop_front() {
+            return Poll::Ready(Some(Ok(data)));
+        }
+        match this.rest.as_mut() {
+            None => Poll::Ready(None),
+            Some(rest) => {
+                let data = ready!(Pin::new(rest).poll_data(cx));
+                Poll::Ready(data.map(|d| d.map(|mut d| d.copy_to_bytes(d.remaining()))))
+            }
+        }
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::HeaderMap>, Error>> {
+        let this = self.get_mut();
+        match this.rest.as_mut() {
+            None => Poll::Ready(Ok(this.trailers.take())),
+            Some(rest) => Pin::new(rest).poll_trailers(cx),
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.data.is_empty()
+            && self.trailers.is_none()
+            && self
+                .rest
+                .as_ref()
+                .map(|r| r.is_end_stream())
+                .unwrap_or(true)
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        let buffered = self.data.iter().map(|d| d.len() as u64).sum::<u64>();
+        let rest = self
+            .rest
+            .as_ref()
+            .map(|r| r.size_hint())
+            .unwrap_or_else(|| http_body::SizeHint::with_exact(0));
+        let mut hint = http_body::SizeHint::new();
+        hint.set_lower(rest.lower() + buffered);
+        if let Some(upper) = rest.upper() {
+            hint.set_upper(upper + buffered);
+        }
+        hint
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::metrics::FmtMetrics;
+    use linkerd_app_core::{metrics::RouteLabels, profiles::http::ResponseMatch};
+    use linkerd_retry::{Policy, PrepareRequest};
+    use std::time::Duration;
+
+    fn policy(classes: Vec<profiles::http::ResponseClass>) -> RetryPolicy {
+        policy_with_metrics(&metrics::HttpRouteRetry::default(), classes)
+    }
+
+    fn policy_with_metrics(
+        metrics: &metrics::HttpRouteRetry,
+        classes: Vec<profiles::http::ResponseClass>,
+    ) -> RetryPolicy {
+        let route = profiles::http::Route::new(std::iter::empty(), classes);
+        RetryPolicy {
+            metrics: metrics.get_handle(RouteLabels::outbound(
+                profiles::LogicalAddr("web.ns.svc.cluster.local:8080".parse().unwrap()),
+                &route,
+            )),
+            budget: Arc::new(retry::Budget::new(Duration::from_secs(10), 10, 0.2)),
+            response_classes: route.response_classes().clone(),
+            max_buffered_bytes: 1024,
+            backoff: None,
+            max_attempts: None,
+            attempt: 1,
+        }
+    }
+
+    fn request() -> http::Request<ReplayBody<hyper::Body>> {
+        let body = ReplayBody::try_new(hyper::Body::empty(), 1024).expect("body must be buffered");
+        http::Request::new(body)
+    }
+
+    fn should_retry<B>(policy: &RetryPolicy, rsp: &http::Response<B>) -> bool {
+        Policy::<_, _, Error>::retry(policy, &request(), Ok(rsp)).is_some()
+    }
+
+    fn server_errors() -> Vec<profiles::http::ResponseClass> {
+        vec![profiles::http::ResponseClass::new(
+            true,
+            ResponseMatch::Status {
+                min: http::StatusCode::INTERNAL_SERVER_ERROR,
+                max: http::StatusCode::from_u16(599).unwrap(),
+            },
+        )]
+    }
+
+    /// Retries a failed request, returning the policy for the next attempt.
+    async fn retry_failure(policy: &RetryPolicy) -> Option<RetryPolicy> {
+        let rsp = http::Response::builder()
+            .status(http::StatusCode::SERVICE_UNAVAILABLE)
+            .body(hyper::Body::empty())
+            .unwrap();
+        let backoff = Policy::<_, _, Error>::retry(policy, &request(), Ok(&rsp))?;
+        Some(backoff.await)
+    }
+
+    /// Builds a response whose body is followed by a `grpc-status` trailer.
+    fn grpc_response(status: u32) -> http::Response<hyper::Body> {
+        let (mut tx, body) = hyper::Body::channel();
+        tokio::spawn(async move {
+            // The response may be dropped before its body is read.
+            let _ = tx.send_data(Bytes::from_static(b"message")).await;
+            let mut trailers = http::HeaderMap::new();
+            trailers.insert("grpc-status", status.into());
+            let _ = tx.send_trailers(trailers).await;
+        });
+        http::Response::new(body)
+    }
+
+    #[tokio::test]
+    async fn classifies_buffered_trailers() {
+        let policy = policy(vec![profiles::http::ResponseClass::new(
+            true,
+            ResponseMatch::GrpcStatus(14),
+        )]);
+
+        let rsp = buffer(grpc_response(14), 1024).await.unwrap();
+        assert!(should_retry(&policy, &rsp));
+
+        let rsp = buffer(grpc_response(0), 1024).await.unwrap();
+        assert!(!should_retry(&policy, &rsp));
+
+        // Responses that are not buffered are classified by their headers alone.
+        assert!(!should_retry(&policy, &grpc_response(14)));
+    }
+
+    #[tokio::test]
+    async fn streams_responses_that_exceed_the_buffer() {
+        let rsp = buffer(grpc_response(14), 4).await.unwrap();
+        assert!(rsp.extensions().get::<BufferedTrailers>().is_none());
+
+        let mut body = rsp.into_body();
+        let mut data = Vec::new();
+        while let Some(chunk) = body.data().await {
+            let mut chunk = chunk.unwrap();
+            data.extend_from_slice(&chunk.copy_to_bytes(chunk.remaining()));
+        }
+        assert_eq!(data, b"message");
+        let trailers = body
+            .trailers()
+            .await
+            .unwrap()
+            .expect("trailers must be read");
+        assert_eq!(trailers.get("grpc-status").unwrap(), "14");
+    }
+
+    #[test]
+    fn counts_bodies_too_large_when_failures_are_not_retried() {
+        let metrics = metrics::HttpRouteRetry::default();
+        let policy = policy_with_metrics(&metrics, server_errors());
+        let body_too_large = || {
+            let report = metrics.clone().into_report(Duration::from_secs(60));
+            report
+                .as_display()
+                .to_string()
+                .contains("skipped=\"body_too_large\"} 1")
+        };
+
+        let req = http::Request::new(hyper::Body::from(vec![0u8; 2048]));
+        let prepared =
+            PrepareRequest::<_, http::Response<hyper::Body>, Error>::prepare_request(&policy, req);
+        assert!(matches!(prepared, Either::B(_)));
+        assert!(
+            !body_too_large(),
+            "requests must not be counted until they fail"
+        );
+
+        let rsp = http::Response::new(hyper::Body::empty());
+        PrepareRequest::<http::Request<hyper::Body>, _, Error>::not_retried(&policy, Ok(&rsp));
+        assert!(
+            !body_too_large(),
+            "successful responses must not be counted"
+        );
+
+        let rsp = http::Response::builder()
+            .status(http::StatusCode::SERVICE_UNAVAILABLE)
+            .body(hyper::Body::empty())
+            .unwrap();
+        PrepareRequest::<http::Request<hyper::Body>, _, Error>::not_retried(&policy, Ok(&rsp));
+        assert!(body_too_large());
+    }
+
+    #[test]
+    fn sets_retry_attempt_header() {
+        let policy = policy(server_errors());
+        let mut req = request();
+        req.headers_mut()
+            .insert(L5D_RETRY_ATTEMPT, http::HeaderValue::from_static("7"));
+
+        let clone = Policy::<_, http::Response<hyper::Body>, Error>::clone_request(&policy, &req)
+            .expect("request must be cloned");
+        assert_eq!(clone.headers().get(L5D_RETRY_ATTEMPT).unwrap(), "2");
+
+        let policy = RetryPolicy {
+            attempt: 2,
+            ..policy
+        };
+        let clone = Policy::<_, http::Response<hyper::Body>, Error>::clone_request(&policy, &req)
+            .expect("request must be cloned");
+        assert_eq!(clone.headers().get(L5D_RETRY_ATTEMPT).unwrap(), "3");
+    }
+
+    #[tokio::test]
+    async fn limits_attempts() {
+        let policy = RetryPolicy {
+            max_attempts: Some(3),
+            ..policy(server_errors())
+        };
+
+        let second = retry_failure(&policy)
+            .await
+            .expect("the first attempt must be retried");
+        assert_eq!(second.attempt, 2);
+        let third = retry_failure(&second)
+            .await
+            .expect("the second attempt must be retried");
+        assert_eq!(third.attempt, 3);
+        assert!(
+            retry_failure(&third).await.is_none(),
+            "the third attempt must not be retried"
+        );
+    }
+
+    #[tokio::test]
+    async fn deposits_when_attempts_are_exhausted() {
+        // Each deposit permits a single retry, and no retries are permitted without deposits.
+        let budget = Arc::new(retry::Budget::new(Duration::from_secs(10), 0, 1.0));
+        let policy = RetryPolicy {
+            budget: budget.clone(),
+            max_attempts: Some(1),
+            ..policy(server_errors())
+        };
+
+        assert!(
+            retry_failure(&policy).await.is_none(),
+            "the first attempt must not be retried"
+        );
+        assert!(
+            budget.withdraw().is_ok(),
+            "the final attempt must deposit into the budget"
+        );
+        assert!(budget.withdraw().is_err());
+    }
+
+    #[tokio::test]
+    async fn backs_off_before_retrying() {
+        time::pause();
+        let policy = RetryPolicy {
+            backoff: Some(
+                ExponentialBackoff::new(Duration::from_secs(1), Duration::from_secs(10), 0.0)
+                    .unwrap(),
+            ),
+            ..policy(server_errors())
+        };
+
+        let start = time::Instant::now();
+        let second = retry_failure(&policy).await.expect("must be retried");
+        assert_eq!(time::Instant::now() - start, Duration::from_secs(1));
+
+        let start = time::Instant::now();
+        retry_failure(&second).await.expect("must be retried");
+        assert_eq!(
+            time::Instant::now() - start,
+            Duration::from_secs(2),
+            "the backoff must grow with each attempt"
+        );
     }
 }
@@ -1,9 +1,9 @@
-use super::{IdentityRequired, ProxyConnectionClose};
+use super::{retry::L5D_RETRY_ATTEMPT, IdentityRequired, ProxyConnectionClose};
 use crate::{http, trace_labels, Outbound};
 use linkerd_app_core::{
-    config, errors, http_tracing,
+    access_log, config, errors, http_tracing,
     svc::{self, ExtractParam},
-    Error, Result,
+    tls, Error, Result,
 };
 
 #[derive(Copy, Clone, Debug)]
@@ -45,6 +45,9 @@ impl<N> Outbound<N> {
                 .push_on_service(
                     svc::layers()
                         .push(http::BoxRequest::layer())
+                        // Only this proxy's retries may tell the upstream which attempt of a
+                        // request is being made.
+                        .push(http::strip_header::request::layer(L5D_RETRY_ATTEMPT))
                         // Limit the number of in-flight requests. When the proxy is
                         // at capacity, go into failfast after a dispatch timeout. If
                         // the router is unavailable, then spawn the service on a
@@ -73,6 +76,14 @@ impl<N> Outbound<N> {
                 .push(http::NewNormalizeUri::layer())
                 // Record when a HTTP/1 URI originated in absolute form
                 .push_on_service(http::normalize_uri::MarkAbsoluteForm::layer())
+                // Writes an access log record for each request, if enabled. Outbound clients are
+                // local to the pod and have no identity.
+                .push(access_log::NewAccessLog::layer(
+                    access_log::Direction::Outbound,
+                    rt.access_log.clone(),
+                    |_: &T| tls::ConditionalServerTls::None(tls::NoServerTls::Loopback),
+                ))
+                .push_on_service(http::BoxResponse::layer())
                 .push(svc::ArcNewService::layer())
         })
     }
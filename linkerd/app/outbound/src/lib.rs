@@ -12,16 +12,19 @@ mod ingress;
 pub mod logical;
 mod metrics;
 mod resolve;
+mod state;
 mod switch_logical;
 pub mod tcp;
 #[cfg(test)]
 pub(crate) mod test_util;
 
-pub use self::metrics::Metrics;
+pub use self::{metrics::Metrics, state::State};
 use futures::Stream;
 use linkerd_app_core::{
+    access_log::AccessLog,
     config::ProxyConfig,
     drain,
+    exp_backoff::ExponentialBackoff,
     http_tracing::OpenCensusSink,
     identity, io, profiles,
     proxy::{
@@ -60,6 +63,37 @@ pub struct Config {
 
     // Whether the proxy may include informational headers on HTTP responses.
     pub emit_headers: bool,
+
+    // The maximum size of a request body that may be buffered so that the request can be retried.
+    pub retry_max_buffered_bytes: usize,
+
+    // The backoff to wait before each retry. Retries are issued immediately if unset.
+    pub retry_backoff: Option<ExponentialBackoff>,
+
+    // The maximum number of attempts, including the original request, that may be made for each
+    // request. Only the retry budget limits retries if unset.
+    pub retry_max_attempts: Option<u32>,
+
+    // The delay after which a second copy of a request on a retryable route is sent.
+    // Requests are not hedged if unset.
+    pub hedge_delay: Option<Duration>,
+
+    // Determines when endpoints are ejected from HTTP load balancers.
+    pub http_outlier_ejection: http::OutlierEjection,
+
+    // The key by which HTTP requests are balanced over a consistent hash ring when a service's
+    // profile does not configure a load balancer. Requests are balanced by peak-EWMA if unset.
+    pub http_hash_key: Option<profiles::http::HashKey>,
+
+    // The TCP load balancing mode used when a service's profile does not configure one.
+    pub tcp_balance_mode: tcp::balance::Mode,
+
+    // The zone in which the proxy runs. When set, HTTP balancers prefer endpoints in this zone.
+    pub local_zone: Option<Arc<str>>,
+
+    // Pins HTTP requests that match a header or cookie to a backend of a service's traffic split.
+    // These apply after the overrides of a request's profile route, if any.
+    pub backend_overrides: Vec<profiles::http::BackendOverride>,
 }
 
 #[derive(Clone, Debug)]
@@ -75,7 +109,9 @@ struct Runtime {
     identity: identity::NewClient,
     tap: tap::Registry,
     span_sink: OpenCensusSink,
+    access_log: Option<AccessLog>,
     drain: drain::Watch,
+    state: State,
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
@@ -95,7 +131,9 @@ impl Outbound<()> {
             identity: runtime.identity.new_client(),
             tap: runtime.tap,
             span_sink: runtime.span_sink,
+            access_log: runtime.access_log,
             drain: runtime.drain,
+            state: State::default(),
         };
         Self {
             config,
@@ -108,6 +146,11 @@ impl Outbound<()> {
         self.runtime.metrics.clone()
     }
 
+    /// Returns a handle to the outbound proxy's discovery state.
+    pub fn state(&self) -> State {
+        self.runtime.state.clone()
+    }
+
     pub fn with_stack<S>(self, stack: S) -> Outbound<S> {
         self.map_stack(move |_, _, _| svc::stack(stack))
     }
@@ -166,6 +209,7 @@ impl Outbound<()> {
         P::Future: Send,
         P::Error: Send,
     {
+        let resolve = self.runtime.state.track_resolve(resolve);
         if self.config.ingress_mode {
             info!("Outbound routing in ingress-mode");
             let stack = self
@@ -9,6 +9,8 @@
 //! `DashMap` as we migrate other metrics registries.
 
 pub(crate) mod error;
+pub(crate) mod hedge;
+pub(crate) mod outlier;
 
 pub use linkerd_app_core::metrics::*;
 
@@ -17,6 +19,8 @@ pub use linkerd_app_core::metrics::*;
 pub struct Metrics {
     pub(crate) http_errors: error::Http,
     pub(crate) tcp_errors: error::Tcp,
+    pub(crate) http_outliers: outlier::Outliers,
+    pub(crate) http_hedges: hedge::Hedges,
 
     /// Holds metrics that are common to both inbound and outbound proxies. These metrics are
     /// reported separately
@@ -28,6 +32,8 @@ impl Metrics {
         Self {
             http_errors: error::Http::default(),
             tcp_errors: error::Tcp::default(),
+            http_outliers: outlier::Outliers::default(),
+            http_hedges: hedge::Hedges::default(),
             proxy,
         }
     }
@@ -37,6 +43,8 @@ impl FmtMetrics for Metrics {
     fn fmt_metrics(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         self.http_errors.fmt_metrics(f)?;
         self.tcp_errors.fmt_metrics(f)?;
+        self.http_outliers.fmt_metrics(f)?;
+        self.http_hedges.fmt_metrics(f)?;
 
         // XXX: Proxy metrics are reported elsewhere.
 
@@ -0,0 +1,73 @@
+use linkerd_app_core::metrics::{metrics, Counter, FmtMetrics, RouteLabels};
+use parking_lot::RwLock;
+use std::{collections::HashMap, sync::Arc};
+
+metrics! {
+    outbound_http_route_hedges_total: Counter {
+        "The total number of hedged copies of HTTP requests that were issued."
+    },
+    outbound_http_route_hedge_wins_total: Counter {
+        "The total number of HTTP responses that were returned from a hedged copy of the request."
+    }
+}
+
+/// Tracks hedged requests for HTTP routes.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Hedges(Arc<RwLock<HashMap<RouteLabels, Arc<Route>>>>);
+
+#[derive(Debug, Default)]
+pub(crate) struct Route {
+    hedges: Counter,
+    wins: Counter,
+}
+
+// === impl Hedges ===
+
+impl Hedges {
+    pub(crate) fn route(&self, labels: RouteLabels) -> Arc<Route> {
+        if let Some(route) = self.0.read().get(&labels) {
+            return route.clone();
+        }
+        self.0.write().entry(labels).or_default().clone()
+    }
+}
+
+impl FmtMetrics for Hedges {
+    fn fmt_metrics(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut metrics = self.0.write();
+
+        // Drop routes that are no longer in use.
+        metrics.retain(|_, route| Arc::strong_count(route) > 1);
+        if metrics.is_empty() {
+            return Ok(());
+        }
+
+        outbound_http_route_hedges_total.fmt_help(f)?;
+        outbound_http_route_hedges_total.fmt_scopes(f, metrics.iter(), |r| &r.hedges)?;
+
+        outbound_http_route_hedge_wins_total.fmt_help(f)?;
+        outbound_http_route_hedge_wins_total.fmt_scopes(f, metrics.iter(), |r| &r.wins)
+    }
+}
+
+// === impl Route ===
+
+impl Route {
+    pub(crate) fn hedge(&self) {
+        self.hedges.incr();
+    }
+
+    pub(crate) fn win(&self) {
+        self.wins.incr();
+    }
+
+    #[cfg(test)]
+    pub(crate) fn hedges(&self) -> f64 {
+        self.hedges.value()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn wins(&self) -> f64 {
+        self.wins.value()
+    }
+}
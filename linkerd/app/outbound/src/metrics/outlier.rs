@@ -0,0 +1,65 @@
+use linkerd_app_core::metrics::{metrics, Counter, FmtMetrics, Gauge, OutboundEndpointLabels};
+use parking_lot::RwLock;
+use std::{collections::HashMap, sync::Arc};
+
+metrics! {
+    outbound_http_balancer_endpoint_ejections_total: Counter {
+        "The total number of times an endpoint was ejected from an HTTP load balancer."
+    },
+    outbound_http_balancer_endpoint_ejected: Gauge {
+        "Whether an endpoint is currently ejected from an HTTP load balancer."
+    }
+}
+
+/// Tracks outlier ejections for HTTP balancer endpoints.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Outliers(Arc<RwLock<HashMap<OutboundEndpointLabels, Arc<Endpoint>>>>);
+
+#[derive(Debug, Default)]
+pub(crate) struct Endpoint {
+    ejections: Counter,
+    ejected: Gauge,
+}
+
+// === impl Outliers ===
+
+impl Outliers {
+    pub(crate) fn endpoint(&self, labels: OutboundEndpointLabels) -> Arc<Endpoint> {
+        if let Some(ep) = self.0.read().get(&labels) {
+            return ep.clone();
+        }
+        self.0.write().entry(labels).or_default().clone()
+    }
+}
+
+impl FmtMetrics for Outliers {
+    fn fmt_metrics(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut metrics = self.0.write();
+
+        // Drop endpoints that are no longer part of a balancer.
+        metrics.retain(|_, ep| Arc::strong_count(ep) > 1);
+        if metrics.is_empty() {
+            return Ok(());
+        }
+
+        outbound_http_balancer_endpoint_ejections_total.fmt_help(f)?;
+        outbound_http_balancer_endpoint_ejections_total
+            .fmt_scopes(f, metrics.iter(), |ep| &ep.ejections)?;
+
+        outbound_http_balancer_endpoint_ejected.fmt_help(f)?;
+        outbound_http_balancer_endpoint_ejected.fmt_scopes(f, metrics.iter(), |ep| &ep.ejected)
+    }
+}
+
+// === impl Endpoint ===
+
+impl Endpoint {
+    pub(crate) fn eject(&self) {
+        self.ejections.incr();
+        self.ejected.incr();
+    }
+
+    pub(crate) fn readmit(&self) {
+        self.ejected.decr();
+    }
+}
@@ -0,0 +1,322 @@
+use futures::{prelude::*, ready};
+use linkerd_app_core::{
+    profiles,
+    proxy::{
+        api_resolve::{ConcreteAddr, Metadata},
+        core::{Resolve, Update},
+    },
+    svc::{self, layer, Param},
+    transport::OrigDstAddr,
+    Error,
+};
+use parking_lot::Mutex;
+use pin_project::pin_project;
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::SocketAddr,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+/// Tracks the outbound proxy's discovery state--the profiles held by cached services and the
+/// endpoints known to each load balancer--so that it may be inspected by the admin server.
+///
+/// Entries are removed when the service or balancer that holds them is dropped.
+#[derive(Clone, Debug, Default)]
+pub struct State(Arc<Inner>);
+
+#[derive(Debug, Default)]
+struct Inner {
+    next_id: AtomicU64,
+    profiles: Mutex<HashMap<u64, (OrigDstAddr, profiles::Receiver)>>,
+    balancers: Mutex<HashMap<u64, (ConcreteAddr, BTreeMap<SocketAddr, Metadata>)>>,
+}
+
+/// Removes a tracked entry when dropped.
+#[derive(Debug)]
+struct Handle {
+    id: u64,
+    state: State,
+}
+
+/// Records the profile discovered for each target.
+#[derive(Clone, Debug)]
+pub(crate) struct NewTrackProfile<N> {
+    state: State,
+    inner: N,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct TrackProfile<S> {
+    inner: S,
+    _handle: Option<Arc<Handle>>,
+}
+
+/// Records the endpoints of each resolution, i.e. of each balancer.
+#[derive(Clone, Debug)]
+pub(crate) struct TrackResolve<R> {
+    state: State,
+    inner: R,
+}
+
+#[derive(Debug)]
+pub(crate) struct TrackResolveFuture<F> {
+    inner: F,
+    target: Option<ConcreteAddr>,
+    state: State,
+}
+
+#[pin_project]
+#[derive(Debug)]
+pub(crate) struct TrackResolution<S> {
+    #[pin]
+    inner: S,
+    handle: Handle,
+}
+
+// === impl State ===
+
+impl State {
+    /// Returns the current profile of each cached destination, ordered by address.
+    pub fn profiles(&self) -> Vec<(OrigDstAddr, profiles::Profile)> {
+        let mut profiles = self
+            .0
+            .profiles
+            .lock()
+            .values()
+            .map(|(addr, rx)| (*addr, rx.profile()))
+            .collect::<Vec<_>>();
+        profiles.sort_by_key(|(OrigDstAddr(addr), _)| *addr);
+        profiles
+    }
+
+    /// Returns the endpoints of each active balancer, ordered by address.
+    pub fn balancers(&self) -> Vec<(ConcreteAddr, Vec<(SocketAddr, Metadata)>)> {
+        let mut balancers = self
+            .0
+            .balancers
+            .lock()
+            .values()
+            .map(|(addr, endpoints)| {
+                let endpoints = endpoints
+                    .iter()
+                    .map(|(addr, meta)| (*addr, meta.clone()))
+                    .collect();
+                (addr.clone(), endpoints)
+            })
+            .collect::<Vec<_>>();
+        balancers.sort_by_key(|(ConcreteAddr(addr), _)| addr.to_string());
+        balancers
+    }
+
+    pub(crate) fn track_resolve<R>(&self, inner: R) -> TrackResolve<R> {
+        TrackResolve {
+            state: self.clone(),
+            inner,
+        }
+    }
+
+    fn register_profile(&self, addr: OrigDstAddr, rx: profiles::Receiver) -> Handle {
+        let id = self.0.next_id.fetch_add(1, Ordering::Relaxed);
+        self.0.profiles.lock().insert(id, (addr, rx));
+        Handle {
+            id,
+            state: self.clone(),
+        }
+    }
+
+    fn register_balancer(&self, addr: ConcreteAddr) -> Handle {
+        let id = self.0.next_id.fetch_add(1, Ordering::Relaxed);
+        self.0.balancers.lock().insert(id, (addr, BTreeMap::new()));
+        Handle {
+            id,
+            state: self.clone(),
+        }
+    }
+}
+
+// === impl Handle ===
+
+impl Handle {
+    fn update(&self, update: &Update<Metadata>) {
+        let mut balancers = self.state.0.balancers.lock();
+        let endpoints = match balancers.get_mut(&self.id) {
+            Some((_, endpoints)) => endpoints,
+            None => return,
+        };
+        match update {
+            Update::Reset(eps) => *endpoints = eps.iter().cloned().collect(),
+            Update::Add(eps) => endpoints.extend(eps.iter().cloned()),
+            Update::Remove(addrs) => {
+                for addr in addrs {
+                    endpoints.remove(addr);
+                }
+            }
+            Update::DoesNotExist => endpoints.clear(),
+        }
+    }
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        // IDs are unique across both maps, so at most one entry is removed.
+        self.state.0.profiles.lock().remove(&self.id);
+        self.state.0.balancers.lock().remove(&self.id);
+    }
+}
+
+// === impl NewTrackProfile ===
+
+impl<N> NewTrackProfile<N> {
+    pub(crate) fn layer(state: State) -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(move |inner| Self {
+            state: state.clone(),
+            inner,
+        })
+    }
+}
+
+impl<T, N> svc::NewService<(Option<profiles::Receiver>, T)> for NewTrackProfile<N>
+where
+    T: Param<OrigDstAddr>,
+    N: svc::NewService<(Option<profiles::Receiver>, T)>,
+{
+    type Service = TrackProfile<N::Service>;
+
+    fn new_service(&self, (profile, target): (Option<profiles::Receiver>, T)) -> Self::Service {
+        let handle = profile
+            .as_ref()
+            .map(|rx| Arc::new(self.state.register_profile(target.param(), rx.clone())));
+        TrackProfile {
+            inner: self.inner.new_service((profile, target)),
+            _handle: handle,
+        }
+    }
+}
+
+// === impl TrackProfile ===
+
+impl<Req, S> svc::Service<Req> for TrackProfile<S>
+where
+    S: svc::Service<Req>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    #[inline]
+    fn call(&mut self, req: Req) -> Self::Future {
+        self.inner.call(req)
+    }
+}
+
+// === impl TrackResolve ===
+
+impl<R> svc::Service<ConcreteAddr> for TrackResolve<R>
+where
+    R: Resolve<ConcreteAddr, Endpoint = Metadata, Error = Error>,
+    R::Future: Unpin,
+{
+    type Response = TrackResolution<R::Resolution>;
+    type Error = Error;
+    type Future = TrackResolveFuture<R::Future>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, target: ConcreteAddr) -> Self::Future {
+        TrackResolveFuture {
+            inner: self.inner.resolve(target.clone()),
+            target: Some(target),
+            state: self.state.clone(),
+        }
+    }
+}
+
+// === impl TrackResolveFuture ===
+
+impl<F, S> Future for TrackResolveFuture<F>
+where
+    F: TryFuture<Ok = S, Error = Error> + Unpin,
+{
+    type Output = Result<TrackResolution<S>, Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let inner = ready!(self.inner.try_poll_unpin(cx))?;
+        let target = self.target.take().expect("polled after ready");
+        let handle = self.state.register_balancer(target);
+        Poll::Ready(Ok(TrackResolution { inner, handle }))
+    }
+}
+
+// === impl TrackResolution ===
+
+impl<S> Stream for TrackResolution<S>
+where
+    S: TryStream<Ok = Update<Metadata>, Error = Error>,
+{
+    type Item = Result<Update<Metadata>, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let update = ready!(this.inner.try_poll_next(cx));
+        if let Some(Ok(ref update)) = update {
+            this.handle.update(update);
+        }
+        Poll::Ready(update)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{future, stream};
+    use linkerd_app_core::svc::ServiceExt;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn tracks_balancer_endpoints_until_dropped() {
+        let addr = |n: u8| SocketAddr::from(([192, 0, 2, n], 8080));
+
+        let state = State::default();
+        let resolve = state.track_resolve(svc::mk(move |_: ConcreteAddr| {
+            let updates = vec![
+                Ok::<_, Error>(Update::Add(vec![
+                    (addr(1), Metadata::default()),
+                    (addr(2), Metadata::default()),
+                ])),
+                Ok(Update::Remove(vec![addr(1)])),
+            ];
+            future::ok::<_, Error>(stream::iter(updates))
+        }));
+        let concrete = ConcreteAddr("foo.ns.svc.cluster.local:8080".parse().unwrap());
+        let mut resolution = resolve.oneshot(concrete).await.expect("must resolve");
+        let balancer_endpoints = || {
+            state
+                .balancers()
+                .into_iter()
+                .map(|(_, eps)| eps.into_iter().map(|(a, _)| a).collect::<Vec<_>>())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(balancer_endpoints(), vec![Vec::<SocketAddr>::new()]);
+
+        resolution.next().await.unwrap().unwrap();
+        assert_eq!(balancer_endpoints(), vec![vec![addr(1), addr(2)]]);
+
+        resolution.next().await.unwrap().unwrap();
+        assert_eq!(balancer_endpoints(), vec![vec![addr(2)]]);
+
+        drop(resolution);
+        assert!(state.balancers().is_empty());
+    }
+}
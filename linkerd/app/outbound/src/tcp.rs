@@ -3,8 +3,11 @@ pub mod logical;
 pub mod opaque_transport;
 
 pub use self::connect::Connect;
-pub use linkerd_app_core::proxy::tcp::Forward;
-use linkerd_app_core::{svc::Param, transport::OrigDstAddr, transport_header::SessionProtocol};
+pub use linkerd_app_core::proxy::tcp::{balance, Forward};
+use linkerd_app_core::{
+    access_log, profiles, svc::Param, tls, transport::OrigDstAddr,
+    transport_header::SessionProtocol,
+};
 
 pub type Accept = crate::Accept<()>;
 pub type Logical = crate::logical::Logical<()>;
@@ -31,3 +34,29 @@ impl Param<Option<SessionProtocol>> for Endpoint {
         None
     }
 }
+
+impl Param<access_log::TcpConnection> for Logical {
+    fn param(&self) -> access_log::TcpConnection {
+        access_log::TcpConnection {
+            // The logical stack is shared by all connections to the service, so the original
+            // destination is not known.
+            orig_dst: None,
+            // Outbound clients are local to the pod and have no identity.
+            tls: tls::ConditionalServerTls::None(tls::NoServerTls::Loopback),
+            server: None,
+            authz: None,
+            target: Some(self.logical_addr.to_string()),
+        }
+    }
+}
+
+impl Param<Option<balance::Mode>> for Concrete {
+    fn param(&self) -> Option<balance::Mode> {
+        match self.logical.profile.load_balancer()? {
+            profiles::LoadBalancer::PeakEwma => Some(balance::Mode::PeakEwma),
+            profiles::LoadBalancer::LeastConnections => Some(balance::Mode::LeastConnections),
+            // Consistent hashing only applies to HTTP requests.
+            profiles::LoadBalancer::ConsistentHash(_) => None,
+        }
+    }
+}
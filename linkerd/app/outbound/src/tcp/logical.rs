@@ -1,7 +1,7 @@
 use super::{Concrete, Endpoint, Logical};
 use crate::{endpoint, resolve, Outbound};
 use linkerd_app_core::{
-    config, drain, io, profiles,
+    access_log, config, drain, io, profiles,
     proxy::{
         api_resolve::{ConcreteAddr, Metadata},
         core::Resolve,
@@ -29,7 +29,7 @@ impl<C> Outbound<C> {
         C::Metadata: Send + Unpin,
         C::Future: Send,
         C: Send + Sync + 'static,
-        I: io::AsyncRead + io::AsyncWrite + std::fmt::Debug + Send + Unpin + 'static,
+        I: io::AsyncRead + io::AsyncWrite + io::PeerAddr + std::fmt::Debug + Send + Unpin + 'static,
         R: Resolve<ConcreteAddr, Endpoint = Metadata, Error = Error>
             + Clone
             + Send
@@ -71,12 +71,15 @@ impl<C> Outbound<C> {
                     )
                 })
                 .push(resolve::layer(resolve, config.proxy.cache_max_idle_age * 2))
+                // Balances connections by peak-EWMA connect latency or by open connections,
+                // as configured by the profile or the proxy's default.
+                .push(tcp::balance::MakeBalance::layer(
+                    config.tcp_balance_mode,
+                    crate::EWMA_DEFAULT_RTT,
+                    crate::EWMA_DECAY,
+                ))
                 .push_on_service(
                     svc::layers()
-                        .push(tcp::balance::layer(
-                            crate::EWMA_DEFAULT_RTT,
-                            crate::EWMA_DECAY,
-                        ))
                         .push(
                             rt.metrics
                                 .proxy
@@ -89,8 +92,8 @@ impl<C> Outbound<C> {
                 .into_new_service()
                 .push_map_target(Concrete::from)
                 .push(svc::ArcNewService::layer())
-                .check_new_service::<(ConcreteAddr, Logical), I>()
-                .push(profiles::split::layer())
+                .check_new_service::<(ConcreteAddr, Logical), access_log::TcpIo<I>>()
+                .push(profiles::split::layer(profiles::split::Unpinned))
                 .push_on_service(
                     svc::layers()
                         .push(
@@ -103,6 +106,12 @@ impl<C> Outbound<C> {
                         .push(svc::FailFast::layer("TCP Logical", dispatch_timeout))
                         .push_spawn_buffer(buffer_capacity),
                 )
+                // Writes an access log record for each forwarded connection, if enabled.
+                .push(access_log::NewTcpAccessLog::layer(
+                    access_log::Direction::Outbound,
+                    rt.access_log.clone(),
+                    (),
+                ))
                 .push_cache(cache_max_idle_age)
                 .check_new_service::<Logical, I>()
                 .instrument(|_: &Logical| debug_span!("tcp"))
@@ -217,7 +217,7 @@ mod test {
             None,
             ProtocolHint::Unknown,
             Some(4143),
-            Some(tls::ServerId(
+            Some(tls::ClientTls::from(
                 identity::Name::from_str("server.id").unwrap(),
             )),
             None,
@@ -257,7 +257,7 @@ mod test {
             None,
             ProtocolHint::Unknown,
             Some(4143),
-            Some(tls::ServerId(
+            Some(tls::ClientTls::from(
                 identity::Name::from_str("server.id").unwrap(),
             )),
             Some(http::uri::Authority::from_str("foo.bar.example.com:5555").unwrap()),
@@ -297,7 +297,7 @@ mod test {
             None,
             ProtocolHint::Unknown,
             Some(4143),
-            Some(tls::ServerId(
+            Some(tls::ClientTls::from(
                 identity::Name::from_str("server.id").unwrap(),
             )),
             None,
@@ -16,6 +16,22 @@ pub(crate) fn default_config() -> Config {
     Config {
         ingress_mode: false,
         emit_headers: true,
+        retry_max_buffered_bytes: 64 * 1024,
+        retry_backoff: None,
+        retry_max_attempts: None,
+        hedge_delay: None,
+        http_outlier_ejection: crate::http::OutlierEjection {
+            consecutive_failures: None,
+            failure_rate: None,
+            failure_rate_min_requests: 0,
+            window: Duration::from_secs(10),
+            backoff: exp_backoff::ExponentialBackoff::default(),
+            max_ejected_ratio: 0.5,
+        },
+        http_hash_key: None,
+        tcp_balance_mode: crate::tcp::balance::Mode::PeakEwma,
+        local_zone: None,
+        backend_overrides: Vec::new(),
         allow_discovery: IpMatch::new(Some(IpNet::from_str("0.0.0.0/0").unwrap())).into(),
         proxy: config::ProxyConfig {
             server: config::ServerConfig {
@@ -57,6 +73,7 @@ pub(crate) fn runtime() -> (ProxyRuntime, drain::Signal) {
         metrics: metrics.proxy,
         tap,
         span_sink: None,
+        access_log: None,
         drain,
     };
     (runtime, drain_tx)
@@ -0,0 +1,603 @@
+use crate::{identity, oc_collector, tap, Config};
+use linkerd_app_admin::DumpConfig;
+use linkerd_app_core::{
+    config::ProxyConfig,
+    exp_backoff::ExponentialBackoff,
+    profiles,
+    proxy::api_resolve::{ConcreteAddr, Metadata},
+    transport::OrigDstAddr,
+};
+use linkerd_app_inbound::policy::{self, Authentication, Authorization, ServerPolicy};
+use linkerd_app_outbound as outbound;
+use serde_json::{json, Value};
+use std::{net::SocketAddr, time::Duration};
+
+/// Describes the proxy's configuration along with the inbound policies, outbound profiles, and
+/// balancer endpoints it currently holds.
+pub(crate) struct ConfigDump {
+    config: Value,
+    inbound: policy::Store,
+    outbound: outbound::State,
+}
+
+// === impl ConfigDump ===
+
+impl ConfigDump {
+    pub(crate) fn new(config: Value, inbound: policy::Store, outbound: outbound::State) -> Self {
+        Self {
+            config,
+            inbound,
+            outbound,
+        }
+    }
+}
+
+impl DumpConfig for ConfigDump {
+    fn dump_config(&self) -> Value {
+        let ports = self
+            .inbound
+            .port_policies()
+            .into_iter()
+            .map(|(port, policy)| json!({ "port": port, "policy": server_policy(&policy) }))
+            .collect::<Vec<_>>();
+
+        let profiles = self
+            .outbound
+            .profiles()
+            .into_iter()
+            .map(|(OrigDstAddr(addr), p)| profile(addr, p))
+            .collect::<Vec<_>>();
+
+        let balancers = self
+            .outbound
+            .balancers()
+            .into_iter()
+            .map(|(ConcreteAddr(addr), endpoints)| {
+                let endpoints = endpoints
+                    .iter()
+                    .map(|(addr, meta)| endpoint(*addr, meta))
+                    .collect::<Vec<_>>();
+                json!({ "addr": addr.to_string(), "endpoints": endpoints })
+            })
+            .collect::<Vec<_>>();
+
+        json!({
+            "config": self.config,
+            "inbound": {
+                "ports": ports,
+                "default": self.inbound.default_policy().as_ref().map(server_policy),
+            },
+            "outbound": {
+                "profiles": profiles,
+                "balancers": balancers,
+            },
+        })
+    }
+}
+
+/// Describes the proxy's static configuration.
+pub(crate) fn config(config: &Config) -> Value {
+    let Config {
+        outbound,
+        inbound,
+        gateway,
+        dns: _,
+        identity,
+        dst,
+        admin,
+        tap,
+        oc_collector,
+        access_log,
+    } = config;
+
+    let inbound_policy = match &inbound.policy {
+        policy::Config::Discover {
+            control,
+            workload,
+            default,
+            ports,
+        } => {
+            let mut ports = ports.iter().copied().collect::<Vec<_>>();
+            ports.sort_unstable();
+            json!({
+                "discover": {
+                    "addr": control.addr.to_string(),
+                    "workload": workload,
+                    "default": default_policy(default),
+                    "ports": ports,
+                }
+            })
+        }
+        policy::Config::Fixed { default, ports } => {
+            let mut ports = ports.iter().collect::<Vec<_>>();
+            ports.sort_unstable_by_key(|(port, _)| **port);
+            let ports = ports
+                .into_iter()
+                .map(|(port, policy)| json!({ "port": port, "policy": server_policy(policy) }))
+                .collect::<Vec<_>>();
+            json!({
+                "fixed": {
+                    "default": default_policy(default),
+                    "ports": ports,
+                }
+            })
+        }
+    };
+
+    let identity_provider = match &identity.provider {
+        identity::Provider::Certify { control, .. } => json!({
+            "certify": { "addr": control.addr.to_string() }
+        }),
+        identity::Provider::Files(files) => json!({
+            "files": {
+                "cert_chain": files.cert_chain_path.display().to_string(),
+                "key": files.key_path.display().to_string(),
+                "poll_interval": duration(files.poll_interval),
+            }
+        }),
+    };
+
+    let trust_anchors_file = identity
+        .trust_anchors
+        .as_ref()
+        .map(|ta| ta.path.display().to_string());
+
+    let tap = match tap {
+        tap::Config::Disabled => Value::Null,
+        tap::Config::Enabled {
+            config,
+            permitted_client_ids,
+        } => {
+            let mut ids = permitted_client_ids
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>();
+            ids.sort_unstable();
+            json!({
+                "addr": config.addr.to_string(),
+                "permitted_client_ids": ids,
+            })
+        }
+    };
+
+    let trace_collector = match oc_collector {
+        oc_collector::Config::Disabled => Value::Null,
+        oc_collector::Config::Enabled(oc) => json!({
+            "addr": oc.control.addr.to_string(),
+            "protocol": oc.protocol.to_string(),
+        }),
+    };
+
+    json!({
+        "inbound": {
+            "proxy": proxy(&inbound.proxy),
+            "allow_discovery": inbound.allow_discovery.to_string(),
+            "profile_idle_timeout": duration(inbound.profile_idle_timeout),
+            "max_in_flight_requests_per_client": inbound.max_in_flight_requests_per_client,
+            "policy": inbound_policy,
+        },
+        "outbound": {
+            "proxy": proxy(&outbound.proxy),
+            "allow_discovery": {
+                "names": outbound.allow_discovery.names().to_string(),
+                "networks": outbound.allow_discovery.nets().to_string(),
+            },
+            "ingress_mode": outbound.ingress_mode,
+            "emit_headers": outbound.emit_headers,
+            "retry_max_buffered_bytes": outbound.retry_max_buffered_bytes,
+            "retry_backoff": outbound.retry_backoff.as_ref().map(backoff),
+            "retry_max_attempts": outbound.retry_max_attempts,
+            "hedge_delay": outbound.hedge_delay.map(duration),
+            "http_outlier_ejection": outlier_ejection(&outbound.http_outlier_ejection),
+            "http_hash_key": outbound.http_hash_key.as_ref().map(hash_key),
+            "tcp_balance_mode": tcp_balance_mode(outbound.tcp_balance_mode),
+            "local_zone": outbound.local_zone.as_deref(),
+        },
+        "gateway": {
+            "allow_discovery": gateway.allow_discovery.to_string(),
+        },
+        "identity": {
+            "name": identity.documents.id.to_string(),
+            "provider": identity_provider,
+            "trust_anchors_file": trust_anchors_file,
+        },
+        "dst": {
+            "addr": dst.control.addr.to_string(),
+            "context": dst.context,
+        },
+        "admin": {
+            "addr": admin.server.addr.to_string(),
+            "metrics_retain_idle": duration(admin.metrics_retain_idle),
+            "shutdown_grace_period": duration(admin.shutdown_grace_period),
+        },
+        "tap": tap,
+        "trace_collector": trace_collector,
+        "access_log": access_log.as_ref().map(|log| json!({
+            "format": format!("{:?}", log.format),
+            "path": log.path.as_ref().map(|p| p.display().to_string()),
+        })),
+    })
+}
+
+fn proxy(config: &ProxyConfig) -> Value {
+    json!({
+        "addr": config.server.addr.to_string(),
+        "connect_timeout": duration(config.connect.timeout),
+        "buffer_capacity": config.buffer_capacity,
+        "cache_max_idle_age": duration(config.cache_max_idle_age),
+        "dispatch_timeout": duration(config.dispatch_timeout),
+        "max_in_flight_requests": config.max_in_flight_requests,
+        "detect_protocol_timeout": duration(config.detect_protocol_timeout),
+    })
+}
+
+fn outlier_ejection(config: &outbound::http::OutlierEjection) -> Value {
+    json!({
+        "consecutive_failures": config.consecutive_failures,
+        "failure_rate": config.failure_rate,
+        "failure_rate_min_requests": config.failure_rate_min_requests,
+        "window": duration(config.window),
+        "backoff": backoff(&config.backoff),
+        "max_ejected_ratio": config.max_ejected_ratio,
+    })
+}
+
+fn tcp_balance_mode(mode: outbound::tcp::balance::Mode) -> Value {
+    match mode {
+        outbound::tcp::balance::Mode::PeakEwma => json!("peak_ewma"),
+        outbound::tcp::balance::Mode::LeastConnections => json!("least_connections"),
+    }
+}
+
+fn default_policy(default: &policy::DefaultPolicy) -> Value {
+    match default {
+        policy::DefaultPolicy::Allow(policy) => server_policy(policy),
+        policy::DefaultPolicy::Deny => Value::String("deny".to_string()),
+    }
+}
+
+fn server_policy(policy: &ServerPolicy) -> Value {
+    let routes = policy
+        .http_routes
+        .iter()
+        .map(|route| {
+            json!({
+                "name": &*route.name,
+                "matches": route.matches.iter().map(server_request_match).collect::<Vec<_>>(),
+                "authorizations": authorizations(&route.authorizations),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "name": &*policy.name,
+        "protocol": server_protocol(policy.protocol),
+        "authorizations": authorizations(&policy.authorizations),
+        "http_routes": routes,
+        "rate_limit": policy.rate_limit.map(|rl| json!({
+            "total": rl.total,
+            "identity": rl.identity,
+        })),
+    })
+}
+
+fn server_protocol(protocol: policy::Protocol) -> Value {
+    match protocol {
+        policy::Protocol::Detect { timeout } => {
+            json!({ "detect": { "timeout": duration(timeout) } })
+        }
+        policy::Protocol::Http1 => json!("http1"),
+        policy::Protocol::Http2 => json!("http2"),
+        policy::Protocol::Grpc => json!("grpc"),
+        policy::Protocol::Opaque => json!("opaque"),
+        policy::Protocol::Tls => json!("tls"),
+    }
+}
+
+fn server_request_match(m: &policy::http::RequestMatch) -> Value {
+    let path = m.path.as_ref().map(|path| match path {
+        policy::http::PathMatch::Exact(p) => json!({ "exact": p }),
+        policy::http::PathMatch::Prefix(p) => json!({ "prefix": p }),
+        policy::http::PathMatch::Regex(re) => json!({ "regex": re.as_str() }),
+    });
+    let headers = m
+        .headers
+        .iter()
+        .map(|h| match h {
+            policy::http::HeaderMatch::Exact(name, value) => json!({
+                "name": name.as_str(),
+                "exact": String::from_utf8_lossy(value.as_bytes()),
+            }),
+            policy::http::HeaderMatch::Regex(name, re) => json!({
+                "name": name.as_str(),
+                "regex": re.as_str(),
+            }),
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "path": path,
+        "method": m.method.as_ref().map(|m| m.as_str()),
+        "headers": headers,
+    })
+}
+
+fn authorizations(authzs: &[Authorization]) -> Vec<Value> {
+    authzs.iter().map(authorization).collect()
+}
+
+fn authorization(authz: &Authorization) -> Value {
+    let networks = authz
+        .networks
+        .iter()
+        .map(|n| {
+            json!({
+                "net": n.net.to_string(),
+                "except": n.except.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let authentication = match &authz.authentication {
+        Authentication::Unauthenticated => json!("unauthenticated"),
+        Authentication::TlsUnauthenticated => json!("tls_unauthenticated"),
+        Authentication::TlsAuthenticated {
+            identities,
+            suffixes,
+        } => {
+            let mut identities = identities.iter().collect::<Vec<_>>();
+            identities.sort_unstable();
+            json!({
+                "identities": identities,
+                "suffixes": suffixes.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            })
+        }
+    };
+
+    json!({
+        "name": &*authz.name,
+        "networks": networks,
+        "authentication": authentication,
+    })
+}
+
+fn profile(addr: SocketAddr, profile: profiles::Profile) -> Value {
+    let targets = profile
+        .targets
+        .iter()
+        .map(|t| json!({ "addr": t.addr.to_string(), "weight": t.weight }))
+        .collect::<Vec<_>>();
+    let routes = profile
+        .http_routes
+        .iter()
+        .map(|(m, route)| {
+            json!({ "match": profile_request_match(m), "route": profile_route(route) })
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "orig_dst": addr.to_string(),
+        "logical_addr": profile.addr.as_ref().map(ToString::to_string),
+        "opaque_protocol": profile.opaque_protocol,
+        "targets": targets,
+        "endpoint": profile.endpoint.as_ref().map(|(addr, meta)| endpoint(*addr, meta)),
+        "load_balancer": profile.load_balancer.as_ref().map(load_balancer),
+        "http_routes": routes,
+    })
+}
+
+fn profile_request_match(m: &profiles::http::RequestMatch) -> Value {
+    use profiles::http::RequestMatch;
+    match m {
+        RequestMatch::All(ms) => {
+            json!({ "all": ms.iter().map(profile_request_match).collect::<Vec<_>>() })
+        }
+        RequestMatch::Any(ms) => {
+            json!({ "any": ms.iter().map(profile_request_match).collect::<Vec<_>>() })
+        }
+        RequestMatch::Not(m) => json!({ "not": profile_request_match(m) }),
+        RequestMatch::Path(re) => json!({ "path": re.as_str() }),
+        RequestMatch::Method(method) => json!({ "method": method.as_str() }),
+        RequestMatch::Header(name, value) => {
+            json!({ "header": { "name": name.as_str(), "value": value_match(value) } })
+        }
+        RequestMatch::QueryParam(name, value) => {
+            json!({ "query_param": { "name": name, "value": value_match(value) } })
+        }
+    }
+}
+
+fn value_match(m: &profiles::http::ValueMatch) -> Value {
+    match m {
+        profiles::http::ValueMatch::Exact(value) => json!({ "exact": value }),
+        profiles::http::ValueMatch::Regex(re) => json!({ "regex": re.as_str() }),
+    }
+}
+
+fn profile_route(route: &profiles::http::Route) -> Value {
+    json!({
+        "labels": &**route.labels(),
+        "timeout": route.timeout().map(duration),
+        "retries": route.retries().is_some(),
+    })
+}
+
+fn load_balancer(lb: &profiles::LoadBalancer) -> Value {
+    match lb {
+        profiles::LoadBalancer::PeakEwma => json!("peak_ewma"),
+        profiles::LoadBalancer::ConsistentHash(key) => json!({ "consistent_hash": hash_key(key) }),
+        profiles::LoadBalancer::LeastConnections => json!("least_connections"),
+    }
+}
+
+fn hash_key(key: &profiles::http::HashKey) -> Value {
+    match key {
+        profiles::http::HashKey::Header(name) => json!({ "header": name.as_str() }),
+        profiles::http::HashKey::Cookie(name) => json!({ "cookie": name }),
+        profiles::http::HashKey::ClientAddr => json!("client_addr"),
+    }
+}
+
+fn endpoint(addr: SocketAddr, meta: &Metadata) -> Value {
+    json!({
+        "addr": addr.to_string(),
+        "labels": &*meta.labels(),
+        "protocol_hint": format!("{:?}", meta.protocol_hint()),
+        "identity": meta.identity().map(|tls| tls.server_id.to_string()),
+        "opaque_transport_port": meta.opaque_transport_port(),
+        "authority_override": meta.authority_override().map(ToString::to_string),
+    })
+}
+
+fn backoff(backoff: &ExponentialBackoff) -> Value {
+    json!({
+        "min": duration(backoff.min),
+        "max": duration(backoff.max),
+        "jitter": backoff.jitter,
+    })
+}
+
+fn duration(d: Duration) -> String {
+    format!("{:?}", d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::proxy::http;
+    use std::num::NonZeroU32;
+
+    #[test]
+    fn dumps_inbound_policies() {
+        let prom = "prom.linkerd-viz.serviceaccount.identity.linkerd.cluster.local";
+        let policy = ServerPolicy {
+            protocol: policy::Protocol::Detect {
+                timeout: Duration::from_secs(10),
+            },
+            authorizations: vec![Authorization {
+                networks: vec!["10.0.0.0/8".parse().unwrap()],
+                authentication: Authentication::Unauthenticated,
+                name: "cluster".into(),
+            }],
+            http_routes: vec![policy::defaults::identity_route(
+                "/metrics",
+                vec![prom.to_string()],
+            )],
+            rate_limit: Some(policy::RateLimit {
+                total: NonZeroU32::new(100),
+                identity: None,
+            }),
+            name: "web".into(),
+        };
+        let (inbound, _tx) =
+            policy::Store::fixed(policy::DefaultPolicy::Deny, vec![(8080, policy)]);
+        let dump = ConfigDump::new(json!({ "test": true }), inbound, outbound::State::default())
+            .dump_config();
+
+        let all_nets = json!([
+            { "net": "0.0.0.0/0", "except": [] },
+            { "net": "::/0", "except": [] },
+        ]);
+        assert_eq!(
+            dump,
+            json!({
+                "config": { "test": true },
+                "inbound": {
+                    "ports": [{
+                        "port": 8080,
+                        "policy": {
+                            "name": "web",
+                            "protocol": { "detect": { "timeout": "10s" } },
+                            "authorizations": [{
+                                "name": "cluster",
+                                "networks": [{ "net": "10.0.0.0/8", "except": [] }],
+                                "authentication": "unauthenticated",
+                            }],
+                            "http_routes": [{
+                                "name": "default:route:/metrics",
+                                "matches": [{
+                                    "path": { "prefix": "/metrics" },
+                                    "method": null,
+                                    "headers": [],
+                                }],
+                                "authorizations": [{
+                                    "name": "default:route:/metrics",
+                                    "networks": all_nets,
+                                    "authentication": {
+                                        "identities": [prom],
+                                        "suffixes": [],
+                                    },
+                                }],
+                            }],
+                            "rate_limit": { "total": 100, "identity": null },
+                        },
+                    }],
+                    "default": null,
+                },
+                "outbound": {
+                    "profiles": [],
+                    "balancers": [],
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn dumps_profiles() {
+        let mut route = profiles::http::Route::new(
+            vec![("route".to_string(), "api".to_string())].into_iter(),
+            vec![],
+        );
+        route.set_timeout(Duration::from_secs(3));
+        let m = profiles::http::RequestMatch::All(vec![
+            profiles::http::RequestMatch::Path(Box::new(regex::Regex::new("^/api/").unwrap())),
+            profiles::http::RequestMatch::Not(Box::new(profiles::http::RequestMatch::Method(
+                http::Method::DELETE,
+            ))),
+            profiles::http::RequestMatch::Header(
+                http::HeaderName::from_static("x-api-version"),
+                profiles::http::ValueMatch::Exact("2".into()),
+            ),
+        ]);
+        let profile = profiles::Profile {
+            addr: Some(profiles::LogicalAddr(
+                "web.ns.svc.cluster.local:8080".parse().unwrap(),
+            )),
+            http_routes: vec![(m, route)],
+            targets: vec![profiles::Target {
+                addr: "web.ns.svc.cluster.local:8080".parse().unwrap(),
+                weight: 1,
+            }],
+            opaque_protocol: false,
+            endpoint: None,
+            load_balancer: Some(profiles::LoadBalancer::ConsistentHash(
+                profiles::http::HashKey::Header(http::HeaderName::from_static("x-user")),
+            )),
+        };
+
+        assert_eq!(
+            super::profile(([10, 0, 0, 1], 8080).into(), profile),
+            json!({
+                "orig_dst": "10.0.0.1:8080",
+                "logical_addr": "web.ns.svc.cluster.local:8080",
+                "opaque_protocol": false,
+                "targets": [{ "addr": "web.ns.svc.cluster.local:8080", "weight": 1 }],
+                "endpoint": null,
+                "load_balancer": { "consistent_hash": { "header": "x-user" } },
+                "http_routes": [{
+                    "match": {
+                        "all": [
+                            { "path": "^/api/" },
+                            { "not": { "method": "DELETE" } },
+                            { "header": { "name": "x-api-version", "value": { "exact": "2" } } },
+                        ]
+                    },
+                    "route": {
+                        "labels": { "route": "api" },
+                        "timeout": "3s",
+                        "retries": false,
+                    },
+                }],
+            })
+        );
+    }
+}
@@ -1,8 +1,9 @@
 use crate::core::{
-    addr,
+    access_log, addr,
     config::*,
     control::{Config as ControlConfig, ControlAddr},
-    proxy::http::{h1, h2},
+    http_tracing, profiles,
+    proxy::http::{h1, h2, HeaderName, HeaderValue},
     tls,
     transport::{Keepalive, ListenAddr},
     Addr, AddrMatch, Conditional, IpNet,
@@ -13,6 +14,7 @@ use std::{
     collections::{HashMap, HashSet},
     fs,
     net::{IpAddr, SocketAddr},
+    num::NonZeroU32,
     path::PathBuf,
     str::FromStr,
     time::Duration,
@@ -84,6 +86,22 @@ pub enum ParseError {
     InvalidTrustAnchors,
     #[error("not a valid port policy: {0}")]
     InvalidPortPolicy(String),
+    #[error("not a valid HTTP route: {0}")]
+    InvalidHttpRoute(String),
+    #[error("not a valid rate limit: {0}")]
+    InvalidRateLimit(String),
+    #[error("not a valid backend override: {0}")]
+    InvalidBackendOverride(String),
+    #[error("not a valid load balancer: {0}")]
+    InvalidLoadBalancer(String),
+    #[error("not a valid access log format: {0}")]
+    InvalidAccessLogFormat(String),
+    #[error("not a valid trace protocol: {0}")]
+    InvalidTraceProtocol(String),
+    #[error("not a valid sample ratio: {0}")]
+    InvalidSampleRatio(String),
+    #[error("not a valid header: {0}")]
+    InvalidHeader(String),
 }
 
 // Environment variables to look at when loading the configuration
@@ -94,6 +112,18 @@ pub const ENV_ADMIN_LISTEN_ADDR: &str = "LINKERD2_PROXY_ADMIN_LISTEN_ADDR";
 
 pub const ENV_METRICS_RETAIN_IDLE: &str = "LINKERD2_PROXY_METRICS_RETAIN_IDLE";
 
+/// Configures how long the proxy waits for connections to close when it drains, i.e. on
+/// `SIGTERM` or when requested via the admin server.
+pub const ENV_SHUTDOWN_GRACE_PERIOD: &str = "LINKERD2_PROXY_SHUTDOWN_GRACE_PERIOD";
+
+/// Enables an access log of the HTTP requests and forwarded TCP connections handled by the proxy.
+/// The value configures the format of the log's records, either `json` or `apache`. When unset,
+/// neither requests nor connections are logged.
+pub const ENV_ACCESS_LOG: &str = "LINKERD2_PROXY_ACCESS_LOG";
+
+/// The file to which access log records are appended. When unset, records are written to stderr.
+pub const ENV_ACCESS_LOG_PATH: &str = "LINKERD2_PROXY_ACCESS_LOG_PATH";
+
 const ENV_INGRESS_MODE: &str = "LINKERD2_PROXY_INGRESS_MODE";
 
 const ENV_INBOUND_DISPATCH_TIMEOUT: &str = "LINKERD2_PROXY_INBOUND_DISPATCH_TIMEOUT";
@@ -121,11 +151,86 @@ const ENV_OUTBOUND_MAX_IDLE_CONNS_PER_ENDPOINT: &str =
     "LINKERD2_PROXY_OUTBOUND_MAX_IDLE_CONNS_PER_ENDPOINT";
 
 pub const ENV_INBOUND_MAX_IN_FLIGHT: &str = "LINKERD2_PROXY_INBOUND_MAX_IN_FLIGHT";
+
+/// Limits the number of in-flight requests that each client identity (or, for clients without an
+/// identity, each client IP address) may have on each inbound server. Unlimited by default.
+pub const ENV_INBOUND_MAX_IN_FLIGHT_PER_CLIENT: &str =
+    "LINKERD2_PROXY_INBOUND_MAX_IN_FLIGHT_PER_CLIENT";
 pub const ENV_OUTBOUND_MAX_IN_FLIGHT: &str = "LINKERD2_PROXY_OUTBOUND_MAX_IN_FLIGHT";
 
 const ENV_OUTBOUND_DISABLE_INFORMATIONAL_HEADERS: &str =
     "LINKERD2_PROXY_OUTBOUND_DISABLE_INFORMATIONAL_HEADERS";
 
+/// Limits the size of request bodies that are buffered so that requests may be retried (or
+/// hedged). Requests with larger bodies are not retried. This limit applies to all routes: the
+/// destination API (as of linkerd2-proxy-api v0.3) does not describe per-route limits, so a
+/// route-level override is blocked until it does.
+pub const ENV_OUTBOUND_RETRY_MAX_BUFFERED_BYTES: &str =
+    "LINKERD2_PROXY_OUTBOUND_RETRY_MAX_BUFFERED_BYTES";
+
+/// Limits the number of attempts, including the original request, that may be made for each
+/// retryable request. Must be greater than zero. Unlimited (except by the retry budget) by default.
+///
+/// This limit applies to all routes: the destination API (as of linkerd2-proxy-api v0.3) does not
+/// describe per-route attempt limits, so a route-level override is blocked until it does.
+pub const ENV_OUTBOUND_RETRY_MAX_ATTEMPTS: &str = "LINKERD2_PROXY_OUTBOUND_RETRY_MAX_ATTEMPTS";
+
+/// Sends a second copy of requests on retryable routes when no response has been received after
+/// this delay. Request bodies are replayed up to the retry buffer limit. Requests are not hedged
+/// by default.
+///
+/// Only fixed delays are supported; delays based on a latency percentile are not.
+pub const ENV_OUTBOUND_HEDGE_DELAY: &str = "LINKERD2_PROXY_OUTBOUND_HEDGE_DELAY";
+
+/// Ejects an endpoint from HTTP load balancers after this many consecutive failures. Must be
+/// greater than zero.
+///
+/// If neither this nor the failure rate is set, endpoints are never ejected.
+pub const ENV_OUTBOUND_OUTLIER_CONSECUTIVE_FAILURES: &str =
+    "LINKERD2_PROXY_OUTBOUND_OUTLIER_CONSECUTIVE_FAILURES";
+
+/// Ejects an endpoint from HTTP load balancers when at least this ratio (between 0.0 and 1.0) of
+/// its responses fail within the outlier window.
+pub const ENV_OUTBOUND_OUTLIER_FAILURE_RATE: &str = "LINKERD2_PROXY_OUTBOUND_OUTLIER_FAILURE_RATE";
+const ENV_OUTBOUND_OUTLIER_FAILURE_RATE_MIN_REQUESTS: &str =
+    "LINKERD2_PROXY_OUTBOUND_OUTLIER_FAILURE_RATE_MIN_REQUESTS";
+const ENV_OUTBOUND_OUTLIER_WINDOW: &str = "LINKERD2_PROXY_OUTBOUND_OUTLIER_WINDOW";
+
+/// The maximum ratio (between 0.0 and 1.0) of an HTTP load balancer's endpoints that may be
+/// ejected at once.
+pub const ENV_OUTBOUND_OUTLIER_MAX_EJECTED_RATIO: &str =
+    "LINKERD2_PROXY_OUTBOUND_OUTLIER_MAX_EJECTED_RATIO";
+
+/// Configures how opaque TCP connections are balanced when a service's profile does not configure
+/// a load balancer. Either `ewma` (the default) or `least-connections`.
+///
+/// The destination API cannot yet configure load balancers on profiles, so this currently applies
+/// to every service.
+pub const ENV_OUTBOUND_TCP_LOAD_BALANCER: &str = "LINKERD2_PROXY_OUTBOUND_TCP_LOAD_BALANCER";
+
+/// Configures how HTTP requests are balanced when a service's profile does not configure a load
+/// balancer. Either `ewma` (the default), or a consistent hash of the request's `header:<name>`,
+/// `cookie:<name>`, or `client-addr`.
+///
+/// The destination API cannot yet configure load balancers on profiles, so this currently applies
+/// to every service.
+pub const ENV_OUTBOUND_HTTP_LOAD_BALANCER: &str = "LINKERD2_PROXY_OUTBOUND_HTTP_LOAD_BALANCER";
+
+/// The zone in which the proxy runs. When set, HTTP load balancers prefer endpoints whose
+/// `topology.kubernetes.io/zone` label matches, spilling over to other zones when no local
+/// endpoint is available or local endpoints are more loaded than remote ones.
+pub const ENV_OUTBOUND_LOCAL_ZONE: &str = "LINKERD2_PROXY_OUTBOUND_LOCAL_ZONE";
+
+/// Pins HTTP requests that match a header or cookie to a backend of a service's traffic split,
+/// e.g. so that canaries may be tested deterministically.
+///
+/// This is a comma-separated list of `header:<name>=<value>@<backend>` and
+/// `cookie:<name>=<value>@<backend>` entries, e.g.
+/// `header:x-canary=true@web-canary.ns.svc.cluster.local:8080`. Overrides only apply to services
+/// whose traffic split includes the backend, and the overrides of a profile's routes take
+/// precedence.
+pub const ENV_OUTBOUND_BACKEND_OVERRIDES: &str = "LINKERD2_PROXY_OUTBOUND_BACKEND_OVERRIDES";
+
 pub const ENV_TRACE_ATTRIBUTES_PATH: &str = "LINKERD2_PROXY_TRACE_ATTRIBUTES_PATH";
 
 /// Constrains which destination names may be used for profile/route discovery.
@@ -172,6 +277,26 @@ pub const ENV_INBOUND_PORTS_REQUIRE_TLS: &str = "LINKERD2_PROXY_INBOUND_PORTS_RE
 /// By default, this is `unauthenticated`.
 pub const ENV_INBOUND_DEFAULT_POLICY: &str = "LINKERD2_PROXY_INBOUND_DEFAULT_POLICY";
 
+/// Configures HTTP routes that only authorize clients with specific identities.
+///
+/// This is a comma-separated list of `<path-prefix>=<identity>[|<identity>...]` entries, e.g.
+/// `/metrics=prometheus.linkerd-viz.serviceaccount.identity.linkerd.cluster.local`. Requests that
+/// match no route are authorized by the server's authorizations.
+///
+/// Routes apply to the default policy and to statically-configured port policies; policies
+/// discovered from the control plane are not modified.
+pub const ENV_INBOUND_HTTP_ROUTES: &str = "LINKERD2_PROXY_INBOUND_HTTP_ROUTES";
+
+/// Configures the rate at which each inbound server accepts HTTP requests.
+///
+/// This is a comma-separated list of `total=<requests-per-second>` and
+/// `identity=<requests-per-second>` entries. Each port is limited separately; and, when an
+/// `identity` limit is set, so is each client identity. Rates must be greater than zero.
+///
+/// Like `ENV_INBOUND_HTTP_ROUTES`, limits apply to the default policy and to statically-configured
+/// port policies.
+pub const ENV_INBOUND_RATE_LIMIT: &str = "LINKERD2_PROXY_INBOUND_RATE_LIMIT";
+
 pub const ENV_INBOUND_PORTS: &str = "LINKERD2_PROXY_INBOUND_PORTS";
 pub const ENV_POLICY_SVC_BASE: &str = "LINKERD2_PROXY_POLICY_SVC";
 pub const ENV_POLICY_WORKLOAD: &str = "LINKERD2_PROXY_POLICY_WORKLOAD";
@@ -187,6 +312,24 @@ pub const ENV_IDENTITY_TOKEN_FILE: &str = "LINKERD2_PROXY_IDENTITY_TOKEN_FILE";
 pub const ENV_IDENTITY_MIN_REFRESH: &str = "LINKERD2_PROXY_IDENTITY_MIN_REFRESH";
 pub const ENV_IDENTITY_MAX_REFRESH: &str = "LINKERD2_PROXY_IDENTITY_MAX_REFRESH";
 
+/// Configures the proxy to load its certificate chain and PKCS#8 private key from PEM files,
+/// instead of obtaining a certificate from the Identity service.
+///
+/// The certificate file is watched for rotated certificates, which must be issued for the same
+/// private key.
+pub const ENV_IDENTITY_CERT_FILE: &str = "LINKERD2_PROXY_IDENTITY_CERT_FILE";
+pub const ENV_IDENTITY_KEY_FILE: &str = "LINKERD2_PROXY_IDENTITY_KEY_FILE";
+
+/// Configures the proxy to load its trust anchors from a PEM file, instead of from
+/// `LINKERD2_PROXY_IDENTITY_TRUST_ANCHORS`.
+///
+/// The file is watched so that roots may be rotated without restarting the proxy. A new bundle is
+/// only applied if it trusts the proxy's current certificate.
+pub const ENV_IDENTITY_TRUST_ANCHORS_FILE: &str = "LINKERD2_PROXY_IDENTITY_TRUST_ANCHORS_FILE";
+
+/// Configures how often the identity certificate and trust anchors files are checked for changes.
+pub const ENV_IDENTITY_FILE_POLL_INTERVAL: &str = "LINKERD2_PROXY_IDENTITY_FILE_POLL_INTERVAL";
+
 pub const ENV_IDENTITY_SVC_BASE: &str = "LINKERD2_PROXY_IDENTITY_SVC";
 
 pub const ENV_DESTINATION_SVC_BASE: &str = "LINKERD2_PROXY_DESTINATION_SVC";
@@ -195,6 +338,25 @@ pub const ENV_HOSTNAME: &str = "HOSTNAME";
 
 pub const ENV_TRACE_COLLECTOR_SVC_BASE: &str = "LINKERD2_PROXY_TRACE_COLLECTOR_SVC";
 
+/// Configures the protocol used to export spans to the trace collector. Either `opencensus` (the
+/// default) or `opentelemetry`, which exports spans with the OpenTelemetry Protocol (OTLP).
+pub const ENV_TRACE_PROTOCOL: &str = "LINKERD2_PROXY_TRACE_PROTOCOL";
+
+/// Configures the proportion of requests, between 0.0 and 1.0, for which the proxy starts a trace
+/// when the request is not already sampled. By default, the proxy never starts traces.
+pub const ENV_TRACE_SAMPLE_RATIO: &str = "LINKERD2_PROXY_TRACE_SAMPLE_RATIO";
+
+/// Limits the number of traces the proxy starts each second.
+pub const ENV_TRACE_SAMPLE_MAX_PER_SECOND: &str = "LINKERD2_PROXY_TRACE_SAMPLE_MAX_PER_SECOND";
+
+/// A comma-separated list of path prefixes. Requests whose paths start with one of these prefixes
+/// are always sampled.
+pub const ENV_TRACE_FORCE_SAMPLE_PATHS: &str = "LINKERD2_PROXY_TRACE_FORCE_SAMPLE_PATHS";
+
+/// A comma-separated list of `name` or `name=value` headers. Requests that have a matching header
+/// are always sampled.
+pub const ENV_TRACE_FORCE_SAMPLE_HEADERS: &str = "LINKERD2_PROXY_TRACE_FORCE_SAMPLE_HEADERS";
+
 pub const ENV_DESTINATION_CONTEXT: &str = "LINKERD2_PROXY_DESTINATION_CONTEXT";
 pub const ENV_DESTINATION_PROFILE_INITIAL_TIMEOUT: &str =
     "LINKERD2_PROXY_DESTINATION_PROFILE_INITIAL_TIMEOUT";
@@ -224,6 +386,7 @@ pub const DEFAULT_INBOUND_LISTEN_ADDR: &str = "0.0.0.0:4143";
 pub const DEFAULT_CONTROL_LISTEN_ADDR: &str = "0.0.0.0:4190";
 const DEFAULT_ADMIN_LISTEN_ADDR: &str = "127.0.0.1:4191";
 const DEFAULT_METRICS_RETAIN_IDLE: Duration = Duration::from_secs(10 * 60);
+const DEFAULT_SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(2 * 60);
 const DEFAULT_INBOUND_DISPATCH_TIMEOUT: Duration = Duration::from_secs(1);
 const DEFAULT_INBOUND_DETECT_TIMEOUT: Duration = Duration::from_secs(10);
 const DEFAULT_INBOUND_CONNECT_TIMEOUT: Duration = Duration::from_millis(300);
@@ -272,6 +435,21 @@ const DEFAULT_OUTBOUND_MAX_IDLE_CONNS_PER_ENDPOINT: usize = std::usize::MAX;
 const DEFAULT_INBOUND_MAX_IN_FLIGHT: usize = 100_000;
 const DEFAULT_OUTBOUND_MAX_IN_FLIGHT: usize = 100_000;
 
+// Requests with bodies larger than this are not retried, on any route.
+const DEFAULT_OUTBOUND_RETRY_MAX_BUFFERED_BYTES: usize = 64 * 1024;
+
+// Outlier ejection is disabled unless a consecutive failure limit or failure rate is configured.
+// Ejected endpoints are readmitted after a backoff that grows each time an endpoint is ejected
+// again.
+const DEFAULT_OUTBOUND_OUTLIER_FAILURE_RATE_MIN_REQUESTS: u32 = 20;
+const DEFAULT_OUTBOUND_OUTLIER_WINDOW: Duration = Duration::from_secs(10);
+const DEFAULT_OUTBOUND_OUTLIER_MAX_EJECTED_RATIO: f64 = 0.5;
+const DEFAULT_OUTBOUND_OUTLIER_EJECTION_BACKOFF: ExponentialBackoff = ExponentialBackoff {
+    min: Duration::from_secs(5),
+    max: Duration::from_secs(5 * 60),
+    jitter: 0.1,
+};
+
 // This value should be large enough to admit requests without exerting
 // backpressure so that requests implicitly buffer in the executor; but it
 // should be small enough that callers can't force the proxy to consume an
@@ -287,9 +465,14 @@ const DEFAULT_DESTINATION_PROFILE_IDLE_TIMEOUT: Duration = Duration::from_millis
 
 const DEFAULT_IDENTITY_MIN_REFRESH: Duration = Duration::from_secs(10);
 const DEFAULT_IDENTITY_MAX_REFRESH: Duration = Duration::from_secs(60 * 60 * 24);
+const DEFAULT_IDENTITY_FILE_POLL_INTERVAL: Duration = Duration::from_secs(10);
 
 const INBOUND_CONNECT_BASE: &str = "INBOUND_CONNECT";
 const OUTBOUND_CONNECT_BASE: &str = "OUTBOUND_CONNECT";
+const OUTBOUND_OUTLIER_EJECTION_BASE: &str = "OUTBOUND_OUTLIER_EJECTION";
+// Retries are issued without a backoff unless one is configured. The backoff applies to all
+// routes; the destination API (as of linkerd2-proxy-api v0.3) cannot configure it per route.
+const OUTBOUND_RETRY_BASE: &str = "OUTBOUND_RETRY";
 
 /// Load a `App` by reading ENV variables.
 pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError> {
@@ -338,9 +521,54 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
     );
 
     let inbound_max_in_flight = parse(strings, ENV_INBOUND_MAX_IN_FLIGHT, parse_number);
+    let inbound_max_in_flight_per_client =
+        parse(strings, ENV_INBOUND_MAX_IN_FLIGHT_PER_CLIENT, parse_number);
     let outbound_max_in_flight = parse(strings, ENV_OUTBOUND_MAX_IN_FLIGHT, parse_number);
+    let outbound_retry_max_buffered_bytes =
+        parse(strings, ENV_OUTBOUND_RETRY_MAX_BUFFERED_BYTES, parse_number);
+    let outbound_hedge_delay = parse(strings, ENV_OUTBOUND_HEDGE_DELAY, parse_duration);
+    let outbound_retry_max_attempts = parse(
+        strings,
+        ENV_OUTBOUND_RETRY_MAX_ATTEMPTS,
+        parse_number::<NonZeroU32>,
+    );
+    let outbound_outlier_consecutive_failures = parse(
+        strings,
+        ENV_OUTBOUND_OUTLIER_CONSECUTIVE_FAILURES,
+        parse_number::<NonZeroU32>,
+    );
+    let outbound_outlier_failure_rate = parse(
+        strings,
+        ENV_OUTBOUND_OUTLIER_FAILURE_RATE,
+        parse_number::<f64>,
+    );
+    let outbound_outlier_failure_rate_min_requests = parse(
+        strings,
+        ENV_OUTBOUND_OUTLIER_FAILURE_RATE_MIN_REQUESTS,
+        parse_number,
+    );
+    let outbound_outlier_window = parse(strings, ENV_OUTBOUND_OUTLIER_WINDOW, parse_duration);
+    let outbound_outlier_max_ejected_ratio = parse(
+        strings,
+        ENV_OUTBOUND_OUTLIER_MAX_EJECTED_RATIO,
+        parse_number::<f64>,
+    );
+    let outbound_http_load_balancer = parse(
+        strings,
+        ENV_OUTBOUND_HTTP_LOAD_BALANCER,
+        parse_http_hash_key,
+    );
+    let outbound_tcp_load_balancer = parse(
+        strings,
+        ENV_OUTBOUND_TCP_LOAD_BALANCER,
+        parse_tcp_balance_mode,
+    );
 
     let metrics_retain_idle = parse(strings, ENV_METRICS_RETAIN_IDLE, parse_duration);
+    let shutdown_grace_period = parse(strings, ENV_SHUTDOWN_GRACE_PERIOD, parse_duration);
+
+    let access_log_format = parse(strings, ENV_ACCESS_LOG, parse_access_log_format);
+    let access_log_path = strings.get(ENV_ACCESS_LOG_PATH);
 
     // DNS
 
@@ -356,11 +584,28 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
     let oc_attributes_file_path = strings.get(ENV_TRACE_ATTRIBUTES_PATH);
 
     let trace_collector_addr = parse_control_addr(strings, ENV_TRACE_COLLECTOR_SVC_BASE);
+    let trace_protocol = parse(strings, ENV_TRACE_PROTOCOL, parse_trace_protocol);
+    let trace_sample_ratio = parse(strings, ENV_TRACE_SAMPLE_RATIO, parse_sample_ratio);
+    let trace_sample_max_per_second = parse(strings, ENV_TRACE_SAMPLE_MAX_PER_SECOND, parse_number);
+    let trace_force_sample_paths =
+        parse(strings, ENV_TRACE_FORCE_SAMPLE_PATHS, parse_path_prefixes);
+    let trace_force_sample_headers = parse(
+        strings,
+        ENV_TRACE_FORCE_SAMPLE_HEADERS,
+        parse_header_matches,
+    );
 
     let gateway_suffixes = parse(strings, ENV_INBOUND_GATEWAY_SUFFIXES, parse_dns_suffixes);
 
     let dst_addr = parse_control_addr(strings, ENV_DESTINATION_SVC_BASE);
     let dst_token = strings.get(ENV_DESTINATION_CONTEXT);
+
+    let outbound_local_zone = strings.get(ENV_OUTBOUND_LOCAL_ZONE);
+    let outbound_backend_overrides = parse(
+        strings,
+        ENV_OUTBOUND_BACKEND_OVERRIDES,
+        parse_backend_overrides,
+    );
     let dst_profile_idle_timeout = parse(
         strings,
         ENV_DESTINATION_PROFILE_INITIAL_TIMEOUT,
@@ -455,9 +700,55 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
         let dispatch_timeout =
             outbound_dispatch_timeout?.unwrap_or(DEFAULT_OUTBOUND_DISPATCH_TIMEOUT);
 
+        let failure_rate = outbound_outlier_failure_rate?;
+        if let Some(rate) = failure_rate {
+            if !(0.0..=1.0).contains(&rate) {
+                error!(
+                    "{} must be between 0.0 and 1.0",
+                    ENV_OUTBOUND_OUTLIER_FAILURE_RATE
+                );
+                return Err(EnvError::InvalidEnvVar);
+            }
+        }
+        let max_ejected_ratio = outbound_outlier_max_ejected_ratio?
+            .unwrap_or(DEFAULT_OUTBOUND_OUTLIER_MAX_EJECTED_RATIO);
+        if !(0.0..=1.0).contains(&max_ejected_ratio) {
+            error!(
+                "{} must be between 0.0 and 1.0",
+                ENV_OUTBOUND_OUTLIER_MAX_EJECTED_RATIO
+            );
+            return Err(EnvError::InvalidEnvVar);
+        }
+        let http_outlier_ejection = outbound::http::OutlierEjection {
+            consecutive_failures: outbound_outlier_consecutive_failures?.map(NonZeroU32::get),
+            failure_rate,
+            failure_rate_min_requests: outbound_outlier_failure_rate_min_requests?
+                .unwrap_or(DEFAULT_OUTBOUND_OUTLIER_FAILURE_RATE_MIN_REQUESTS),
+            window: outbound_outlier_window?.unwrap_or(DEFAULT_OUTBOUND_OUTLIER_WINDOW),
+            backoff: parse_backoff(
+                strings,
+                OUTBOUND_OUTLIER_EJECTION_BASE,
+                DEFAULT_OUTBOUND_OUTLIER_EJECTION_BACKOFF,
+            )?,
+            max_ejected_ratio,
+        };
+
         outbound::Config {
             ingress_mode,
             emit_headers: !disable_headers,
+            retry_max_buffered_bytes: outbound_retry_max_buffered_bytes?
+                .unwrap_or(DEFAULT_OUTBOUND_RETRY_MAX_BUFFERED_BYTES),
+            retry_backoff: parse_optional_backoff(strings, OUTBOUND_RETRY_BASE)?,
+            retry_max_attempts: outbound_retry_max_attempts?.map(NonZeroU32::get),
+            hedge_delay: outbound_hedge_delay?,
+            http_outlier_ejection,
+            http_hash_key: outbound_http_load_balancer?.flatten(),
+            tcp_balance_mode: outbound_tcp_load_balancer?
+                .unwrap_or(outbound::tcp::balance::Mode::PeakEwma),
+            local_zone: outbound_local_zone?
+                .filter(|z| !z.is_empty())
+                .map(Into::into),
+            backend_overrides: outbound_backend_overrides?.unwrap_or_default(),
             allow_discovery: AddrMatch::new(dst_profile_suffixes.clone(), dst_profile_networks),
             proxy: ProxyConfig {
                 server,
@@ -532,7 +823,7 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
 
             // We always configure a default policy. This policy applies when no other policy is
             // configured, especially when the port is not documented in via `ENV_INBOUND_PORTS`.
-            let default = parse(strings, ENV_INBOUND_DEFAULT_POLICY, |s| {
+            let mut default = parse(strings, ENV_INBOUND_DEFAULT_POLICY, |s| {
                 parse_default_policy(s, cluster_nets, detect_protocol_timeout)
             })?
             .unwrap_or_else(|| {
@@ -543,6 +834,14 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
                 policy::defaults::all_unauthenticated(detect_protocol_timeout).into()
             });
 
+            let http_routes =
+                parse(strings, ENV_INBOUND_HTTP_ROUTES, parse_http_routes)?.unwrap_or_default();
+            let rate_limit = parse(strings, ENV_INBOUND_RATE_LIMIT, parse_rate_limit)?;
+            if let policy::DefaultPolicy::Allow(ref mut policy) = default {
+                policy.http_routes = http_routes.clone();
+                policy.rate_limit = rate_limit;
+            }
+
             match parse_control_addr(strings, ENV_POLICY_SVC_BASE)? {
                 Some(addr) => {
                     // If the inbound is proxy is configured to discover policies, then load the set
@@ -674,6 +973,11 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
                             .into_iter()
                             .chain(require_tls_ports)
                             .chain(opaque_ports)
+                            .map(|(port, mut policy)| {
+                                policy.http_routes = http_routes.clone();
+                                policy.rate_limit = rate_limit;
+                                (port, policy)
+                            })
                             .collect(),
                     }
                 }
@@ -696,6 +1000,7 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
             profile_idle_timeout: dst_profile_idle_timeout?
                 .unwrap_or(DEFAULT_DESTINATION_PROFILE_IDLE_TIMEOUT),
             allowed_ips: inbound_ips.into(),
+            max_in_flight_requests_per_client: inbound_max_in_flight_per_client?,
         }
     };
 
@@ -718,6 +1023,7 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
 
     let admin = super::admin::Config {
         metrics_retain_idle: metrics_retain_idle?.unwrap_or(DEFAULT_METRICS_RETAIN_IDLE),
+        shutdown_grace_period: shutdown_grace_period?.unwrap_or(DEFAULT_SHUTDOWN_GRACE_PERIOD),
         server: ServerConfig {
             addr: ListenAddr(admin_listener_addr),
             keepalive: inbound.proxy.server.keepalive,
@@ -752,6 +1058,15 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
             oc_collector::Config::Enabled(Box::new(oc_collector::EnabledConfig {
                 attributes,
                 hostname: hostname?,
+                protocol: trace_protocol?.unwrap_or(oc_collector::Protocol::OpenCensus),
+                sampler: http_tracing::Sampler::new(
+                    trace_sample_ratio?.unwrap_or(0.0),
+                    trace_sample_max_per_second?,
+                    http_tracing::ForceSample {
+                        path_prefixes: trace_force_sample_paths?.unwrap_or_default(),
+                        headers: trace_force_sample_headers?.unwrap_or_default(),
+                    },
+                ),
                 control: ControlConfig {
                     addr,
                     connect,
@@ -761,6 +1076,11 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
         }
     };
 
+    let access_log = {
+        let path = access_log_path?.map(PathBuf::from);
+        access_log_format?.map(|format| access_log::Config { format, path })
+    };
+
     let tap = tap?
         .map(|(addr, ids)| super::tap::Config::Enabled {
             permitted_client_ids: ids,
@@ -773,21 +1093,30 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
         .unwrap_or(super::tap::Config::Disabled);
 
     let identity = {
-        let (addr, certify, documents) = identity_config?;
-        // If the address doesn't have a server identity, then we're on localhost.
-        let connect = if addr.addr.is_loopback() {
-            inbound.proxy.connect.clone()
-        } else {
-            outbound.proxy.connect.clone()
+        let (provider, documents, trust_anchors) = identity_config?;
+        let provider = match provider {
+            IdentityProvider::Certify(addr, certify) => {
+                // If the address doesn't have a server identity, then we're on localhost.
+                let connect = if addr.addr.is_loopback() {
+                    inbound.proxy.connect.clone()
+                } else {
+                    outbound.proxy.connect.clone()
+                };
+                identity::Provider::Certify {
+                    certify,
+                    control: ControlConfig {
+                        addr,
+                        connect,
+                        buffer_capacity: 1,
+                    },
+                }
+            }
+            IdentityProvider::Files(files) => identity::Provider::Files(files),
         };
         identity::Config {
-            certify,
-            control: ControlConfig {
-                addr,
-                connect,
-                buffer_capacity: 1,
-            },
+            provider,
             documents,
+            trust_anchors,
         }
     };
 
@@ -801,6 +1130,7 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
         outbound,
         gateway,
         inbound,
+        access_log,
     })
 }
 
@@ -873,7 +1203,7 @@ fn parse_tap_config(
     if let Some(id) = tap_identity {
         return Ok(Some((
             addr,
-            vec![id].into_iter().map(tls::ClientId).collect(),
+            vec![id].into_iter().map(tls::ClientId::from).collect(),
         )));
     }
     Ok(None)
@@ -1049,11 +1379,165 @@ fn parse_default_policy(
         name => Err(ParseError::InvalidPortPolicy(name.to_string())),
     }
 }
+
+fn parse_http_routes(s: &str) -> Result<Vec<policy::http::Route>, ParseError> {
+    let mut routes = Vec::new();
+    for route in s.split(',') {
+        let route = route.trim();
+        if route.is_empty() {
+            continue;
+        }
+        let invalid = || ParseError::InvalidHttpRoute(route.to_string());
+        let (prefix, identities) = route.split_once('=').ok_or_else(invalid)?;
+        if !prefix.starts_with('/') {
+            return Err(invalid());
+        }
+        let identities = identities
+            .split('|')
+            .map(|id| parse_identity(id.trim()).map(|id| id.to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+        routes.push(policy::defaults::identity_route(prefix, identities));
+    }
+    Ok(routes)
+}
+
+fn parse_rate_limit(s: &str) -> Result<policy::RateLimit, ParseError> {
+    let mut limit = policy::RateLimit {
+        total: None,
+        identity: None,
+    };
+    for kv in s.split(',') {
+        let invalid = || ParseError::InvalidRateLimit(kv.to_string());
+        let (key, rate) = kv.trim().split_once('=').ok_or_else(invalid)?;
+        let rate = parse_number::<NonZeroU32>(rate)?;
+        match key {
+            "total" => limit.total = Some(rate),
+            "identity" => limit.identity = Some(rate),
+            _ => return Err(invalid()),
+        }
+    }
+    Ok(limit)
+}
+
+fn parse_tcp_balance_mode(s: &str) -> Result<outbound::tcp::balance::Mode, ParseError> {
+    match s {
+        "ewma" => Ok(outbound::tcp::balance::Mode::PeakEwma),
+        "least-connections" => Ok(outbound::tcp::balance::Mode::LeastConnections),
+        name => Err(ParseError::InvalidLoadBalancer(name.to_string())),
+    }
+}
+
+fn parse_backend_overrides(s: &str) -> Result<Vec<profiles::http::BackendOverride>, ParseError> {
+    use profiles::http::{BackendMatch, BackendOverride, ValueMatch};
+
+    let mut overrides = Vec::new();
+    for o in s.split(',') {
+        let o = o.trim();
+        if o.is_empty() {
+            continue;
+        }
+        let invalid = || ParseError::InvalidBackendOverride(o.to_string());
+        let (m, backend) = o.rsplit_once('@').ok_or_else(invalid)?;
+        let backend = addr::NameAddr::from_str(backend).map_err(|_| invalid())?;
+        let (name, value) = m.split_once('=').ok_or_else(invalid)?;
+        let value = ValueMatch::Exact(value.to_string());
+        let m = match name.split_once(':') {
+            Some(("header", name)) => HeaderName::from_bytes(name.as_bytes())
+                .map(|name| BackendMatch::Header(name, value))
+                .map_err(|_| invalid())?,
+            Some(("cookie", name)) if !name.is_empty() => {
+                BackendMatch::Cookie(name.to_string(), value)
+            }
+            _ => return Err(invalid()),
+        };
+        overrides.push(BackendOverride::new(m, backend));
+    }
+    Ok(overrides)
+}
+
+fn parse_http_hash_key(s: &str) -> Result<Option<profiles::http::HashKey>, ParseError> {
+    use profiles::http::HashKey;
+    match s.split_once(':') {
+        None if s == "ewma" => Ok(None),
+        None if s == "client-addr" => Ok(Some(HashKey::ClientAddr)),
+        Some(("header", name)) => HeaderName::from_bytes(name.as_bytes())
+            .map(|name| Some(HashKey::Header(name)))
+            .map_err(|_| ParseError::InvalidLoadBalancer(s.to_string())),
+        Some(("cookie", name)) if !name.is_empty() => Ok(Some(HashKey::Cookie(name.to_string()))),
+        _ => Err(ParseError::InvalidLoadBalancer(s.to_string())),
+    }
+}
+
+fn parse_access_log_format(s: &str) -> Result<access_log::Format, ParseError> {
+    match s {
+        "json" => Ok(access_log::Format::Json),
+        "apache" => Ok(access_log::Format::Apache),
+        name => Err(ParseError::InvalidAccessLogFormat(name.to_string())),
+    }
+}
+
+fn parse_sample_ratio(s: &str) -> Result<f64, ParseError> {
+    let ratio = parse_number::<f64>(s)?;
+    if (0.0..=1.0).contains(&ratio) {
+        Ok(ratio)
+    } else {
+        Err(ParseError::InvalidSampleRatio(s.to_string()))
+    }
+}
+
+fn parse_path_prefixes(list: &str) -> Result<Vec<String>, ParseError> {
+    Ok(list
+        .split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+fn parse_header_matches(list: &str) -> Result<Vec<(HeaderName, Option<HeaderValue>)>, ParseError> {
+    let mut headers = Vec::new();
+    for item in list.split(',') {
+        let item = item.trim();
+        if item.is_empty() {
+            continue;
+        }
+
+        let (name, value) = match item.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (item, None),
+        };
+        let name = HeaderName::from_str(name.trim())
+            .map_err(|_| ParseError::InvalidHeader(item.to_string()))?;
+        let value = value
+            .map(|v| HeaderValue::from_str(v.trim()))
+            .transpose()
+            .map_err(|_| ParseError::InvalidHeader(item.to_string()))?;
+        headers.push((name, value));
+    }
+    Ok(headers)
+}
+
+fn parse_trace_protocol(s: &str) -> Result<oc_collector::Protocol, ParseError> {
+    match s {
+        "opencensus" => Ok(oc_collector::Protocol::OpenCensus),
+        "opentelemetry" => Ok(oc_collector::Protocol::OpenTelemetry),
+        name => Err(ParseError::InvalidTraceProtocol(name.to_string())),
+    }
+}
+
 pub fn parse_backoff<S: Strings>(
     strings: &S,
     base: &str,
     default: ExponentialBackoff,
 ) -> Result<ExponentialBackoff, EnvError> {
+    parse_optional_backoff(strings, base).map(|backoff| backoff.unwrap_or(default))
+}
+
+/// Parses a backoff like [`parse_backoff`], but returns `None` when no backoff is configured.
+fn parse_optional_backoff<S: Strings>(
+    strings: &S,
+    base: &str,
+) -> Result<Option<ExponentialBackoff>, EnvError> {
     let min_env = format!("LINKERD2_PROXY_{}_EXP_BACKOFF_MIN", base);
     let min = parse(strings, &min_env, parse_duration);
     let max_env = format!("LINKERD2_PROXY_{}_EXP_BACKOFF_MAX", base);
@@ -1062,12 +1546,14 @@ pub fn parse_backoff<S: Strings>(
     let jitter = parse(strings, &jitter_env, parse_number::<f64>);
 
     match (min?, max?, jitter?) {
-        (None, None, None) => Ok(default),
+        (None, None, None) => Ok(None),
         (Some(min), Some(max), jitter) => {
-            ExponentialBackoff::new(min, max, jitter.unwrap_or_default()).map_err(|error| {
-                error!(message="Invalid backoff", %error, %min_env, ?min, %max_env, ?max, %jitter_env, ?jitter);
-                EnvError::InvalidEnvVar
-            })
+            ExponentialBackoff::new(min, max, jitter.unwrap_or_default())
+                .map(Some)
+                .map_err(|error| {
+                    error!(message="Invalid backoff", %error, %min_env, ?min, %max_env, ?max, %jitter_env, ?jitter);
+                    EnvError::InvalidEnvVar
+                })
         }
         _ => {
             error!("You need to specify either all of {} {} {} or none of them to use the default backoff", min_env, max_env,jitter_env );
@@ -1090,7 +1576,7 @@ pub fn parse_control_addr<S: Strings>(
         })),
         (Some(addr), Some(name)) => Ok(Some(ControlAddr {
             addr,
-            identity: Conditional::Some(tls::ServerId(name).into()),
+            identity: Conditional::Some(name.into()),
         })),
         _ => {
             error!("{}_ADDR and {}_NAME must be specified together", base, base);
@@ -1099,9 +1585,24 @@ pub fn parse_control_addr<S: Strings>(
     }
 }
 
+/// Describes how the proxy obtains its certificate, before the Identity service's client is
+/// configured.
+#[derive(Debug)]
+pub enum IdentityProvider {
+    Certify(ControlAddr, identity::certify::Config),
+    Files(identity::files::Config),
+}
+
 pub fn parse_identity_config<S: Strings>(
     strings: &S,
-) -> Result<(ControlAddr, identity::certify::Config, identity::Documents), EnvError> {
+) -> Result<
+    (
+        IdentityProvider,
+        identity::Documents,
+        Option<identity::TrustAnchorsFile>,
+    ),
+    EnvError,
+> {
     let control = parse_control_addr(strings, ENV_IDENTITY_SVC_BASE);
     let ta = parse(strings, ENV_IDENTITY_TRUST_ANCHORS, |s| {
         if s.is_empty() {
@@ -1119,6 +1620,15 @@ pub fn parse_identity_config<S: Strings>(
     let li = parse(strings, ENV_IDENTITY_IDENTITY_LOCAL_NAME, parse_identity);
     let min_refresh = parse(strings, ENV_IDENTITY_MIN_REFRESH, parse_duration);
     let max_refresh = parse(strings, ENV_IDENTITY_MAX_REFRESH, parse_duration);
+    let cert_file = parse(strings, ENV_IDENTITY_CERT_FILE, |ref s| {
+        Ok(PathBuf::from(s))
+    });
+    let key_file = parse(strings, ENV_IDENTITY_KEY_FILE, |ref s| Ok(PathBuf::from(s)));
+    let ta_file = parse(strings, ENV_IDENTITY_TRUST_ANCHORS_FILE, |ref s| {
+        Ok(PathBuf::from(s))
+    });
+    let poll_interval = parse(strings, ENV_IDENTITY_FILE_POLL_INTERVAL, parse_duration)?
+        .unwrap_or(DEFAULT_IDENTITY_FILE_POLL_INTERVAL);
 
     if strings
         .get(ENV_IDENTITY_DISABLED)?
@@ -1132,6 +1642,74 @@ pub fn parse_identity_config<S: Strings>(
         return Err(EnvError::InvalidEnvVar);
     }
 
+    let (ta, trust_anchors) = match ta_file? {
+        None => (ta, None),
+        Some(path) => {
+            if matches!(ta, Ok(Some(_))) {
+                error!(
+                    "{} and {} must not both be set",
+                    ENV_IDENTITY_TRUST_ANCHORS, ENV_IDENTITY_TRUST_ANCHORS_FILE
+                );
+                return Err(EnvError::InvalidEnvVar);
+            }
+            let pem = fs::read_to_string(&path).map_err(|e| {
+                error!("Failed to read trust anchors: {}", e);
+                EnvError::InvalidEnvVar
+            })?;
+            let trust_anchors = identity::TrustAnchorsFile {
+                path,
+                poll_interval,
+            };
+            (Ok(Some(pem)), Some(trust_anchors))
+        }
+    };
+
+    match (cert_file?, key_file?) {
+        (None, None) => {}
+        (Some(cert_chain_path), Some(key_path)) => {
+            let (trust_anchors_pem, local_name) = match (ta?, li?) {
+                (Some(ta), Some(li)) => (ta, li),
+                (ta, li) => {
+                    for (unset, name) in &[
+                        (ta.is_none(), ENV_IDENTITY_TRUST_ANCHORS),
+                        (li.is_none(), ENV_IDENTITY_IDENTITY_LOCAL_NAME),
+                    ] {
+                        if *unset {
+                            error!("{} must be set.", name);
+                        }
+                    }
+                    return Err(EnvError::InvalidEnvVar);
+                }
+            };
+
+            let key = identity::files::read_key(&key_path).map_err(|e| {
+                error!("Failed to read key: {}", e);
+                EnvError::InvalidEnvVar
+            })?;
+
+            let files = identity::files::Config {
+                cert_chain_path,
+                key_path,
+                poll_interval,
+            };
+            let docs = identity::Documents {
+                id: identity::LocalId(local_name),
+                trust_anchors_pem,
+                key_pkcs8: key,
+                // Certificates are not requested, so no CSR is needed.
+                csr_der: Vec::new(),
+            };
+            return Ok((IdentityProvider::Files(files), docs, trust_anchors));
+        }
+        _ => {
+            error!(
+                "{} and {} must be specified together",
+                ENV_IDENTITY_CERT_FILE, ENV_IDENTITY_KEY_FILE
+            );
+            return Err(EnvError::InvalidEnvVar);
+        }
+    }
+
     match (control?, ta?, dir?, li?, tok?, min_refresh?, max_refresh?) {
         (
             Some(control),
@@ -1191,7 +1769,11 @@ pub fn parse_identity_config<S: Strings>(
                 key_pkcs8: key?,
                 csr_der: csr?,
             };
-            Ok((control, certify, docs))
+            Ok((
+                IdentityProvider::Certify(control, certify),
+                docs,
+                trust_anchors,
+            ))
         }
         (addr, trust_anchors, end_entity_dir, local_id, token, _minr, _maxr) => {
             let s = format!("{0}_ADDR and {0}_NAME", ENV_IDENTITY_SVC_BASE);
@@ -1287,6 +1869,212 @@ mod tests {
         assert_eq!(parse_duration("1"), Err(ParseError::NotADuration));
     }
 
+    #[test]
+    fn parse_tcp_balance_modes() {
+        use outbound::tcp::balance::Mode;
+        assert_eq!(parse_tcp_balance_mode("ewma"), Ok(Mode::PeakEwma));
+        assert_eq!(
+            parse_tcp_balance_mode("least-connections"),
+            Ok(Mode::LeastConnections)
+        );
+        assert_eq!(
+            parse_tcp_balance_mode("round-robin"),
+            Err(ParseError::InvalidLoadBalancer("round-robin".to_string()))
+        );
+    }
+
+    #[test]
+    fn parse_http_routes_with_identities() {
+        let prom = "prometheus.linkerd-viz.serviceaccount.identity.linkerd.cluster.local";
+        let admin = "admin.ns.serviceaccount.identity.linkerd.cluster.local";
+        let routes = parse_http_routes(&format!("/metrics={}|{}, /ready={}", prom, admin, admin))
+            .expect("routes must parse");
+        assert_eq!(
+            routes,
+            vec![
+                policy::defaults::identity_route(
+                    "/metrics",
+                    vec![prom.to_string(), admin.to_string()]
+                ),
+                policy::defaults::identity_route("/ready", vec![admin.to_string()]),
+            ]
+        );
+
+        let req = |path: &str| {
+            crate::core::proxy::http::Request::get(path)
+                .body(())
+                .unwrap()
+        };
+        assert_eq!(
+            policy::http::find(&routes, &req("/metrics")).map(|r| &*r.name),
+            Some("default:route:/metrics")
+        );
+        assert!(policy::http::find(&routes, &req("/live")).is_none());
+
+        assert_eq!(
+            parse_http_routes("metrics=foo.ns"),
+            Err(ParseError::InvalidHttpRoute("metrics=foo.ns".to_string()))
+        );
+        assert_eq!(
+            parse_http_routes("/metrics"),
+            Err(ParseError::InvalidHttpRoute("/metrics".to_string()))
+        );
+        assert_eq!(parse_http_routes("/metrics="), Err(ParseError::NameError));
+    }
+
+    #[test]
+    fn parse_rate_limits() {
+        assert_eq!(
+            parse_rate_limit("total=100, identity=10"),
+            Ok(policy::RateLimit {
+                total: NonZeroU32::new(100),
+                identity: NonZeroU32::new(10),
+            })
+        );
+        assert_eq!(
+            parse_rate_limit("identity=10"),
+            Ok(policy::RateLimit {
+                total: None,
+                identity: NonZeroU32::new(10),
+            })
+        );
+        assert_eq!(
+            parse_rate_limit("client=10"),
+            Err(ParseError::InvalidRateLimit("client=10".to_string()))
+        );
+        assert!(parse_rate_limit("total=lots").is_err());
+        assert!(
+            parse_rate_limit("total=0").is_err(),
+            "a zero limit would reject every request"
+        );
+        assert!(parse_rate_limit("identity=0").is_err());
+    }
+
+    #[test]
+    fn parse_backend_override_list() {
+        use crate::core::proxy::http::Request;
+
+        let overrides = parse_backend_overrides(
+            "header:x-canary=true@web-canary.ns.svc.cluster.local:8080, \
+             cookie:track=stable@web-stable.ns.svc.cluster.local:8080",
+        )
+        .expect("overrides must parse");
+        assert_eq!(overrides.len(), 2);
+
+        let header = Request::get("/")
+            .header("x-canary", "true")
+            .body(())
+            .unwrap();
+        assert_eq!(
+            overrides[0].backend().to_string(),
+            "web-canary.ns.svc.cluster.local:8080"
+        );
+        assert!(overrides[0].is_match(&header));
+        assert!(!overrides[1].is_match(&header));
+
+        let cookie = Request::get("/")
+            .header("cookie", "track=stable")
+            .body(())
+            .unwrap();
+        assert_eq!(
+            overrides[1].backend().to_string(),
+            "web-stable.ns.svc.cluster.local:8080"
+        );
+        assert!(overrides[1].is_match(&cookie));
+
+        for invalid in &[
+            "header:x-canary=true",
+            "header:x-canary@web-canary.ns.svc.cluster.local:8080",
+            "query:canary=true@web-canary.ns.svc.cluster.local:8080",
+            "header:x-canary=true@web-canary",
+        ] {
+            assert_eq!(
+                parse_backend_overrides(invalid).err(),
+                Some(ParseError::InvalidBackendOverride(invalid.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn parse_http_hash_keys() {
+        use profiles::http::HashKey;
+        assert_eq!(parse_http_hash_key("ewma"), Ok(None));
+        assert_eq!(
+            parse_http_hash_key("header:x-user"),
+            Ok(Some(HashKey::Header(HeaderName::from_static("x-user"))))
+        );
+        assert_eq!(
+            parse_http_hash_key("cookie:session"),
+            Ok(Some(HashKey::Cookie("session".to_string())))
+        );
+        assert_eq!(
+            parse_http_hash_key("client-addr"),
+            Ok(Some(HashKey::ClientAddr))
+        );
+        for invalid in &["least-connections", "header:", "header:x y", "cookie:"] {
+            assert_eq!(
+                parse_http_hash_key(invalid),
+                Err(ParseError::InvalidLoadBalancer(invalid.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn parse_trace_protocols() {
+        use oc_collector::Protocol;
+        assert_eq!(parse_trace_protocol("opencensus"), Ok(Protocol::OpenCensus));
+        assert_eq!(
+            parse_trace_protocol("opentelemetry"),
+            Ok(Protocol::OpenTelemetry)
+        );
+        assert_eq!(
+            parse_trace_protocol("zipkin"),
+            Err(ParseError::InvalidTraceProtocol("zipkin".to_string()))
+        );
+    }
+
+    #[test]
+    fn parse_outlier_consecutive_failures() {
+        assert_eq!(parse_number::<NonZeroU32>("5").map(NonZeroU32::get), Ok(5));
+        assert!(matches!(
+            parse_number::<NonZeroU32>("0"),
+            Err(ParseError::NotAnInteger(_))
+        ));
+    }
+
+    #[test]
+    fn parse_sample_ratios() {
+        assert_eq!(parse_sample_ratio("0"), Ok(0.0));
+        assert_eq!(parse_sample_ratio("0.25"), Ok(0.25));
+        assert_eq!(parse_sample_ratio("1.0"), Ok(1.0));
+        assert_eq!(
+            parse_sample_ratio("1.5"),
+            Err(ParseError::InvalidSampleRatio("1.5".to_string()))
+        );
+        assert_eq!(
+            parse_sample_ratio("NaN"),
+            Err(ParseError::InvalidSampleRatio("NaN".to_string()))
+        );
+    }
+
+    #[test]
+    fn parse_force_sample_headers() {
+        assert_eq!(
+            parse_header_matches("x-debug-trace, x-canary=true"),
+            Ok(vec![
+                (HeaderName::from_static("x-debug-trace"), None),
+                (
+                    HeaderName::from_static("x-canary"),
+                    Some(HeaderValue::from_static("true"))
+                ),
+            ])
+        );
+        assert_eq!(
+            parse_header_matches("bad header"),
+            Err(ParseError::InvalidHeader("bad header".to_string()))
+        );
+    }
+
     #[test]
     fn convert_attributes_string_to_map_different_values() {
         let attributes_string = "\
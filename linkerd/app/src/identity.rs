@@ -1,26 +1,47 @@
 pub use linkerd_app_core::identity::{
-    client::{certify, TokenSource},
+    client::{certify, files, TokenSource},
     InvalidName, LocalId, Name,
 };
 use linkerd_app_core::{
     control, dns,
     exp_backoff::{ExponentialBackoff, ExponentialBackoffStream},
     identity::{
-        client::{Certify, Metrics as IdentityMetrics},
+        client::{Certify, Files, Metrics as IdentityMetrics, Status as IdentityStatus},
         creds, Credentials, DerX509, Mode,
     },
     metrics::ControlHttp as ClientMetrics,
     Error, Result,
 };
-use std::{future::Future, pin::Pin};
-use tokio::sync::watch;
-use tracing::Instrument;
+use parking_lot::Mutex;
+use std::{future::Future, path::PathBuf, pin::Pin, sync::Arc, time::Duration};
+use tokio::{sync::watch, time};
+use tracing::{debug, info, warn, Instrument};
 
 #[derive(Clone, Debug)]
 pub struct Config {
-    pub control: control::Config,
-    pub certify: certify::Config,
+    pub provider: Provider,
     pub documents: Documents,
+    pub trust_anchors: Option<TrustAnchorsFile>,
+}
+
+/// Configures the proxy to reload its trust anchors when a PEM bundle on disk changes.
+#[derive(Clone, Debug)]
+pub struct TrustAnchorsFile {
+    pub path: PathBuf,
+    pub poll_interval: Duration,
+}
+
+/// Configures how the proxy obtains its certificate.
+#[derive(Clone, Debug)]
+pub enum Provider {
+    /// Certificates are issued by the Identity service.
+    Certify {
+        control: control::Config,
+        certify: certify::Config,
+    },
+
+    /// Certificates are loaded from files issued by another process.
+    Files(files::Config),
 }
 
 #[derive(Clone)]
@@ -28,14 +49,16 @@ pub struct Documents {
     pub id: LocalId,
     pub trust_anchors_pem: String,
     pub key_pkcs8: Vec<u8>,
+    /// Empty when certificates are loaded from files.
     pub csr_der: Vec<u8>,
 }
 
 pub struct Identity {
-    addr: control::ControlAddr,
+    addr: Option<control::ControlAddr>,
     receiver: creds::Receiver,
     ready: watch::Receiver<bool>,
     metrics: IdentityMetrics,
+    status: IdentityStatus,
     task: Task,
 }
 
@@ -46,8 +69,11 @@ struct Recover(ExponentialBackoff);
 
 /// Wraps a credential with a watch sender that notifies receivers when the store has been updated
 /// at least once.
+///
+/// The store is shared with the task that reloads trust anchors.
 struct NotifyReady {
-    store: creds::Store,
+    name: Name,
+    store: Arc<Mutex<creds::Store>>,
     tx: watch::Sender<bool>,
 }
 
@@ -62,29 +88,63 @@ impl Config {
             &self.documents.csr_der,
         )?;
 
-        let certify = Certify::from(self.certify);
-        let metrics = certify.metrics();
-
-        let addr = self.control.addr.clone();
-
+        let store = Arc::new(Mutex::new(store));
         let (tx, ready) = watch::channel(false);
+        let credentials = NotifyReady {
+            name: receiver.name().clone(),
+            store: store.clone(),
+            tx,
+        };
 
         // Save to be spawned on an auxiliary runtime.
-        let task = Box::pin({
-            let addr = addr.clone();
-            let svc = self
-                .control
-                .build(dns, client_metrics, receiver.new_client());
+        let (addr, metrics, status, task): (_, _, _, Task) = match self.provider {
+            Provider::Certify { control, certify } => {
+                let certify = Certify::from(certify);
+                let metrics = certify.metrics();
+                let status = certify.status();
+                let addr = control.addr.clone();
+                let svc = control.build(dns, client_metrics, receiver.new_client());
+                let task = Box::pin(certify.run(credentials, svc).instrument(
+                    tracing::debug_span!("identity", server.addr = %addr).or_current(),
+                ));
+                (Some(addr), metrics, status, task)
+            }
+
+            Provider::Files(config) => {
+                let files = Files::from(config);
+                let metrics = files.metrics();
+                let status = files.status();
+                let task = Box::pin(
+                    files
+                        .run(credentials, self.documents.key_pkcs8)
+                        .instrument(tracing::debug_span!("identity").or_current()),
+                );
+                (None, metrics, status, task)
+            }
+        };
 
-            certify
-                .run(NotifyReady { store, tx }, svc)
-                .instrument(tracing::debug_span!("identity", server.addr = %addr).or_current())
-        });
+        status.set_trust_anchors(&self.documents.trust_anchors_pem);
+        let task = match self.trust_anchors {
+            None => task,
+            Some(config) => {
+                let roots = watch_trust_anchors(
+                    config,
+                    store,
+                    status.clone(),
+                    self.documents.trust_anchors_pem,
+                )
+                .instrument(tracing::debug_span!("trust_anchors").or_current());
+                Box::pin(async move {
+                    futures::future::join(task, roots).await;
+                })
+            }
+        };
 
         Ok(Identity {
             addr,
             receiver,
             metrics,
+            status,
             ready,
             task,
         })
@@ -94,12 +154,12 @@ impl Config {
 impl Credentials for NotifyReady {
     #[inline]
     fn dns_name(&self) -> &Name {
-        self.store.dns_name()
+        &self.name
     }
 
     #[inline]
     fn gen_certificate_signing_request(&mut self) -> DerX509 {
-        self.store.gen_certificate_signing_request()
+        self.store.lock().gen_certificate_signing_request()
     }
 
     fn set_certificate(
@@ -108,7 +168,21 @@ impl Credentials for NotifyReady {
         chain: Vec<DerX509>,
         expiry: std::time::SystemTime,
     ) -> Result<()> {
-        self.store.set_certificate(leaf, chain, expiry)?;
+        self.store.lock().set_certificate(leaf, chain, expiry)?;
+        let _ = self.tx.send(true);
+        Ok(())
+    }
+
+    fn set_key_and_certificate(
+        &mut self,
+        key_pkcs8: &[u8],
+        leaf: DerX509,
+        chain: Vec<DerX509>,
+        expiry: std::time::SystemTime,
+    ) -> Result<()> {
+        self.store
+            .lock()
+            .set_key_and_certificate(key_pkcs8, leaf, chain, expiry)?;
         let _ = self.tx.send(true);
         Ok(())
     }
@@ -128,7 +202,8 @@ impl std::fmt::Debug for Documents {
 // === impl Identity ===
 
 impl Identity {
-    pub fn addr(&self) -> control::ControlAddr {
+    /// Returns the Identity service's address, unless certificates are loaded from files.
+    pub fn addr(&self) -> Option<control::ControlAddr> {
         self.addr.clone()
     }
 
@@ -150,6 +225,10 @@ impl Identity {
         self.metrics.clone()
     }
 
+    pub fn status(&self) -> IdentityStatus {
+        self.status.clone()
+    }
+
     pub fn run(self) -> Task {
         self.task
     }
@@ -164,3 +243,52 @@ impl<E: Into<Error>> linkerd_error::Recover<E> for Recover {
         Ok(self.0.stream())
     }
 }
+
+/// Reloads the trust anchors whenever the file's contents change.
+///
+/// Bundles that fail to load (e.g. because they do not include the root that issued the current
+/// certificate) are retried until the file changes or the store accepts them. A warning is logged
+/// once for each rejected bundle.
+async fn watch_trust_anchors(
+    config: TrustAnchorsFile,
+    store: Arc<Mutex<creds::Store>>,
+    status: IdentityStatus,
+    mut curr_pem: String,
+) {
+    let mut interval = time::interval(config.poll_interval);
+    interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+    let mut rejected_pem = None::<String>;
+    loop {
+        interval.tick().await;
+
+        let pem = match tokio::fs::read_to_string(&config.path).await {
+            Ok(pem) => pem,
+            Err(error) => {
+                warn!(%error, path = %config.path.display(), "Failed to read trust anchors");
+                continue;
+            }
+        };
+        if pem == curr_pem {
+            rejected_pem = None;
+            continue;
+        }
+
+        let res = store.lock().set_roots(&pem);
+        match res {
+            Ok(()) => {
+                info!("Reloaded trust anchors");
+                status.set_trust_anchors(&pem);
+                curr_pem = pem;
+                rejected_pem = None;
+            }
+            Err(error) => {
+                if rejected_pem.as_deref() == Some(pem.as_str()) {
+                    debug!(%error, "Trust anchors still rejected");
+                } else {
+                    warn!(%error, "Failed to reload trust anchors");
+                    rejected_pem = Some(pem);
+                }
+            }
+        }
+    }
+}
@@ -3,6 +3,7 @@
 #![deny(warnings, rust_2018_idioms)]
 #![forbid(unsafe_code)]
 
+mod config_dump;
 pub mod dst;
 pub mod env;
 pub mod identity;
@@ -14,6 +15,7 @@ use futures::{future, Future, FutureExt};
 use linkerd_app_admin as admin;
 pub use linkerd_app_core::{self as core, metrics, trace};
 use linkerd_app_core::{
+    access_log,
     config::ServerConfig,
     control::ControlAddr,
     dns, drain,
@@ -25,7 +27,7 @@ use linkerd_app_core::{
 use linkerd_app_gateway as gateway;
 use linkerd_app_inbound::{self as inbound, Inbound};
 use linkerd_app_outbound::{self as outbound, Outbound};
-use std::pin::Pin;
+use std::{pin::Pin, sync::Arc};
 use tokio::{
     sync::mpsc,
     time::{self, Duration},
@@ -56,11 +58,13 @@ pub struct Config {
     pub admin: admin::Config,
     pub tap: tap::Config,
     pub oc_collector: oc_collector::Config,
+    pub access_log: Option<access_log::Config>,
 }
 
 pub struct App {
     admin: admin::Task,
-    drain: drain::Signal,
+    admin_drain: drain::Signal,
+    drain: admin::Drain,
     dst: ControlAddr,
     identity: identity::Identity,
     inbound_addr: Local<ServerAddr>,
@@ -97,6 +101,7 @@ impl Config {
         BAdmin: Bind<ServerConfig> + Clone + 'static,
         BAdmin::Addrs: Param<Remote<ClientAddr>> + Param<Local<ServerAddr>>,
     {
+        let config_json = config_dump::config(&self);
         let Config {
             admin,
             dns,
@@ -107,6 +112,7 @@ impl Config {
             outbound,
             gateway,
             tap,
+            access_log,
         } = self;
         debug!("building app");
         let (metrics, report) = Metrics::new(admin.metrics_retain_idle);
@@ -119,7 +125,11 @@ impl Config {
 
         let report = identity.metrics().and_report(report);
 
+        // The admin server is drained separately so that it continues to serve (i.e. to report
+        // readiness and metrics) while the proxy's connections drain.
         let (drain_tx, drain_rx) = drain::channel();
+        let (admin_drain_tx, admin_drain_rx) = drain::channel();
+        let drain = admin::Drain::new(drain_tx, admin.shutdown_grace_period);
 
         let tap = {
             let bind = bind_admin.clone();
@@ -137,17 +147,23 @@ impl Config {
             let identity = identity.receiver().new_client();
             let dns = dns.resolver.clone();
             let client_metrics = metrics.control.clone();
-            let metrics = metrics.opencensus;
-            info_span!("opencensus")
-                .in_scope(|| oc_collector.build(identity, dns, metrics, client_metrics))
+            let oc_metrics = metrics.opencensus;
+            let otel_metrics = metrics.opentelemetry;
+            info_span!("trace_collector").in_scope(|| {
+                oc_collector.build(identity, dns, oc_metrics, otel_metrics, client_metrics)
+            })
         }?;
 
+        let access_log = access_log.map(access_log::Config::build).transpose()?;
+        let report = access_log.clone().and_report(report);
+
         let runtime = ProxyRuntime {
             identity: identity.receiver(),
             metrics: metrics.proxy.clone(),
             tap: tap.registry(),
             span_sink: oc_collector.span_sink(),
-            drain: drain_rx.clone(),
+            access_log,
+            drain: drain_rx,
         };
         let inbound = Inbound::new(inbound, runtime.clone());
         let outbound = Outbound::new(outbound, runtime);
@@ -159,22 +175,32 @@ impl Config {
         };
 
         let admin = {
+            let identity_status = identity.status();
+            let config_dump = Arc::new(config_dump::ConfigDump::new(
+                config_json,
+                inbound_policies.clone(),
+                outbound.state(),
+            ));
             let identity = identity.receiver().server();
             let metrics = inbound.metrics();
             let policy = inbound_policies.clone();
             let report = inbound
                 .metrics()
                 .and_report(outbound.metrics())
+                .and_report(drain.clone())
                 .and_report(report);
             info_span!("admin").in_scope(move || {
                 admin.build(
                     bind_admin,
                     policy,
                     identity,
+                    identity_status,
+                    config_dump,
                     report,
                     metrics,
                     log_level,
-                    drain_rx,
+                    admin_drain_rx,
+                    drain.clone(),
                     shutdown_tx,
                 )
             })?
@@ -231,8 +257,9 @@ impl Config {
 
         Ok(App {
             admin,
+            admin_drain: admin_drain_tx,
             dst: dst_addr,
-            drain: drain_tx,
+            drain,
             identity,
             inbound_addr,
             oc_collector,
@@ -286,20 +313,19 @@ impl App {
         self.identity.receiver().name().clone()
     }
 
-    pub fn identity_addr(&self) -> ControlAddr {
+    pub fn identity_addr(&self) -> Option<ControlAddr> {
         self.identity.addr()
     }
 
-    pub fn opencensus_addr(&self) -> Option<&ControlAddr> {
-        match self.oc_collector {
-            oc_collector::OcCollector::Disabled { .. } => None,
-            oc_collector::OcCollector::Enabled(ref oc) => Some(&oc.addr),
-        }
+    pub fn trace_collector(&self) -> Option<(oc_collector::Protocol, &ControlAddr)> {
+        self.oc_collector.collector()
     }
 
-    pub fn spawn(self) -> drain::Signal {
+    /// Spawns the proxy's tasks, returning a handle that drains the proxy's connections.
+    pub fn spawn(self) -> admin::Drain {
         let App {
             admin,
+            admin_drain,
             drain,
             identity,
             oc_collector,
@@ -368,12 +394,16 @@ impl App {
                         }
 
                         if let oc_collector::OcCollector::Enabled(oc) = oc_collector {
-                            tokio::spawn(oc.task.instrument(info_span!("opencensus").or_current()));
+                            tokio::spawn(
+                                oc.task
+                                    .instrument(info_span!("trace_collector").or_current()),
+                            );
                         }
 
                         // we don't care if the admin shutdown channel is
                         // dropped or actually triggered.
                         let _ = admin_shutdown_rx.await;
+                        drop(admin_drain);
                     }
                     .instrument(info_span!("daemon")),
                 )
@@ -1,12 +1,16 @@
 use linkerd_app_core::{
-    control, dns, identity, metrics::ControlHttp as HttpMetrics, svc::NewService, Error,
+    control, dns, http_tracing, identity, metrics::ControlHttp as HttpMetrics, svc::NewService,
+    Error,
 };
 use linkerd_opencensus::{self as opencensus, metrics, proto};
-use std::{collections::HashMap, future::Future, pin::Pin, time::SystemTime};
+use linkerd_opentelemetry as opentelemetry;
+use std::{collections::HashMap, fmt, future::Future, pin::Pin, time::SystemTime};
 use tokio::sync::mpsc;
-use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::{wrappers::ReceiverStream, StreamExt};
 use tracing::Instrument;
 
+mod otlp;
+
 #[derive(Clone, Debug)]
 pub enum Config {
     Disabled,
@@ -18,6 +22,15 @@ pub struct EnabledConfig {
     pub control: control::Config,
     pub attributes: HashMap<String, String>,
     pub hostname: Option<String>,
+    pub protocol: Protocol,
+    pub sampler: http_tracing::Sampler,
+}
+
+/// The protocol used to export spans to the collector.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Protocol {
+    OpenCensus,
+    OpenTelemetry,
 }
 
 pub type Task = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
@@ -31,6 +44,8 @@ pub enum OcCollector {
 
 pub struct EnabledCollector {
     pub addr: control::ControlAddr,
+    pub protocol: Protocol,
+    pub sampler: http_tracing::Sampler,
     pub span_sink: SpanSink,
     pub task: Task,
 }
@@ -43,7 +58,8 @@ impl Config {
         self,
         identity: identity::NewClient,
         dns: dns::Resolver,
-        metrics: metrics::Registry,
+        oc_metrics: metrics::Registry,
+        otel_metrics: opentelemetry::metrics::Registry,
         client_metrics: HttpMetrics,
     ) -> Result<OcCollector, Error> {
         match self {
@@ -58,32 +74,52 @@ impl Config {
                 let (span_sink, spans_rx) = mpsc::channel(Self::SPAN_BUFFER_CAPACITY);
                 let spans_rx = ReceiverStream::new(spans_rx);
 
-                let task = {
-                    use self::proto::agent::common::v1 as oc;
-
-                    let node = oc::Node {
-                        identifier: Some(oc::ProcessIdentifier {
-                            host_name: inner.hostname.unwrap_or_default(),
-                            pid: std::process::id(),
-                            start_timestamp: Some(SystemTime::now().into()),
-                        }),
-                        service_info: Some(oc::ServiceInfo {
-                            name: Self::SERVICE_NAME.to_string(),
-                        }),
-                        attributes: inner.attributes,
-                        ..oc::Node::default()
-                    };
-
-                    let addr = addr.clone();
-                    Box::pin(
-                        opencensus::export_spans(svc, node, spans_rx, metrics).instrument(
-                            tracing::debug_span!("opencensus", peer.addr = %addr).or_current(),
-                        ),
-                    )
+                let task: Task = match inner.protocol {
+                    Protocol::OpenCensus => {
+                        use self::proto::agent::common::v1 as oc;
+
+                        let node = oc::Node {
+                            identifier: Some(oc::ProcessIdentifier {
+                                host_name: inner.hostname.unwrap_or_default(),
+                                pid: std::process::id(),
+                                start_timestamp: Some(SystemTime::now().into()),
+                            }),
+                            service_info: Some(oc::ServiceInfo {
+                                name: Self::SERVICE_NAME.to_string(),
+                            }),
+                            attributes: inner.attributes,
+                            ..oc::Node::default()
+                        };
+
+                        let addr = addr.clone();
+                        Box::pin(
+                            opencensus::export_spans(svc, node, spans_rx, oc_metrics).instrument(
+                                tracing::debug_span!("opencensus", peer.addr = %addr).or_current(),
+                            ),
+                        )
+                    }
+                    Protocol::OpenTelemetry => {
+                        let resource =
+                            otlp::resource(Self::SERVICE_NAME, inner.hostname, inner.attributes);
+                        // Spans are produced in the OpenCensus format and converted as they are
+                        // exported.
+                        let spans = spans_rx.map(otlp::span);
+
+                        let addr = addr.clone();
+                        Box::pin(
+                            opentelemetry::export_spans(svc, resource, spans, otel_metrics)
+                                .instrument(
+                                    tracing::debug_span!("opentelemetry", peer.addr = %addr)
+                                        .or_current(),
+                                ),
+                        )
+                    }
                 };
 
                 Ok(OcCollector::Enabled(Box::new(EnabledCollector {
                     addr,
+                    protocol: inner.protocol,
+                    sampler: inner.sampler,
                     task,
                     span_sink,
                 })))
@@ -93,10 +129,32 @@ impl Config {
 }
 
 impl OcCollector {
-    pub fn span_sink(&self) -> Option<SpanSink> {
+    /// Returns the protocol and address of the trace collector, if one is configured.
+    pub fn collector(&self) -> Option<(Protocol, &control::ControlAddr)> {
         match self {
             OcCollector::Disabled => None,
-            OcCollector::Enabled(inner) => Some(inner.span_sink.clone()),
+            OcCollector::Enabled(inner) => Some((inner.protocol, &inner.addr)),
+        }
+    }
+
+    pub fn span_sink(&self) -> http_tracing::OpenCensusSink {
+        match self {
+            OcCollector::Disabled => None,
+            OcCollector::Enabled(inner) => Some(http_tracing::SpanSink {
+                spans: inner.span_sink.clone(),
+                sampler: inner.sampler.clone(),
+            }),
+        }
+    }
+}
+
+// === impl Protocol ===
+
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OpenCensus => "OpenCensus".fmt(f),
+            Self::OpenTelemetry => "OpenTelemetry".fmt(f),
         }
     }
 }
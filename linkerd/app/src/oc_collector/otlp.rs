@@ -0,0 +1,307 @@
+//! Converts the proxy's OpenCensus spans into OTLP spans.
+
+use linkerd_opencensus::proto::trace::v1 as oc;
+use linkerd_opentelemetry::proto::{
+    common::v1::{any_value, AnyValue, KeyValue},
+    resource::v1::Resource,
+    trace::v1 as otlp,
+};
+use std::{collections::HashMap, time::SystemTime};
+
+/// Describes the proxy process to the collector.
+pub(super) fn resource(
+    service_name: &str,
+    hostname: Option<String>,
+    attributes: HashMap<String, String>,
+) -> Resource {
+    let mut attrs = vec![
+        string_attribute("service.name", service_name.to_string()),
+        KeyValue {
+            key: "process.pid".to_string(),
+            value: Some(AnyValue {
+                value: Some(any_value::Value::IntValue(std::process::id().into())),
+            }),
+        },
+    ];
+    if let Some(hostname) = hostname {
+        attrs.push(string_attribute("host.name", hostname));
+    }
+
+    let mut attributes = attributes.into_iter().collect::<Vec<_>>();
+    attributes.sort_unstable();
+    attrs.extend(attributes.into_iter().map(|(k, v)| string_attribute(k, v)));
+
+    Resource {
+        attributes: attrs,
+        dropped_attributes_count: 0,
+    }
+}
+
+pub(super) fn span(span: oc::Span) -> otlp::Span {
+    let kind = match oc::span::SpanKind::from_i32(span.kind) {
+        Some(oc::span::SpanKind::Server) => otlp::span::SpanKind::Server,
+        Some(oc::span::SpanKind::Client) => otlp::span::SpanKind::Client,
+        _ => otlp::span::SpanKind::Unspecified,
+    };
+
+    let events = span
+        .time_events
+        .map(|te| te.time_event.into_iter().filter_map(event).collect())
+        .unwrap_or_default();
+
+    // Only the gRPC status is recorded, and any status other than OK is an error.
+    let status = span.status.map(|status| {
+        let code = if status.code == 0 {
+            otlp::status::StatusCode::Ok
+        } else {
+            otlp::status::StatusCode::Error
+        };
+        otlp::Status {
+            code: code as i32,
+            message: status.message,
+            ..otlp::Status::default()
+        }
+    });
+
+    otlp::Span {
+        trace_id: span.trace_id,
+        span_id: span.span_id,
+        parent_span_id: span.parent_span_id,
+        trace_state: span
+            .tracestate
+            .map(|ts| {
+                ts.entries
+                    .into_iter()
+                    .map(|e| format!("{}={}", e.key, e.value))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            })
+            .unwrap_or_default(),
+        name: span.name.map(|n| n.value).unwrap_or_default(),
+        kind: kind as i32,
+        start_time_unix_nano: unix_nanos(span.start_time),
+        end_time_unix_nano: unix_nanos(span.end_time),
+        attributes: attributes(span.attributes),
+        events,
+        status,
+        ..otlp::Span::default()
+    }
+}
+
+fn event(event: oc::span::TimeEvent) -> Option<otlp::span::Event> {
+    match event.value? {
+        oc::span::time_event::Value::Annotation(annotation) => Some(otlp::span::Event {
+            time_unix_nano: unix_nanos(event.time),
+            name: annotation.description.map(|d| d.value).unwrap_or_default(),
+            attributes: attributes(annotation.attributes),
+            dropped_attributes_count: 0,
+        }),
+        oc::span::time_event::Value::MessageEvent(_) => None,
+    }
+}
+
+fn attributes(attrs: Option<oc::span::Attributes>) -> Vec<KeyValue> {
+    let mut attributes = attrs
+        .map(|attrs| attrs.attribute_map.into_iter().collect::<Vec<_>>())
+        .unwrap_or_default();
+    // Order attributes so that exported spans are stable.
+    attributes.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+    attributes
+        .into_iter()
+        .map(|(key, v)| KeyValue {
+            key,
+            value: Some(AnyValue {
+                value: v.value.map(|v| match v {
+                    oc::attribute_value::Value::StringValue(s) => {
+                        any_value::Value::StringValue(s.value)
+                    }
+                    oc::attribute_value::Value::IntValue(i) => any_value::Value::IntValue(i),
+                    oc::attribute_value::Value::BoolValue(b) => any_value::Value::BoolValue(b),
+                    oc::attribute_value::Value::DoubleValue(d) => any_value::Value::DoubleValue(d),
+                }),
+            }),
+        })
+        .collect()
+}
+
+fn string_attribute(key: impl Into<String>, value: String) -> KeyValue {
+    KeyValue {
+        key: key.into(),
+        value: Some(AnyValue {
+            value: Some(any_value::Value::StringValue(value)),
+        }),
+    }
+}
+
+fn unix_nanos<T>(ts: Option<T>) -> u64
+where
+    SystemTime: TryFrom<T>,
+{
+    ts.and_then(|ts| SystemTime::try_from(ts).ok())
+        .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn converts_span() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        let end = start + Duration::from_millis(5);
+
+        let mut attribute_map = HashMap::new();
+        attribute_map.insert(
+            "http.method".to_string(),
+            oc::AttributeValue {
+                value: Some(oc::attribute_value::Value::StringValue(
+                    oc::TruncatableString {
+                        value: "GET".to_string(),
+                        truncated_byte_count: 0,
+                    },
+                )),
+            },
+        );
+        attribute_map.insert(
+            "http.status_code".to_string(),
+            oc::AttributeValue {
+                value: Some(oc::attribute_value::Value::IntValue(200)),
+            },
+        );
+
+        let span = span(oc::Span {
+            trace_id: vec![1; 16],
+            span_id: vec![2; 8],
+            parent_span_id: vec![3; 8],
+            name: Some(oc::TruncatableString {
+                value: "foo.ns.svc.cluster.local:8080".to_string(),
+                truncated_byte_count: 0,
+            }),
+            kind: oc::span::SpanKind::Server as i32,
+            start_time: Some(start.into()),
+            end_time: Some(end.into()),
+            attributes: Some(oc::span::Attributes {
+                attribute_map,
+                dropped_attributes_count: 0,
+            }),
+            ..oc::Span::default()
+        });
+
+        assert_eq!(span.trace_id, vec![1; 16]);
+        assert_eq!(span.span_id, vec![2; 8]);
+        assert_eq!(span.parent_span_id, vec![3; 8]);
+        assert_eq!(span.name, "foo.ns.svc.cluster.local:8080");
+        assert_eq!(span.kind, otlp::span::SpanKind::Server as i32);
+        assert_eq!(span.start_time_unix_nano, 1_600_000_000_000_000_000);
+        assert_eq!(span.end_time_unix_nano, 1_600_000_000_005_000_000);
+        assert_eq!(
+            span.attributes,
+            vec![
+                string_attribute("http.method", "GET".to_string()),
+                KeyValue {
+                    key: "http.status_code".to_string(),
+                    value: Some(AnyValue {
+                        value: Some(any_value::Value::IntValue(200)),
+                    }),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn converts_events_and_status() {
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        let mut attribute_map = HashMap::new();
+        attribute_map.insert(
+            "attempt".to_string(),
+            oc::AttributeValue {
+                value: Some(oc::attribute_value::Value::StringValue(
+                    oc::TruncatableString {
+                        value: "2".to_string(),
+                        truncated_byte_count: 0,
+                    },
+                )),
+            },
+        );
+
+        let span = span(oc::Span {
+            time_events: Some(oc::span::TimeEvents {
+                time_event: vec![oc::span::TimeEvent {
+                    time: Some(time.into()),
+                    value: Some(oc::span::time_event::Value::Annotation(
+                        oc::span::time_event::Annotation {
+                            description: Some(oc::TruncatableString {
+                                value: "retry".to_string(),
+                                truncated_byte_count: 0,
+                            }),
+                            attributes: Some(oc::span::Attributes {
+                                attribute_map,
+                                dropped_attributes_count: 0,
+                            }),
+                        },
+                    )),
+                }],
+                dropped_annotations_count: 0,
+                dropped_message_events_count: 0,
+            }),
+            status: Some(oc::Status {
+                code: 14,
+                message: String::new(),
+            }),
+            ..oc::Span::default()
+        });
+
+        assert_eq!(span.events.len(), 1);
+        assert_eq!(span.events[0].name, "retry");
+        assert_eq!(span.events[0].time_unix_nano, 1_600_000_000_000_000_000);
+        assert_eq!(
+            span.events[0].attributes,
+            vec![string_attribute("attempt", "2".to_string())]
+        );
+        assert_eq!(
+            span.status.map(|s| s.code),
+            Some(otlp::status::StatusCode::Error as i32)
+        );
+    }
+
+    #[test]
+    fn converts_client_kind() {
+        let span = span(oc::Span {
+            kind: oc::span::SpanKind::Client as i32,
+            ..oc::Span::default()
+        });
+        assert_eq!(span.kind, otlp::span::SpanKind::Client as i32);
+        assert_eq!(span.start_time_unix_nano, 0);
+    }
+
+    #[test]
+    fn describes_resource() {
+        let mut attributes = HashMap::new();
+        attributes.insert("k8s.pod.name".to_string(), "web-0".to_string());
+        attributes.insert("k8s.namespace.name".to_string(), "emojivoto".to_string());
+
+        let resource = resource("linkerd-proxy", Some("web-0".to_string()), attributes);
+        let keys = resource
+            .attributes
+            .iter()
+            .map(|kv| kv.key.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            keys,
+            vec![
+                "service.name",
+                "process.pid",
+                "host.name",
+                "k8s.namespace.name",
+                "k8s.pod.name",
+            ]
+        );
+        assert_eq!(
+            resource.attributes[0],
+            string_attribute("service.name", "linkerd-proxy".to_string())
+        );
+    }
+}
@@ -74,6 +74,15 @@ impl ExponentialBackoff {
         Ok(ExponentialBackoff { min, max, jitter })
     }
 
+    /// Returns the jittered delay to wait before the given (zero-indexed) iteration.
+    ///
+    /// This is useful when a backoff must be computed without polling an
+    /// [`ExponentialBackoffStream`].
+    pub fn delay(&self, iterations: u32) -> Duration {
+        let base = self.base(iterations);
+        base + self.jitter(base, &mut thread_rng())
+    }
+
     fn base(&self, iterations: u32) -> Duration {
         debug_assert!(
             self.min <= self.max,
@@ -166,6 +175,17 @@ mod tests {
             TestResult::from_bool(min <= delay && delay <= max)
         }
 
+        fn backoff_delay(min_ms: u64, max_ms: u64, jitter: f64, iterations: u32) -> TestResult {
+            let min = Duration::from_millis(min_ms);
+            let max = Duration::from_millis(max_ms);
+            let backoff = match ExponentialBackoff::new(min, max, jitter) {
+                Err(_) => return TestResult::discard(),
+                Ok(backoff) => backoff,
+            };
+            let delay = backoff.delay(iterations);
+            TestResult::from_bool(min <= delay && delay <= max)
+        }
+
         fn backoff_jitter(base_ms: u64, max_ms: u64, jitter: f64) -> TestResult {
             let base = Duration::from_millis(base_ms);
             let max = Duration::from_millis(max_ms);
@@ -2,6 +2,7 @@ use super::{Prefixed, Registry, Report};
 use linkerd_metrics::{Counter, FmtLabels, FmtMetric, FmtMetrics, LastUpdate, Metric};
 use parking_lot::Mutex;
 use std::{
+    collections::BTreeMap,
     fmt,
     hash::Hash,
     sync::Arc,
@@ -9,6 +10,11 @@ use std::{
 };
 use tracing::trace;
 
+/// Retries issued as this attempt or any later one are counted together, with an
+/// `attempt="10+"` label, so that requests with unlimited attempts do not add a label per
+/// attempt.
+const MAX_ATTEMPT_LABEL: u32 = 10;
+
 #[derive(Debug)]
 pub struct Retries<T>(Registry<T, Metrics>)
 where
@@ -22,10 +28,17 @@ pub struct Metrics {
     last_update: Instant,
     retryable: Counter,
     no_budget: Counter,
+    body_too_large: Counter,
+    /// Retries by attempt, up to `MAX_ATTEMPT_LABEL`.
+    attempts: BTreeMap<u32, Counter>,
 }
 
 struct NoBudgetLabel;
 
+struct AttemptLabel(u32);
+
+struct BodyTooLargeLabel;
+
 // === impl Retries ===
 
 impl<T: Hash + Eq> Default for Retries<T> {
@@ -62,6 +75,25 @@ impl Handle {
             m.no_budget.incr();
         }
     }
+
+    /// Records that a retry was issued as the given attempt of a request, where the original
+    /// request is the first attempt.
+    pub fn incr_retry(&self, attempt: u32) {
+        let mut m = self.0.lock();
+        m.last_update = Instant::now();
+        m.attempts
+            .entry(attempt.min(MAX_ATTEMPT_LABEL))
+            .or_default()
+            .incr();
+    }
+
+    /// Records that a failed request could not be retried because its body exceeded the retry
+    /// buffer.
+    pub fn incr_body_too_large(&self) {
+        let mut m = self.0.lock();
+        m.last_update = Instant::now();
+        m.body_too_large.incr();
+    }
 }
 
 // === impl Metrics ===
@@ -72,6 +104,8 @@ impl Default for Metrics {
             last_update: Instant::now(),
             retryable: Counter::default(),
             no_budget: Counter::default(),
+            body_too_large: Counter::default(),
+            attempts: BTreeMap::new(),
         }
     }
 }
@@ -94,6 +128,20 @@ where
             "Total count of retryable HTTP responses.",
         )
     }
+
+    fn retries_total(&self) -> Metric<'_, Prefixed<'_, &'static str>, Counter> {
+        Metric::new(
+            self.prefix_key("retries_total"),
+            "Total count of HTTP retries issued, by attempt.",
+        )
+    }
+
+    fn skipped_total(&self) -> Metric<'_, Prefixed<'_, &'static str>, Counter> {
+        Metric::new(
+            self.prefix_key("retry_skipped_total"),
+            "Total count of HTTP requests that could not be retried.",
+        )
+    }
 }
 
 impl<T> FmtMetrics for Report<T, Metrics>
@@ -121,6 +169,23 @@ where
                 .fmt_metric_labeled(f, &metric.name, (tgt, NoBudgetLabel))?;
         }
 
+        let metric = self.retries_total();
+        metric.fmt_help(f)?;
+        for (tgt, tm) in registry.iter() {
+            let m = tm.lock();
+            for (attempt, retries) in m.attempts.iter() {
+                retries.fmt_metric_labeled(f, &metric.name, (tgt, AttemptLabel(*attempt)))?;
+            }
+        }
+
+        let metric = self.skipped_total();
+        metric.fmt_help(f)?;
+        for (tgt, tm) in registry.iter() {
+            let m = tm.lock();
+            m.body_too_large
+                .fmt_metric_labeled(f, &metric.name, (tgt, BodyTooLargeLabel))?;
+        }
+
         registry.retain_since(Instant::now() - self.retain_idle);
 
         Ok(())
@@ -132,3 +197,48 @@ impl FmtLabels for NoBudgetLabel {
         write!(f, "skipped=\"no_budget\"")
     }
 }
+
+impl FmtLabels for AttemptLabel {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 >= MAX_ATTEMPT_LABEL {
+            return write!(f, "attempt=\"{}+\"", MAX_ATTEMPT_LABEL);
+        }
+        write!(f, "attempt=\"{}\"", self.0)
+    }
+}
+
+impl FmtLabels for BodyTooLargeLabel {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "skipped=\"body_too_large\"")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, Hash, Eq, PartialEq)]
+    struct Target;
+
+    impl FmtLabels for Target {
+        fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "route=\"web\"")
+        }
+    }
+
+    #[test]
+    fn caps_attempt_labels() {
+        let retries = Retries::<Target>::default();
+        let handle = retries.get_handle(Target);
+        for attempt in 2..=20 {
+            handle.incr_retry(attempt);
+        }
+
+        let report = retries.into_report(Duration::from_secs(60));
+        let out = report.as_display().to_string();
+        assert!(out.contains("retries_total{route=\"web\",attempt=\"9\"} 1"));
+        assert!(out.contains("retries_total{route=\"web\",attempt=\"10+\"} 11"));
+        assert!(!out.contains("attempt=\"10\"}"));
+        assert!(!out.contains("attempt=\"11"));
+    }
+}
@@ -19,6 +19,18 @@ pub trait Credentials {
         chain: Vec<DerX509>,
         expiry: SystemTime,
     ) -> Result<()>;
+
+    /// Replace the private key and set a certificate issued for it.
+    ///
+    /// Fails if the key is not valid or if the certificate was not issued for it, in which case
+    /// the current key and certificate are retained.
+    fn set_key_and_certificate(
+        &mut self,
+        key_pkcs8: &[u8],
+        leaf: DerX509,
+        chain: Vec<DerX509>,
+        expiry: SystemTime,
+    ) -> Result<()>;
 }
 
 /// DER-formatted X.509 data.
@@ -0,0 +1,193 @@
+use crate::Name;
+use linkerd_dns_name::InvalidName;
+use std::{fmt, str::FromStr, sync::Arc};
+
+/// The scheme prefix of a SPIFFE ID.
+const SPIFFE_SCHEME: &str = "spiffe://";
+
+/// SPIFFE IDs may not be longer than 2048 bytes.
+const SPIFFE_MAX_LEN: usize = 2048;
+
+/// A peer's identity, as described by its certificate's subject alternative names.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Id {
+    /// A DNS-like name, as issued by Linkerd's identity controller.
+    Dns(Name),
+
+    /// A SPIFFE ID in a URI SAN, e.g. as issued by SPIRE.
+    Spiffe(SpiffeId),
+}
+
+/// A SPIFFE ID, i.e. `spiffe://<trust-domain>/<path>`.
+///
+/// See https://github.com/spiffe/spiffe/blob/main/standards/SPIFFE-ID.md.
+#[derive(Clone, Eq, PartialEq, Hash)]
+pub struct SpiffeId(Arc<str>);
+
+// === impl Id ===
+
+impl Id {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Dns(name) => name.as_str(),
+            Self::Spiffe(id) => id.as_str(),
+        }
+    }
+
+    /// Returns the identity's DNS-like name, if it has one.
+    pub fn dns_name(&self) -> Option<&Name> {
+        match self {
+            Self::Dns(name) => Some(name),
+            Self::Spiffe(_) => None,
+        }
+    }
+}
+
+impl From<Name> for Id {
+    fn from(name: Name) -> Self {
+        Self::Dns(name)
+    }
+}
+
+impl From<SpiffeId> for Id {
+    fn from(id: SpiffeId) -> Self {
+        Self::Spiffe(id)
+    }
+}
+
+impl FromStr for Id {
+    type Err = InvalidName;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with(SPIFFE_SCHEME) {
+            return s.parse().map(Self::Spiffe);
+        }
+
+        s.parse().map(Self::Dns)
+    }
+}
+
+impl fmt::Display for Id {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Dns(name) => name.fmt(f),
+            Self::Spiffe(id) => id.fmt(f),
+        }
+    }
+}
+
+// === impl SpiffeId ===
+
+impl SpiffeId {
+    pub fn as_str(&self) -> &str {
+        &*self.0
+    }
+
+    pub fn trust_domain(&self) -> &str {
+        let rest = &self.0[SPIFFE_SCHEME.len()..];
+        match rest.find('/') {
+            Some(idx) => &rest[..idx],
+            None => rest,
+        }
+    }
+
+    fn is_valid(s: &str) -> bool {
+        if s.len() > SPIFFE_MAX_LEN {
+            return false;
+        }
+        let rest = match s.strip_prefix(SPIFFE_SCHEME) {
+            Some(rest) => rest,
+            None => return false,
+        };
+        let (trust_domain, path) = match rest.find('/') {
+            Some(idx) => rest.split_at(idx),
+            None => (rest, ""),
+        };
+
+        let is_td_char = |c: char| {
+            c.is_ascii_lowercase() || c.is_ascii_digit() || c == '.' || c == '-' || c == '_'
+        };
+        if trust_domain.is_empty() || !trust_domain.chars().all(is_td_char) {
+            return false;
+        }
+
+        // The path is optional, but each of its segments must be non-empty and may not be a
+        // relative reference.
+        if path.is_empty() {
+            return true;
+        }
+        let is_path_char = |c: char| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_';
+        path[1..].split('/').all(|segment| {
+            !segment.is_empty()
+                && segment != "."
+                && segment != ".."
+                && segment.chars().all(is_path_char)
+        })
+    }
+}
+
+impl FromStr for SpiffeId {
+    type Err = InvalidName;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !Self::is_valid(s) {
+            return Err(InvalidName);
+        }
+
+        Ok(Self(s.into()))
+    }
+}
+
+impl fmt::Debug for SpiffeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Display for SpiffeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&*self.0, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_dns_and_spiffe_ids() {
+        let id = "foo.ns.serviceaccount.identity.linkerd.cluster.local"
+            .parse::<Id>()
+            .unwrap();
+        assert!(id.dns_name().is_some());
+
+        let id = "spiffe://example.org/ns/foo/sa/bar".parse::<Id>().unwrap();
+        match id {
+            Id::Spiffe(ref spiffe) => assert_eq!(spiffe.trust_domain(), "example.org"),
+            Id::Dns(_) => panic!("expected a SPIFFE ID"),
+        }
+        assert_eq!(id.to_string(), "spiffe://example.org/ns/foo/sa/bar");
+
+        assert!("spiffe://example.org".parse::<Id>().is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_spiffe_ids() {
+        for invalid in &[
+            "spiffe://",
+            "spiffe:///ns/foo",
+            "spiffe://Example.org/ns/foo",
+            "spiffe://example.org/",
+            "spiffe://example.org/ns//foo",
+            "spiffe://example.org/ns/../foo",
+            "spiffe://example.org/ns/foo?bar",
+            "spiffe://user@example.org/ns/foo",
+        ] {
+            assert!(
+                invalid.parse::<Id>().is_err(),
+                "{} must be invalid",
+                invalid
+            );
+        }
+    }
+}
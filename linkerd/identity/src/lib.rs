@@ -2,11 +2,13 @@
 #![forbid(unsafe_code)]
 
 mod credentials;
+mod id;
 mod local;
 mod name;
 
 pub use self::{
     credentials::{Credentials, DerX509},
+    id::{Id, SpiffeId},
     local::LocalId,
     name::Name,
 };
@@ -1,8 +1,8 @@
 use crate::creds::CredsRx;
-use linkerd_identity::Name;
+use linkerd_identity::{Id, Name};
 use linkerd_io as io;
 use linkerd_stack::{NewService, Service};
-use linkerd_tls::{client::AlpnProtocols, ClientTls, NegotiatedProtocolRef, ServerId};
+use linkerd_tls::{client::AlpnProtocols, ClientTls, NegotiatedProtocolRef, ServerId, ServerName};
 use std::{future::Future, pin::Pin, sync::Arc, task::Context};
 use tracing::debug;
 
@@ -13,7 +13,10 @@ pub struct NewClient(CredsRx);
 pub struct Connect {
     rx: CredsRx,
     alpn: Option<Arc<[Vec<u8>]>>,
-    server_id: Name,
+    server_name: Name,
+    /// Set when the server's identity differs from its server name, in which case the peer
+    /// certificate is verified for the identity rather than the name.
+    server_id: Option<Id>,
 }
 
 pub type ConnectFuture<I> = Pin<Box<dyn Future<Output = io::Result<ClientIo<I>>> + Send>>;
@@ -47,11 +50,16 @@ impl std::fmt::Debug for NewClient {
 
 impl Connect {
     pub(crate) fn new(client_tls: ClientTls, rx: CredsRx) -> Self {
-        let ServerId(server_id) = client_tls.server_id;
+        let ServerName(server_name) = client_tls.server_name;
+        let server_id = match client_tls.server_id {
+            ServerId(Id::Dns(name)) if name == server_name => None,
+            ServerId(id) => Some(id),
+        };
         let alpn = client_tls.alpn.map(|AlpnProtocols(ps)| ps.into());
         Self {
             rx,
             alpn,
+            server_name,
             server_id,
         }
     }
@@ -70,17 +78,23 @@ where
     }
 
     fn call(&mut self, io: I) -> Self::Future {
-        let id = self.server_id.clone();
+        let server_name = self.server_name.clone();
+        let server_id = self.server_id.clone();
         let connector = self
             .rx
             .borrow()
             .connector(self.alpn.as_deref().unwrap_or(&[]));
         Box::pin(async move {
             let conn = connector.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
-            let config = conn
+            let mut config = conn
                 .configure()
                 .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
-            let io = tokio_boring::connect(config, id.as_str(), io)
+            // boring can only verify the peer's DNS SANs for the server name, so a server with
+            // another identity (e.g. a SPIFFE ID) is verified once the handshake completes.
+            if server_id.is_some() {
+                config.set_verify_hostname(false);
+            }
+            let io = tokio_boring::connect(config, server_name.as_str(), io)
                 .await
                 .map_err(|e| match e.as_io_error() {
                     // TODO(ver) boring should let us take ownership of the error directly.
@@ -90,6 +104,19 @@ where
                     None => io::Error::new(io::ErrorKind::Other, "unexpected TLS handshake error"),
                 })?;
 
+            if let Some(id) = server_id {
+                let verified = io
+                    .ssl()
+                    .peer_certificate()
+                    .map_or(false, |cert| super::has_identity(&cert, &id));
+                if !verified {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("peer certificate is not valid for {}", id),
+                    ));
+                }
+            }
+
             debug!(
                 tls = io.ssl().version_str(),
                 client.cert = ?io.ssl().certificate().and_then(super::fingerprint),
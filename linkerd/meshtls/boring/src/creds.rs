@@ -41,6 +41,7 @@ struct BaseCreds {
     key: PKey<Private>,
 }
 
+#[derive(Clone)]
 struct Certs {
     leaf: X509,
     intermediates: Vec<X509>,
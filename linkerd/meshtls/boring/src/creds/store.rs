@@ -1,5 +1,8 @@
 use super::{BaseCreds, Certs, Creds, CredsTx};
-use boring::x509::{X509StoreContext, X509};
+use boring::{
+    pkey::PKey,
+    x509::{X509StoreContext, X509},
+};
 use linkerd_error::Result;
 use linkerd_identity as id;
 use std::sync::Arc;
@@ -8,6 +11,8 @@ pub struct Store {
     creds: Arc<BaseCreds>,
     csr: Vec<u8>,
     name: id::Name,
+    /// The most recently published certificate, if any.
+    certs: Option<Certs>,
     tx: CredsTx,
 }
 
@@ -19,10 +24,40 @@ impl Store {
             creds,
             csr: csr.into(),
             name,
+            certs: None,
             tx,
         }
     }
 
+    /// Replaces the trust anchors, publishing new credentials.
+    ///
+    /// Fails if the current certificate is not valid under the new trust anchors, so rotated
+    /// bundles must include both the old and new roots until a new certificate is issued.
+    pub fn set_roots(&mut self, roots_pem: &str) -> Result<()> {
+        let roots = X509::stack_from_pem(roots_pem.as_bytes())?;
+        if roots.is_empty() {
+            return Err("no trust roots in PEM file".into());
+        }
+
+        let base = Arc::new(BaseCreds {
+            roots,
+            key: self.creds.key.clone(),
+        });
+        let creds = Creds {
+            base: base.clone(),
+            certs: self.certs.clone(),
+        };
+        if creds.certs.is_some() {
+            verify(&creds)?;
+        }
+
+        self.creds = base;
+        tracing::debug!("Updated trust anchors");
+        let _ = self.tx.send(creds);
+
+        Ok(())
+    }
+
     fn cert_matches_name(&self, cert: &X509) -> bool {
         for san in cert.subject_alt_names().into_iter().flatten() {
             if let Some(n) = san.dnsname() {
@@ -66,28 +101,16 @@ impl id::Credentials for Store {
             .map(|id::DerX509(der)| X509::from_der(&der).map_err(Into::into))
             .collect::<Result<Vec<_>>>()?;
 
+        let certs = Certs {
+            leaf,
+            intermediates,
+        };
         let creds = Creds {
             base: self.creds.clone(),
-            certs: Some(Certs {
-                leaf,
-                intermediates,
-            }),
-        };
-
-        let mut context = X509StoreContext::new()?;
-        let roots = creds.root_store()?;
-
-        let mut chain = boring::stack::Stack::new()?;
-        for i in &creds.certs.as_ref().unwrap().intermediates {
-            chain.push(i.to_owned())?;
-        }
-        let init = {
-            let leaf = &creds.certs.as_ref().unwrap().leaf;
-            context.init(&roots, leaf, &chain, |c| c.verify_cert())?
+            certs: Some(certs.clone()),
         };
-        if !init {
-            return Err("certificate could not be validated against the trust chain".into());
-        }
+        verify(&creds)?;
+        self.certs = Some(certs);
 
         // If receivers are dropped, we don't return an error (as this would likely cause the
         // updater to retry more aggressively). It's fine to silently ignore these errors.
@@ -95,4 +118,52 @@ impl id::Credentials for Store {
 
         Ok(())
     }
+
+    /// Replaces the key with which certificates are published.
+    fn set_key_and_certificate(
+        &mut self,
+        key_pkcs8: &[u8],
+        leaf: id::DerX509,
+        intermediates: Vec<id::DerX509>,
+        expiry: std::time::SystemTime,
+    ) -> Result<()> {
+        let key = PKey::private_key_from_pkcs8(key_pkcs8)?;
+        if !X509::from_der(&leaf)?.public_key()?.public_eq(&key) {
+            return Err("certificate was not issued for the private key".into());
+        }
+
+        let base = Arc::new(BaseCreds {
+            roots: self.creds.roots.clone(),
+            key,
+        });
+        let prior = std::mem::replace(&mut self.creds, base);
+        if let Err(error) = self.set_certificate(leaf, intermediates, expiry) {
+            self.creds = prior;
+            return Err(error);
+        }
+        tracing::debug!("Updated private key");
+        Ok(())
+    }
+}
+
+/// Ensures that the credentials' certificate is valid under its trust anchors.
+fn verify(creds: &Creds) -> Result<()> {
+    let certs = creds
+        .certs
+        .as_ref()
+        .expect("credentials must have a certificate");
+
+    let mut context = X509StoreContext::new()?;
+    let roots = creds.root_store()?;
+
+    let mut chain = boring::stack::Stack::new()?;
+    for i in &certs.intermediates {
+        chain.push(i.to_owned())?;
+    }
+    let init = context.init(&roots, &certs.leaf, &chain, |c| c.verify_cert())?;
+    if !init {
+        return Err("certificate could not be validated against the trust chain".into());
+    }
+
+    Ok(())
 }
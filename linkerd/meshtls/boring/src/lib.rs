@@ -34,7 +34,38 @@ pub use self::{
     server::{Server, ServerIo, TerminateFuture},
 };
 
+use linkerd_identity::{Id, Name, SpiffeId};
+
 fn fingerprint(c: &boring::x509::X509Ref) -> Option<String> {
     let digest = c.digest(boring::hash::MessageDigest::sha256()).ok()?;
     Some(hex::encode(digest)[0..8].to_string())
 }
+
+/// Returns the peer's identity from its certificate, preferring a DNS-like name in the DNS SANs
+/// and falling back to a SPIFFE ID in the URI SANs.
+fn peer_identity(c: &boring::x509::X509Ref) -> Option<Id> {
+    let sans = c.subject_alt_names()?;
+    let dns = sans
+        .iter()
+        .find_map(|san| san.dnsname()?.parse::<Name>().ok());
+    dns.map(Id::Dns).or_else(|| {
+        sans.iter()
+            .find_map(|san| san.uri()?.parse::<SpiffeId>().ok())
+            .map(Id::Spiffe)
+    })
+}
+
+/// Indicates whether the certificate's SANs include the given identity.
+fn has_identity(c: &boring::x509::X509Ref, id: &Id) -> bool {
+    let sans = match c.subject_alt_names() {
+        Some(sans) => sans,
+        None => return false,
+    };
+    sans.iter().any(|san| match id {
+        Id::Dns(name) => san
+            .dnsname()
+            .and_then(|n| n.parse::<Name>().ok())
+            .map_or(false, |n| n == *name),
+        Id::Spiffe(id) => san.uri().map_or(false, |uri| uri == id.as_str()),
+    })
+}
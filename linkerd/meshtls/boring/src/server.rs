@@ -114,17 +114,10 @@ impl<I> ServerIo<I> {
             debug!("Connection missing peer certificate");
             None
         })?;
-        let sans = cert.subject_alt_names().or_else(|| {
-            debug!("Peer certificate missing SANs");
+        super::peer_identity(&cert).map(ClientId).or_else(|| {
+            debug!("Peer certificate missing DNS or SPIFFE SANs");
             None
-        })?;
-        sans.into_iter()
-            .filter_map(|san| san.dnsname()?.parse().ok())
-            .next()
-            .or_else(|| {
-                debug!("Peer certificate missing DNS SANs");
-                None
-            })
+        })
     }
 }
 
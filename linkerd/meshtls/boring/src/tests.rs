@@ -46,3 +46,51 @@ fn recognize_cert_is_not_valid_for_identity() {
         )
         .is_err());
 }
+
+#[test]
+fn roots_must_trust_current_certificate() {
+    let mut store = load(&FOO_NS1);
+    store
+        .set_certificate(
+            DerX509(FOO_NS1.crt.to_vec()),
+            vec![],
+            std::time::SystemTime::now() + Duration::from_secs(600),
+        )
+        .expect("certificate must be valid");
+
+    let ca1 = std::str::from_utf8(FOO_NS1.trust_anchors).expect("valid PEM");
+    let ca2 = std::str::from_utf8(FOO_NS1_CA2.trust_anchors).expect("valid PEM");
+    assert!(store.set_roots(ca2).is_err());
+    assert!(store.set_roots(&format!("{}\n{}", ca1, ca2)).is_ok());
+}
+
+#[test]
+fn key_must_match_certificate() {
+    let mut store = load(&FOO_NS1);
+    let expiry = std::time::SystemTime::now() + Duration::from_secs(600);
+    assert!(store
+        .set_key_and_certificate(BAR_NS1.key, DerX509(FOO_NS1.crt.to_vec()), vec![], expiry)
+        .is_err());
+    assert!(store
+        .set_key_and_certificate(FOO_NS1.key, DerX509(FOO_NS1.crt.to_vec()), vec![], expiry)
+        .is_ok());
+}
+
+#[test]
+fn reads_identities_from_sans() {
+    let dns = boring::x509::X509::from_der(FOO_NS1.crt).expect("certificate must be valid");
+    let foo: linkerd_identity::Id = FOO_NS1.name.parse().unwrap();
+    assert_eq!(crate::peer_identity(&dns), Some(foo.clone()));
+    assert!(crate::has_identity(&dns, &foo));
+
+    let spiffe =
+        boring::x509::X509::from_der(FOO_NS1_SPIFFE.crt).expect("certificate must be valid");
+    let foo_spiffe: linkerd_identity::Id = FOO_NS1_SPIFFE.name.parse().unwrap();
+    assert_eq!(crate::peer_identity(&spiffe), Some(foo_spiffe.clone()));
+    assert!(crate::has_identity(&spiffe, &foo_spiffe));
+
+    assert!(!crate::has_identity(&dns, &foo_spiffe));
+    assert!(!crate::has_identity(&spiffe, &foo));
+    let bar_spiffe = "spiffe://cluster.local/ns/ns1/sa/bar".parse().unwrap();
+    assert!(!crate::has_identity(&spiffe, &bar_spiffe));
+}
@@ -1,4 +1,6 @@
+use crate::verify::{ServerIdVerifier, TrustRoots};
 use futures::prelude::*;
+use linkerd_identity as id;
 use linkerd_io as io;
 use linkerd_stack::{NewService, Service};
 use linkerd_tls::{client::AlpnProtocols, ClientTls, NegotiatedProtocolRef};
@@ -10,12 +12,13 @@ use tokio_rustls::rustls::{self, ClientConfig};
 #[derive(Clone)]
 pub struct NewClient {
     config: watch::Receiver<Arc<ClientConfig>>,
+    roots: watch::Receiver<TrustRoots>,
 }
 
 /// A `Service` that initiates client-side TLS connections.
 #[derive(Clone)]
 pub struct Connect {
-    server_id: rustls::ServerName,
+    server_name: rustls::ServerName,
     config: Arc<ClientConfig>,
 }
 
@@ -30,8 +33,11 @@ pub struct ClientIo<I>(tokio_rustls::client::TlsStream<I>);
 // === impl NewClient ===
 
 impl NewClient {
-    pub(crate) fn new(config: watch::Receiver<Arc<ClientConfig>>) -> Self {
-        Self { config }
+    pub(crate) fn new(
+        config: watch::Receiver<Arc<ClientConfig>>,
+        roots: watch::Receiver<TrustRoots>,
+    ) -> Self {
+        Self { config, roots }
     }
 }
 
@@ -39,7 +45,11 @@ impl NewService<ClientTls> for NewClient {
     type Service = Connect;
 
     fn new_service(&self, target: ClientTls) -> Self::Service {
-        Connect::new(target, (*self.config.borrow()).clone())
+        Connect::new(
+            target,
+            (*self.config.borrow()).clone(),
+            self.roots.borrow().clone(),
+        )
     }
 }
 
@@ -52,7 +62,7 @@ impl std::fmt::Debug for NewClient {
 // === impl Connect ===
 
 impl Connect {
-    pub(crate) fn new(client_tls: ClientTls, config: Arc<ClientConfig>) -> Self {
+    pub(crate) fn new(client_tls: ClientTls, config: Arc<ClientConfig>, roots: TrustRoots) -> Self {
         // If ALPN protocols are configured by the endpoint, we have to clone the entire
         // configuration and set the protocols. If there are no ALPN options, clone the Arc'd base
         // configuration without extra allocation.
@@ -68,10 +78,26 @@ impl Connect {
             }
         };
 
-        let server_id = rustls::ServerName::try_from(client_tls.server_id.as_str())
-            .expect("identity must be a valid DNS name");
+        // The configured verifier checks that the server's certificate is valid for the name that's
+        // sent in the SNI extension. If the server's identity differs from this name (e.g. it's a
+        // SPIFFE ID), the certificate must be verified for the identity instead.
+        let config = match &*client_tls.server_id {
+            id::Id::Dns(name) if *name == *client_tls.server_name => config,
+            id => {
+                let mut c = (*config).clone();
+                c.dangerous()
+                    .set_certificate_verifier(Arc::new(ServerIdVerifier::new(roots, id.clone())));
+                Arc::new(c)
+            }
+        };
+
+        let server_name = rustls::ServerName::try_from(client_tls.server_name.as_str())
+            .expect("server name must be a valid DNS name");
 
-        Self { server_id, config }
+        Self {
+            server_name,
+            config,
+        }
     }
 }
 
@@ -90,7 +116,7 @@ where
     fn call(&mut self, io: I) -> Self::Future {
         tokio_rustls::TlsConnector::from(self.config.clone())
             // XXX(eliza): it's a bummer that the server name has to be cloned here...
-            .connect(self.server_id.clone(), io)
+            .connect(self.server_name.clone(), io)
             .map_ok(ClientIo)
     }
 }
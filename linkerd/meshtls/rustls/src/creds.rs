@@ -2,6 +2,7 @@ mod receiver;
 mod store;
 
 pub use self::{receiver::Receiver, store::Store};
+use crate::verify::TrustRoots;
 use linkerd_error::Result;
 use linkerd_identity as id;
 use ring::{error::KeyRejected, signature::EcdsaKeyPair};
@@ -25,38 +26,12 @@ pub fn watch(
     key_pkcs8: &[u8],
     csr: &[u8],
 ) -> Result<(Store, Receiver)> {
-    let mut roots = rustls::RootCertStore::empty();
-    let certs = match rustls_pemfile::certs(&mut std::io::Cursor::new(roots_pem)) {
-        Err(error) => {
-            warn!(%error, "invalid trust anchors file");
-            return Err(error.into());
-        }
-        Ok(certs) if certs.is_empty() => {
-            warn!("no valid certs in trust anchors file");
-            return Err("no trust roots in PEM file".into());
-        }
-        Ok(certs) => certs,
-    };
-
-    let (added, skipped) = roots.add_parsable_certificates(&certs[..]);
-    if skipped != 0 {
-        warn!("Skipped {} invalid trust anchors", skipped);
-    }
-    if added == 0 {
-        return Err("no trust roots loaded".into());
-    }
+    let (roots, trust_roots) = parse_roots(roots_pem)?;
 
     let key = EcdsaKeyPair::from_pkcs8(params::SIGNATURE_ALG_RING_SIGNING, key_pkcs8)
         .map_err(InvalidKey)?;
 
-    // XXX: Rustls's built-in verifiers don't let us tweak things as fully as we'd like (e.g.
-    // controlling the set of trusted signature algorithms), but they provide good enough
-    // defaults for now.
-    // TODO: lock down the verification further.
-    let server_cert_verifier = Arc::new(rustls::client::WebPkiVerifier::new(
-        roots.clone(),
-        None, // no certificate transparency policy
-    ));
+    let server_cert_verifier = server_cert_verifier(roots.clone());
 
     let (client_tx, client_rx) = {
         // Since we don't have a certificate yet, build a client configuration
@@ -76,7 +51,9 @@ pub fn watch(
         watch::channel(store::server_config(roots.clone(), empty_resolver))
     };
 
-    let rx = Receiver::new(identity.clone(), client_rx, server_rx);
+    let (roots_tx, roots_rx) = watch::channel(trust_roots);
+
+    let rx = Receiver::new(identity.clone(), client_rx, roots_rx, server_rx);
     let store = Store::new(
         roots,
         server_cert_verifier,
@@ -84,12 +61,50 @@ pub fn watch(
         csr,
         identity,
         client_tx,
+        roots_tx,
         server_tx,
     );
 
     Ok((store, rx))
 }
 
+fn parse_roots(roots_pem: &str) -> Result<(rustls::RootCertStore, TrustRoots)> {
+    let mut roots = rustls::RootCertStore::empty();
+    let certs = match rustls_pemfile::certs(&mut std::io::Cursor::new(roots_pem)) {
+        Err(error) => {
+            warn!(%error, "invalid trust anchors file");
+            return Err(error.into());
+        }
+        Ok(certs) if certs.is_empty() => {
+            warn!("no valid certs in trust anchors file");
+            return Err("no trust roots in PEM file".into());
+        }
+        Ok(certs) => certs,
+    };
+
+    let (added, skipped) = roots.add_parsable_certificates(&certs[..]);
+    if skipped != 0 {
+        warn!("Skipped {} invalid trust anchors", skipped);
+    }
+    if added == 0 {
+        return Err("no trust roots loaded".into());
+    }
+
+    Ok((roots, TrustRoots::new(&certs)))
+}
+
+fn server_cert_verifier(
+    roots: rustls::RootCertStore,
+) -> Arc<dyn rustls::client::ServerCertVerifier> {
+    // XXX: Rustls's built-in verifiers don't let us tweak things as fully as we'd like (e.g.
+    // controlling the set of trusted signature algorithms), but they provide good enough
+    // defaults for now.
+    // TODO: lock down the verification further.
+    //
+    // No certificate transparency policy is configured.
+    Arc::new(rustls::client::WebPkiVerifier::new(roots, None))
+}
+
 #[cfg(feature = "test-util")]
 pub fn for_test(ent: &linkerd_tls_test_util::Entity) -> (Store, Receiver) {
     watch(
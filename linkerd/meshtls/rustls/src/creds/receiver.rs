@@ -1,4 +1,4 @@
-use crate::{NewClient, Server};
+use crate::{verify::TrustRoots, NewClient, Server};
 use linkerd_identity::Name;
 use std::sync::Arc;
 use tokio::sync::watch;
@@ -9,6 +9,7 @@ use tokio_rustls::rustls;
 pub struct Receiver {
     name: Name,
     client_rx: watch::Receiver<Arc<rustls::ClientConfig>>,
+    roots_rx: watch::Receiver<TrustRoots>,
     server_rx: watch::Receiver<Arc<rustls::ServerConfig>>,
 }
 
@@ -18,11 +19,13 @@ impl Receiver {
     pub(super) fn new(
         name: Name,
         client_rx: watch::Receiver<Arc<rustls::ClientConfig>>,
+        roots_rx: watch::Receiver<TrustRoots>,
         server_rx: watch::Receiver<Arc<rustls::ServerConfig>>,
     ) -> Self {
         Self {
             name,
             client_rx,
+            roots_rx,
             server_rx,
         }
     }
@@ -34,7 +37,7 @@ impl Receiver {
 
     /// Returns a `NewClient` that can be used to establish TLS on client connections.
     pub fn new_client(&self) -> NewClient {
-        NewClient::new(self.client_rx.clone())
+        NewClient::new(self.client_rx.clone(), self.roots_rx.clone())
     }
 
     /// Returns a `Server` that can be used to terminate TLS on server connections.
@@ -84,10 +87,12 @@ mod tests {
         let init_config = Arc::new(empty_server_config());
         let (server_tx, server_rx) = watch::channel(init_config.clone());
         let (_, client_rx) = watch::channel(Arc::new(empty_client_config()));
+        let (_, roots_rx) = watch::channel(TrustRoots::new(&[]));
         let receiver = Receiver {
             name: "example".parse().unwrap(),
             server_rx,
             client_rx,
+            roots_rx,
         };
 
         let server = receiver.server();
@@ -108,10 +113,12 @@ mod tests {
         let init_config = Arc::new(empty_server_config());
         let (server_tx, server_rx) = watch::channel(init_config.clone());
         let (_, client_rx) = watch::channel(Arc::new(empty_client_config()));
+        let (_, roots_rx) = watch::channel(TrustRoots::new(&[]));
         let receiver = Receiver {
             name: "example".parse().unwrap(),
             server_rx,
             client_rx,
+            roots_rx,
         };
 
         let server = receiver
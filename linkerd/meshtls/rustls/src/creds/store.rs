@@ -1,4 +1,5 @@
-use super::params::*;
+use super::{params::*, InvalidKey};
+use crate::verify::TrustRoots;
 use linkerd_error::Result;
 use linkerd_identity as id;
 use ring::{rand, signature::EcdsaKeyPair};
@@ -13,7 +14,10 @@ pub struct Store {
     key: Arc<EcdsaKeyPair>,
     csr: Arc<[u8]>,
     name: id::Name,
+    /// The most recently published certificate, if any.
+    resolver: Option<Arc<CertResolver>>,
     client_tx: watch::Sender<Arc<rustls::ClientConfig>>,
+    roots_tx: watch::Sender<TrustRoots>,
     server_tx: watch::Sender<Arc<rustls::ServerConfig>>,
 }
 
@@ -75,6 +79,7 @@ impl Store {
         csr: &[u8],
         name: id::Name,
         client_tx: watch::Sender<Arc<rustls::ClientConfig>>,
+        roots_tx: watch::Sender<TrustRoots>,
         server_tx: watch::Sender<Arc<rustls::ServerConfig>>,
     ) -> Self {
         Self {
@@ -83,7 +88,9 @@ impl Store {
             server_cert_verifier,
             csr: csr.into(),
             name,
+            resolver: None,
             client_tx,
+            roots_tx,
             server_tx,
         }
     }
@@ -100,29 +107,65 @@ impl Store {
         cfg.into()
     }
 
-    /// Ensures the certificate is valid for the services we terminate for TLS. This assumes that
-    /// server cert validation does the same or more validation than client cert validation.
-    fn validate(&self, certs: &[rustls::Certificate]) -> Result<()> {
-        let name = rustls::ServerName::try_from(self.name.as_str())
-            .expect("server name must be a valid DNS name");
-        static NO_OCSP: &[u8] = &[];
-        let end_entity = &certs[0];
-        let intermediates = &certs[1..];
-        let no_scts = &mut std::iter::empty();
-        let now = std::time::SystemTime::now();
-        self.server_cert_verifier.verify_server_cert(
-            end_entity,
-            intermediates,
-            &name,
-            no_scts,
-            NO_OCSP,
-            now,
-        )?;
-        debug!("Certified");
+    /// Replaces the trust anchors, publishing new TLS client and server configurations.
+    ///
+    /// Fails if the current certificate is not valid under the new trust anchors, so rotated
+    /// bundles must include both the old and new roots until a new certificate is issued.
+    pub fn set_roots(&mut self, roots_pem: &str) -> Result<()> {
+        let (roots, trust_roots) = super::parse_roots(roots_pem)?;
+        let server_cert_verifier = super::server_cert_verifier(roots.clone());
+        if let Some(resolver) = self.resolver.as_ref() {
+            validate(&self.name, &*server_cert_verifier, &resolver.0.cert)?;
+        }
+
+        self.roots = roots;
+        self.server_cert_verifier = server_cert_verifier;
+        debug!("Updated trust anchors");
+
+        let (client, server) = match self.resolver.clone() {
+            Some(resolver) => (
+                self.client_config(resolver.clone()),
+                server_config(self.roots.clone(), resolver),
+            ),
+            None => {
+                let mut client =
+                    client_config_builder(self.server_cert_verifier.clone()).with_no_client_auth();
+                client.enable_tickets = false;
+                let empty_resolver = Arc::new(rustls::server::ResolvesServerCertUsingSni::new());
+                (
+                    client.into(),
+                    server_config(self.roots.clone(), empty_resolver),
+                )
+            }
+        };
+
+        let _ = self.client_tx.send(client);
+        let _ = self.roots_tx.send(trust_roots);
+        let _ = self.server_tx.send(server);
+
         Ok(())
     }
 }
 
+/// Ensures the certificate is valid for the services we terminate for TLS. This assumes that
+/// server cert validation does the same or more validation than client cert validation.
+fn validate(
+    name: &id::Name,
+    verifier: &dyn rustls::client::ServerCertVerifier,
+    certs: &[rustls::Certificate],
+) -> Result<()> {
+    let name =
+        rustls::ServerName::try_from(name.as_str()).expect("server name must be a valid DNS name");
+    static NO_OCSP: &[u8] = &[];
+    let end_entity = &certs[0];
+    let intermediates = &certs[1..];
+    let no_scts = &mut std::iter::empty();
+    let now = std::time::SystemTime::now();
+    verifier.verify_server_cert(end_entity, intermediates, &name, no_scts, NO_OCSP, now)?;
+    debug!("Certified");
+    Ok(())
+}
+
 impl id::Credentials for Store {
     /// Returns the proxy's identity.
     fn dns_name(&self) -> &id::Name {
@@ -150,7 +193,7 @@ impl id::Credentials for Store {
         );
 
         // Use the client's verifier to validate the certificate for our local name.
-        self.validate(&*chain)?;
+        validate(&self.name, &*self.server_cert_verifier, &*chain)?;
 
         let resolver = Arc::new(CertResolver(Arc::new(rustls::sign::CertifiedKey::new(
             chain,
@@ -159,7 +202,8 @@ impl id::Credentials for Store {
 
         // Build new client and server TLS configs.
         let client = self.client_config(resolver.clone());
-        let server = server_config(self.roots.clone(), resolver);
+        let server = server_config(self.roots.clone(), resolver.clone());
+        self.resolver = Some(resolver);
 
         // Publish the new configs.
         let _ = self.client_tx.send(client);
@@ -167,6 +211,42 @@ impl id::Credentials for Store {
 
         Ok(())
     }
+
+    /// Replaces the key with which certificates are published.
+    fn set_key_and_certificate(
+        &mut self,
+        key_pkcs8: &[u8],
+        leaf: id::DerX509,
+        intermediates: Vec<id::DerX509>,
+        expiry: std::time::SystemTime,
+    ) -> Result<()> {
+        let key =
+            EcdsaKeyPair::from_pkcs8(SIGNATURE_ALG_RING_SIGNING, key_pkcs8).map_err(InvalidKey)?;
+        verify_key(&key, &*leaf)?;
+
+        let prior = std::mem::replace(&mut self.key, Arc::new(key));
+        if let Err(error) = self.set_certificate(leaf, intermediates, expiry) {
+            self.key = prior;
+            return Err(error);
+        }
+        debug!("Updated private key");
+        Ok(())
+    }
+}
+
+/// Ensures that the certificate was issued for the key by verifying a signature made with it.
+fn verify_key(key: &EcdsaKeyPair, leaf: &[u8]) -> Result<()> {
+    const MESSAGE: &[u8] = b"linkerd identity key check";
+
+    let signature = key
+        .sign(&rand::SystemRandom::new(), MESSAGE)
+        .map_err(|ring::error::Unspecified| "failed to sign with the private key")?;
+    webpki::EndEntityCert::try_from(leaf)
+        .and_then(|cert| {
+            cert.verify_signature(&webpki::ECDSA_P256_SHA256, MESSAGE, signature.as_ref())
+        })
+        .map_err(|_| "certificate was not issued for the private key")?;
+    Ok(())
 }
 
 // === impl Key ===
@@ -6,6 +6,7 @@ pub mod creds;
 mod server;
 #[cfg(test)]
 mod tests;
+mod verify;
 
 pub use self::{
     client::{ClientIo, Connect, ConnectFuture, NewClient},
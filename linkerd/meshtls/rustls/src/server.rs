@@ -129,7 +129,12 @@ fn client_identity<I>(tls: &tokio_rustls::server::TlsStream<I>) -> Option<Client
     let (_io, session) = tls.get_ref();
     let certs = session.peer_certificates()?;
     let c = certs.first().map(Certificate::as_ref)?;
-    let end_cert = webpki::EndEntityCert::try_from(c).ok()?;
+    dns_identity(c).or_else(|| crate::verify::spiffe_id(c).map(|id| ClientId(id.into())))
+}
+
+/// Returns the first DNS-like name in the certificate's DNS SANs.
+fn dns_identity(cert: &[u8]) -> Option<ClientId> {
+    let end_cert = webpki::EndEntityCert::try_from(cert).ok()?;
     let dns_names = end_cert.dns_names().ok()?;
 
     match dns_names.first()? {
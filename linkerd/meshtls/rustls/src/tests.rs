@@ -46,3 +46,32 @@ fn recognize_cert_is_not_valid_for_identity() {
         )
         .is_err());
 }
+
+#[test]
+fn roots_must_trust_current_certificate() {
+    let mut store = load(&FOO_NS1);
+    store
+        .set_certificate(
+            DerX509(FOO_NS1.crt.to_vec()),
+            vec![],
+            std::time::SystemTime::now() + Duration::from_secs(600),
+        )
+        .expect("certificate must be valid");
+
+    let ca1 = std::str::from_utf8(FOO_NS1.trust_anchors).expect("valid PEM");
+    let ca2 = std::str::from_utf8(FOO_NS1_CA2.trust_anchors).expect("valid PEM");
+    assert!(store.set_roots(ca2).is_err());
+    assert!(store.set_roots(&format!("{}\n{}", ca1, ca2)).is_ok());
+}
+
+#[test]
+fn key_must_match_certificate() {
+    let mut store = load(&FOO_NS1);
+    let expiry = std::time::SystemTime::now() + Duration::from_secs(600);
+    assert!(store
+        .set_key_and_certificate(BAR_NS1.key, DerX509(FOO_NS1.crt.to_vec()), vec![], expiry)
+        .is_err());
+    assert!(store
+        .set_key_and_certificate(FOO_NS1.key, DerX509(FOO_NS1.crt.to_vec()), vec![], expiry)
+        .is_ok());
+}
@@ -0,0 +1,199 @@
+use linkerd_identity::{Id, SpiffeId};
+use std::{convert::TryFrom, sync::Arc, time::SystemTime};
+use tokio_rustls::rustls::{
+    self,
+    client::{ServerCertVerified, ServerCertVerifier},
+    Certificate,
+};
+
+/// Trust anchors, as DER-encoded certificates.
+///
+/// rustls doesn't expose the trust anchors in a `RootCertStore`, so they're retained to verify
+/// servers whose identities differ from the names with which they're addressed.
+#[derive(Clone)]
+pub(crate) struct TrustRoots(Arc<[Vec<u8>]>);
+
+/// Verifies that a server's certificate is valid for its identity (e.g. a SPIFFE ID in its URI
+/// SANs), rather than for the name sent in the client's SNI extension.
+pub(crate) struct ServerIdVerifier {
+    roots: TrustRoots,
+    id: Id,
+}
+
+/// The signature algorithms that rustls's `WebPkiVerifier` trusts.
+static SUPPORTED_SIG_ALGS: &[&webpki::SignatureAlgorithm] = &[
+    &webpki::ECDSA_P256_SHA256,
+    &webpki::ECDSA_P256_SHA384,
+    &webpki::ECDSA_P384_SHA256,
+    &webpki::ECDSA_P384_SHA384,
+    &webpki::ED25519,
+    &webpki::RSA_PSS_2048_8192_SHA256_LEGACY_KEY,
+    &webpki::RSA_PSS_2048_8192_SHA384_LEGACY_KEY,
+    &webpki::RSA_PSS_2048_8192_SHA512_LEGACY_KEY,
+    &webpki::RSA_PKCS1_2048_8192_SHA256,
+    &webpki::RSA_PKCS1_2048_8192_SHA384,
+    &webpki::RSA_PKCS1_2048_8192_SHA512,
+    &webpki::RSA_PKCS1_3072_8192_SHA384,
+];
+
+/// Returns the first SPIFFE ID in the certificate's URI SANs.
+///
+/// webpki only exposes DNS SANs, so the certificate is parsed again to find URI SANs.
+pub(crate) fn spiffe_id(cert: &[u8]) -> Option<SpiffeId> {
+    use x509_parser::extensions::GeneralName;
+
+    let (_, cert) = x509_parser::parse_x509_certificate(cert).ok()?;
+    let (_critical, sans) = cert.tbs_certificate.subject_alternative_name()?;
+    sans.general_names.iter().find_map(|san| match san {
+        GeneralName::URI(uri) => uri.parse::<SpiffeId>().ok(),
+        _ => None,
+    })
+}
+
+// === impl TrustRoots ===
+
+impl TrustRoots {
+    /// Retains the certificates that may be used as trust anchors.
+    pub(crate) fn new(certs: &[Vec<u8>]) -> Self {
+        let roots = certs
+            .iter()
+            .filter(|der| webpki::TrustAnchor::try_from_cert_der(der.as_slice()).is_ok())
+            .cloned()
+            .collect::<Vec<_>>();
+        Self(roots.into())
+    }
+}
+
+// === impl ServerIdVerifier ===
+
+impl ServerIdVerifier {
+    pub(crate) fn new(roots: TrustRoots, id: Id) -> Self {
+        Self { roots, id }
+    }
+}
+
+impl ServerCertVerifier for ServerIdVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        _server_name: &rustls::ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let anchors = self
+            .roots
+            .0
+            .iter()
+            .filter_map(|der| webpki::TrustAnchor::try_from_cert_der(der.as_slice()).ok())
+            .collect::<Vec<_>>();
+        let chain = intermediates
+            .iter()
+            .map(|c| c.0.as_slice())
+            .collect::<Vec<_>>();
+        let now = webpki::Time::try_from(now).map_err(|_| rustls::Error::FailedToGetCurrentTime)?;
+
+        let cert = webpki::EndEntityCert::try_from(end_entity.0.as_slice()).map_err(pki_error)?;
+        cert.verify_is_valid_tls_server_cert(
+            SUPPORTED_SIG_ALGS,
+            &webpki::TlsServerTrustAnchors(&anchors),
+            &chain,
+            now,
+        )
+        .map_err(pki_error)?;
+
+        match &self.id {
+            Id::Dns(name) => {
+                let name = webpki::DnsNameRef::try_from_ascii_str(name.as_str())
+                    .map_err(|_| rustls::Error::General(format!("invalid DNS name: {}", name)))?;
+                cert.verify_is_valid_for_dns_name(name).map_err(pki_error)?;
+            }
+            Id::Spiffe(id) => {
+                if spiffe_id(&end_entity.0).as_ref() != Some(id) {
+                    return Err(rustls::Error::InvalidCertificateData(format!(
+                        "certificate is not valid for {}",
+                        id
+                    )));
+                }
+            }
+        }
+
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+fn pki_error(error: webpki::Error) -> rustls::Error {
+    use webpki::Error::*;
+    match error {
+        BadDer | BadDerTime => rustls::Error::InvalidCertificateEncoding,
+        InvalidSignatureForPublicKey => rustls::Error::InvalidCertificateSignature,
+        UnsupportedSignatureAlgorithm | UnsupportedSignatureAlgorithmForPublicKey => {
+            rustls::Error::InvalidCertificateSignatureType
+        }
+        e => rustls::Error::InvalidCertificateData(format!("invalid peer certificate: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_tls_test_util::*;
+
+    fn roots(ent: &Entity) -> TrustRoots {
+        let pem = std::str::from_utf8(ent.trust_anchors).expect("roots must be PEM");
+        let certs = rustls_pemfile::certs(&mut std::io::Cursor::new(pem)).expect("valid PEM");
+        TrustRoots::new(&certs)
+    }
+
+    fn verify(verifier: &ServerIdVerifier, ent: &Entity) -> Result<(), rustls::Error> {
+        // The SNI is ignored, so any name will do.
+        let sni = rustls::ServerName::try_from("example.com").unwrap();
+        verifier
+            .verify_server_cert(
+                &Certificate(ent.crt.to_vec()),
+                &[],
+                &sni,
+                &mut std::iter::empty(),
+                &[],
+                SystemTime::now(),
+            )
+            .map(|_| ())
+    }
+
+    #[test]
+    fn extracts_spiffe_id_from_uri_san() {
+        assert_eq!(
+            spiffe_id(FOO_NS1_SPIFFE.crt),
+            Some(FOO_NS1_SPIFFE.name.parse().unwrap())
+        );
+        assert_eq!(spiffe_id(FOO_NS1.crt), None);
+    }
+
+    #[test]
+    fn verifies_spiffe_ids() {
+        let id = Id::Spiffe(FOO_NS1_SPIFFE.name.parse().unwrap());
+        let verifier = ServerIdVerifier::new(roots(&FOO_NS1_SPIFFE), id);
+        verify(&verifier, &FOO_NS1_SPIFFE).expect("certificate must be valid for its SPIFFE ID");
+        verify(&verifier, &FOO_NS1).expect_err("certificate has no SPIFFE ID");
+
+        let other = Id::Spiffe("spiffe://cluster.local/ns/ns1/sa/bar".parse().unwrap());
+        let verifier = ServerIdVerifier::new(roots(&FOO_NS1_SPIFFE), other);
+        verify(&verifier, &FOO_NS1_SPIFFE).expect_err("certificate has another SPIFFE ID");
+    }
+
+    #[test]
+    fn verifies_dns_names() {
+        let id = Id::Dns(FOO_NS1.name.parse().unwrap());
+        let verifier = ServerIdVerifier::new(roots(&FOO_NS1), id);
+        verify(&verifier, &FOO_NS1).expect("certificate must be valid for its name");
+        verify(&verifier, &BAR_NS1).expect_err("certificate has another name");
+    }
+
+    #[test]
+    fn requires_trusted_issuer() {
+        let id = Id::Dns(FOO_NS1.name.parse().unwrap());
+        let verifier = ServerIdVerifier::new(roots(&FOO_NS1_CA2), id);
+        verify(&verifier, &FOO_NS1).expect_err("certificate must be issued by a trust anchor");
+    }
+}
@@ -31,6 +31,21 @@ pub enum Receiver {
 
 // === impl Store ===
 
+impl Store {
+    /// Replaces the trust anchors, publishing new client and server configurations.
+    pub fn set_roots(&mut self, roots_pem: &str) -> Result<()> {
+        match self {
+            #[cfg(feature = "boring")]
+            Self::Boring(store) => store.set_roots(roots_pem),
+
+            #[cfg(feature = "rustls")]
+            Self::Rustls(store) => store.set_roots(roots_pem),
+            #[cfg(not(feature = "__has_any_tls_impls"))]
+            _ => crate::no_tls!(roots_pem),
+        }
+    }
+}
+
 impl Credentials for Store {
     fn dns_name(&self) -> &Name {
         match self {
@@ -72,6 +87,24 @@ impl Credentials for Store {
             _ => crate::no_tls!(leaf, chain, expiry),
         }
     }
+
+    fn set_key_and_certificate(
+        &mut self,
+        key_pkcs8: &[u8],
+        leaf: DerX509,
+        chain: Vec<DerX509>,
+        expiry: std::time::SystemTime,
+    ) -> Result<()> {
+        match self {
+            #[cfg(feature = "boring")]
+            Self::Boring(store) => store.set_key_and_certificate(key_pkcs8, leaf, chain, expiry),
+
+            #[cfg(feature = "rustls")]
+            Self::Rustls(store) => store.set_key_and_certificate(key_pkcs8, leaf, chain, expiry),
+            #[cfg(not(feature = "__has_any_tls_impls"))]
+            _ => crate::no_tls!(key_pkcs8, leaf, chain, expiry),
+        }
+    }
 }
 
 // === impl Receiver ===
@@ -49,10 +49,10 @@ pub async fn plaintext(mode: meshtls::Mode) {
 pub async fn proxy_to_proxy_tls_works(mode: meshtls::Mode) {
     let (_foo, _, server_tls) = load(mode, &test_util::FOO_NS1);
     let (_bar, client_tls, _) = load(mode, &test_util::BAR_NS1);
-    let server_id = tls::ServerId(test_util::FOO_NS1.name.parse().unwrap());
+    let server_tls_params = tls::ClientTls::from(test_util::FOO_NS1.name.parse::<Name>().unwrap());
     let (client_result, server_result) = run_test(
         client_tls.clone(),
-        Conditional::Some(server_id.clone()),
+        Conditional::Some(server_tls_params.clone()),
         |conn| write_then_read(conn, PING),
         server_tls,
         |(_, conn)| read_then_write(conn, PING.len(), PONG),
@@ -60,10 +60,7 @@ pub async fn proxy_to_proxy_tls_works(mode: meshtls::Mode) {
     .await;
     assert_eq!(
         client_result.tls,
-        Some(Conditional::Some(tls::ClientTls {
-            server_id,
-            alpn: None,
-        }))
+        Some(Conditional::Some(server_tls_params))
     );
     assert_eq!(&client_result.result.expect("pong")[..], PONG);
     assert_eq!(
@@ -86,7 +83,7 @@ pub async fn proxy_to_proxy_tls_pass_through_when_identity_does_not_match(mode:
 
     let (client_result, server_result) = run_test(
         client_tls,
-        Conditional::Some(tls::ServerId(sni.clone())),
+        Conditional::Some(tls::ClientTls::from(sni.clone())),
         |conn| write_then_read(conn, PING),
         server_tls,
         |(_, conn)| read_then_write(conn, START_OF_TLS.len(), PONG),
@@ -100,7 +97,7 @@ pub async fn proxy_to_proxy_tls_pass_through_when_identity_does_not_match(mode:
     assert_eq!(
         server_result.tls,
         Some(Conditional::Some(tls::ServerTls::Passthru {
-            sni: tls::ServerId(sni)
+            sni: tls::ServerName(sni)
         }))
     );
     assert_eq!(&server_result.result.unwrap()[..], START_OF_TLS);
@@ -153,7 +150,7 @@ type ClientIo =
 /// side.
 async fn run_test<C, CF, CR, S, SF, SR>(
     client_tls: meshtls::NewClient,
-    client_server_id: Conditional<tls::ServerId, tls::NoClientTls>,
+    client_server_tls: tls::ConditionalClientTls,
     client: C,
     server_tls: meshtls::Server,
     server: S,
@@ -230,11 +227,11 @@ where
         // parallels the server side.
         let (sender, receiver) = mpsc::channel::<Transported<tls::ConditionalClientTls, CR>>();
 
-        let tls = Some(client_server_id.clone().map(Into::into));
+        let tls = Some(client_server_tls.clone());
         let client = async move {
             let conn = tls::Client::layer(client_tls)
                 .layer(ConnectTcp::new(Keepalive(None)))
-                .oneshot(Target(server_addr.into(), client_server_id.map(Into::into)))
+                .oneshot(Target(server_addr.into(), client_server_tls))
                 .await;
             match conn {
                 Err(e) => {
@@ -0,0 +1,152 @@
+#![deny(warnings, rust_2018_idioms)]
+#![forbid(unsafe_code)]
+
+pub mod metrics;
+
+use futures::stream::{Stream, StreamExt};
+use http_body::Body as HttpBody;
+use linkerd_error::Error;
+use metrics::Registry;
+pub use opentelemetry_proto as proto;
+use opentelemetry_proto::collector::trace::v1::{
+    trace_service_client::TraceServiceClient, ExportTraceServiceRequest,
+};
+use opentelemetry_proto::resource::v1::Resource;
+use opentelemetry_proto::trace::v1::{InstrumentationLibrarySpans, ResourceSpans, Span};
+use tokio::time;
+use tonic::{self as grpc, body::BoxBody, client::GrpcService};
+use tracing::{debug, trace};
+
+pub async fn export_spans<T, S>(client: T, resource: Resource, spans: S, metrics: Registry)
+where
+    T: GrpcService<BoxBody> + Clone,
+    T::Error: Into<Error>,
+    <T::ResponseBody as HttpBody>::Error: Into<Error> + Send + Sync,
+    T::ResponseBody: Send + Sync + 'static,
+    S: Stream<Item = Span> + Unpin,
+{
+    debug!("Span exporter running");
+    SpanExporter::new(client, resource, spans, metrics)
+        .run()
+        .await
+}
+
+/// SpanExporter sends a Stream of spans to the given OTLP TraceService gRPC service.
+///
+/// Unlike the OpenCensus agent API, OTLP does not stream spans to the collector: each batch of
+/// spans is exported with its own request, and every request describes the proxy's resource.
+struct SpanExporter<T, S> {
+    client: T,
+    resource: Resource,
+    spans: S,
+    metrics: Registry,
+}
+
+#[derive(Debug)]
+struct SpanRxClosed;
+
+// === impl SpanExporter ===
+
+impl<T, S> SpanExporter<T, S>
+where
+    T: GrpcService<BoxBody>,
+    T::Error: Into<Error>,
+    <T::ResponseBody as HttpBody>::Error: Into<Error> + Send + Sync,
+    T::ResponseBody: Send + Sync + 'static,
+    S: Stream<Item = Span> + Unpin,
+{
+    const MAX_BATCH_SIZE: usize = 1000;
+    const MAX_BATCH_IDLE: time::Duration = time::Duration::from_secs(10);
+
+    fn new(client: T, resource: Resource, spans: S, metrics: Registry) -> Self {
+        Self {
+            client,
+            resource,
+            spans,
+            metrics,
+        }
+    }
+
+    async fn run(self) {
+        let Self {
+            client,
+            resource,
+            mut spans,
+            mut metrics,
+        } = self;
+
+        // Holds the batch of pending spans. Cleared as the spans are flushed.
+        // Contains no more than MAX_BATCH_SIZE spans.
+        let mut accum = Vec::new();
+
+        let mut svc = TraceServiceClient::new(client);
+        loop {
+            // Collect spans into a batch.
+            let collect = Self::collect_batch(&mut spans, &mut accum).await;
+
+            // If we collected spans, flush them.
+            if !accum.is_empty() {
+                let len = accum.len();
+                let msg = ExportTraceServiceRequest {
+                    resource_spans: vec![ResourceSpans {
+                        resource: Some(resource.clone()),
+                        instrumentation_library_spans: vec![InstrumentationLibrarySpans {
+                            instrumentation_library: None,
+                            spans: accum.drain(..).collect(),
+                        }],
+                    }],
+                };
+                trace!(spans = len, "Sending batch");
+                match svc.export(grpc::Request::new(msg)).await {
+                    Ok(_rsp) => metrics.send(len as u64),
+                    Err(error) => {
+                        // Failed batches are not retried so that spans do not accumulate while
+                        // the collector is unavailable.
+                        debug!(%error, spans = len, "Failed to export spans");
+                        metrics.fail();
+                    }
+                }
+            }
+
+            // If the span source was closed, end the task.
+            if let Err(SpanRxClosed) = collect {
+                debug!("Span channel lost");
+                return;
+            }
+        }
+    }
+
+    /// Collects spans from the proxy into `accum`.
+    ///
+    /// Returns an error when the span stream has completed. An error may be
+    /// returned after accumulating spans.
+    async fn collect_batch(spans: &mut S, accum: &mut Vec<Span>) -> Result<(), SpanRxClosed> {
+        loop {
+            if accum.len() == Self::MAX_BATCH_SIZE {
+                trace!(capacity = Self::MAX_BATCH_SIZE, "Batch capacity reached");
+                return Ok(());
+            }
+
+            tokio::select! {
+                biased;
+
+                res = spans.next() => match res {
+                    Some(span) => {
+                        trace!(?span, "Adding to batch");
+                        accum.push(span);
+                    }
+                    None => return Err(SpanRxClosed),
+                },
+
+                // Don't hold spans indefinitely. Return if we hit an idle
+                // timeout and spans have been collected.
+                _ = time::sleep(Self::MAX_BATCH_IDLE) => {
+                    if !accum.is_empty() {
+                        trace!(spans = accum.len(), "Flushing spans due to inactivity");
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+}
@@ -7,7 +7,7 @@ use crate::{
     metadata::{Metadata, ProtocolHint},
 };
 use http::uri::Authority;
-use linkerd_tls::client::ServerId;
+use linkerd_tls::client::{ClientTls, ServerName};
 use std::{collections::HashMap, net::SocketAddr, str::FromStr};
 
 /// Construct a new labeled `SocketAddr `from a protobuf `WeightedAddr`.
@@ -52,12 +52,13 @@ pub fn to_addr_meta(
     Some((addr, meta))
 }
 
-fn to_id(pb: TlsIdentity) -> Option<ServerId> {
+fn to_id(pb: TlsIdentity) -> Option<ClientTls> {
     use crate::api::destination::tls_identity::Strategy;
 
+    // Endpoints with DNS-like identities are addressed by their identities.
     let Strategy::DnsLikeIdentity(i) = pb.strategy?;
-    match ServerId::from_str(&i.name) {
-        Ok(i) => Some(i),
+    match ServerName::from_str(&i.name) {
+        Ok(ServerName(name)) => Some(name.into()),
         Err(_) => {
             tracing::warn!("Ignoring invalid identity: {}", i.name);
             None
@@ -1,14 +1,34 @@
 use crate::Error;
+use futures::{ready, TryFuture};
 use hyper::body::HttpBody;
 pub use hyper_balance::{PendingUntilFirstData, PendingUntilFirstDataBody};
+use linkerd_stack::{layer, Either, Param};
+use pin_project::pin_project;
 use rand::thread_rng;
-use std::{hash::Hash, marker::PhantomData, time::Duration};
+use std::{
+    future::Future,
+    hash::Hash,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
 use tower::discover::Discover;
 pub use tower::{
     balance::p2c::Balance,
     load::{Load, PeakEwmaDiscover},
 };
 
+mod distinct;
+mod hash;
+mod locality;
+
+pub use self::{
+    distinct::{DistinctEndpoints, DistinctRequest},
+    hash::{HashBalance, HashRequest, RingKey, StableHasher},
+    locality::{Locality, LocalityBalance, Localized, PeakEwmaLocalized},
+};
+
 /// Configures a stack to resolve `T` typed targets to balance requests over
 /// `M`-typed endpoint stacks.
 #[derive(Debug)]
@@ -18,6 +38,48 @@ pub struct Layer<A, B> {
     _marker: PhantomData<fn(A) -> B>,
 }
 
+/// Builds a balancer over the endpoints discovered for each target.
+///
+/// Targets that provide an `H`-typed request hasher are balanced over a consistent hash ring, as
+/// are all targets when the layer is configured with a default hasher. Otherwise, requests are
+/// balanced with p2c over peak-EWMA latency, preferring endpoints in the proxy's locality. When
+/// the proxy's locality is unknown, all endpoints are remote and are balanced alike.
+///
+/// Both balancers select an endpoint when a request is dispatched, so that copies of a request
+/// that share `DistinctEndpoints` are sent to different endpoints.
+#[derive(Debug)]
+pub struct MakeBalance<M, H, A, B> {
+    inner: M,
+    layer: Layer<A, B>,
+    default_hasher: Option<H>,
+}
+
+#[pin_project]
+#[derive(Debug)]
+pub struct MakeBalanceFuture<F, H, A, B> {
+    #[pin]
+    inner: F,
+    hasher: Option<H>,
+    layer: Layer<A, B>,
+}
+
+/// Indicates that none of the balancer's endpoints could accept a request that was dispatched
+/// after the balancer became ready, either because they are no longer ready or because the request
+/// does not permit them.
+#[derive(Debug, thiserror::Error)]
+#[error("no endpoints are ready")]
+pub struct NoReadyEndpoints(());
+
+pub type Loaded<D> = PeakEwmaDiscover<D, PendingUntilFirstData>;
+
+pub type LoadedLocalized<D> = PeakEwmaLocalized<D, PendingUntilFirstData>;
+
+/// A balancer built by `MakeBalance`.
+pub type Balancer<D, H, A> = Either<
+    LocalityBalance<LoadedLocalized<D>, http::Request<A>>,
+    HashBalance<Loaded<D>, H, http::Request<A>>,
+>;
+
 // === impl Layer ===
 
 pub fn layer<A, B>(default_rtt: Duration, decay: Duration) -> Layer<A, B> {
@@ -28,6 +90,34 @@ pub fn layer<A, B>(default_rtt: Duration, decay: Duration) -> Layer<A, B> {
     }
 }
 
+impl<A, B> Layer<A, B> {
+    fn load<D, S>(&self, discover: D) -> Loaded<D>
+    where
+        A: HttpBody,
+        B: HttpBody,
+        D: Discover<Service = S>,
+        D::Key: Hash,
+        S: tower::Service<http::Request<A>, Response = http::Response<B>>,
+        S::Error: Into<Error>,
+    {
+        let instrument = PendingUntilFirstData::default();
+        PeakEwmaDiscover::new(discover, self.default_rtt, self.decay, instrument)
+    }
+
+    fn load_localized<D, S>(&self, discover: D) -> LoadedLocalized<D>
+    where
+        A: HttpBody,
+        B: HttpBody,
+        D: Discover<Service = Localized<S>>,
+        D::Key: Hash,
+        S: tower::Service<http::Request<A>, Response = http::Response<B>>,
+        S::Error: Into<Error>,
+    {
+        let instrument = PendingUntilFirstData::default();
+        PeakEwmaLocalized::new(discover, self.default_rtt, self.decay, instrument)
+    }
+}
+
 impl<A, B> Clone for Layer<A, B> {
     fn clone(&self) -> Self {
         Self {
@@ -46,14 +136,94 @@ where
     D::Key: Hash,
     S: tower::Service<http::Request<A>, Response = http::Response<B>>,
     S::Error: Into<Error>,
-    Balance<PeakEwmaDiscover<D, PendingUntilFirstData>, http::Request<A>>:
-        tower::Service<http::Request<A>>,
+    Balance<Loaded<D>, http::Request<A>>: tower::Service<http::Request<A>>,
 {
-    type Service = Balance<PeakEwmaDiscover<D, PendingUntilFirstData>, http::Request<A>>;
+    type Service = Balance<Loaded<D>, http::Request<A>>;
 
     fn layer(&self, discover: D) -> Self::Service {
-        let instrument = PendingUntilFirstData::default();
-        let loaded = PeakEwmaDiscover::new(discover, self.default_rtt, self.decay, instrument);
+        let loaded = self.load(discover);
         Balance::from_rng(loaded, &mut thread_rng()).expect("RNG must be valid")
     }
 }
+
+// === impl MakeBalance ===
+
+impl<M, H: Clone, A, B> MakeBalance<M, H, A, B> {
+    pub fn layer(
+        default_rtt: Duration,
+        decay: Duration,
+        default_hasher: Option<H>,
+    ) -> impl tower::layer::Layer<M, Service = Self> + Clone {
+        let balance = self::layer(default_rtt, decay);
+        layer::mk(move |inner| Self {
+            inner,
+            layer: balance.clone(),
+            default_hasher: default_hasher.clone(),
+        })
+    }
+}
+
+impl<M: Clone, H: Clone, A, B> Clone for MakeBalance<M, H, A, B> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            layer: self.layer.clone(),
+            default_hasher: self.default_hasher.clone(),
+        }
+    }
+}
+
+impl<T, M, D, S, H, A, B> tower::Service<T> for MakeBalance<M, H, A, B>
+where
+    T: Param<Option<H>>,
+    H: Clone,
+    M: tower::Service<T, Response = D>,
+    A: HttpBody,
+    B: HttpBody,
+    D: Discover<Service = Localized<S>>,
+    D::Key: Hash,
+    S: tower::Service<http::Request<A>, Response = http::Response<B>>,
+    S::Error: Into<Error>,
+{
+    type Response = Balancer<D, H, A>;
+    type Error = M::Error;
+    type Future = MakeBalanceFuture<M::Future, H, A, B>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), M::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, target: T) -> Self::Future {
+        MakeBalanceFuture {
+            hasher: target.param().or_else(|| self.default_hasher.clone()),
+            inner: self.inner.call(target),
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+// === impl MakeBalanceFuture ===
+
+impl<F, D, S, H, A, B> Future for MakeBalanceFuture<F, H, A, B>
+where
+    F: TryFuture<Ok = D>,
+    A: HttpBody,
+    B: HttpBody,
+    D: Discover<Service = Localized<S>>,
+    D::Key: Hash,
+    S: tower::Service<http::Request<A>, Response = http::Response<B>>,
+    S::Error: Into<Error>,
+{
+    type Output = Result<Balancer<D, H, A>, F::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let discover = ready!(this.inner.try_poll(cx))?;
+        let balance = match this.hasher.take() {
+            Some(hasher) => Either::B(HashBalance::new(this.layer.load(discover), hasher)),
+            None => Either::A(LocalityBalance::new(this.layer.load_localized(discover))),
+        };
+        Poll::Ready(Ok(balance))
+    }
+}
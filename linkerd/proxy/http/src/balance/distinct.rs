@@ -0,0 +1,75 @@
+use parking_lot::Mutex;
+use std::{net::SocketAddr, sync::Arc};
+
+/// Determines the endpoints to which a request may be dispatched.
+///
+/// Balancers only dispatch a request to an endpoint that the request permits, and record the
+/// endpoint to which the request is dispatched.
+pub trait DistinctRequest<K> {
+    /// Returns false if the request must not be dispatched to the endpoint.
+    fn permits(&self, key: &K) -> bool;
+
+    /// Records that the request is being dispatched to the endpoint.
+    fn dispatched(&self, key: &K);
+}
+
+/// A request extension shared by the copies of a request (e.g. a hedged request and its
+/// original), so that each copy is dispatched to a different endpoint.
+///
+/// Requests without this extension may be dispatched to any endpoint.
+#[derive(Clone, Debug, Default)]
+pub struct DistinctEndpoints(Arc<Mutex<Vec<SocketAddr>>>);
+
+// === impl DistinctEndpoints ===
+
+impl DistinctEndpoints {
+    fn contains(&self, addr: &SocketAddr) -> bool {
+        self.0.lock().contains(addr)
+    }
+
+    fn insert(&self, addr: SocketAddr) {
+        self.0.lock().push(addr);
+    }
+}
+
+impl<B> DistinctRequest<SocketAddr> for http::Request<B> {
+    fn permits(&self, addr: &SocketAddr) -> bool {
+        match self.extensions().get::<DistinctEndpoints>() {
+            Some(distinct) => !distinct.contains(addr),
+            None => true,
+        }
+    }
+
+    fn dispatched(&self, addr: &SocketAddr) {
+        if let Some(distinct) = self.extensions().get::<DistinctEndpoints>() {
+            distinct.insert(*addr);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn excludes_dispatched_endpoints() {
+        let a = SocketAddr::from(([10, 0, 0, 1], 8080));
+        let b = SocketAddr::from(([10, 0, 0, 2], 8080));
+
+        let distinct = DistinctEndpoints::default();
+        let mut original = http::Request::new(());
+        original.extensions_mut().insert(distinct.clone());
+        let mut copy = http::Request::new(());
+        copy.extensions_mut().insert(distinct);
+
+        original.dispatched(&a);
+        assert!(!copy.permits(&a));
+        assert!(copy.permits(&b));
+
+        // Requests without the extension may be sent anywhere.
+        let other = http::Request::new(());
+        other.dispatched(&b);
+        assert!(other.permits(&a));
+        assert!(copy.permits(&b));
+    }
+}
@@ -0,0 +1,680 @@
+use super::{DistinctRequest, NoReadyEndpoints};
+use crate::Error;
+use futures::{future, task::noop_waker_ref, TryFutureExt};
+use rand::{thread_rng, Rng};
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    net::{IpAddr, SocketAddr},
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower::{
+    discover::{Change, Discover},
+    ready_cache::{error::Failed, ReadyCache},
+};
+use tracing::{debug, trace};
+
+/// The number of points each endpoint occupies on the hash ring. More points spread requests more
+/// evenly over endpoints, at the cost of a larger ring.
+const POINTS_PER_ENDPOINT: u64 = 100;
+
+/// Keys for the ring's hash function. These must not change, or requests would move to different
+/// endpoints when the proxy is upgraded.
+const HASH_KEYS: (u64, u64) = (0x6c69_6e6b_6572_6432, 0x6861_7368_2d72_696e);
+
+/// Hashes requests so that they may be dispatched over a consistent hash ring.
+pub trait HashRequest<Req> {
+    /// Returns the request's hash, or `None` if the request should be sent to a random endpoint.
+    fn hash_request(&self, req: &Req) -> Option<u64>;
+}
+
+/// Endpoint keys that may be placed on a hash ring.
+///
+/// Keys are written to the ring's hasher explicitly rather than through `Hash`, since the bytes
+/// that `Hash` implementations write are unspecified and may change between Rust releases.
+pub trait RingKey {
+    fn write_ring_key(&self, hasher: &mut StableHasher);
+}
+
+/// Dispatches each request to the first ready endpoint at or after the request's hash on a ring
+/// of endpoints.
+///
+/// Each endpoint is placed at several points on the ring, so that when an endpoint is added or
+/// removed, only the requests that hash near its points move to a different endpoint.
+pub struct HashBalance<D, H, Req>
+where
+    D: Discover,
+    D::Key: Hash,
+{
+    discover: D,
+    hasher: H,
+    services: ReadyCache<D::Key, D::Service, Req>,
+    /// Points on the hash ring, ordered by hash.
+    ring: Vec<(u64, D::Key)>,
+}
+
+/// A SipHash-2-4 hasher with fixed keys.
+///
+/// Unlike `std`'s `DefaultHasher`, its output is the same across processes, platforms, and Rust
+/// releases, so that every proxy places endpoints and requests at the same points on a hash ring.
+#[derive(Clone, Debug)]
+pub struct StableHasher {
+    v0: u64,
+    v1: u64,
+    v2: u64,
+    v3: u64,
+    tail: u64,
+    ntail: usize,
+    length: usize,
+}
+
+// === impl HashBalance ===
+
+impl<D, H, Req> HashBalance<D, H, Req>
+where
+    D: Discover,
+    D::Key: Hash,
+    D::Service: tower::Service<Req>,
+{
+    pub fn new(discover: D, hasher: H) -> Self {
+        Self {
+            discover,
+            hasher,
+            services: ReadyCache::default(),
+            ring: Vec::new(),
+        }
+    }
+}
+
+impl<D, H, Req> HashBalance<D, H, Req>
+where
+    D: Discover + Unpin,
+    D::Key: Hash + Clone + RingKey,
+    D::Error: Into<Error>,
+    D::Service: tower::Service<Req>,
+    <D::Service as tower::Service<Req>>::Error: Into<Error>,
+{
+    fn update_pending_from_discover(&mut self, cx: &mut Context<'_>) -> Result<(), Error> {
+        // Inserted points are appended to the ring, which is sorted once the batch of changes has
+        // been consumed. Removals preserve the ring's order.
+        let mut inserted = false;
+        let res = loop {
+            match Pin::new(&mut self.discover).poll_discover(cx) {
+                Poll::Pending | Poll::Ready(None) => break Ok(()),
+                Poll::Ready(Some(Err(error))) => break Err(error.into()),
+                Poll::Ready(Some(Ok(Change::Remove(key)))) => {
+                    trace!("Removing endpoint");
+                    self.remove(&key);
+                }
+                Poll::Ready(Some(Ok(Change::Insert(key, svc)))) => {
+                    trace!("Inserting endpoint");
+                    self.remove(&key);
+                    for point in 0..POINTS_PER_ENDPOINT {
+                        self.ring.push((ring_hash(&key, point), key.clone()));
+                    }
+                    self.services.push(key, svc);
+                    inserted = true;
+                }
+            }
+        };
+        if inserted {
+            self.ring.sort_unstable_by_key(|(hash, _)| *hash);
+        }
+        res
+    }
+
+    fn promote_pending_to_ready(&mut self, cx: &mut Context<'_>) {
+        loop {
+            match self.services.poll_pending(cx) {
+                Poll::Ready(Ok(())) | Poll::Pending => return,
+                Poll::Ready(Err(Failed(key, error))) => {
+                    // The ready cache has already dropped the failed service.
+                    debug!(%error, "Endpoint failed");
+                    self.ring.retain(|(_, k)| *k != key);
+                }
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &D::Key) {
+        self.ring.retain(|(_, k)| k != key);
+        self.services.evict(key);
+    }
+
+    /// Returns the index of a ready service that should handle the request.
+    ///
+    /// Services are checked for readiness before they are selected, since a service may no
+    /// longer be ready when the request is dispatched. Services that are no longer ready are
+    /// moved back to the pending set and the next endpoint on the ring is tried. Endpoints that
+    /// the request does not permit are skipped.
+    fn ready_index(&mut self, req: &Req) -> Option<usize>
+    where
+        H: HashRequest<Req>,
+        Req: DistinctRequest<D::Key>,
+    {
+        // Services that are no longer ready are driven by `poll_ready` with the balancer's
+        // waker, so none is registered here.
+        let mut cx = Context::from_waker(noop_waker_ref());
+
+        let hash = match self.hasher.hash_request(req) {
+            Some(hash) => hash,
+            None => loop {
+                let permitted = (0..self.services.ready_len())
+                    .filter(|&i| {
+                        let (key, _) = self.services.get_ready_index(i).expect("index is ready");
+                        req.permits(key)
+                    })
+                    .collect::<Vec<_>>();
+                if permitted.is_empty() {
+                    return None;
+                }
+                let index = permitted[thread_rng().gen_range(0..permitted.len())];
+                if self.check_ready_index(&mut cx, index) {
+                    return Some(index);
+                }
+            },
+        };
+
+        // Walk the ring from the request's hash, wrapping around, until a ready endpoint is
+        // found. If an endpoint is no longer ready, it is either moved to the pending set or
+        // dropped from the ring, so the walk is restarted.
+        'walk: loop {
+            let len = self.ring.len();
+            let start = self.ring.partition_point(|(point, _)| *point < hash);
+            for offset in 0..len {
+                let (_, key) = &self.ring[(start + offset) % len];
+                if !req.permits(key) {
+                    continue;
+                }
+                let index = match self.services.get_ready(key) {
+                    Some((index, _, _)) => index,
+                    None => continue,
+                };
+                if self.check_ready_index(&mut cx, index) {
+                    return Some(index);
+                }
+                continue 'walk;
+            }
+            return None;
+        }
+    }
+
+    /// Returns true if the ready service at `index` is still ready. Otherwise, the service is
+    /// either moved to the pending set or, if it failed, removed from the ring.
+    fn check_ready_index(&mut self, cx: &mut Context<'_>, index: usize) -> bool {
+        match self.services.check_ready_index(cx, index) {
+            Ok(ready) => ready,
+            Err(Failed(key, error)) => {
+                debug!(%error, "Endpoint failed");
+                self.ring.retain(|(_, k)| *k != key);
+                false
+            }
+        }
+    }
+}
+
+impl<D, H, Req> tower::Service<Req> for HashBalance<D, H, Req>
+where
+    D: Discover + Unpin,
+    D::Key: Hash + Clone + RingKey,
+    D::Error: Into<Error>,
+    D::Service: tower::Service<Req>,
+    <D::Service as tower::Service<Req>>::Error: Into<Error>,
+    H: HashRequest<Req>,
+    Req: DistinctRequest<D::Key>,
+{
+    type Response = <D::Service as tower::Service<Req>>::Response;
+    type Error = Error;
+    type Future = future::Either<
+        future::MapErr<
+            <D::Service as tower::Service<Req>>::Future,
+            fn(<D::Service as tower::Service<Req>>::Error) -> Error,
+        >,
+        future::Ready<Result<Self::Response, Error>>,
+    >;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.update_pending_from_discover(cx)?;
+        self.promote_pending_to_ready(cx);
+
+        if self.services.ready_len() == 0 {
+            trace!(pending = self.services.pending_len(), "No ready endpoints");
+            return Poll::Pending;
+        }
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        match self.ready_index(&req) {
+            Some(index) => {
+                let (key, _) = self
+                    .services
+                    .get_ready_index(index)
+                    .expect("index is ready");
+                req.dispatched(key);
+                future::Either::Left(
+                    self.services
+                        .call_ready_index(index, req)
+                        .map_err(Into::into as fn(_) -> _),
+                )
+            }
+            None => {
+                debug!(pending = self.services.pending_len(), "No ready endpoints");
+                future::Either::Right(future::err(NoReadyEndpoints(()).into()))
+            }
+        }
+    }
+}
+
+impl<D, H, Req> fmt::Debug for HashBalance<D, H, Req>
+where
+    D: Discover + fmt::Debug,
+    D::Key: Hash,
+    D::Service: tower::Service<Req>,
+    H: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HashBalance")
+            .field("discover", &self.discover)
+            .field("hasher", &self.hasher)
+            .field("endpoints", &self.services.len())
+            .finish()
+    }
+}
+
+fn ring_hash<K: RingKey>(key: &K, point: u64) -> u64 {
+    let mut hasher = StableHasher::new();
+    key.write_ring_key(&mut hasher);
+    hasher.write(&point.to_le_bytes());
+    hasher.finish()
+}
+
+// === impl RingKey ===
+
+impl RingKey for SocketAddr {
+    fn write_ring_key(&self, hasher: &mut StableHasher) {
+        match self.ip() {
+            IpAddr::V4(ip) => hasher.write(&ip.octets()),
+            IpAddr::V6(ip) => hasher.write(&ip.octets()),
+        }
+        hasher.write(&self.port().to_be_bytes());
+    }
+}
+
+// === impl StableHasher ===
+
+impl StableHasher {
+    pub fn new() -> Self {
+        let (k0, k1) = HASH_KEYS;
+        Self::with_keys(k0, k1)
+    }
+
+    fn with_keys(k0: u64, k1: u64) -> Self {
+        Self {
+            v0: k0 ^ 0x736f_6d65_7073_6575,
+            v1: k1 ^ 0x646f_7261_6e64_6f6d,
+            v2: k0 ^ 0x6c79_6765_6e65_7261,
+            v3: k1 ^ 0x7465_6462_7974_6573,
+            tail: 0,
+            ntail: 0,
+            length: 0,
+        }
+    }
+
+    fn round(&mut self) {
+        self.v0 = self.v0.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(13) ^ self.v0;
+        self.v0 = self.v0.rotate_left(32);
+        self.v2 = self.v2.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(16) ^ self.v2;
+        self.v0 = self.v0.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(21) ^ self.v0;
+        self.v2 = self.v2.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(17) ^ self.v2;
+        self.v2 = self.v2.rotate_left(32);
+    }
+
+    fn compress(&mut self, m: u64) {
+        self.v3 ^= m;
+        self.round();
+        self.round();
+        self.v0 ^= m;
+    }
+}
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.length = self.length.wrapping_add(bytes.len());
+        for &b in bytes {
+            self.tail |= u64::from(b) << (8 * self.ntail);
+            self.ntail += 1;
+            if self.ntail == 8 {
+                let m = self.tail;
+                self.compress(m);
+                self.tail = 0;
+                self.ntail = 0;
+            }
+        }
+    }
+
+    // Integers are hashed as little-endian bytes (and `usize` as a `u64`) so that hashes do not
+    // depend on the platform.
+
+    fn write_u16(&mut self, n: u16) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_u128(&mut self, n: u128) {
+        self.write(&n.to_le_bytes())
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.write_u64(n as u64)
+    }
+
+    fn finish(&self) -> u64 {
+        let mut state = self.clone();
+        let b = ((self.length as u64 & 0xff) << 56) | self.tail;
+        state.compress(b);
+        state.v2 ^= 0xff;
+        for _ in 0..4 {
+            state.round();
+        }
+        state.v0 ^ state.v1 ^ state.v2 ^ state.v3
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        collections::{HashMap, VecDeque},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+    };
+    use tower::Service;
+
+    /// Uses each request's hash as its own hash.
+    #[derive(Debug)]
+    struct Identity;
+
+    /// A request with a hash that may not be sent to an excluded endpoint.
+    #[derive(Debug)]
+    struct Req {
+        hash: Option<u64>,
+        exclude: Option<usize>,
+    }
+
+    /// Responds to each request with its endpoint's key.
+    #[derive(Clone, Debug)]
+    struct Endpoint {
+        key: usize,
+        ready: Arc<AtomicBool>,
+    }
+
+    /// Yields changes as they are pushed onto the queue.
+    #[derive(Debug, Default)]
+    struct Changes(VecDeque<Change<usize, Endpoint>>);
+
+    type Balance = HashBalance<Changes, Identity, Req>;
+
+    impl RingKey for usize {
+        fn write_ring_key(&self, hasher: &mut StableHasher) {
+            hasher.write(&(*self as u64).to_le_bytes())
+        }
+    }
+
+    impl HashRequest<Req> for Identity {
+        fn hash_request(&self, req: &Req) -> Option<u64> {
+            req.hash
+        }
+    }
+
+    impl DistinctRequest<usize> for Req {
+        fn permits(&self, key: &usize) -> bool {
+            self.exclude != Some(*key)
+        }
+
+        fn dispatched(&self, _: &usize) {}
+    }
+
+    impl Service<Req> for Endpoint {
+        type Response = usize;
+        type Error = Error;
+        type Future = future::Ready<Result<usize, Error>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Error>> {
+            if self.ready.load(Ordering::Acquire) {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        }
+
+        fn call(&mut self, _: Req) -> Self::Future {
+            future::ok(self.key)
+        }
+    }
+
+    impl futures::Stream for Changes {
+        type Item = Result<Change<usize, Endpoint>, Error>;
+
+        fn poll_next(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            match self.0.pop_front() {
+                Some(change) => Poll::Ready(Some(Ok(change))),
+                None => Poll::Pending,
+            }
+        }
+    }
+
+    fn endpoint(key: usize) -> (Change<usize, Endpoint>, Arc<AtomicBool>) {
+        let ready = Arc::new(AtomicBool::new(true));
+        let ep = Endpoint {
+            key,
+            ready: ready.clone(),
+        };
+        (Change::Insert(key, ep), ready)
+    }
+
+    fn balance(
+        keys: impl IntoIterator<Item = usize>,
+    ) -> (Balance, HashMap<usize, Arc<AtomicBool>>) {
+        let mut changes = Changes::default();
+        let mut ready = HashMap::new();
+        for key in keys {
+            let (change, r) = endpoint(key);
+            changes.0.push_back(change);
+            ready.insert(key, r);
+        }
+        let mut balance = HashBalance::new(changes, Identity);
+        poll_ready(&mut balance).expect("balancer must be ready");
+        (balance, ready)
+    }
+
+    fn poll_ready(balance: &mut Balance) -> Poll<Result<(), Error>> {
+        let mut cx = Context::from_waker(noop_waker_ref());
+        balance.poll_ready(&mut cx)
+    }
+
+    fn send(balance: &mut Balance, hash: Option<u64>) -> Result<usize, Error> {
+        send_req(
+            balance,
+            Req {
+                hash,
+                exclude: None,
+            },
+        )
+    }
+
+    fn send_req(balance: &mut Balance, req: Req) -> Result<usize, Error> {
+        use futures::FutureExt;
+        balance
+            .call(req)
+            .now_or_never()
+            .expect("endpoints respond immediately")
+    }
+
+    /// Returns the key that owns the first point at or after `hash`.
+    fn owner(balance: &Balance, hash: u64) -> usize {
+        let start = balance.ring.partition_point(|(point, _)| *point < hash);
+        balance.ring[start % balance.ring.len()].1
+    }
+
+    /// Hashes spread over the whole ring.
+    fn hashes() -> impl Iterator<Item = u64> {
+        (0..1000u64).map(|i| i * (u64::MAX / 1000))
+    }
+
+    #[test]
+    fn stable_hasher_is_siphash() {
+        // Reference vectors from the SipHash-2-4 paper, hashing `[0, 1, .., n)` with the key
+        // `[0, 1, .., 16)`.
+        for (n, expected) in [
+            (0u8, 0x726f_db47_dd0e_0e31),
+            (1, 0x74f8_39c5_93dc_67fd),
+            (8, 0x93f5_f579_9a93_2462),
+            (15, 0xa129_ca61_49be_45e5),
+        ] {
+            let mut hasher = StableHasher::with_keys(0x0706_0504_0302_0100, 0x0f0e_0d0c_0b0a_0908);
+            hasher.write(&(0..n).collect::<Vec<u8>>());
+            assert_eq!(hasher.finish(), expected, "length {}", n);
+        }
+    }
+
+    #[test]
+    fn ring_hashes_are_stable() {
+        // Every proxy must place an endpoint at the same points on the ring, so the hash of a
+        // given address must never change.
+        let addr = SocketAddr::from(([10, 0, 0, 1], 8080));
+        assert_eq!(ring_hash(&addr, 0), 0x4f39_bd75_b2f8_05dc);
+        assert_eq!(ring_hash(&addr, 1), 0xc071_4a4f_ef63_4dde);
+
+        let addr = SocketAddr::from(([0xfd00, 0, 0, 0, 0, 0, 0, 1], 8080));
+        assert_eq!(ring_hash(&addr, 0), 0x7959_5492_5f4f_85f5);
+    }
+
+    #[test]
+    fn places_requests_on_ring() {
+        let (mut balance, _) = balance(0..3);
+        assert_eq!(balance.ring.len(), 3 * POINTS_PER_ENDPOINT as usize);
+        assert!(
+            balance.ring.windows(2).all(|w| w[0].0 <= w[1].0),
+            "ring must be sorted"
+        );
+
+        let mut used = std::collections::HashSet::new();
+        for hash in hashes() {
+            let key = send(&mut balance, Some(hash)).unwrap();
+            assert_eq!(key, owner(&balance, hash), "hash {}", hash);
+            used.insert(key);
+        }
+        assert_eq!(used.len(), 3, "requests must be spread over all endpoints");
+
+        // The same endpoints are placed at the same points by a new balancer.
+        let (other, _) = self::balance(0..3);
+        assert_eq!(balance.ring, other.ring);
+    }
+
+    #[test]
+    fn remaps_only_removed_endpoint() {
+        let (mut balance, _) = balance(0..4);
+        let before = hashes()
+            .map(|hash| (hash, send(&mut balance, Some(hash)).unwrap()))
+            .collect::<Vec<_>>();
+
+        balance.discover.0.push_back(Change::Remove(3));
+        poll_ready(&mut balance).expect("balancer must be ready");
+        assert!(balance.ring.iter().all(|(_, key)| *key != 3));
+
+        for (hash, key) in before {
+            let moved = send(&mut balance, Some(hash)).unwrap();
+            if key == 3 {
+                assert_ne!(moved, 3);
+                assert_eq!(moved, owner(&balance, hash));
+            } else {
+                assert_eq!(moved, key, "hash {} must not move", hash);
+            }
+        }
+    }
+
+    #[test]
+    fn falls_back_to_ready_endpoints() {
+        let (mut balance, ready) = balance(0..3);
+        let hash = 1 << 63;
+        let owner = owner(&balance, hash);
+
+        // The owner is no longer ready when the request is dispatched, so the request is sent
+        // to the next ready endpoint on the ring.
+        ready[&owner].store(false, Ordering::Release);
+        let key = send(&mut balance, Some(hash)).unwrap();
+        assert_ne!(key, owner);
+        assert_eq!(balance.services.pending_len(), 1);
+
+        // Requests without a hash are sent to a random ready endpoint.
+        for _ in 0..10 {
+            assert_ne!(send(&mut balance, None).unwrap(), owner);
+        }
+
+        // When no endpoints are ready, the request fails rather than being dispatched to an
+        // endpoint that cannot handle it.
+        for r in ready.values() {
+            r.store(false, Ordering::Release);
+        }
+        let error = send(&mut balance, Some(hash)).unwrap_err();
+        assert!(error.is::<NoReadyEndpoints>());
+        assert!(poll_ready(&mut balance).is_pending());
+    }
+
+    #[test]
+    fn skips_excluded_endpoints() {
+        let (mut balance, _) = balance(0..3);
+        let hash = 1 << 63;
+        let owner = owner(&balance, hash);
+
+        // A request that excludes the owner of its hash is sent to the next endpoint on the ring.
+        for _ in 0..10 {
+            let req = Req {
+                hash: Some(hash),
+                exclude: Some(owner),
+            };
+            assert_ne!(send_req(&mut balance, req).unwrap(), owner);
+        }
+        assert_eq!(send(&mut balance, Some(hash)).unwrap(), owner);
+
+        // Requests without a hash are sent to a random endpoint that is not excluded.
+        for _ in 0..10 {
+            let req = Req {
+                hash: None,
+                exclude: Some(owner),
+            };
+            assert_ne!(send_req(&mut balance, req).unwrap(), owner);
+        }
+
+        // When the only endpoint is excluded, the request fails.
+        let (mut balance, _) = self::balance(0..1);
+        let req = Req {
+            hash: Some(hash),
+            exclude: Some(0),
+        };
+        let error = send_req(&mut balance, req).unwrap_err();
+        assert!(error.is::<NoReadyEndpoints>());
+    }
+}
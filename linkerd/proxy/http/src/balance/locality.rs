@@ -0,0 +1,639 @@
+use super::{DistinctRequest, NoReadyEndpoints};
+use crate::Error;
+use futures::{future, ready, task::noop_waker_ref, Stream, TryFutureExt};
+use pin_project::pin_project;
+use rand::{thread_rng, Rng};
+use std::{
+    fmt,
+    hash::Hash,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tower::{
+    discover::{Change, Discover},
+    load::{Load, PeakEwma},
+    ready_cache::{error::Failed, ReadyCache},
+};
+use tracing::{debug, trace};
+
+/// Describes whether an endpoint is in the same locality (e.g. zone) as the proxy.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Locality {
+    Local,
+    Remote,
+}
+
+/// A service annotated with its endpoint's locality.
+#[derive(Clone, Debug)]
+pub struct Localized<S> {
+    locality: Locality,
+    inner: S,
+}
+
+/// Wraps each discovered endpoint in a peak-EWMA load measurement, preserving the endpoint's
+/// locality.
+#[pin_project]
+#[derive(Debug)]
+pub struct PeakEwmaLocalized<D, C> {
+    #[pin]
+    discover: D,
+    default_rtt: Duration,
+    decay_ns: f64,
+    completion: C,
+}
+
+/// Dispatches requests with p2c over the ready endpoints in the proxy's locality.
+///
+/// When no local endpoint is ready--because there are none, or because they are failing or
+/// saturated--requests spill over to ready endpoints in other localities. Requests also spill
+/// over when local endpoints are overloaded: p2c picks an endpoint in each locality and the
+/// remote endpoint is used only if its load is lower. Since remote endpoints' latency usually
+/// includes the cost of crossing localities, this happens when local endpoints' queues outweigh
+/// that cost.
+///
+/// If the request does not permit the endpoint chosen when the balancer became ready, the least
+/// loaded ready endpoint that it permits is used instead, preferring local endpoints.
+pub struct LocalityBalance<D, Req>
+where
+    D: Discover,
+    D::Key: Hash,
+{
+    discover: D,
+    /// Endpoints in the proxy's locality.
+    local: ReadyCache<D::Key, D::Service, Req>,
+    /// Endpoints in other localities.
+    remote: ReadyCache<D::Key, D::Service, Req>,
+    /// The ready service chosen to handle the next request.
+    ready_index: Option<(Locality, usize)>,
+}
+
+// === impl Localized ===
+
+impl<S> Localized<S> {
+    pub fn new(locality: Locality, inner: S) -> Self {
+        Self { locality, inner }
+    }
+
+    pub fn locality(&self) -> Locality {
+        self.locality
+    }
+}
+
+impl<Req, S: tower::Service<Req>> tower::Service<Req> for Localized<S> {
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    #[inline]
+    fn call(&mut self, req: Req) -> Self::Future {
+        self.inner.call(req)
+    }
+}
+
+impl<L: Load> Load for Localized<L> {
+    type Metric = L::Metric;
+
+    #[inline]
+    fn load(&self) -> L::Metric {
+        self.inner.load()
+    }
+}
+
+// === impl PeakEwmaLocalized ===
+
+impl<D, C> PeakEwmaLocalized<D, C> {
+    pub fn new(discover: D, default_rtt: Duration, decay: Duration, completion: C) -> Self {
+        Self {
+            discover,
+            default_rtt,
+            decay_ns: decay.as_secs_f64() * 1_000_000_000.0,
+            completion,
+        }
+    }
+}
+
+impl<D, S, C> Stream for PeakEwmaLocalized<D, C>
+where
+    D: Discover<Service = Localized<S>>,
+    C: Clone,
+{
+    type Item = Result<Change<D::Key, Localized<PeakEwma<S, C>>>, D::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let change = match ready!(this.discover.poll_discover(cx)) {
+            None => return Poll::Ready(None),
+            Some(Err(error)) => return Poll::Ready(Some(Err(error))),
+            Some(Ok(Change::Remove(key))) => Change::Remove(key),
+            Some(Ok(Change::Insert(key, Localized { locality, inner }))) => {
+                let inner = PeakEwma::new(
+                    inner,
+                    *this.default_rtt,
+                    *this.decay_ns,
+                    this.completion.clone(),
+                );
+                Change::Insert(key, Localized { locality, inner })
+            }
+        };
+        Poll::Ready(Some(Ok(change)))
+    }
+}
+
+// === impl LocalityBalance ===
+
+impl<D, Req> LocalityBalance<D, Req>
+where
+    D: Discover,
+    D::Key: Hash,
+    D::Service: tower::Service<Req>,
+{
+    pub fn new(discover: D) -> Self {
+        Self {
+            discover,
+            local: ReadyCache::default(),
+            remote: ReadyCache::default(),
+            ready_index: None,
+        }
+    }
+}
+
+impl<D, L, Req> LocalityBalance<D, Req>
+where
+    D: Discover<Service = Localized<L>> + Unpin,
+    D::Key: Hash + Clone,
+    D::Error: Into<Error>,
+    L: tower::Service<Req> + Load,
+    L::Error: Into<Error>,
+    L::Metric: PartialOrd,
+{
+    fn update_pending_from_discover(&mut self, cx: &mut Context<'_>) -> Result<(), Error> {
+        loop {
+            let change = match Pin::new(&mut self.discover).poll_discover(cx) {
+                Poll::Pending | Poll::Ready(None) => return Ok(()),
+                Poll::Ready(Some(Err(error))) => return Err(error.into()),
+                Poll::Ready(Some(Ok(change))) => change,
+            };
+
+            // Evicting a ready service may move other ready services, so the chosen index is
+            // no longer valid.
+            self.ready_index = None;
+            match change {
+                Change::Remove(key) => {
+                    trace!("Removing endpoint");
+                    self.local.evict(&key);
+                    self.remote.evict(&key);
+                }
+                Change::Insert(key, svc) => {
+                    trace!(locality = ?svc.locality, "Inserting endpoint");
+                    // The endpoint's locality may have changed.
+                    self.local.evict(&key);
+                    self.remote.evict(&key);
+                    self.services_mut(svc.locality).push(key, svc);
+                }
+            }
+        }
+    }
+
+    fn promote_pending_to_ready(&mut self, cx: &mut Context<'_>) {
+        for services in [&mut self.local, &mut self.remote] {
+            loop {
+                match services.poll_pending(cx) {
+                    Poll::Ready(Ok(())) | Poll::Pending => break,
+                    Poll::Ready(Err(Failed(_, error))) => {
+                        // The ready cache has already dropped the failed service.
+                        debug!(%error, "Endpoint failed");
+                    }
+                }
+            }
+        }
+    }
+
+    /// Picks a ready local endpoint with p2c, unless no local endpoint is ready or the remote
+    /// endpoint picked with p2c is less loaded.
+    fn p2c_ready_index(&self) -> Option<(Locality, usize)> {
+        match (p2c(&self.local), p2c(&self.remote)) {
+            (Some(local), Some(remote)) => {
+                if load(&self.remote, remote) < load(&self.local, local) {
+                    trace!("Local endpoints are overloaded");
+                    Some((Locality::Remote, remote))
+                } else {
+                    Some((Locality::Local, local))
+                }
+            }
+            (Some(local), None) => Some((Locality::Local, local)),
+            (None, Some(remote)) => {
+                trace!("No local endpoints are ready");
+                Some((Locality::Remote, remote))
+            }
+            (None, None) => None,
+        }
+    }
+
+    /// Picks the least loaded ready endpoint that the request permits, preferring local
+    /// endpoints.
+    ///
+    /// Endpoints are checked for readiness before they are selected, since only the endpoint
+    /// chosen by `poll_ready` is known to be ready. Endpoints that are no longer ready are moved
+    /// back to the pending set.
+    fn distinct_ready_index(&mut self, req: &Req) -> Option<(Locality, usize)>
+    where
+        Req: DistinctRequest<D::Key>,
+    {
+        // Services that are no longer ready are driven by `poll_ready` with the balancer's
+        // waker, so none is registered here.
+        let mut cx = Context::from_waker(noop_waker_ref());
+
+        for locality in [Locality::Local, Locality::Remote] {
+            let services = self.services_mut(locality);
+            loop {
+                let ready = &*services;
+                let index = (0..ready.ready_len())
+                    .filter(|&i| {
+                        let (key, _) = ready.get_ready_index(i).expect("index is ready");
+                        req.permits(key)
+                    })
+                    .min_by(|&a, &b| {
+                        load(ready, a)
+                            .partial_cmp(&load(ready, b))
+                            .unwrap_or(std::cmp::Ordering::Equal)
+                    });
+                let index = match index {
+                    Some(index) => index,
+                    None => break,
+                };
+                match services.check_ready_index(&mut cx, index) {
+                    Ok(true) => return Some((locality, index)),
+                    Ok(false) => trace!("Endpoint is no longer ready"),
+                    Err(Failed(_, error)) => debug!(%error, "Endpoint failed"),
+                }
+            }
+        }
+
+        None
+    }
+
+    fn services_mut(&mut self, locality: Locality) -> &mut ReadyCache<D::Key, D::Service, Req> {
+        match locality {
+            Locality::Local => &mut self.local,
+            Locality::Remote => &mut self.remote,
+        }
+    }
+
+    fn pending_len(&self) -> usize {
+        self.local.pending_len() + self.remote.pending_len()
+    }
+}
+
+impl<D, L, Req> tower::Service<Req> for LocalityBalance<D, Req>
+where
+    D: Discover<Service = Localized<L>> + Unpin,
+    D::Key: Hash + Clone,
+    D::Error: Into<Error>,
+    L: tower::Service<Req> + Load,
+    L::Error: Into<Error>,
+    L::Metric: PartialOrd,
+    Req: DistinctRequest<D::Key>,
+{
+    type Response = L::Response;
+    type Error = Error;
+    type Future = future::Either<
+        future::MapErr<L::Future, fn(L::Error) -> Error>,
+        future::Ready<Result<L::Response, Error>>,
+    >;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.update_pending_from_discover(cx)?;
+        self.promote_pending_to_ready(cx);
+
+        loop {
+            // If an endpoint was already chosen, ensure that it is still ready. Otherwise, it is
+            // moved to the pending set (or dropped if it failed) and another endpoint is chosen.
+            if let Some((locality, index)) = self.ready_index.take() {
+                match self.services_mut(locality).check_ready_index(cx, index) {
+                    Ok(true) => {
+                        self.ready_index = Some((locality, index));
+                        return Poll::Ready(Ok(()));
+                    }
+                    Ok(false) => trace!("Endpoint is no longer ready"),
+                    Err(Failed(_, error)) => debug!(%error, "Endpoint failed"),
+                }
+            }
+
+            self.ready_index = self.p2c_ready_index();
+            if self.ready_index.is_none() {
+                trace!(pending = self.pending_len(), "No ready endpoints");
+                return Poll::Pending;
+            }
+        }
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        let chosen = self.ready_index.take().expect("called before ready");
+
+        let (key, _) = self
+            .services_mut(chosen.0)
+            .get_ready_index(chosen.1)
+            .expect("index is ready");
+        let (locality, index) = if req.permits(key) {
+            chosen
+        } else {
+            match self.distinct_ready_index(&req) {
+                Some(ready) => ready,
+                None => {
+                    debug!(
+                        pending = self.pending_len(),
+                        "No distinct endpoints are ready"
+                    );
+                    return future::Either::Right(future::err(NoReadyEndpoints(()).into()));
+                }
+            }
+        };
+
+        let services = self.services_mut(locality);
+        let (key, _) = services.get_ready_index(index).expect("index is ready");
+        req.dispatched(key);
+        future::Either::Left(
+            services
+                .call_ready_index(index, req)
+                .map_err(Into::into as fn(_) -> _),
+        )
+    }
+}
+
+impl<D, Req> fmt::Debug for LocalityBalance<D, Req>
+where
+    D: Discover + fmt::Debug,
+    D::Key: Hash,
+    D::Service: tower::Service<Req>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalityBalance")
+            .field("discover", &self.discover)
+            .field("local", &self.local.len())
+            .field("remote", &self.remote.len())
+            .finish()
+    }
+}
+
+/// Picks the less loaded of two random ready endpoints.
+fn p2c<K, L, Req>(services: &ReadyCache<K, L, Req>) -> Option<usize>
+where
+    K: Clone + Eq + Hash,
+    L: tower::Service<Req> + Load,
+    L::Metric: PartialOrd,
+{
+    let len = services.ready_len();
+    match len {
+        0 => None,
+        1 => Some(0),
+        len => {
+            let mut rng = thread_rng();
+            let a = rng.gen_range(0..len);
+            let mut b = rng.gen_range(0..len - 1);
+            if b >= a {
+                b += 1;
+            }
+            if load(services, a) <= load(services, b) {
+                Some(a)
+            } else {
+                Some(b)
+            }
+        }
+    }
+}
+
+fn load<K, L, Req>(services: &ReadyCache<K, L, Req>, index: usize) -> L::Metric
+where
+    K: Clone + Eq + Hash,
+    L: tower::Service<Req> + Load,
+{
+    let (_, svc) = services
+        .get_ready_index(index)
+        .expect("ready index must be valid");
+    svc.load()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{task::noop_waker_ref, FutureExt};
+    use std::{
+        collections::{HashMap, VecDeque},
+        sync::{
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+    use tower::Service;
+
+    /// Responds to each request with its endpoint's key.
+    #[derive(Debug)]
+    struct Endpoint {
+        key: usize,
+        state: Arc<State>,
+    }
+
+    #[derive(Debug)]
+    struct State {
+        load: AtomicUsize,
+        ready: AtomicBool,
+    }
+
+    /// Yields changes as they are pushed onto the queue.
+    #[derive(Debug, Default)]
+    struct Changes(VecDeque<Change<usize, Localized<Endpoint>>>);
+
+    /// Requests may exclude an endpoint by its key.
+    type Balance = LocalityBalance<Changes, Option<usize>>;
+
+    impl DistinctRequest<usize> for Option<usize> {
+        fn permits(&self, key: &usize) -> bool {
+            *self != Some(*key)
+        }
+
+        fn dispatched(&self, _: &usize) {}
+    }
+
+    impl Service<Option<usize>> for Endpoint {
+        type Response = usize;
+        type Error = Error;
+        type Future = future::Ready<Result<usize, Error>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Error>> {
+            if self.state.ready.load(Ordering::Acquire) {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        }
+
+        fn call(&mut self, _: Option<usize>) -> Self::Future {
+            future::ok(self.key)
+        }
+    }
+
+    impl Load for Endpoint {
+        type Metric = usize;
+
+        fn load(&self) -> usize {
+            self.state.load.load(Ordering::Acquire)
+        }
+    }
+
+    impl Stream for Changes {
+        type Item = Result<Change<usize, Localized<Endpoint>>, Error>;
+
+        fn poll_next(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            match self.0.pop_front() {
+                Some(change) => Poll::Ready(Some(Ok(change))),
+                None => Poll::Pending,
+            }
+        }
+    }
+
+    /// Builds a balancer over endpoints with the given keys and localities.
+    fn balance(endpoints: &[(usize, Locality)]) -> (Balance, HashMap<usize, Arc<State>>) {
+        let mut changes = Changes::default();
+        let mut states = HashMap::new();
+        for &(key, locality) in endpoints {
+            let state = Arc::new(State {
+                load: AtomicUsize::new(1),
+                ready: AtomicBool::new(true),
+            });
+            let ep = Endpoint {
+                key,
+                state: state.clone(),
+            };
+            changes
+                .0
+                .push_back(Change::Insert(key, Localized::new(locality, ep)));
+            states.insert(key, state);
+        }
+        (LocalityBalance::new(changes), states)
+    }
+
+    /// Returns the key of the endpoint that handles the next request, or `None` if the balancer
+    /// is not ready.
+    fn send(balance: &mut Balance) -> Option<usize> {
+        send_excluding(balance, None).map(|rsp| rsp.expect("endpoints must not fail"))
+    }
+
+    /// Sends a request that may not be dispatched to the `exclude` endpoint.
+    fn send_excluding(
+        balance: &mut Balance,
+        exclude: Option<usize>,
+    ) -> Option<Result<usize, Error>> {
+        let mut cx = Context::from_waker(noop_waker_ref());
+        match balance.poll_ready(&mut cx) {
+            Poll::Ready(res) => res.expect("balancer must not fail"),
+            Poll::Pending => return None,
+        }
+        let rsp = balance
+            .call(exclude)
+            .now_or_never()
+            .expect("endpoints respond immediately");
+        Some(rsp)
+    }
+
+    const ENDPOINTS: &[(usize, Locality)] = &[
+        (0, Locality::Local),
+        (1, Locality::Local),
+        (10, Locality::Remote),
+        (11, Locality::Remote),
+    ];
+
+    #[test]
+    fn prefers_local_endpoints() {
+        let (mut balance, _) = balance(ENDPOINTS);
+        for _ in 0..100 {
+            let key = send(&mut balance).unwrap();
+            assert!(key < 10, "request must be sent to a local endpoint");
+        }
+    }
+
+    #[test]
+    fn spills_over_when_local_endpoints_are_overloaded() {
+        let (mut balance, states) = balance(ENDPOINTS);
+        states[&0].load.store(10, Ordering::Release);
+        states[&1].load.store(10, Ordering::Release);
+        for _ in 0..100 {
+            let key = send(&mut balance).unwrap();
+            assert!(key >= 10, "request must spill over to a remote endpoint");
+        }
+
+        // Once the local endpoints are no more loaded than remote ones, they are preferred again.
+        states[&0].load.store(1, Ordering::Release);
+        for _ in 0..100 {
+            assert_eq!(send(&mut balance), Some(0));
+        }
+    }
+
+    #[test]
+    fn spills_over_when_no_local_endpoints_are_ready() {
+        let (mut balance, states) = balance(ENDPOINTS);
+        states[&0].ready.store(false, Ordering::Release);
+        states[&1].ready.store(false, Ordering::Release);
+        for _ in 0..100 {
+            let key = send(&mut balance).unwrap();
+            assert!(key >= 10, "request must spill over to a remote endpoint");
+        }
+    }
+
+    #[test]
+    fn checks_readiness_of_chosen_endpoint() {
+        let (mut balance, states) = balance(&[(0, Locality::Local), (10, Locality::Remote)]);
+        let mut cx = Context::from_waker(noop_waker_ref());
+        assert!(balance.poll_ready(&mut cx).is_ready());
+        assert_eq!(balance.ready_index, Some((Locality::Local, 0)));
+
+        // The chosen endpoint becomes unready before the request is dispatched, so another
+        // endpoint is chosen when the balancer is polled again.
+        states[&0].ready.store(false, Ordering::Release);
+        assert_eq!(send(&mut balance), Some(10));
+        assert_eq!(balance.local.pending_len(), 1);
+
+        states[&10].ready.store(false, Ordering::Release);
+        assert_eq!(send(&mut balance), None);
+    }
+
+    #[test]
+    fn removes_endpoints() {
+        let (mut balance, _) = balance(ENDPOINTS);
+        assert!(send(&mut balance).is_some());
+        balance.discover.0.push_back(Change::Remove(0));
+        balance.discover.0.push_back(Change::Remove(1));
+        for _ in 0..100 {
+            let key = send(&mut balance).unwrap();
+            assert!(key >= 10, "request must spill over to a remote endpoint");
+        }
+        assert_eq!(balance.local.len(), 0);
+    }
+
+    #[test]
+    fn dispatches_to_permitted_endpoints() {
+        let (mut balance, states) = balance(ENDPOINTS);
+        states[&1].load.store(10, Ordering::Release);
+        for _ in 0..100 {
+            let key = send_excluding(&mut balance, Some(0)).unwrap().unwrap();
+            assert_eq!(key, 1, "request must be sent to the other local endpoint");
+        }
+
+        // When no local endpoint is permitted and ready, the request spills over.
+        states[&1].ready.store(false, Ordering::Release);
+        for _ in 0..100 {
+            let key = send_excluding(&mut balance, Some(0)).unwrap().unwrap();
+            assert!(key >= 10, "request must spill over to a remote endpoint");
+        }
+
+        // When the only endpoint is excluded, the request fails.
+        let (mut balance, _) = self::balance(&[(0, Locality::Local)]);
+        let error = send_excluding(&mut balance, Some(0)).unwrap().unwrap_err();
+        assert!(error.is::<NoReadyEndpoints>());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{Metrics, TokenSource};
+use crate::{Metrics, Status, TokenSource};
 use http_body::Body;
 use linkerd2_proxy_api::identity::{self as api, identity_client::IdentityClient};
 use linkerd_error::{Error, Result};
@@ -26,6 +26,7 @@ pub struct LostDaemon(());
 pub struct Certify {
     config: Config,
     metrics: Metrics,
+    status: Status,
 }
 
 // === impl Certify ===
@@ -35,6 +36,7 @@ impl From<Config> for Certify {
         Self {
             config,
             metrics: Metrics::default(),
+            status: Status::default(),
         }
     }
 }
@@ -44,6 +46,10 @@ impl Certify {
         self.metrics.clone()
     }
 
+    pub fn status(&self) -> Status {
+        self.status.clone()
+    }
+
     pub async fn run<C, N, S>(self, mut credentials: C, new_client: N)
     where
         C: Credentials,
@@ -64,6 +70,7 @@ impl Certify {
                 // so clients are instantiated on-demand rather than held.
                 new_client.new_service(()),
                 &mut credentials,
+                &self.status,
             )
             .await;
 
@@ -80,6 +87,7 @@ impl Certify {
 
             let sleep = refresh_in(&self.config, curr_expiry);
             debug!(?sleep, "Waiting to refresh identity");
+            self.status.set_next_refresh(SystemTime::now() + sleep);
             time::sleep(sleep).await;
         }
     }
@@ -87,7 +95,12 @@ impl Certify {
 
 /// Issues a certificate signing request to the identity service with a token loaded from the token
 /// source.
-async fn certify<C, S>(token: &TokenSource, client: S, credentials: &mut C) -> Result<SystemTime>
+async fn certify<C, S>(
+    token: &TokenSource,
+    client: S,
+    credentials: &mut C,
+    status: &Status,
+) -> Result<SystemTime>
 where
     C: Credentials,
     S: GrpcService<BoxBody>,
@@ -112,11 +125,13 @@ where
     if expiry <= SystemTime::now() {
         return Err("certificate already expired".into());
     }
-    credentials.set_certificate(
-        DerX509(leaf_certificate),
-        intermediate_certificates.into_iter().map(DerX509).collect(),
-        expiry,
-    )?;
+    let leaf = DerX509(leaf_certificate);
+    let intermediates = intermediate_certificates
+        .into_iter()
+        .map(DerX509)
+        .collect::<Vec<_>>();
+    credentials.set_certificate(leaf.clone(), intermediates.clone(), expiry)?;
+    status.set_certificate(&leaf, &intermediates);
 
     Ok(expiry)
 }
@@ -0,0 +1,347 @@
+use crate::{Metrics, Status};
+use linkerd_error::Result;
+use linkerd_identity::{Credentials, DerX509};
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::{fs, time};
+use tracing::{debug, error, info, warn};
+
+/// Configures the proxy to load an already-issued certificate from files, rather than obtaining
+/// one from the Identity service.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// A PEM file containing the leaf certificate followed by any intermediate certificates.
+    pub cert_chain_path: PathBuf,
+    /// A PEM file containing the certificate's PKCS#8-encoded private key.
+    pub key_path: PathBuf,
+    /// How often the files are checked for a rotated certificate.
+    pub poll_interval: Duration,
+}
+
+/// Publishes credentials from a certificate chain and private key on disk, updating them when the
+/// files change.
+///
+/// When the private key changes, it is published along with the certificate chain, so the files
+/// may be rotated together (e.g. by the SPIFFE helper or cert-manager).
+#[derive(Debug)]
+pub struct Files {
+    config: Config,
+    metrics: Metrics,
+    status: Status,
+}
+
+/// The contents of the files, as last read and as last published.
+#[derive(Debug)]
+struct Loaded {
+    /// The files' most recently read contents, which are not reloaded until they change.
+    read: Option<(Vec<u8>, Vec<u8>)>,
+    /// The private key with which credentials are currently published.
+    key_pkcs8: Vec<u8>,
+}
+
+// === impl Files ===
+
+impl From<Config> for Files {
+    fn from(config: Config) -> Self {
+        Self {
+            config,
+            metrics: Metrics::default(),
+            status: Status::default(),
+        }
+    }
+}
+
+impl Files {
+    pub fn metrics(&self) -> Metrics {
+        self.metrics.clone()
+    }
+
+    pub fn status(&self) -> Status {
+        self.status.clone()
+    }
+
+    /// Watches the certificate and key files, publishing each new certificate chain to
+    /// `credentials`.
+    ///
+    /// `key_pkcs8` must be the key with which `credentials` were created.
+    pub async fn run<C>(self, mut credentials: C, key_pkcs8: Vec<u8>)
+    where
+        C: Credentials,
+    {
+        debug!(
+            cert = %self.config.cert_chain_path.display(),
+            key = %self.config.key_path.display(),
+            "Identity file watcher running"
+        );
+
+        let mut loaded = Loaded {
+            read: None,
+            key_pkcs8,
+        };
+        let mut interval = time::interval(self.config.poll_interval);
+        interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            self.reload(&mut loaded, &mut credentials).await;
+        }
+    }
+
+    /// Reads the certificate chain and key, publishing them if either changed.
+    async fn reload<C: Credentials>(&self, loaded: &mut Loaded, credentials: &mut C) {
+        // The files' contents, rather than their modification times, are compared so that updates
+        // made by swapping symlinks (as Kubernetes does for mounted secrets) are observed.
+        let chain = match fs::read(&self.config.cert_chain_path).await {
+            Ok(chain) => chain,
+            Err(error) => {
+                let path = self.config.cert_chain_path.display();
+                warn!(%error, %path, "Failed to read certificate chain");
+                return;
+            }
+        };
+        let key = match fs::read(&self.config.key_path).await {
+            Ok(key) => key,
+            Err(error) => {
+                let path = self.config.key_path.display();
+                warn!(%error, %path, "Failed to read private key");
+                return;
+            }
+        };
+        let read = (chain, key);
+        if loaded.read.as_ref() == Some(&read) {
+            return;
+        }
+
+        match self.load(&read.0, &read.1, &mut loaded.key_pkcs8, credentials) {
+            Ok(expiry) => {
+                info!(?expiry, "Loaded identity certificate");
+                self.metrics.refresh(expiry);
+            }
+            Err(error) => {
+                error!(%error, "Failed to load identity certificate");
+            }
+        }
+        // Don't retry files that failed to load until their contents change again.
+        loaded.read = Some(read);
+    }
+
+    fn load<C: Credentials>(
+        &self,
+        chain_pem: &[u8],
+        key_pem: &[u8],
+        key_pkcs8: &mut Vec<u8>,
+        credentials: &mut C,
+    ) -> Result<SystemTime> {
+        let mut certs = rustls_pemfile::certs(&mut &*chain_pem)?.into_iter();
+        let leaf = certs.next().ok_or("no certificates found")?;
+        let expiry = expiry(&leaf)?;
+        if expiry <= SystemTime::now() {
+            return Err("certificate already expired".into());
+        }
+
+        let leaf = DerX509(leaf);
+        let intermediates = certs.map(DerX509).collect::<Vec<_>>();
+        let key = parse_key(key_pem, &self.config.key_path)?;
+        if key == *key_pkcs8 {
+            credentials.set_certificate(leaf.clone(), intermediates.clone(), expiry)?;
+        } else {
+            credentials.set_key_and_certificate(
+                &key,
+                leaf.clone(),
+                intermediates.clone(),
+                expiry,
+            )?;
+            info!("Loaded private key");
+            *key_pkcs8 = key;
+        }
+        self.status.set_certificate(&leaf, &intermediates);
+        Ok(expiry)
+    }
+}
+
+/// Reads the first PKCS#8-encoded private key from a PEM file.
+///
+/// This blocks, so it should only be used when the proxy is configured.
+pub fn read_key(path: &Path) -> Result<Vec<u8>> {
+    let pem = std::fs::read(path)?;
+    parse_key(&pem, path)
+}
+
+fn parse_key(pem: &[u8], path: &Path) -> Result<Vec<u8>> {
+    rustls_pemfile::pkcs8_private_keys(&mut &*pem)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("no PKCS#8 private key found in {}", path.display()).into())
+}
+
+/// Returns the time at which a DER-encoded certificate expires.
+fn expiry(der: &[u8]) -> Result<SystemTime> {
+    let (_, cert) = x509_parser::parse_x509_certificate(der)?;
+    let secs = cert.validity().not_after.timestamp();
+    let secs = u64::try_from(secs).map_err(|_| "certificate expired before the UNIX epoch")?;
+    Ok(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_identity::Name;
+
+    static CRT: &[u8] = include_bytes!("testdata/foo-ns1-ca1.crt.pem");
+    static KEY: &[u8] = include_bytes!("testdata/foo-ns1-ca1.key.pem");
+    static RENEWED_CRT: &[u8] = include_bytes!("testdata/foo-ns1-ca1-renewed.crt.pem");
+    static EXPIRED_CRT: &[u8] = include_bytes!("testdata/foo-ns1-ca1-expired.crt.pem");
+    static CA2_CRT: &[u8] = include_bytes!("testdata/foo-ns1-ca2.crt.pem");
+    static CA2_KEY: &[u8] = include_bytes!("testdata/foo-ns1-ca2.key.pem");
+
+    /// Records the credentials published by the watcher.
+    struct Published {
+        name: Name,
+        key_pkcs8: Vec<u8>,
+        leaf: Option<Vec<u8>>,
+        updates: usize,
+    }
+
+    /// A directory that holds a certificate chain and key, removed when dropped.
+    struct Dir(PathBuf);
+
+    impl Credentials for Published {
+        fn dns_name(&self) -> &Name {
+            &self.name
+        }
+
+        fn gen_certificate_signing_request(&mut self) -> DerX509 {
+            unimplemented!("certificates are loaded from files")
+        }
+
+        fn set_certificate(
+            &mut self,
+            DerX509(leaf): DerX509,
+            _: Vec<DerX509>,
+            _: SystemTime,
+        ) -> Result<()> {
+            self.leaf = Some(leaf);
+            self.updates += 1;
+            Ok(())
+        }
+
+        fn set_key_and_certificate(
+            &mut self,
+            key_pkcs8: &[u8],
+            leaf: DerX509,
+            chain: Vec<DerX509>,
+            expiry: SystemTime,
+        ) -> Result<()> {
+            self.key_pkcs8 = key_pkcs8.to_vec();
+            self.set_certificate(leaf, chain, expiry)
+        }
+    }
+
+    impl Dir {
+        fn new(test: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "linkerd-identity-files-{}-{}",
+                std::process::id(),
+                test
+            ));
+            std::fs::create_dir_all(&path).expect("directory must be created");
+            Self(path)
+        }
+
+        fn write(&self, chain: &[u8], key: &[u8]) {
+            std::fs::write(self.0.join("crt.pem"), chain).expect("chain must be written");
+            std::fs::write(self.0.join("key.pem"), key).expect("key must be written");
+        }
+
+        fn files(&self) -> Files {
+            Files::from(Config {
+                cert_chain_path: self.0.join("crt.pem"),
+                key_path: self.0.join("key.pem"),
+                poll_interval: Duration::from_secs(10),
+            })
+        }
+    }
+
+    impl Drop for Dir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn key(pem: &[u8]) -> Vec<u8> {
+        parse_key(pem, Path::new("key.pem")).expect("key must be valid")
+    }
+
+    fn leaf(pem: &[u8]) -> Vec<u8> {
+        rustls_pemfile::certs(&mut &*pem)
+            .expect("chain must be valid")
+            .remove(0)
+    }
+
+    fn init(dir: &Dir) -> (Files, Loaded, Published) {
+        dir.write(CRT, KEY);
+        let loaded = Loaded {
+            read: None,
+            key_pkcs8: key(KEY),
+        };
+        let published = Published {
+            name: "foo.ns1.serviceaccount.identity.linkerd.cluster.local"
+                .parse()
+                .unwrap(),
+            key_pkcs8: key(KEY),
+            leaf: None,
+            updates: 0,
+        };
+        (dir.files(), loaded, published)
+    }
+
+    #[tokio::test]
+    async fn reloads_rotated_chain() {
+        let dir = Dir::new("chain");
+        let (files, mut loaded, mut published) = init(&dir);
+
+        files.reload(&mut loaded, &mut published).await;
+        assert_eq!(published.leaf, Some(leaf(CRT)));
+
+        // Unchanged files are not published again.
+        files.reload(&mut loaded, &mut published).await;
+        assert_eq!(published.updates, 1);
+
+        dir.write(RENEWED_CRT, KEY);
+        files.reload(&mut loaded, &mut published).await;
+        assert_eq!(published.leaf, Some(leaf(RENEWED_CRT)));
+        assert_eq!(published.key_pkcs8, key(KEY));
+        assert_eq!(published.updates, 2);
+    }
+
+    #[tokio::test]
+    async fn reloads_rotated_key() {
+        let dir = Dir::new("key");
+        let (files, mut loaded, mut published) = init(&dir);
+        files.reload(&mut loaded, &mut published).await;
+
+        dir.write(CA2_CRT, CA2_KEY);
+        files.reload(&mut loaded, &mut published).await;
+        assert_eq!(published.leaf, Some(leaf(CA2_CRT)));
+        assert_eq!(published.key_pkcs8, key(CA2_KEY));
+        assert_eq!(loaded.key_pkcs8, key(CA2_KEY));
+        assert_eq!(published.updates, 2);
+    }
+
+    #[tokio::test]
+    async fn ignores_expired_chain() {
+        let dir = Dir::new("expired");
+        let (files, mut loaded, mut published) = init(&dir);
+        files.reload(&mut loaded, &mut published).await;
+
+        dir.write(EXPIRED_CRT, KEY);
+        files.reload(&mut loaded, &mut published).await;
+        assert_eq!(
+            published.leaf,
+            Some(leaf(CRT)),
+            "the expired certificate must not be published"
+        );
+        assert_eq!(published.updates, 1);
+    }
+}
@@ -2,7 +2,11 @@
 #![forbid(unsafe_code)]
 
 pub mod certify;
+pub mod files;
 pub mod metrics;
+mod status;
 mod token;
 
-pub use self::{certify::Certify, metrics::Metrics, token::TokenSource};
+pub use self::{
+    certify::Certify, files::Files, metrics::Metrics, status::Status, token::TokenSource,
+};
@@ -12,7 +12,7 @@ metrics! {
     },
 
     identity_cert_refresh_count: Counter {
-        "The total number of times this proxy's mTLS identity certificate has been refreshed by the Identity service."
+        "The total number of times this proxy's mTLS identity certificate has been refreshed."
     }
 }
 
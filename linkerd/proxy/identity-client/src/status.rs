@@ -0,0 +1,56 @@
+use linkerd_identity::DerX509;
+use parking_lot::RwLock;
+use std::{sync::Arc, time::SystemTime};
+
+/// Describes the proxy's current certificate and trust anchors, so that they may be inspected
+/// when debugging certificate rotation.
+#[derive(Clone, Debug, Default)]
+pub struct Status(Arc<RwLock<Inner>>);
+
+#[derive(Debug, Default)]
+struct Inner {
+    certificates: Vec<DerX509>,
+    trust_anchors_pem: String,
+    next_refresh: Option<SystemTime>,
+}
+
+// === impl Status ===
+
+impl Status {
+    /// Records the certificate chain most recently published to the proxy's credentials.
+    pub(crate) fn set_certificate(&self, leaf: &DerX509, intermediates: &[DerX509]) {
+        let mut inner = self.0.write();
+        inner.certificates = Some(leaf)
+            .into_iter()
+            .chain(intermediates)
+            .cloned()
+            .collect();
+    }
+
+    /// Records when the certificate will next be refreshed.
+    pub(crate) fn set_next_refresh(&self, next_refresh: SystemTime) {
+        self.0.write().next_refresh = Some(next_refresh);
+    }
+
+    /// Records the trust anchors currently used to validate peers' certificates.
+    pub fn set_trust_anchors(&self, pem: &str) {
+        self.0.write().trust_anchors_pem = pem.to_string();
+    }
+
+    /// Returns the current certificate chain, beginning with the leaf certificate.
+    ///
+    /// Empty until a certificate has been issued.
+    pub fn certificates(&self) -> Vec<DerX509> {
+        self.0.read().certificates.clone()
+    }
+
+    /// Returns the PEM-encoded trust anchors.
+    pub fn trust_anchors_pem(&self) -> String {
+        self.0.read().trust_anchors_pem.clone()
+    }
+
+    /// Returns when the certificate will next be refreshed, if a refresh is scheduled.
+    pub fn next_refresh(&self) -> Option<SystemTime> {
+        self.0.read().next_refresh
+    }
+}
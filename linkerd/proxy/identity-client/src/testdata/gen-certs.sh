@@ -0,0 +1,36 @@
+#!/bin/sh
+#
+# Generates PEM-encoded certificates for the identity file watcher's tests from the fixtures in
+# `linkerd/tls/test-util`.
+#
+# Requires OpenSSL 3.4+ (for `-not_before` and `-not_after`).
+#
+set -euox pipefail
+
+src=../../../../tls/test-util/src/testdata
+host=foo.ns1.serviceaccount.identity.linkerd.cluster.local
+ext=$(mktemp)
+cat >"$ext" <<EXT
+subjectAltName=DNS:${host}
+keyUsage=critical,digitalSignature,keyEncipherment
+extendedKeyUsage=serverAuth,clientAuth
+basicConstraints=critical,CA:FALSE
+EXT
+
+for ee in foo-ns1-ca1 foo-ns1-ca2; do
+  openssl x509 -inform der -in "${src}/${ee}/crt.der" -out "${ee}.crt.pem"
+  openssl pkcs8 -inform der -nocrypt -in "${src}/${ee}/key.p8" -out "${ee}.key.pem"
+done
+
+# A new certificate for foo-ns1-ca1's key.
+openssl x509 -req -sha256 -in "${src}/foo-ns1-ca1/csr.pem" \
+  -CA "${src}/ca1.pem" -CAkey "${src}/ca1-key.pem" -set_serial 0x1002 \
+  -days 3650 -extfile "$ext" -out foo-ns1-ca1-renewed.crt.pem
+
+# An expired certificate for foo-ns1-ca1's key.
+openssl x509 -req -sha256 -in "${src}/foo-ns1-ca1/csr.pem" \
+  -CA "${src}/ca1.pem" -CAkey "${src}/ca1-key.pem" -set_serial 0x1001 \
+  -not_before 20200101000000Z -not_after 20210101000000Z \
+  -extfile "$ext" -out foo-ns1-ca1-expired.crt.pem
+
+rm "$ext"
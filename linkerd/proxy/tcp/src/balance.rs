@@ -1,28 +1,305 @@
+use futures::{ready, TryFuture};
 use linkerd_error::Error;
-use linkerd_stack::layer;
+use linkerd_stack::{layer, Either, Param};
+use pin_project::pin_project;
 use rand::thread_rng;
-use std::{hash::Hash, time::Duration};
+use std::{
+    future::Future,
+    hash::Hash,
+    io,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 pub use tower::{
     balance::p2c::Balance,
-    load::{Load, PeakEwmaDiscover},
+    load::{Load, PeakEwmaDiscover, PendingRequestsDiscover},
+};
+use tower::{
+    discover::Discover,
+    load::{completion::TrackCompletion, peak_ewma, pending_requests},
 };
-use tower::{discover::Discover, load::CompleteOnResponse};
 
-/// Produces a PeakEWMA balancer that uses connect latency (and pending
-/// connections) as its load metric.
-pub fn layer<T, D>(
+/// Determines the load metric that is used to pick between endpoints.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Mode {
+    /// Uses connect latency (and pending connections) as the load metric.
+    PeakEwma,
+
+    /// Uses the number of open connections as the load metric. This is better suited to
+    /// long-lived connections, for which connect latency says little about an endpoint's load.
+    LeastConnections,
+}
+
+/// Builds a p2c balancer over the endpoints discovered for each target.
+///
+/// Targets may choose a balancing `Mode`; otherwise the layer's default mode is used.
+#[derive(Debug)]
+pub struct MakeBalance<M, T> {
+    inner: M,
+    config: Config,
+    _marker: PhantomData<fn(T)>,
+}
+
+#[pin_project]
+#[derive(Debug)]
+pub struct MakeBalanceFuture<F, T> {
+    #[pin]
+    inner: F,
+    mode: Mode,
+    config: Config,
+    _marker: PhantomData<fn(T)>,
+}
+
+/// A balancer built by `MakeBalance`.
+pub type Balancer<D, T> = Either<
+    Balance<PeakEwmaDiscover<D, CompleteOnConnect>, T>,
+    Balance<PendingRequestsDiscover<D, CompleteOnClose>, T>,
+>;
+
+/// Completes a load measurement once a connection is established.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct CompleteOnConnect(());
+
+/// Completes a load measurement once a connection is closed.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct CompleteOnClose(());
+
+/// A connection that holds its load handle until it is dropped.
+#[pin_project]
+#[derive(Debug)]
+pub struct Connection<I> {
+    #[pin]
+    io: I,
+    _handle: Option<pending_requests::Handle>,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Config {
+    default_mode: Mode,
     default_rtt: Duration,
     decay: Duration,
-) -> impl tower::layer::Layer<D, Service = Balance<PeakEwmaDiscover<D, CompleteOnResponse>, T>> + Clone
+}
+
+// === impl MakeBalance ===
+
+impl<M, T> MakeBalance<M, T> {
+    pub fn layer(
+        default_mode: Mode,
+        default_rtt: Duration,
+        decay: Duration,
+    ) -> impl tower::layer::Layer<M, Service = Self> + Clone {
+        let config = Config {
+            default_mode,
+            default_rtt,
+            decay,
+        };
+        layer::mk(move |inner| Self {
+            inner,
+            config,
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<M: Clone, T> Clone for MakeBalance<M, T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            config: self.config,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<Tgt, M, D, T> tower::Service<Tgt> for MakeBalance<M, T>
 where
+    Tgt: Param<Option<Mode>>,
+    M: tower::Service<Tgt, Response = D>,
     D: Discover,
     D::Key: Hash,
     D::Service: tower::Service<T>,
     <D::Service as tower::Service<T>>::Error: Into<Error>,
 {
-    layer::mk(move |discover| {
-        let loaded =
-            PeakEwmaDiscover::new(discover, default_rtt, decay, CompleteOnResponse::default());
-        Balance::from_rng(loaded, &mut thread_rng()).expect("RNG must be valid")
-    })
+    type Response = Balancer<D, T>;
+    type Error = M::Error;
+    type Future = MakeBalanceFuture<M::Future, T>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), M::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, target: Tgt) -> Self::Future {
+        let mode = target.param().unwrap_or(self.config.default_mode);
+        MakeBalanceFuture {
+            mode,
+            inner: self.inner.call(target),
+            config: self.config,
+            _marker: PhantomData,
+        }
+    }
+}
+
+// === impl MakeBalanceFuture ===
+
+impl<F, D, T> Future for MakeBalanceFuture<F, T>
+where
+    F: TryFuture<Ok = D>,
+    D: Discover,
+    D::Key: Hash,
+    D::Service: tower::Service<T>,
+    <D::Service as tower::Service<T>>::Error: Into<Error>,
+{
+    type Output = Result<Balancer<D, T>, F::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let discover = ready!(this.inner.try_poll(cx))?;
+        let Config {
+            default_rtt, decay, ..
+        } = *this.config;
+        let balance = match *this.mode {
+            Mode::PeakEwma => {
+                let loaded =
+                    PeakEwmaDiscover::new(discover, default_rtt, decay, CompleteOnConnect(()));
+                Either::A(Balance::from_rng(loaded, &mut thread_rng()).expect("RNG must be valid"))
+            }
+            Mode::LeastConnections => {
+                let loaded = PendingRequestsDiscover::new(discover, CompleteOnClose(()));
+                Either::B(Balance::from_rng(loaded, &mut thread_rng()).expect("RNG must be valid"))
+            }
+        };
+        Poll::Ready(Ok(balance))
+    }
+}
+
+// === impl CompleteOnConnect ===
+
+impl<I> TrackCompletion<peak_ewma::Handle, I> for CompleteOnConnect {
+    type Output = Connection<I>;
+
+    fn track_completion(&self, handle: peak_ewma::Handle, io: I) -> Connection<I> {
+        // Dropping the handle records the connect latency.
+        drop(handle);
+        Connection { io, _handle: None }
+    }
+}
+
+// === impl CompleteOnClose ===
+
+impl<I> TrackCompletion<pending_requests::Handle, I> for CompleteOnClose {
+    type Output = Connection<I>;
+
+    fn track_completion(&self, handle: pending_requests::Handle, io: I) -> Connection<I> {
+        Connection {
+            io,
+            _handle: Some(handle),
+        }
+    }
+}
+
+// === impl Connection ===
+
+impl<I: AsyncRead> AsyncRead for Connection<I> {
+    #[inline]
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        self.project().io.poll_read(cx, buf)
+    }
+}
+
+impl<I: AsyncWrite> AsyncWrite for Connection<I> {
+    #[inline]
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.project().io.poll_write(cx, buf)
+    }
+
+    #[inline]
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().io.poll_flush(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().io.poll_shutdown(cx)
+    }
+
+    #[inline]
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.project().io.poll_write_vectored(cx, bufs)
+    }
+
+    #[inline]
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_stack::{service_fn, Service, ServiceExt};
+    use tower::{discover::ServiceList, layer::Layer};
+
+    fn endpoint(name: &'static str) -> impl Service<(), Response = &'static str, Error = Error> {
+        service_fn(move |()| futures::future::ok::<_, Error>(name))
+    }
+
+    async fn connect<S>(balance: &mut S) -> Connection<&'static str>
+    where
+        S: Service<(), Response = Connection<&'static str>, Error = Error>,
+    {
+        balance
+            .ready()
+            .await
+            .expect("balancer must be ready")
+            .call(())
+            .await
+            .expect("connection must be established")
+    }
+
+    #[tokio::test]
+    async fn least_connections_steers_new_connections() {
+        let make = service_fn(|_: Option<Mode>| {
+            futures::future::ok::<_, Error>(ServiceList::new(vec![endpoint("a"), endpoint("b")]))
+        });
+        let mut balance = MakeBalance::<_, ()>::layer(
+            Mode::PeakEwma,
+            Duration::from_secs(1),
+            Duration::from_secs(10),
+        )
+        .layer(make)
+        .oneshot(Some(Mode::LeastConnections))
+        .await
+        .expect("balancer must be built");
+        assert!(matches!(balance, Either::B(_)));
+
+        // While a connection is open to one endpoint, new connections are steered to the other.
+        let open = connect(&mut balance).await;
+        for _ in 0..10 {
+            let conn = connect(&mut balance).await;
+            assert_ne!(conn.io, open.io);
+        }
+
+        // Once the connection is closed, both endpoints are idle, so connections are spread
+        // across them.
+        drop(open);
+        let first = connect(&mut balance).await;
+        let second = connect(&mut balance).await;
+        assert_ne!(first.io, second.io);
+    }
 }
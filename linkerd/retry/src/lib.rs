@@ -4,7 +4,13 @@
 use futures::future;
 use linkerd_error::Error;
 use linkerd_stack::{layer, Either, NewService, Oneshot, Service, ServiceExt};
-use std::task::{Context, Poll};
+use pin_project::pin_project;
+use std::{
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+};
 pub use tower::retry::{budget::Budget, Policy};
 use tracing::trace;
 
@@ -34,6 +40,10 @@ pub trait PrepareRequest<Req, Res, E>: tower::retry::Policy<Self::RetryRequest,
     /// to this policy, this function may transform the request into a request
     /// of that type.
     fn prepare_request(&self, req: Req) -> Either<Self::RetryRequest, Req>;
+
+    /// Observes the result of a request that `prepare_request` did not prepare for retries, e.g.
+    /// so that failures that could not be retried may be recorded.
+    fn not_retried(&self, _result: Result<&Res, &E>) {}
 }
 
 /// Applies per-target retry policies.
@@ -49,6 +59,16 @@ pub struct Retry<P, S> {
     inner: S,
 }
 
+/// Notifies a policy of the result of a request that could not be retried.
+#[pin_project]
+#[derive(Debug)]
+pub struct NotRetried<P, F, Req> {
+    policy: P,
+    #[pin]
+    inner: F,
+    _req: PhantomData<fn(Req)>,
+}
+
 // === impl NewRetry ===
 
 impl<P: Clone, N> NewRetry<P, N> {
@@ -88,7 +108,10 @@ where
 {
     type Response = Rsp;
     type Error = Error;
-    type Future = future::Either<Fut, Oneshot<tower::retry::Retry<P, S>, P::RetryRequest>>;
+    type Future = future::Either<
+        future::Either<Fut, NotRetried<P, Fut, Req>>,
+        Oneshot<tower::retry::Retry<P, S>, P::RetryRequest>,
+    >;
 
     #[inline]
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
@@ -99,13 +122,19 @@ where
         trace!(retryable = %self.policy.is_some());
 
         let policy = match self.policy.as_ref() {
-            None => return future::Either::Left(self.inner.call(req)),
+            None => return future::Either::Left(future::Either::Left(self.inner.call(req))),
             Some(p) => p,
         };
 
         let retry_req = match policy.prepare_request(req) {
             Either::A(retry_req) => retry_req,
-            Either::B(req) => return future::Either::Left(self.inner.call(req)),
+            Either::B(req) => {
+                return future::Either::Left(future::Either::Right(NotRetried {
+                    policy: policy.clone(),
+                    inner: self.inner.call(req),
+                    _req: PhantomData,
+                }))
+            }
         };
 
         let inner = self.inner.clone();
@@ -113,3 +142,20 @@ where
         future::Either::Right(retry.oneshot(retry_req))
     }
 }
+
+// === impl NotRetried ===
+
+impl<P, F, Req, Rsp> Future for NotRetried<P, F, Req>
+where
+    P: PrepareRequest<Req, Rsp, Error>,
+    F: Future<Output = Result<Rsp, Error>>,
+{
+    type Output = Result<Rsp, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = futures::ready!(this.inner.poll(cx));
+        this.policy.not_retried(res.as_ref());
+        Poll::Ready(res)
+    }
+}
@@ -0,0 +1,198 @@
+use crate::Authorization;
+use regex::Regex;
+use std::sync::Arc;
+
+/// An HTTP route on a server, with its own set of authorizations.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Route {
+    /// Matches requests for this route. A request matches the route if any of the matches apply.
+    pub matches: Vec<RequestMatch>,
+    pub authorizations: Vec<Authorization>,
+    pub name: Arc<str>,
+}
+
+/// Matches a request when all of the configured criteria match.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RequestMatch {
+    pub path: Option<PathMatch>,
+    pub method: Option<http::Method>,
+    pub headers: Vec<HeaderMatch>,
+}
+
+#[derive(Clone, Debug)]
+pub enum PathMatch {
+    Exact(String),
+    Prefix(String),
+    Regex(Regex),
+}
+
+#[derive(Clone, Debug)]
+pub enum HeaderMatch {
+    Exact(http::header::HeaderName, http::HeaderValue),
+    Regex(http::header::HeaderName, Regex),
+}
+
+/// Returns the first route that matches the request, if any.
+pub fn find<'r, B>(routes: &'r [Route], req: &http::Request<B>) -> Option<&'r Route> {
+    routes.iter().find(|r| r.is_match(req))
+}
+
+// === impl Route ===
+
+impl Route {
+    #[inline]
+    pub fn is_match<B>(&self, req: &http::Request<B>) -> bool {
+        self.matches.iter().any(|m| m.is_match(req))
+    }
+}
+
+// === impl RequestMatch ===
+
+impl RequestMatch {
+    pub fn is_match<B>(&self, req: &http::Request<B>) -> bool {
+        if let Some(method) = self.method.as_ref() {
+            if req.method() != *method {
+                return false;
+            }
+        }
+
+        if let Some(path) = self.path.as_ref() {
+            if !path.is_match(req.uri().path()) {
+                return false;
+            }
+        }
+
+        self.headers.iter().all(|h| h.is_match(req.headers()))
+    }
+}
+
+// === impl PathMatch ===
+
+impl PathMatch {
+    pub fn is_match(&self, path: &str) -> bool {
+        match self {
+            Self::Exact(p) => path == p,
+            Self::Prefix(p) => {
+                // A prefix only matches on path segment boundaries, so that `/foo` matches
+                // `/foo/bar` but not `/foobar`.
+                match path.strip_prefix(p.as_str()) {
+                    Some(rest) => p.ends_with('/') || rest.is_empty() || rest.starts_with('/'),
+                    None => false,
+                }
+            }
+            Self::Regex(re) => re.is_match(path),
+        }
+    }
+}
+
+impl PartialEq for PathMatch {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Exact(a), Self::Exact(b)) => a == b,
+            (Self::Prefix(a), Self::Prefix(b)) => a == b,
+            (Self::Regex(a), Self::Regex(b)) => a.as_str() == b.as_str(),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for PathMatch {}
+
+// === impl HeaderMatch ===
+
+impl HeaderMatch {
+    pub fn is_match(&self, headers: &http::HeaderMap) -> bool {
+        match self {
+            Self::Exact(name, value) => headers.get_all(name).iter().any(|v| v == value),
+            Self::Regex(name, re) => headers
+                .get_all(name)
+                .iter()
+                .any(|v| v.to_str().map(|v| re.is_match(v)).unwrap_or(false)),
+        }
+    }
+}
+
+impl PartialEq for HeaderMatch {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Exact(an, av), Self::Exact(bn, bv)) => an == bn && av == bv,
+            (Self::Regex(an, ar), Self::Regex(bn, br)) => an == bn && ar.as_str() == br.as_str(),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for HeaderMatch {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn req(method: http::Method, uri: &str) -> http::Request<()> {
+        http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("x-api-version", "2")
+            .body(())
+            .unwrap()
+    }
+
+    #[test]
+    fn path_prefix_respects_segments() {
+        let m = PathMatch::Prefix("/metrics".into());
+        assert!(m.is_match("/metrics"));
+        assert!(m.is_match("/metrics/foo"));
+        assert!(!m.is_match("/metricsfoo"));
+        assert!(!m.is_match("/"));
+    }
+
+    #[test]
+    fn request_match_requires_all_criteria() {
+        let m = RequestMatch {
+            path: Some(PathMatch::Exact("/metrics".into())),
+            method: Some(http::Method::GET),
+            headers: vec![HeaderMatch::Exact(
+                http::header::HeaderName::from_static("x-api-version"),
+                http::HeaderValue::from_static("2"),
+            )],
+        };
+        assert!(m.is_match(&req(http::Method::GET, "http://example.com/metrics")));
+        assert!(!m.is_match(&req(http::Method::POST, "http://example.com/metrics")));
+        assert!(!m.is_match(&req(http::Method::GET, "http://example.com/other")));
+
+        let m = RequestMatch {
+            headers: vec![HeaderMatch::Regex(
+                http::header::HeaderName::from_static("x-api-version"),
+                Regex::new("^[3-9]$").unwrap(),
+            )],
+            ..Default::default()
+        };
+        assert!(!m.is_match(&req(http::Method::GET, "http://example.com/metrics")));
+    }
+
+    #[test]
+    fn find_first_matching_route() {
+        let routes = vec![
+            Route {
+                matches: vec![RequestMatch {
+                    path: Some(PathMatch::Prefix("/metrics".into())),
+                    ..Default::default()
+                }],
+                authorizations: vec![],
+                name: "metrics".into(),
+            },
+            Route {
+                matches: vec![RequestMatch::default()],
+                authorizations: vec![],
+                name: "default".into(),
+            },
+        ];
+        let r = find(
+            &routes,
+            &req(http::Method::GET, "http://example.com/metrics"),
+        );
+        assert_eq!(r.map(|r| &*r.name), Some("metrics"));
+        let r = find(&routes, &req(http::Method::GET, "http://example.com/api"));
+        assert_eq!(r.map(|r| &*r.name), Some("default"));
+    }
+}
@@ -1,18 +1,32 @@
 #![deny(warnings, rust_2018_idioms)]
 #![forbid(unsafe_code)]
 
+pub mod http;
 mod network;
 
 pub use self::network::Network;
-use std::{collections::HashSet, hash::Hash, sync::Arc, time};
+use std::{collections::HashSet, fmt, hash::Hash, num::NonZeroU32, sync::Arc, time};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct ServerPolicy {
     pub protocol: Protocol,
     pub authorizations: Vec<Authorization>,
+    pub http_routes: Vec<http::Route>,
+    pub rate_limit: Option<RateLimit>,
     pub name: Arc<str>,
 }
 
+/// Limits the rate at which a server accepts HTTP requests.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RateLimit {
+    /// The number of requests per second that the server accepts from all clients.
+    pub total: Option<NonZeroU32>,
+
+    /// The number of requests per second that the server accepts from each client identity.
+    /// Clients without an identity share a single limit.
+    pub identity: Option<NonZeroU32>,
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Protocol {
     Detect { timeout: time::Duration },
@@ -59,10 +73,26 @@ impl From<Vec<String>> for Suffix {
 }
 
 impl Suffix {
+    /// Returns true if the DNS-like name ends with this suffix.
     #[inline]
     pub fn contains(&self, name: &str) -> bool {
         name.ends_with(&self.ends_with)
     }
+
+    /// Returns true if the suffix matches all names.
+    ///
+    /// Suffixes only describe DNS-like names, so identities of other forms (i.e. SPIFFE IDs) may
+    /// only match a suffix that matches all names.
+    #[inline]
+    pub fn is_any(&self) -> bool {
+        self.ends_with.is_empty()
+    }
+}
+
+impl fmt::Display for Suffix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "*{}", self.ends_with)
+    }
 }
 
 #[cfg(test)]
@@ -1,6 +1,7 @@
 mod proxy;
 mod service;
 
+use linkerd_addr::NameAddr;
 use regex::Regex;
 use std::{
     fmt,
@@ -19,6 +20,7 @@ pub struct Route {
     response_classes: ResponseClasses,
     retries: Option<Retries>,
     timeout: Option<Duration>,
+    backend_overrides: BackendOverrides,
 }
 
 #[derive(Clone, Debug)]
@@ -28,6 +30,41 @@ pub enum RequestMatch {
     Not(Box<RequestMatch>),
     Path(Box<Regex>),
     Method(http::Method),
+    Header(http::header::HeaderName, ValueMatch),
+    QueryParam(String, ValueMatch),
+}
+
+/// Matches the value of a request header, query parameter or cookie.
+#[derive(Clone, Debug)]
+pub enum ValueMatch {
+    Exact(String),
+    Regex(Box<Regex>),
+}
+
+/// Pins the requests that match to a specific backend of the service's traffic split, so that
+/// they are not distributed by the split's weights.
+#[derive(Clone, Debug)]
+pub struct BackendOverride {
+    match_: BackendMatch,
+    backend: NameAddr,
+}
+
+/// Matches the request header or cookie that pins a request to a backend.
+#[derive(Clone, Debug)]
+pub enum BackendMatch {
+    Header(http::header::HeaderName, ValueMatch),
+    Cookie(String, ValueMatch),
+}
+
+#[derive(Clone, Default)]
+struct BackendOverrides(Arc<Vec<BackendOverride>>);
+
+/// Identifies the part of a request that is hashed to choose its endpoint.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum HashKey {
+    Header(http::header::HeaderName),
+    Cookie(String),
+    ClientAddr,
 }
 
 #[derive(Clone, Debug)]
@@ -48,6 +85,10 @@ pub enum ResponseMatch {
         min: http::StatusCode,
         max: http::StatusCode,
     },
+    /// Matches a gRPC status code. The status is read from the response trailers or, for
+    /// trailers-only responses, from the response headers.
+    GrpcStatus(u32),
+    Header(http::header::HeaderName, ValueMatch),
 }
 
 #[derive(Clone, Debug)]
@@ -84,6 +125,7 @@ impl Route {
             response_classes: ResponseClasses(response_classes.into()),
             retries: None,
             timeout: None,
+            backend_overrides: BackendOverrides::default(),
         }
     }
 
@@ -110,6 +152,90 @@ impl Route {
     pub fn set_timeout(&mut self, timeout: Duration) {
         self.timeout = Some(timeout);
     }
+
+    /// Sets the overrides that pin matching requests to a backend. Overrides are evaluated in
+    /// order, and the first that matches a request determines its backend.
+    ///
+    /// Profiles discovered from the destination API never set overrides, since the API (as of
+    /// linkerd2-proxy-api v0.3) cannot describe them.
+    pub fn set_backend_overrides(&mut self, overrides: Vec<BackendOverride>) {
+        self.backend_overrides = BackendOverrides(overrides.into());
+    }
+
+    pub fn backend_overrides(&self) -> &[BackendOverride] {
+        &*self.backend_overrides.0
+    }
+
+    /// Returns the backend to which the request is pinned, if any of the route's overrides match
+    /// it.
+    pub fn backend_for_request<B>(&self, req: &http::Request<B>) -> Option<&NameAddr> {
+        self.backend_overrides
+            .0
+            .iter()
+            .find(|o| o.is_match(req))
+            .map(|o| &o.backend)
+    }
+}
+
+// === impl BackendOverride ===
+
+impl BackendOverride {
+    pub fn new(match_: BackendMatch, backend: NameAddr) -> Self {
+        Self { match_, backend }
+    }
+
+    pub fn backend(&self) -> &NameAddr {
+        &self.backend
+    }
+
+    #[inline]
+    pub fn is_match<B>(&self, req: &http::Request<B>) -> bool {
+        self.match_.is_match(req)
+    }
+}
+
+// === impl BackendMatch ===
+
+impl BackendMatch {
+    fn is_match<B>(&self, req: &http::Request<B>) -> bool {
+        match self {
+            BackendMatch::Header(ref name, ref m) => req
+                .headers()
+                .get_all(name)
+                .iter()
+                .any(|v| v.to_str().map(|v| m.is_match(v)).unwrap_or(false)),
+            BackendMatch::Cookie(ref name, ref m) => req
+                .headers()
+                .get_all(http::header::COOKIE)
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .flat_map(|v| v.split(';'))
+                .filter_map(|pair| pair.trim().split_once('='))
+                .any(|(k, v)| k == name.as_str() && m.is_match(v)),
+        }
+    }
+}
+
+// === impl BackendOverrides ===
+
+impl PartialEq for BackendOverrides {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for BackendOverrides {}
+
+impl Hash for BackendOverrides {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(Arc::as_ref(&self.0) as *const _ as usize);
+    }
+}
+
+impl fmt::Debug for BackendOverrides {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
 }
 
 // === impl RequestMatch ===
@@ -119,6 +245,18 @@ impl RequestMatch {
         match self {
             RequestMatch::Method(ref method) => req.method() == *method,
             RequestMatch::Path(ref re) => re.is_match(req.uri().path()),
+            RequestMatch::Header(ref name, ref m) => req
+                .headers()
+                .get_all(name)
+                .iter()
+                .any(|v| v.to_str().map(|v| m.is_match(v)).unwrap_or(false)),
+            RequestMatch::QueryParam(ref name, ref m) => req
+                .uri()
+                .query()
+                .map(|q| {
+                    form_urlencoded::parse(q.as_bytes()).any(|(k, v)| k == *name && m.is_match(&v))
+                })
+                .unwrap_or(false),
             RequestMatch::Not(ref m) => !m.is_match(req),
             RequestMatch::All(ref ms) => ms.iter().all(|m| m.is_match(req)),
             RequestMatch::Any(ref ms) => ms.iter().any(|m| m.is_match(req)),
@@ -126,6 +264,17 @@ impl RequestMatch {
     }
 }
 
+// === impl ValueMatch ===
+
+impl ValueMatch {
+    fn is_match(&self, value: &str) -> bool {
+        match self {
+            ValueMatch::Exact(ref v) => value == v,
+            ValueMatch::Regex(ref re) => re.is_match(value),
+        }
+    }
+}
+
 // === impl ResponseClass ===
 
 impl ResponseClass {
@@ -137,13 +286,30 @@ impl ResponseClass {
         self.is_failure
     }
 
-    pub fn is_match<B>(&self, req: &http::Response<B>) -> bool {
-        self.match_.is_match(req)
+    pub fn is_match(
+        &self,
+        status: http::StatusCode,
+        headers: &http::HeaderMap,
+        trailers: Option<&http::HeaderMap>,
+    ) -> bool {
+        self.match_.is_match(status, headers, trailers)
+    }
+
+    /// Returns true if this class can only be determined once the response's trailers have been
+    /// received.
+    pub fn requires_trailers(&self) -> bool {
+        self.match_.requires_trailers()
     }
 }
 
 // === impl ResponseClasses ===
 
+impl ResponseClasses {
+    pub fn requires_trailers(&self) -> bool {
+        self.0.iter().any(ResponseClass::requires_trailers)
+    }
+}
+
 impl Deref for ResponseClasses {
     type Target = [ResponseClass];
 
@@ -175,18 +341,48 @@ impl fmt::Debug for ResponseClasses {
 // === impl ResponseMatch ===
 
 impl ResponseMatch {
-    fn is_match<B>(&self, req: &http::Response<B>) -> bool {
+    fn is_match(
+        &self,
+        status: http::StatusCode,
+        headers: &http::HeaderMap,
+        trailers: Option<&http::HeaderMap>,
+    ) -> bool {
+        match self {
+            ResponseMatch::Status { ref min, ref max } => *min <= status && status <= *max,
+            ResponseMatch::GrpcStatus(code) => trailers
+                .and_then(grpc_status)
+                .or_else(|| grpc_status(headers))
+                .map(|c| c == *code)
+                .unwrap_or(false),
+            ResponseMatch::Header(ref name, ref m) => headers
+                .get_all(name)
+                .iter()
+                .any(|v| v.to_str().map(|v| m.is_match(v)).unwrap_or(false)),
+            ResponseMatch::Not(ref m) => !m.is_match(status, headers, trailers),
+            ResponseMatch::All(ref ms) => ms.iter().all(|m| m.is_match(status, headers, trailers)),
+            ResponseMatch::Any(ref ms) => ms.iter().any(|m| m.is_match(status, headers, trailers)),
+        }
+    }
+
+    fn requires_trailers(&self) -> bool {
         match self {
-            ResponseMatch::Status { ref min, ref max } => {
-                *min <= req.status() && req.status() <= *max
+            ResponseMatch::GrpcStatus(_) => true,
+            ResponseMatch::Status { .. } | ResponseMatch::Header(..) => false,
+            ResponseMatch::Not(ref m) => m.requires_trailers(),
+            ResponseMatch::All(ref ms) | ResponseMatch::Any(ref ms) => {
+                ms.iter().any(ResponseMatch::requires_trailers)
             }
-            ResponseMatch::Not(ref m) => !m.is_match(req),
-            ResponseMatch::All(ref ms) => ms.iter().all(|m| m.is_match(req)),
-            ResponseMatch::Any(ref ms) => ms.iter().any(|m| m.is_match(req)),
         }
     }
 }
 
+fn grpc_status(headers: &http::HeaderMap) -> Option<u32> {
+    headers
+        .get("grpc-status")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|s| s.parse::<u32>().ok())
+}
+
 // === impl Retries ===
 
 impl Retries {
@@ -230,3 +426,80 @@ impl fmt::Debug for Labels {
         self.0.fmt(f)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn req(uri: &str) -> http::Request<()> {
+        http::Request::get(uri)
+            .header("x-api-version", "2")
+            .body(())
+            .unwrap()
+    }
+
+    #[test]
+    fn header_match() {
+        let name = http::header::HeaderName::from_static("x-api-version");
+        let exact = RequestMatch::Header(name.clone(), ValueMatch::Exact("2".into()));
+        assert!(exact.is_match(&req("/")));
+
+        let re = RequestMatch::Header(
+            name,
+            ValueMatch::Regex(Box::new(Regex::new("^[3-9]$").unwrap())),
+        );
+        assert!(!re.is_match(&req("/")));
+
+        let missing = RequestMatch::Header(
+            http::header::HeaderName::from_static("x-missing"),
+            ValueMatch::Exact("2".into()),
+        );
+        assert!(!missing.is_match(&req("/")));
+    }
+
+    #[test]
+    fn query_param_match() {
+        let m = RequestMatch::QueryParam("q".into(), ValueMatch::Exact("a b".into()));
+        assert!(m.is_match(&req("/search?lang=en&q=a+b")));
+        assert!(m.is_match(&req("/search?q=a%20b")));
+        assert!(!m.is_match(&req("/search?q=ab")));
+        assert!(!m.is_match(&req("/search")));
+    }
+
+    #[test]
+    fn backend_overrides() {
+        let canary = NameAddr::from_str("web-canary.ns.svc.cluster.local:8080").unwrap();
+        let stable = NameAddr::from_str("web-stable.ns.svc.cluster.local:8080").unwrap();
+        let mut route = Route::new(std::iter::empty(), Vec::new());
+        route.set_backend_overrides(vec![
+            BackendOverride::new(
+                BackendMatch::Header(
+                    http::header::HeaderName::from_static("x-canary"),
+                    ValueMatch::Exact("true".into()),
+                ),
+                canary.clone(),
+            ),
+            BackendOverride::new(
+                BackendMatch::Cookie("track".into(), ValueMatch::Exact("stable".into())),
+                stable.clone(),
+            ),
+        ]);
+
+        let header = http::Request::get("/")
+            .header("x-canary", "true")
+            .header("cookie", "track=stable")
+            .body(())
+            .unwrap();
+        assert_eq!(route.backend_for_request(&header), Some(&canary));
+
+        let cookie = http::Request::get("/")
+            .header("x-canary", "false")
+            .header("cookie", "session=abc; track=stable")
+            .body(())
+            .unwrap();
+        assert_eq!(route.backend_for_request(&cookie), Some(&stable));
+
+        assert_eq!(route.backend_for_request(&req("/")), None);
+    }
+}
@@ -43,6 +43,29 @@ pub struct Profile {
     pub targets: Vec<Target>,
     pub opaque_protocol: bool,
     pub endpoint: Option<(SocketAddr, Metadata)>,
+    /// The load balancer to use for the service, if the profile configures one.
+    ///
+    /// The destination API (as of linkerd2-proxy-api v0.3) cannot describe load balancers, so
+    /// this is never set on discovered profiles, and the proxy-wide
+    /// `LINKERD2_PROXY_OUTBOUND_HTTP_LOAD_BALANCER` and `LINKERD2_PROXY_OUTBOUND_TCP_LOAD_BALANCER`
+    /// settings apply instead.
+    pub load_balancer: Option<LoadBalancer>,
+}
+
+/// Determines how a service's requests are distributed over its endpoints.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum LoadBalancer {
+    /// Sends each request to the less loaded of two random endpoints, by peak-EWMA latency.
+    PeakEwma,
+
+    /// Sends requests with the same hash key to the same endpoint, so that few requests move to a
+    /// different endpoint as endpoints are added and removed.
+    ConsistentHash(self::http::HashKey),
+
+    /// Sends each connection to whichever of two random endpoints has fewer open connections.
+    ///
+    /// Only applies to opaque TCP services; HTTP services are balanced by peak-EWMA latency.
+    LeastConnections,
 }
 
 /// A profile lookup target.
@@ -130,6 +153,11 @@ impl From<watch::Receiver<Profile>> for Receiver {
 }
 
 impl Receiver {
+    /// Returns a copy of the current profile.
+    pub fn profile(&self) -> Profile {
+        self.inner.borrow().clone()
+    }
+
     pub fn logical_addr(&self) -> Option<LogicalAddr> {
         self.inner.borrow().addr.clone()
     }
@@ -142,6 +170,15 @@ impl Receiver {
         self.inner.borrow().endpoint.clone()
     }
 
+    pub fn load_balancer(&self) -> Option<LoadBalancer> {
+        self.inner.borrow().load_balancer.clone()
+    }
+
+    /// Returns true if the profile's traffic split includes the given backend.
+    pub fn has_target(&self, addr: &NameAddr) -> bool {
+        self.inner.borrow().targets.iter().any(|t| t.addr == *addr)
+    }
+
     fn targets(&self) -> Vec<Target> {
         self.inner.borrow().targets.clone()
     }
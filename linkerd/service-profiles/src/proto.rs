@@ -31,6 +31,9 @@ pub(super) fn convert_profile(proto: api::DestinationProfile, port: u16) -> Prof
         targets,
         opaque_protocol: proto.opaque_protocol,
         endpoint,
+        // The destination API (as of linkerd2-proxy-api v0.3) cannot configure load balancers,
+        // so the load balancers configured on the proxy apply to every service.
+        load_balancer: None,
     }
 }
 
@@ -51,6 +54,9 @@ fn convert_route(
     if let Some(timeout) = orig.timeout {
         set_route_timeout(&mut route, timeout.try_into());
     }
+    // The destination API (as of linkerd2-proxy-api v0.3) does not encode a route's backend
+    // overrides, so requests are only pinned to backends by the overrides configured on the
+    // proxy. Decoding route-level overrides is blocked until the API describes them.
     Some((req_match, route))
 }
 
@@ -88,6 +94,9 @@ fn set_route_timeout(route: &mut http::Route, timeout: Result<Duration, Duration
     }
 }
 
+// The destination API (as of linkerd2-proxy-api v0.3) does not encode header or query parameter
+// matches, so `http::RequestMatch::Header` and `http::RequestMatch::QueryParam` are never produced
+// here. Decoding them is blocked until the API describes them.
 fn convert_req_match(orig: api::RequestMatch) -> Option<http::RequestMatch> {
     let m = match orig.r#match? {
         api::request_match::Match::All(ms) => {
@@ -129,6 +138,9 @@ fn convert_rsp_class(orig: api::ResponseClass) -> Option<http::ResponseClass> {
     Some(http::ResponseClass::new(orig.is_failure, c))
 }
 
+// The destination API (as of linkerd2-proxy-api v0.3) does not encode gRPC status or header
+// matches, so `http::ResponseMatch::GrpcStatus` and `http::ResponseMatch::Header` are never
+// produced here. Decoding them is blocked until the API describes them.
 fn convert_rsp_match(orig: api::ResponseMatch) -> Option<http::ResponseMatch> {
     let m = match orig.r#match? {
         api::response_match::Match::All(ms) => {
@@ -4,7 +4,7 @@ use indexmap::IndexSet;
 use linkerd_addr::NameAddr;
 use linkerd_error::Error;
 use linkerd_proxy_api_resolve::ConcreteAddr;
-use linkerd_stack::{layer, NewService, Param};
+use linkerd_stack::{layer, ExtractParam, NewService, Param};
 use rand::distributions::{Distribution, WeightedIndex};
 use rand::{rngs::SmallRng, thread_rng, SeedableRng};
 use std::{
@@ -15,23 +15,45 @@ use std::{
 use tower::ready_cache::ReadyCache;
 use tracing::{debug, trace};
 
-pub fn layer<N, S, Req>() -> impl layer::Layer<N, Service = NewSplit<N, S, Req>> + Clone {
-    // This RNG doesn't need to be cryptographically secure. Small and fast is
-    // preferable.
+/// Builds a traffic split that distributes requests over a profile's targets.
+///
+/// `pin` extracts the backend to which each request is pinned, if any. Requests that are not
+/// pinned, or that are pinned to a backend that is not one of the profile's current targets, are
+/// distributed by the targets' weights.
+pub fn layer<P: Clone, N, S, Req>(
+    pin: P,
+) -> impl layer::Layer<N, Service = NewSplit<P, N, S, Req>> + Clone {
     layer::mk(move |inner| NewSplit {
+        pin: pin.clone(),
         inner,
         _service: PhantomData,
     })
 }
 
+/// Never pins requests, so that all requests are distributed by the split's weights.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Unpinned;
+
+/// Pins HTTP requests to the backend described by their `PinnedBackend` extension.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct PinHttp;
+
+/// An HTTP request extension that describes the backend to which the request is pinned, if any.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PinnedBackend(pub Option<NameAddr>);
+
 #[derive(Debug)]
-pub struct NewSplit<N, S, Req> {
+pub struct NewSplit<P, N, S, Req> {
+    pin: P,
     inner: N,
     _service: PhantomData<fn(Req) -> S>,
 }
 
-pub struct Split<T, N, S, Req> {
+pub struct Split<T, P, N, S, Req> {
+    // This RNG doesn't need to be cryptographically secure. Small and fast is
+    // preferable.
     rng: SmallRng,
+    pin: P,
     rx: ReceiverStream,
     target: T,
     new_service: N,
@@ -42,23 +64,25 @@ pub struct Split<T, N, S, Req> {
 
 // === impl NewSplit ===
 
-impl<N: Clone, S, Req> Clone for NewSplit<N, S, Req> {
+impl<P: Clone, N: Clone, S, Req> Clone for NewSplit<P, N, S, Req> {
     fn clone(&self) -> Self {
         Self {
+            pin: self.pin.clone(),
             inner: self.inner.clone(),
             _service: self._service,
         }
     }
 }
 
-impl<T, N, S, Req> NewService<T> for NewSplit<N, S, Req>
+impl<T, P, N, S, Req> NewService<T> for NewSplit<P, N, S, Req>
 where
     T: Clone + Param<LogicalAddr> + Param<Receiver>,
+    P: Clone,
     N: NewService<(ConcreteAddr, T), Service = S> + Clone,
     S: tower::Service<Req>,
     S::Error: Into<Error>,
 {
-    type Service = Split<T, N, S, Req>;
+    type Service = Split<T, P, N, S, Req>;
 
     fn new_service(&self, target: T) -> Self::Service {
         let rx: Receiver = target.param();
@@ -83,6 +107,7 @@ where
         }
 
         Split {
+            pin: self.pin.clone(),
             rx: rx.into(),
             target,
             new_service,
@@ -96,10 +121,11 @@ where
 
 // === impl Split ===
 
-impl<T, N, S, Req> tower::Service<Req> for Split<T, N, S, Req>
+impl<T, P, N, S, Req> tower::Service<Req> for Split<T, P, N, S, Req>
 where
     Req: Send + 'static,
     T: Clone + Param<LogicalAddr>,
+    P: ExtractParam<Option<NameAddr>, Req>,
     N: NewService<(ConcreteAddr, T), Service = S> + Clone,
     S: tower::Service<Req> + Send + 'static,
     S::Response: Send + 'static,
@@ -164,13 +190,116 @@ where
     }
 
     fn call(&mut self, req: Req) -> Self::Future {
-        let idx = if self.addrs.len() == 1 {
-            0
-        } else {
-            self.distribution.sample(&mut self.rng)
+        let pinned = self
+            .pin
+            .extract_param(&req)
+            .and_then(|addr| self.addrs.get_index_of(&addr));
+        let idx = match pinned {
+            Some(idx) => idx,
+            None if self.addrs.len() == 1 => 0,
+            None => self.distribution.sample(&mut self.rng),
         };
         let addr = self.addrs.get_index(idx).expect("invalid index");
-        trace!(?addr, "Dispatching");
+        trace!(?addr, pinned = pinned.is_some(), "Dispatching");
         Box::pin(self.services.call_ready(addr, req).err_into::<Error>())
     }
 }
+
+// === impl Unpinned ===
+
+impl<Req> ExtractParam<Option<NameAddr>, Req> for Unpinned {
+    fn extract_param(&self, _: &Req) -> Option<NameAddr> {
+        None
+    }
+}
+
+// === impl PinHttp ===
+
+impl<B> ExtractParam<Option<NameAddr>, http::Request<B>> for PinHttp {
+    fn extract_param(&self, req: &http::Request<B>) -> Option<NameAddr> {
+        req.extensions()
+            .get::<PinnedBackend>()
+            .and_then(|PinnedBackend(addr)| addr.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use tower::{Layer, ServiceExt};
+
+    #[derive(Clone)]
+    struct Logical {
+        addr: LogicalAddr,
+        profile: Receiver,
+    }
+
+    impl Param<LogicalAddr> for Logical {
+        fn param(&self) -> LogicalAddr {
+            self.addr.clone()
+        }
+    }
+
+    impl Param<Receiver> for Logical {
+        fn param(&self) -> Receiver {
+            self.profile.clone()
+        }
+    }
+
+    fn pinned(addr: Option<NameAddr>) -> http::Request<()> {
+        let mut req = http::Request::new(());
+        req.extensions_mut().insert(PinnedBackend(addr));
+        req
+    }
+
+    #[tokio::test]
+    async fn pinned_requests_reach_pinned_target() {
+        let stable = NameAddr::from_str("web-stable.ns.svc.cluster.local:8080").unwrap();
+        let canary = NameAddr::from_str("web-canary.ns.svc.cluster.local:8080").unwrap();
+        let (_tx, rx) = tokio::sync::watch::channel(Profile {
+            targets: vec![
+                Target {
+                    addr: stable.clone(),
+                    weight: 10_000,
+                },
+                Target {
+                    addr: canary.clone(),
+                    weight: 1,
+                },
+            ],
+            ..Profile::default()
+        });
+        let logical = Logical {
+            addr: LogicalAddr(NameAddr::from_str("web.ns.svc.cluster.local:8080").unwrap()),
+            profile: rx.into(),
+        };
+
+        // Each backend responds with its own address.
+        let new_backend = |(ConcreteAddr(addr), _): (ConcreteAddr, Logical)| {
+            tower::service_fn(move |_: http::Request<()>| {
+                futures::future::ok::<_, Error>(addr.clone())
+            })
+        };
+        let mut split = layer::<_, _, _, http::Request<()>>(PinHttp)
+            .layer(new_backend)
+            .new_service(logical);
+
+        // Pinned requests reach the pinned backend, despite its weight.
+        for _ in 0..10 {
+            let addr = (&mut split)
+                .oneshot(pinned(Some(canary.clone())))
+                .await
+                .expect("request must succeed");
+            assert_eq!(addr, canary);
+        }
+
+        // Requests pinned to a backend outside of the split are distributed by weight.
+        let other = NameAddr::from_str("web-other.ns.svc.cluster.local:8080").unwrap();
+        let addr = (&mut split)
+            .oneshot(pinned(Some(other)))
+            .await
+            .expect("request must succeed");
+        assert!(addr == stable || addr == canary);
+    }
+}
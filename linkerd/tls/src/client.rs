@@ -14,13 +14,18 @@ use std::{
 };
 use tracing::debug;
 
-/// A newtype for target server identities.
+/// A newtype for target server identities, which may be DNS-like names or SPIFFE IDs.
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
-pub struct ServerId(pub id::Name);
+pub struct ServerId(pub id::Id);
+
+/// A newtype for the name a client sends to a server in the TLS SNI extension.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ServerName(pub id::Name);
 
 /// A stack parameter that configures a `Client` to establish a TLS connection.
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub struct ClientTls {
+    pub server_name: ServerName,
     pub server_id: ServerId,
     pub alpn: Option<AlpnProtocols>,
 }
@@ -75,15 +80,23 @@ pub struct ConnectMeta<M> {
 
 // === impl ClientTls ===
 
-impl From<ServerId> for ClientTls {
-    fn from(server_id: ServerId) -> Self {
+impl ClientTls {
+    pub fn new(server_id: ServerId, server_name: ServerName) -> Self {
         Self {
+            server_name,
             server_id,
             alpn: None,
         }
     }
 }
 
+/// Servers with DNS-like identities are addressed by their identities.
+impl From<id::Name> for ClientTls {
+    fn from(name: id::Name) -> Self {
+        Self::new(ServerId(name.clone().into()), ServerName(name))
+    }
+}
+
 // === impl Client ===
 
 impl<L: Clone, C> Client<L, C> {
@@ -180,19 +193,54 @@ where
 
 // === impl ServerId ===
 
+impl From<id::Id> for ServerId {
+    fn from(id: id::Id) -> Self {
+        Self(id)
+    }
+}
+
 impl From<id::Name> for ServerId {
+    fn from(n: id::Name) -> Self {
+        Self(n.into())
+    }
+}
+
+impl Deref for ServerId {
+    type Target = id::Id;
+
+    fn deref(&self) -> &id::Id {
+        &self.0
+    }
+}
+
+impl FromStr for ServerId {
+    type Err = id::InvalidName;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        id::Id::from_str(s).map(ServerId)
+    }
+}
+
+impl fmt::Display for ServerId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+// === impl ServerName ===
+
+impl From<id::Name> for ServerName {
     fn from(n: id::Name) -> Self {
         Self(n)
     }
 }
 
-impl From<ServerId> for id::Name {
-    fn from(ServerId(name): ServerId) -> id::Name {
+impl From<ServerName> for id::Name {
+    fn from(ServerName(name): ServerName) -> id::Name {
         name
     }
 }
 
-impl Deref for ServerId {
+impl Deref for ServerName {
     type Target = id::Name;
 
     fn deref(&self) -> &id::Name {
@@ -200,14 +248,14 @@ impl Deref for ServerId {
     }
 }
 
-impl FromStr for ServerId {
+impl FromStr for ServerName {
     type Err = id::InvalidName;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        id::Name::from_str(s).map(ServerId)
+        id::Name::from_str(s).map(ServerName)
     }
 }
 
-impl fmt::Display for ServerId {
+impl fmt::Display for ServerName {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         self.0.fmt(f)
     }
@@ -7,7 +7,9 @@ pub mod server;
 pub use linkerd_identity::LocalId;
 
 pub use self::{
-    client::{Client, ClientTls, ConditionalClientTls, ConnectMeta, NoClientTls, ServerId},
+    client::{
+        Client, ClientTls, ConditionalClientTls, ConnectMeta, NoClientTls, ServerId, ServerName,
+    },
     server::{ClientId, ConditionalServerTls, NewDetectTls, NoServerTls, ServerTls},
 };
 
@@ -1,6 +1,6 @@
 mod client_hello;
 
-use crate::{NegotiatedProtocol, ServerId};
+use crate::{NegotiatedProtocol, ServerName};
 use bytes::BytesMut;
 use futures::prelude::*;
 use linkerd_conditional::Conditional;
@@ -19,9 +19,9 @@ use thiserror::Error;
 use tokio::time::{self, Duration};
 use tracing::{debug, trace, warn};
 
-/// A newtype for remote client idenities.
+/// A newtype for remote client idenities, which may be DNS-like names or SPIFFE IDs.
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
-pub struct ClientId(pub id::Name);
+pub struct ClientId(pub id::Id);
 
 /// Indicates a server-side connection's TLS status.
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
@@ -31,7 +31,7 @@ pub enum ServerTls {
         negotiated_protocol: Option<NegotiatedProtocol>,
     },
     Passthru {
-        sni: ServerId,
+        sni: ServerName,
     },
 }
 
@@ -166,7 +166,7 @@ where
             let id::LocalId(id) = tls.param();
             let (peer, io) = match sni {
                 // If we detected an SNI matching this proxy, terminate TLS.
-                Some(ServerId(sni)) if sni == id => {
+                Some(ServerName(sni)) if sni == id => {
                     trace!("Identified local SNI");
                     let (peer, io) = tls.oneshot(io).await?;
                     (Conditional::Some(peer), EitherIo::Left(io))
@@ -192,7 +192,7 @@ where
 }
 
 /// Peek or buffer the provided stream to determine an SNI value.
-async fn detect_sni<I>(mut io: I) -> io::Result<(Option<ServerId>, DetectIo<I>)>
+async fn detect_sni<I>(mut io: I) -> io::Result<(Option<ServerName>, DetectIo<I>)>
 where
     I: io::Peek + io::AsyncRead + io::AsyncWrite + Send + Sync + Unpin,
 {
@@ -250,20 +250,26 @@ where
 
 impl From<id::Name> for ClientId {
     fn from(n: id::Name) -> Self {
-        Self(n)
+        Self(n.into())
     }
 }
 
-impl From<ClientId> for id::Name {
-    fn from(ClientId(name): ClientId) -> id::Name {
-        name
+impl From<id::Id> for ClientId {
+    fn from(id: id::Id) -> Self {
+        Self(id)
+    }
+}
+
+impl From<ClientId> for id::Id {
+    fn from(ClientId(id): ClientId) -> id::Id {
+        id
     }
 }
 
 impl Deref for ClientId {
-    type Target = id::Name;
+    type Target = id::Id;
 
-    fn deref(&self) -> &id::Name {
+    fn deref(&self) -> &id::Id {
         &self.0
     }
 }
@@ -277,7 +283,7 @@ impl fmt::Display for ClientId {
 impl FromStr for ClientId {
     type Err = id::InvalidName;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        id::Name::from_str(s).map(Self)
+        id::Id::from_str(s).map(Self)
     }
 }
 
@@ -319,7 +325,7 @@ mod tests {
             .expect("SNI detection must not fail");
 
         let identity = id::Name::from_str("example.com").unwrap();
-        assert_eq!(sni, Some(ServerId(identity)));
+        assert_eq!(sni, Some(ServerName(identity)));
 
         match io {
             EitherIo::Left(_) => panic!("Detected IO should be buffered"),
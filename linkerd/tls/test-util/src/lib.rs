@@ -35,3 +35,10 @@ pub static BAR_NS1: Entity = Entity {
     crt: include_bytes!("testdata/bar-ns1-ca1/crt.der"),
     key: include_bytes!("testdata/bar-ns1-ca1/key.p8"),
 };
+
+pub static FOO_NS1_SPIFFE: Entity = Entity {
+    name: "spiffe://cluster.local/ns/ns1/sa/foo",
+    trust_anchors: include_bytes!("testdata/ca1.pem"),
+    crt: include_bytes!("testdata/foo-ns1-spiffe-ca1/crt.der"),
+    key: include_bytes!("testdata/foo-ns1-spiffe-ca1/key.p8"),
+};
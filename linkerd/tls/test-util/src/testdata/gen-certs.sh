@@ -48,6 +48,36 @@ ee() {
   mv "${ee}.csr" "${ee}/csr.pem"
 }
 
+# Issues a certificate with a SPIFFE ID in its URI SAN, like those issued by SPIRE.
+spiffe() {
+  ca_name=$1
+  ee_name=$2
+  ee_ns=$3
+  trust_domain=$4
+
+  ee="${ee_name}-${ee_ns}-spiffe-${ca_name}"
+  mkdir -p "${ee}"
+
+  openssl ecparam -name prime256v1 -genkey -noout -out "${ee}-key.pem"
+  openssl req -new -key "${ee}-key.pem" -subj "/OU=None" -out "${ee}/csr.pem"
+
+  printf '%s\n' \
+    'basicConstraints = critical, CA:FALSE' \
+    'keyUsage = critical, digitalSignature, keyEncipherment' \
+    'extendedKeyUsage = serverAuth, clientAuth' \
+    "subjectAltName = critical, URI:spiffe://${trust_domain}/ns/${ee_ns}/sa/${ee_name}" \
+    > "${ee}.ext"
+  openssl x509 -req -in "${ee}/csr.pem" -CA "${ca_name}.pem" -CAkey "${ca_name}-key.pem" \
+    -CAcreateserial -days 1800 -sha256 -extfile "${ee}.ext" \
+    -outform der -out "${ee}/crt.der"
+  rm "${ee}.ext" "${ca_name}.srl"
+
+  openssl pkcs8 -topk8 -nocrypt -inform pem -outform der \
+    -in "${ee}-key.pem" \
+    -out "${ee}/key.p8"
+  rm "${ee}-key.pem"
+}
+
 ca "Cluster-local CA 1" ca1
 ca "Cluster-local CA 1" ca2 # Same name, different key pair.
 
@@ -58,3 +88,4 @@ ee ca1 default default linkerd
 ee ca1 foo ns1 linkerd
 ee ca2 foo ns1 linkerd # Same, but different CA
 ee ca1 bar ns1 linkerd # Different service.
+spiffe ca1 foo ns1 cluster.local # A SPIFFE ID rather than a DNS-like name.
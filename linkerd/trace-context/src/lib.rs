@@ -2,9 +2,13 @@
 #![forbid(unsafe_code)]
 
 mod propagation;
+mod sample;
 mod service;
 
-pub use self::service::TraceContext;
+pub use self::{
+    sample::{ForceSample, Sampler},
+    service::{ResponseBody, TraceContext},
+};
 use bytes::Bytes;
 use linkerd_error::Error;
 use rand::Rng;
@@ -15,6 +19,7 @@ use std::time::SystemTime;
 use thiserror::Error;
 
 const SPAN_ID_LEN: usize = 8;
+const TRACE_ID_LEN: usize = 16;
 
 #[derive(Debug, Default)]
 pub struct Id(Vec<u8>);
@@ -31,10 +36,20 @@ pub struct Span {
     pub trace_id: Id,
     pub span_id: Id,
     pub parent_id: Id,
+    pub trace_state: Option<String>,
     pub span_name: String,
     pub start: SystemTime,
     pub end: SystemTime,
     pub labels: HashMap<&'static str, String>,
+    pub events: Vec<Event>,
+}
+
+/// Describes something that happened at a point in time during a span.
+#[derive(Debug)]
+pub struct Event {
+    pub name: &'static str,
+    pub time: SystemTime,
+    pub labels: HashMap<&'static str, String>,
 }
 
 pub trait SpanSink {
@@ -63,6 +78,12 @@ impl Id {
         rng.fill(bytes.as_mut_slice());
         Self(bytes)
     }
+
+    fn new_trace_id<R: Rng>(rng: &mut R) -> Self {
+        let mut bytes = vec![0; TRACE_ID_LEN];
+        rng.fill(bytes.as_mut_slice());
+        Self(bytes)
+    }
 }
 
 impl From<Id> for Vec<u8> {
@@ -98,6 +119,10 @@ impl Flags {
     pub fn is_sampled(&self) -> bool {
         self.0 & 1 == 1
     }
+
+    fn set_sampled(&mut self) {
+        self.0 |= 1;
+    }
 }
 
 impl fmt::Display for Flags {
@@ -15,10 +15,15 @@ const GRPC_TRACE_FIELD_TRACE_ID: u8 = 0;
 const GRPC_TRACE_FIELD_SPAN_ID: u8 = 1;
 const GRPC_TRACE_FIELD_TRACE_OPTIONS: u8 = 2;
 
+const W3C_TRACEPARENT_HEADER: &str = "traceparent";
+const W3C_TRACESTATE_HEADER: &str = "tracestate";
+const W3C_VERSION: &str = "00";
+
 #[derive(Debug)]
 pub enum Propagation {
     Http,
     Grpc,
+    W3C,
 }
 
 #[derive(Debug)]
@@ -27,6 +32,9 @@ pub struct TraceContext {
     pub trace_id: Id,
     pub parent_id: Id,
     pub flags: Flags,
+
+    /// The vendor-specific `tracestate` that accompanies a W3C `traceparent`.
+    pub trace_state: Option<String>,
 }
 
 #[derive(Debug, Error)]
@@ -36,13 +44,33 @@ struct UnknownFieldId(u8);
 // === impl TraceContext ===
 
 impl TraceContext {
+    /// Starts a new, sampled trace that is propagated with W3C trace context headers.
+    pub fn new_root() -> Self {
+        let mut flags = Flags::default();
+        flags.set_sampled();
+        Self {
+            propagation: Propagation::W3C,
+            trace_id: Id::new_trace_id(&mut thread_rng()),
+            parent_id: Id::default(),
+            flags,
+            trace_state: None,
+        }
+    }
+
     pub fn is_sampled(&self) -> bool {
         self.flags.is_sampled()
     }
+
+    /// Marks the trace as sampled. The decision is propagated as the span ID is incremented.
+    pub fn set_sampled(&mut self) {
+        self.flags.set_sampled();
+    }
 }
 
 pub fn unpack_trace_context<B>(request: &http::Request<B>) -> Option<TraceContext> {
-    unpack_grpc_trace_context(request).or_else(|| unpack_http_trace_context(request))
+    unpack_grpc_trace_context(request)
+        .or_else(|| unpack_http_trace_context(request))
+        .or_else(|| unpack_w3c_trace_context(request))
 }
 
 // Generates a new span id, writes it to the request in the appropriate
@@ -50,7 +78,8 @@ pub fn unpack_trace_context<B>(request: &http::Request<B>) -> Option<TraceContex
 pub fn increment_span_id<B>(request: &mut http::Request<B>, context: &TraceContext) -> Id {
     match context.propagation {
         Propagation::Grpc => increment_grpc_span_id(request, context),
-        Propagation::Http => increment_http_span_id(request),
+        Propagation::Http => increment_http_span_id(request, context),
+        Propagation::W3C => increment_w3c_span_id(request, context),
     }
 }
 
@@ -82,6 +111,7 @@ fn parse_grpc_trace_context_fields(buf: &mut Bytes) -> Option<TraceContext> {
         trace_id: Default::default(),
         parent_id: Default::default(),
         flags: Default::default(),
+        trace_state: None,
     };
 
     while !buf.is_empty() {
@@ -184,10 +214,11 @@ fn unpack_http_trace_context<B>(request: &http::Request<B>) -> Option<TraceConte
         trace_id,
         parent_id,
         flags,
+        trace_state: None,
     })
 }
 
-fn increment_http_span_id<B>(request: &mut http::Request<B>) -> Id {
+fn increment_http_span_id<B>(request: &mut http::Request<B>, context: &TraceContext) -> Id {
     let span_id = Id::new_span_id(&mut thread_rng());
 
     trace!("incremented span id: {}", span_id);
@@ -199,6 +230,104 @@ fn increment_http_span_id<B>(request: &mut http::Request<B>) -> Id {
     } else {
         warn!("invalid {} header: {:?}", HTTP_SPAN_ID_HEADER, span_str);
     }
+
+    // The proxy may have decided to sample a trace that its client did not.
+    if context.is_sampled() {
+        request
+            .headers_mut()
+            .insert(HTTP_SAMPLED_HEADER, HeaderValue::from_static("1"));
+    }
+    span_id
+}
+
+fn unpack_w3c_trace_context<B>(request: &http::Request<B>) -> Option<TraceContext> {
+    let header_str = get_header_str(request, W3C_TRACEPARENT_HEADER)?;
+    let (trace_id, parent_id, flags) = parse_traceparent(header_str)
+        .map_err(|e| {
+            warn!(
+                "Invalid {} header {:?}: {}",
+                W3C_TRACEPARENT_HEADER, header_str, e
+            )
+        })
+        .ok()?;
+
+    // A `tracestate` may be split over several header fields, which are combined as a list.
+    let trace_state = request
+        .headers()
+        .get_all(W3C_TRACESTATE_HEADER)
+        .iter()
+        .filter_map(|hv| hv.to_str().ok())
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>();
+    let trace_state = if trace_state.is_empty() {
+        None
+    } else {
+        Some(trace_state.join(","))
+    };
+
+    Some(TraceContext {
+        propagation: Propagation::W3C,
+        trace_id,
+        parent_id,
+        flags,
+        trace_state,
+    })
+}
+
+/// Parses a `traceparent` header of the form `{version}-{trace-id}-{parent-id}-{trace-flags}`.
+///
+/// Versions other than `00` may append fields to the header, which are ignored.
+fn parse_traceparent(header: &str) -> Result<(Id, Id, Flags), Error> {
+    let mut fields = header.trim().split('-');
+    let mut next = |len: usize| -> Result<Vec<u8>, Error> {
+        let field = fields.next().ok_or("missing field")?;
+        if field.len() != len * 2 {
+            return Err(format!("field {:?} must have {} hex digits", field, len * 2).into());
+        }
+        Ok(hex::decode(field)?)
+    };
+
+    let version = next(1)?[0];
+    if version == 0xff {
+        return Err("invalid version".into());
+    }
+    let trace_id = next(16)?;
+    let parent_id = next(8)?;
+    let flags = next(1)?[0];
+    if version == 0 && fields.next().is_some() {
+        return Err("unexpected trailing fields".into());
+    }
+
+    if trace_id.iter().all(|b| *b == 0) {
+        return Err("trace id must not be zero".into());
+    }
+    if parent_id.iter().all(|b| *b == 0) {
+        return Err("parent id must not be zero".into());
+    }
+
+    Ok((Id(trace_id), Id(parent_id), Flags(flags)))
+}
+
+fn increment_w3c_span_id<B>(request: &mut http::Request<B>, context: &TraceContext) -> Id {
+    let span_id = Id::new_span_id(&mut thread_rng());
+
+    trace!(message = "incremented span id", %span_id);
+
+    // The parent's `tracestate` is forwarded unmodified; only the `traceparent` is rewritten to
+    // refer to the new span.
+    let traceparent = format!(
+        "{}-{}-{}-{}",
+        W3C_VERSION, context.trace_id, span_id, context.flags
+    );
+    if let Result::Ok(hv) = HeaderValue::from_str(&traceparent) {
+        request.headers_mut().insert(W3C_TRACEPARENT_HEADER, hv);
+    } else {
+        warn!(
+            "invalid {} header: {:?}",
+            W3C_TRACEPARENT_HEADER, traceparent
+        );
+    }
     span_id
 }
 
@@ -235,3 +364,99 @@ fn try_split_to(buf: &mut Bytes, n: usize) -> Result<Bytes, InsufficientBytes> {
         Err(InsufficientBytes)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    fn request(headers: &[(&'static str, &'static str)]) -> http::Request<()> {
+        let mut req = http::Request::builder();
+        for (name, value) in headers {
+            req = req.header(*name, *value);
+        }
+        req.body(()).unwrap()
+    }
+
+    #[test]
+    fn unpacks_w3c_context() {
+        let req = request(&[
+            (W3C_TRACEPARENT_HEADER, TRACEPARENT),
+            (W3C_TRACESTATE_HEADER, "congo=t61rcWkgMzE"),
+            (W3C_TRACESTATE_HEADER, "rojo=00f067aa0ba902b7"),
+        ]);
+        let context = unpack_trace_context(&req).expect("must have a context");
+        assert!(matches!(context.propagation, Propagation::W3C));
+        assert_eq!(
+            context.trace_id.to_string(),
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+        assert_eq!(context.parent_id.to_string(), "00f067aa0ba902b7");
+        assert!(context.is_sampled());
+        assert_eq!(
+            context.trace_state.as_deref(),
+            Some("congo=t61rcWkgMzE,rojo=00f067aa0ba902b7")
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_traceparent() {
+        for traceparent in &[
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+            "00-4bf92f3577b34da6a3ce929d0e0e47-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902zz-01",
+        ] {
+            let req = request(&[(W3C_TRACEPARENT_HEADER, traceparent)]);
+            assert!(
+                unpack_trace_context(&req).is_none(),
+                "{} must be rejected",
+                traceparent
+            );
+        }
+    }
+
+    #[test]
+    fn ignores_fields_of_future_versions() {
+        let req = request(&[(
+            W3C_TRACEPARENT_HEADER,
+            "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00-extra",
+        )]);
+        let context = unpack_trace_context(&req).expect("must have a context");
+        assert_eq!(context.parent_id.to_string(), "00f067aa0ba902b7");
+        assert!(!context.is_sampled());
+    }
+
+    #[test]
+    fn increments_w3c_span_id() {
+        let mut req = request(&[
+            (W3C_TRACEPARENT_HEADER, TRACEPARENT),
+            (W3C_TRACESTATE_HEADER, "congo=t61rcWkgMzE"),
+        ]);
+        let context = unpack_trace_context(&req).expect("must have a context");
+        let span_id = increment_span_id(&mut req, &context);
+
+        assert_eq!(
+            req.headers()[W3C_TRACEPARENT_HEADER],
+            format!("00-4bf92f3577b34da6a3ce929d0e0e4736-{}-01", span_id)
+        );
+        assert_eq!(req.headers()[W3C_TRACESTATE_HEADER], "congo=t61rcWkgMzE");
+        assert!(req.headers().get(HTTP_SPAN_ID_HEADER).is_none());
+        assert!(req.headers().get(GRPC_TRACE_HEADER).is_none());
+    }
+
+    #[test]
+    fn prefers_b3_to_w3c() {
+        let req = request(&[
+            (W3C_TRACEPARENT_HEADER, TRACEPARENT),
+            (HTTP_TRACE_ID_HEADER, "463ac35c9f6413ad48485a3953bb6124"),
+            (HTTP_SPAN_ID_HEADER, "a2fb4a1d1a96d312"),
+        ]);
+        let context = unpack_trace_context(&req).expect("must have a context");
+        assert!(matches!(context.propagation, Propagation::Http));
+    }
+}
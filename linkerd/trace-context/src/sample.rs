@@ -0,0 +1,199 @@
+use http::header::{HeaderName, HeaderValue};
+use parking_lot::Mutex;
+use rand::{thread_rng, Rng};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Decides whether the proxy starts a trace for a request that is not already sampled.
+///
+/// By default, the proxy never starts traces and only records spans for requests whose trace
+/// context is already sampled.
+#[derive(Clone, Debug, Default)]
+pub struct Sampler {
+    ratio: f64,
+    rate_limit: Option<Arc<RateLimit>>,
+    force: Arc<ForceSample>,
+}
+
+/// Describes requests that are always sampled, regardless of the sampler's ratio and rate limit.
+#[derive(Clone, Debug, Default)]
+pub struct ForceSample {
+    /// Requests whose path starts with any of these prefixes are sampled.
+    pub path_prefixes: Vec<String>,
+
+    /// Requests that have any of these headers are sampled. When a value is set, the header must
+    /// have that value.
+    pub headers: Vec<(HeaderName, Option<HeaderValue>)>,
+}
+
+/// Limits the number of traces started in each one-second window.
+#[derive(Debug)]
+struct RateLimit {
+    max_per_second: u32,
+    window: Mutex<Window>,
+}
+
+#[derive(Debug)]
+struct Window {
+    start: Instant,
+    sampled: u32,
+}
+
+// === impl Sampler ===
+
+impl Sampler {
+    /// Returns a sampler that starts a trace for the given `ratio` of requests (between 0.0 and
+    /// 1.0), starting no more than `max_per_second` traces each second.
+    ///
+    /// Ratios outside of this range are clamped to it. A ratio that is not finite (e.g. NaN) never
+    /// samples requests.
+    pub fn new(ratio: f64, max_per_second: Option<u32>, force: ForceSample) -> Self {
+        let ratio = if ratio.is_finite() {
+            ratio.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        Self {
+            ratio,
+            rate_limit: max_per_second.map(|max_per_second| {
+                Arc::new(RateLimit {
+                    max_per_second,
+                    window: Mutex::new(Window {
+                        start: Instant::now(),
+                        sampled: 0,
+                    }),
+                })
+            }),
+            force: Arc::new(force),
+        }
+    }
+
+    pub(crate) fn sample<B>(&self, req: &http::Request<B>) -> bool {
+        if self.force.matches(req) {
+            return true;
+        }
+
+        if self.ratio <= 0.0 || !thread_rng().gen_bool(self.ratio) {
+            return false;
+        }
+
+        self.rate_limit
+            .as_ref()
+            .map(|rl| rl.acquire(Instant::now()))
+            .unwrap_or(true)
+    }
+}
+
+// === impl ForceSample ===
+
+impl ForceSample {
+    fn matches<B>(&self, req: &http::Request<B>) -> bool {
+        let path = req.uri().path();
+        if self.path_prefixes.iter().any(|p| path.starts_with(p)) {
+            return true;
+        }
+
+        self.headers.iter().any(|(name, value)| {
+            req.headers()
+                .get_all(name)
+                .iter()
+                .any(|v| value.as_ref().map(|value| v == value).unwrap_or(true))
+        })
+    }
+}
+
+// === impl RateLimit ===
+
+impl RateLimit {
+    const WINDOW: Duration = Duration::from_secs(1);
+
+    fn acquire(&self, now: Instant) -> bool {
+        let mut window = self.window.lock();
+        if now.saturating_duration_since(window.start) >= Self::WINDOW {
+            window.start = now;
+            window.sampled = 0;
+        }
+
+        if window.sampled < self.max_per_second {
+            window.sampled += 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(path: &str, headers: &[(&'static str, &'static str)]) -> http::Request<()> {
+        let mut req = http::Request::builder().uri(path);
+        for (name, value) in headers {
+            req = req.header(*name, *value);
+        }
+        req.body(()).unwrap()
+    }
+
+    #[test]
+    fn never_samples_by_default() {
+        let sampler = Sampler::default();
+        assert!(!sampler.sample(&request("/", &[])));
+    }
+
+    #[test]
+    fn samples_by_ratio() {
+        let always = Sampler::new(1.0, None, ForceSample::default());
+        assert!(always.sample(&request("/", &[])));
+
+        let never = Sampler::new(0.0, None, ForceSample::default());
+        assert!(!never.sample(&request("/", &[])));
+    }
+
+    #[test]
+    fn ignores_invalid_ratios() {
+        for ratio in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let sampler = Sampler::new(ratio, None, ForceSample::default());
+            assert!(!sampler.sample(&request("/", &[])));
+        }
+
+        let clamped = Sampler::new(2.0, None, ForceSample::default());
+        assert!(clamped.sample(&request("/", &[])));
+    }
+
+    #[test]
+    fn limits_samples_per_second() {
+        let sampler = Sampler::new(1.0, Some(2), ForceSample::default());
+        let req = request("/", &[]);
+        assert!(sampler.sample(&req));
+        assert!(sampler.sample(&req));
+        assert!(!sampler.sample(&req));
+
+        let rl = sampler.rate_limit.as_ref().unwrap();
+        let next = rl.window.lock().start + RateLimit::WINDOW;
+        assert!(rl.acquire(next));
+    }
+
+    #[test]
+    fn force_samples_matching_requests() {
+        let force = ForceSample {
+            path_prefixes: vec!["/checkout".to_string()],
+            headers: vec![
+                (HeaderName::from_static("x-debug-trace"), None),
+                (
+                    HeaderName::from_static("x-canary"),
+                    Some(HeaderValue::from_static("true")),
+                ),
+            ],
+        };
+        let sampler = Sampler::new(0.0, Some(0), force);
+
+        assert!(sampler.sample(&request("/checkout/cart", &[])));
+        assert!(sampler.sample(&request("/", &[("x-debug-trace", "1")])));
+        assert!(sampler.sample(&request("/", &[("x-canary", "true")])));
+        assert!(!sampler.sample(&request("/", &[("x-canary", "false")])));
+        assert!(!sampler.sample(&request("/cart", &[])));
+    }
+}
@@ -1,6 +1,12 @@
-use crate::{propagation, Span, SpanSink};
-use futures::{future::Either, prelude::*};
+use crate::{propagation, Event, Sampler, Span, SpanSink};
+use futures::{
+    future::{self, Either},
+    prelude::*,
+    ready,
+};
+use http::header::HeaderMap;
 use linkerd_stack::layer;
+use pin_project::pin_project;
 use std::{
     collections::HashMap,
     future::Future,
@@ -10,33 +16,63 @@ use std::{
 };
 use tracing::{debug, info, trace};
 
+const GRPC_STATUS_HEADER: &str = "grpc-status";
+const L5D_PROXY_ERROR_HEADER: &str = "l5d-proxy-error";
+const L5D_RETRY_ATTEMPT_HEADER: &str = "l5d-retry-attempt";
+
 /// A layer that adds distributed tracing instrumentation.
 ///
-/// This layer reads the `traceparent` HTTP header from the request. If this
-/// header is absent, the request is fowarded unmodified.  If the header is
-/// present, a new span will be started in the current trace by creating a new
-/// random span id setting it into the `traceparent` header before forwarding
-/// the request. If the sampled bit of the header was set, we emit metadata
-/// about the span to the given SpanSink when the span is complete, i.e. when
-/// we receive the response.
+/// This layer reads the trace context from the request's `grpc-trace-bin`, B3
+/// (`x-b3-*`), or W3C `traceparent` headers. If these headers are absent, the
+/// request is fowarded unmodified.  If a context is present, a new span will be
+/// started in the current trace by creating a new random span id and setting it
+/// into the same header before forwarding the request. If the sampled bit of
+/// the header was set, we emit metadata about the span to the given SpanSink
+/// when the span is complete, i.e. when the response body has been sent.
+///
+/// When the request is not sampled, the `Sampler` may start a trace for it: the
+/// request's context is marked as sampled or, if it has no context, a new W3C
+/// `traceparent` is added to the request.
 #[derive(Clone, Debug)]
 pub struct TraceContext<K, S> {
     inner: S,
     sink: K,
+    sampler: Sampler,
 }
 
+/// A response body that completes its request's span as the body ends.
+#[pin_project]
+pub struct ResponseBody<K: SpanSink, B> {
+    #[pin]
+    inner: B,
+    span: Option<PendingSpan<K>>,
+}
+
+/// Emits a span when dropped.
+struct PendingSpan<K: SpanSink> {
+    sink: K,
+    span: Option<Span>,
+    end: Option<SystemTime>,
+}
+
+type MkResponse<K, B> = fn(http::Response<B>) -> http::Response<ResponseBody<K, B>>;
+
 // === impl TraceContext ===
 
 impl<K: Clone, S> TraceContext<K, S> {
-    pub fn layer(sink: K) -> impl layer::Layer<S, Service = TraceContext<K, S>> + Clone {
+    pub fn layer(
+        sink: K,
+        sampler: Sampler,
+    ) -> impl layer::Layer<S, Service = TraceContext<K, S>> + Clone {
         layer::mk(move |inner| TraceContext {
             inner,
             sink: sink.clone(),
+            sampler: sampler.clone(),
         })
     }
 
     fn request_labels<B>(req: &http::Request<B>) -> HashMap<&'static str, String> {
-        let mut labels = HashMap::with_capacity(5);
+        let mut labels = HashMap::with_capacity(6);
         labels.insert("http.method", format!("{}", req.method()));
         let path = req
             .uri()
@@ -52,15 +88,34 @@ impl<K: Clone, S> TraceContext<K, S> {
                 labels.insert("http.host", host.to_string());
             }
         }
+        if let Some(attempt) = header_str(req.headers(), L5D_RETRY_ATTEMPT_HEADER) {
+            labels.insert("retry.attempt", attempt.to_string());
+        }
         labels
     }
 
-    fn add_response_labels<B>(
-        mut labels: HashMap<&'static str, String>,
-        rsp: &http::Response<B>,
-    ) -> HashMap<&'static str, String> {
+    fn add_response_labels<B>(labels: &mut HashMap<&'static str, String>, rsp: &http::Response<B>) {
         labels.insert("http.status_code", rsp.status().as_str().to_string());
-        labels
+        // Trailers-only gRPC responses set the status in the response headers.
+        add_grpc_status(labels, rsp.headers());
+        if let Some(error) = header_str(rsp.headers(), L5D_PROXY_ERROR_HEADER) {
+            labels.insert("error", error.to_string());
+        }
+    }
+
+    fn events<B>(req: &http::Request<B>, start: SystemTime) -> Vec<Event> {
+        let mut events = Vec::new();
+        if let Some(attempt) = header_str(req.headers(), L5D_RETRY_ATTEMPT_HEADER) {
+            let mut labels = HashMap::with_capacity(1);
+            labels.insert("attempt", attempt.to_string());
+            events.push(Event {
+                name: "retry",
+                time: start,
+                labels,
+            });
+        }
+
+        events
     }
 }
 
@@ -71,11 +126,11 @@ where
     S::Error: Send,
     S::Future: Send + 'static,
 {
-    type Response = S::Response;
+    type Response = http::Response<ResponseBody<K, RspB>>;
     type Error = S::Error;
     type Future = Either<
-        S::Future,
-        Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send + 'static>>,
+        future::MapOk<S::Future, MkResponse<K, RspB>>,
+        Pin<Box<dyn Future<Output = Result<Self::Response, S::Error>> + Send + 'static>>,
     >;
 
     #[inline]
@@ -85,7 +140,22 @@ where
 
     fn call(&mut self, mut req: http::Request<ReqB>) -> Self::Future {
         if self.sink.is_enabled() {
-            if let Some(context) = propagation::unpack_trace_context(&req) {
+            let context = match propagation::unpack_trace_context(&req) {
+                Some(mut context) => {
+                    if !context.is_sampled() && self.sampler.sample(&req) {
+                        debug!("Sampling trace");
+                        context.set_sampled();
+                    }
+                    Some(context)
+                }
+                None if self.sampler.sample(&req) => {
+                    debug!("Starting trace");
+                    Some(propagation::TraceContext::new_root())
+                }
+                None => None,
+            };
+
+            if let Some(context) = context {
                 // Update the trace ID if the request set one and the proxy is configured to emit
                 // spans.
                 let span_id = propagation::increment_span_id(&mut req, &context);
@@ -94,31 +164,274 @@ where
                 if context.is_sampled() {
                     // If the request has been marked for sampling, record its metadata.
                     let start = SystemTime::now();
-                    let req_labels = Self::request_labels(&req);
-                    let mut sink = self.sink.clone();
-                    let span_name = req.uri().path().to_owned();
+                    let span = Span {
+                        span_id,
+                        trace_id: context.trace_id,
+                        parent_id: context.parent_id,
+                        trace_state: context.trace_state,
+                        span_name: req.uri().path().to_owned(),
+                        start,
+                        end: start,
+                        labels: Self::request_labels(&req),
+                        events: Self::events(&req, start),
+                    };
+                    let sink = self.sink.clone();
                     return Either::Right(Box::pin(self.inner.call(req).map_ok(move |rsp| {
-                        // Emit the completed span with the response metadata.
-                        let span = Span {
-                            span_id,
-                            trace_id: context.trace_id,
-                            parent_id: context.parent_id,
-                            span_name,
-                            start,
-                            end: SystemTime::now(),
-                            labels: Self::add_response_labels(req_labels, &rsp),
-                        };
-                        trace!(?span);
-                        if let Err(error) = sink.try_send(span) {
-                            info!(%error, "Span dropped");
-                        }
-                        rsp
+                        // The span is emitted, with the response metadata, once the response
+                        // body completes.
+                        let mut span = span;
+                        Self::add_response_labels(&mut span.labels, &rsp);
+                        rsp.map(move |inner| ResponseBody {
+                            inner,
+                            span: Some(PendingSpan {
+                                sink,
+                                span: Some(span),
+                                end: None,
+                            }),
+                        })
                     })));
                 }
             }
         }
 
         // If there's no tracing to be done, just pass on the request to the inner service.
-        Either::Left(self.inner.call(req))
+        Either::Left(
+            self.inner
+                .call(req)
+                .map_ok(ResponseBody::passthru as MkResponse<K, RspB>),
+        )
+    }
+}
+
+// === impl ResponseBody ===
+
+impl<K: SpanSink, B> ResponseBody<K, B> {
+    fn passthru(rsp: http::Response<B>) -> http::Response<Self> {
+        rsp.map(|inner| Self { inner, span: None })
+    }
+}
+
+impl<K: SpanSink, B: http_body::Body> http_body::Body for ResponseBody<K, B> {
+    type Data = B::Data;
+    type Error = B::Error;
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let this = self.project();
+        let res = ready!(this.inner.poll_data(cx));
+        if res.is_none() {
+            if let Some(span) = this.span.as_mut() {
+                span.end.get_or_insert_with(SystemTime::now);
+            }
+        }
+        Poll::Ready(res)
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        let this = self.project();
+        let res = ready!(this.inner.poll_trailers(cx));
+        if let Some(span) = this.span.as_mut() {
+            if let (Ok(Some(trailers)), Some(s)) = (res.as_ref(), span.span.as_mut()) {
+                add_grpc_status(&mut s.labels, trailers);
+            }
+            span.end = Some(SystemTime::now());
+        }
+        Poll::Ready(res)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+// === impl PendingSpan ===
+
+impl<K: SpanSink> Drop for PendingSpan<K> {
+    fn drop(&mut self) {
+        if let Some(mut span) = self.span.take() {
+            // If the body was dropped before it completed, the span ends now.
+            span.end = self.end.unwrap_or_else(SystemTime::now);
+            trace!(?span);
+            if let Err(error) = self.sink.try_send(span) {
+                info!(%error, "Span dropped");
+            }
+        }
+    }
+}
+
+fn add_grpc_status(labels: &mut HashMap<&'static str, String>, headers: &HeaderMap) {
+    if let Some(status) = header_str(headers, GRPC_STATUS_HEADER) {
+        labels.insert("grpc.status_code", status.to_string());
+    }
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name)?.to_str().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use futures::task::noop_waker_ref;
+    use linkerd_error::Error;
+    use parking_lot::Mutex;
+    use std::{collections::VecDeque, sync::Arc};
+
+    #[derive(Clone, Default)]
+    struct Sink(Arc<Mutex<Vec<Span>>>);
+
+    struct Body {
+        data: VecDeque<Bytes>,
+        trailers: Option<HeaderMap>,
+    }
+
+    impl SpanSink for Sink {
+        fn is_enabled(&self) -> bool {
+            true
+        }
+
+        fn try_send(&mut self, span: Span) -> Result<(), Error> {
+            self.0.lock().push(span);
+            Ok(())
+        }
+    }
+
+    impl http_body::Body for Body {
+        type Data = Bytes;
+        type Error = Error;
+
+        fn poll_data(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<Option<Result<Bytes, Error>>> {
+            Poll::Ready(self.data.pop_front().map(Ok))
+        }
+
+        fn poll_trailers(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+        ) -> Poll<Result<Option<HeaderMap>, Error>> {
+            Poll::Ready(Ok(self.trailers.take()))
+        }
+    }
+
+    fn body(
+        data: &[&'static str],
+        trailers: Option<HeaderMap>,
+    ) -> (Sink, ResponseBody<Sink, Body>) {
+        let sink = Sink::default();
+        let start = SystemTime::UNIX_EPOCH;
+        let span = Span {
+            trace_id: Default::default(),
+            span_id: Default::default(),
+            parent_id: Default::default(),
+            trace_state: None,
+            span_name: "/".to_string(),
+            start,
+            end: start,
+            labels: HashMap::new(),
+            events: Vec::new(),
+        };
+        let body = ResponseBody {
+            inner: Body {
+                data: data
+                    .iter()
+                    .copied()
+                    .map(|d| Bytes::from_static(d.as_bytes()))
+                    .collect(),
+                trailers,
+            },
+            span: Some(PendingSpan {
+                sink: sink.clone(),
+                span: Some(span),
+                end: None,
+            }),
+        };
+        (sink, body)
+    }
+
+    fn poll_data(body: &mut ResponseBody<Sink, Body>) -> Option<Bytes> {
+        let mut cx = Context::from_waker(noop_waker_ref());
+        match http_body::Body::poll_data(Pin::new(body), &mut cx) {
+            Poll::Ready(data) => data.map(|res| res.expect("data must not fail")),
+            Poll::Pending => panic!("body must not be pending"),
+        }
+    }
+
+    fn poll_trailers(body: &mut ResponseBody<Sink, Body>) -> Option<HeaderMap> {
+        let mut cx = Context::from_waker(noop_waker_ref());
+        match http_body::Body::poll_trailers(Pin::new(body), &mut cx) {
+            Poll::Ready(res) => res.expect("trailers must not fail"),
+            Poll::Pending => panic!("body must not be pending"),
+        }
+    }
+
+    #[test]
+    fn ends_span_when_body_ends() {
+        let (sink, mut body) = body(&["hello"], None);
+
+        assert!(poll_data(&mut body).is_some());
+        assert!(poll_data(&mut body).is_none());
+        let ended = SystemTime::now();
+        assert!(
+            sink.0.lock().is_empty(),
+            "span must not be emitted before the body is dropped"
+        );
+
+        drop(body);
+        let spans = sink.0.lock();
+        assert_eq!(spans.len(), 1);
+        assert!(spans[0].end <= ended, "span must end when the body ends");
+        assert!(spans[0].end > spans[0].start);
+        assert!(!spans[0].labels.contains_key("grpc.status_code"));
+    }
+
+    #[test]
+    fn ends_span_when_body_is_dropped() {
+        let (sink, mut body) = body(&["hello", "world"], None);
+
+        assert!(poll_data(&mut body).is_some());
+        let before = SystemTime::now();
+        drop(body);
+
+        let spans = sink.0.lock();
+        assert_eq!(spans.len(), 1);
+        assert!(
+            spans[0].end >= before,
+            "span must end when the body is dropped"
+        );
+    }
+
+    #[test]
+    fn records_grpc_status_from_trailers() {
+        let mut trailers = HeaderMap::new();
+        trailers.insert(GRPC_STATUS_HEADER, "14".parse().unwrap());
+        let (sink, mut body) = body(&["hello"], Some(trailers));
+
+        assert!(poll_data(&mut body).is_some());
+        assert!(poll_data(&mut body).is_none());
+        assert!(poll_trailers(&mut body).is_some());
+        let ended = SystemTime::now();
+        drop(body);
+
+        let spans = sink.0.lock();
+        assert_eq!(spans.len(), 1);
+        assert_eq!(
+            spans[0].labels.get("grpc.status_code").map(String::as_str),
+            Some("14")
+        );
+        assert!(spans[0].end <= ended);
     }
 }
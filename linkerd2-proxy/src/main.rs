@@ -68,12 +68,14 @@ fn main() {
         }
 
         info!("Local identity is {}", app.local_identity());
-        let addr = app.identity_addr();
-        match addr.identity.value() {
-            None => info!("Identity verified via {}", addr.addr),
-            Some(tls) => {
-                info!("Identity verified via {} ({})", addr.addr, tls.server_id);
-            }
+        match app.identity_addr() {
+            None => info!("Identity loaded from files"),
+            Some(addr) => match addr.identity.value() {
+                None => info!("Identity verified via {}", addr.addr),
+                Some(tls) => {
+                    info!("Identity verified via {} ({})", addr.addr, tls.server_id);
+                }
+            },
         }
 
         let dst_addr = app.dst_addr();
@@ -85,13 +87,13 @@ fn main() {
             ),
         }
 
-        if let Some(oc) = app.opencensus_addr() {
+        if let Some((protocol, oc)) = app.trace_collector() {
             match oc.identity.value() {
-                None => info!("OpenCensus tracing collector at {}", oc.addr),
+                None => info!("{} tracing collector at {}", protocol, oc.addr),
                 Some(tls) => {
                     info!(
-                        "OpenCensus tracing collector at {} ({})",
-                        oc.addr, tls.server_id
+                        "{} tracing collector at {} ({})",
+                        protocol, oc.addr, tls.server_id
                     )
                 }
             }
@@ -106,6 +108,13 @@ fn main() {
                 info!("Received shutdown via admin interface");
             }
         }
-        drain.drain().await;
+        // Drain connections for up to the configured grace period. A second signal skips the
+        // remainder of the drain.
+        tokio::select! {
+            _ = drain.drain() => {}
+            _ = signal::shutdown() => {
+                info!("Received second shutdown signal; shutting down without draining");
+            }
+        }
     });
 }